@@ -0,0 +1,330 @@
+//! The assembly of a compilation unit, in the syntax of the GNU assembler.
+//!
+//! Only the data is generated yet. The global variables and the `static`
+//! ones of the functions are allocated in `.data` if they are initialized
+//! and in `.bss` otherwise, with the sizes and the alignments of the target,
+//! and the string literals of their initializers are in `.rodata`. Each
+//! function is a label where its code is to be generated.
+//!
+//! The functions and the variables defined `static` are local symbols of
+//! the object file, the others are `.globl`. A `static` variable of a
+//! function is allocated once for all the calls, under its name and a number
+//! unique in the file, as `count.0`, since another function may have one of
+//! the same name.
+
+use std::collections::HashMap;
+use super::ast::*;
+use super::type_table::TypeTable;
+use super::entity::{EntityId, EntityTable};
+use super::constant_evaluator::ConstantEvaluator;
+use super::visitor::{self, Visitor};
+
+pub struct AssemblyWriter<'a> {
+    table: &'a TypeTable,
+    entities: &'a EntityTable,
+    text: String,
+    data: String,
+    rodata: String,
+    bss: String,
+    labels: HashMap<EntityId, String>, // of the `static` variables in the functions
+    strings: usize, // the string literals so far, `.LC0`, `.LC1`, ...
+    in_function: bool,
+}
+
+impl<'a> AssemblyWriter<'a> {
+    pub fn new(table: &'a TypeTable, entities: &'a EntityTable) -> AssemblyWriter<'a> {
+        AssemblyWriter {
+            table: table,
+            entities: entities,
+            text: String::new(),
+            data: String::new(),
+            rodata: String::new(),
+            bss: String::new(),
+            labels: HashMap::new(),
+            strings: 0,
+            in_function: false,
+        }
+    }
+
+    /// the assembly of the source file `name`, such as `foo.cb`.
+    pub fn write(mut self, ast: &mut AST, name: &str) -> String {
+        self.visit_ast(ast);
+        let mut assembly = format!("\t.file\t\"{}\"\n", name);
+        for &(section, body) in &[("\t.text\n", &self.text),
+                                      ("\t.data\n", &self.data),
+                                      ("\t.section\t.rodata\n", &self.rodata),
+                                      ("\t.bss\n", &self.bss)] {
+            if !body.is_empty() {
+                assembly.push_str(section);
+                assembly.push_str(body);
+            }
+        }
+        assembly.push_str("\t.section\t.note.GNU-stack,\"\",@progbits\n");
+        assembly
+    }
+
+    fn function(&mut self, func: &DefinedFunction) {
+        if !func.is_private {
+            self.text.push_str(&format!("\t.globl\t{}\n", func.name));
+        }
+        self.text.push_str(&format!("\t.type\t{0}, @function\n{0}:\n\
+                                     \t# the code of `{0}` is not generated yet\n\
+                                     \t.size\t{0}, .-{0}\n", func.name));
+    }
+
+    fn variable(&mut self, var: &DefinedVariable, symbol: &str, is_global: bool) {
+        let type_ = match var.type_.type_ {
+            Some(type_) => type_,
+            None => return,
+        };
+        let size = self.table.size(type_).unwrap_or(0);
+        let value = match var.initializer {
+            Some(ref initializer) => self.initial_value(&**initializer),
+            None => None,
+        };
+
+        let mut object = String::new();
+        if is_global {
+            object.push_str(&format!("\t.globl\t{}\n", symbol));
+        }
+        object.push_str(&format!("\t.align\t{1}\n\t.type\t{0}, @object\n\t.size\t{0}, {2}\n\
+                                  {0}:\n", symbol,
+                                 self.table.alignment(type_).unwrap_or(1), size));
+        match value {
+            Some(value) => {
+                object.push_str(&format!("\t{}\t{}\n", directive(size), value));
+                self.data.push_str(&object);
+            }
+            None => {
+                object.push_str(&format!("\t.zero\t{}\n", size));
+                self.bss.push_str(&object);
+            }
+        }
+    }
+
+    // the value of the initializer, an integer or an address, which the
+    // `ConstantChecker` has checked.
+    fn initial_value(&mut self, node: &Node) -> Option<String> {
+        if let Ok(constant) = ConstantEvaluator::new(self.table, self.entities).evaluate(node) {
+            return Some(constant.value.to_string());
+        }
+        let (symbol, offset) = self.address(node)?;
+        Some(match offset {
+            0 => symbol,
+            offset if offset > 0 => format!("{}+{}", symbol, offset),
+            offset => format!("{}{}", symbol, offset),
+        })
+    }
+
+    // the address constant as a symbol and an offset from it.
+    fn address(&mut self, node: &Node) -> Option<(String, i64)> {
+        match node.node_ref() {
+            NodeRef::StringLiteralNode(node) => Some((self.string(&node.value), 0)),
+            NodeRef::AddressNode(node) => self.object(&*node.node),
+            // a function or an array is its address
+            NodeRef::VariableNode(_) => self.object(node),
+            NodeRef::CastNode(node) => self.address(&*node.node),
+            NodeRef::BinaryOpNode(node) => {
+                // the `TypeChecker` has scaled the integer to bytes
+                let (address, offset, sign) = match node.type_ {
+                    BinaryOpType::Addition => match self.address(&*node.left) {
+                        Some(address) => (address, &node.right, 1),
+                        None => (self.address(&*node.right)?, &node.left, 1),
+                    },
+                    BinaryOpType::Subtraction => (self.address(&*node.left)?, &node.right, -1),
+                    _ => return None,
+                };
+                let bytes = ConstantEvaluator::new(self.table, self.entities)
+                                              .evaluate(&**offset).ok()?.value;
+                Some((address.0, address.1 + sign * bytes))
+            }
+            _ => None,
+        }
+    }
+
+    // the address of the object with the static storage.
+    fn object(&mut self, node: &Node) -> Option<(String, i64)> {
+        match node.node_ref() {
+            NodeRef::VariableNode(node) => {
+                let entity = node.entity?;
+                let symbol = self.labels.get(&entity).cloned()
+                                 .unwrap_or_else(|| node.name.clone());
+                Some((symbol, 0))
+            }
+            NodeRef::MemberNode(node) => {
+                let (symbol, offset) = self.object(&*node.expr)?;
+                let member = self.table.member(node.expr.expr_type()?, &node.memb)?;
+                Some((symbol, offset + member.offset as i64))
+            }
+            NodeRef::ArefNode(node) => {
+                let (symbol, offset) = self.object(&*node.expr)?;
+                let index = ConstantEvaluator::new(self.table, self.entities)
+                                              .evaluate(&*node.idx).ok()?.value;
+                Some((symbol, offset + index * self.element_size(&*node.expr)?))
+            }
+            _ => None,
+        }
+    }
+
+    // the size of the elements of the array.
+    fn element_size(&self, node: &Node) -> Option<i64> {
+        let base_type = self.table.base_type(node.expr_type()?)?;
+        self.table.size(base_type).map(|size| size as i64)
+    }
+
+    // the label of the string literal in `.rodata`.
+    fn string(&mut self, value: &str) -> String {
+        let label = format!(".LC{}", self.strings);
+        self.strings += 1;
+        self.rodata.push_str(&format!("{}:\n\t.string\t\"{}\"\n", label, escape(value)));
+        label
+    }
+}
+
+impl<'a> Visitor for AssemblyWriter<'a> {
+    fn visit_defun(&mut self, func: &mut DefinedFunction) {
+        self.function(func);
+        self.in_function = true;
+        visitor::walk_defun(self, func);
+        self.in_function = false;
+    }
+
+    fn visit_defvar(&mut self, var: &mut DefinedVariable) {
+        if !self.in_function {
+            let symbol = var.name.clone();
+            self.variable(var, &symbol, !var.is_private);
+        } else if var.is_private {
+            // before the initializer, which may refer to the variable itself
+            let label = format!("{}.{}", var.name, self.labels.len());
+            if let Some(entity) = var.entity {
+                self.labels.insert(entity, label.clone());
+            }
+            self.variable(var, &label, false);
+        }
+        visitor::walk_defvar(self, var);
+    }
+}
+
+// the directive of the data of the size.
+fn directive(size: usize) -> &'static str {
+    match size {
+        1 => ".byte",
+        2 => ".value",
+        4 => ".long",
+        _ => ".quad",
+    }
+}
+
+// the string in the quotes of `.string`, a char up to `\377` is a byte and
+// the others are in UTF-8.
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            ' '..='~' => escaped.push(c),
+            c if (c as u32) < 0x100 => escaped.push_str(&format!("\\{:03o}", c as u32)),
+            c => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    escaped.push_str(&format!("\\{:03o}", byte));
+                }
+            }
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{compile, MODULE};
+
+    #[test]
+    fn address_constants() {
+        let compiled = compile("struct point { int x; int y; };\n\
+                                struct point p;\n\
+                                int[4] table;\n\
+                                long[2] longs;\n\
+                                int* y = &p.y;\n\
+                                int* third = &table[2];\n\
+                                int* last = table + 3;\n\
+                                int* before = 3 + table - 1;\n\
+                                long* second = longs + 1;\n\
+                                char* string = \"x\" + 1;\n");
+        compiled.assert_clean();
+        let values: Vec<&str> = compiled.output().lines()
+                                        .filter(|line| line.starts_with("\t.long\t"))
+                                        .map(|line| &line[7..])
+                                        .collect();
+        assert_eq!(values, ["p+4", "table+8", "table+12", "table+8", "longs+4", ".LC0+1"]);
+    }
+
+    #[test]
+    fn the_assembly_of_a_module() {
+        // `helper` and `hidden` are local, and `calls` is in `.data` for all the calls
+        assert_eq!(compile(MODULE).output(),
+                   "\t.file\t\"test.cb\"\n\
+                   \t.text\n\
+                   \t.globl\tarea\n\
+                   \t.type\tarea, @function\n\
+                   area:\n\
+                   \t# the code of `area` is not generated yet\n\
+                   \t.size\tarea, .-area\n\
+                   \t.type\thelper, @function\n\
+                   helper:\n\
+                   \t# the code of `helper` is not generated yet\n\
+                   \t.size\thelper, .-helper\n\
+                   \t.globl\tshow\n\
+                   \t.type\tshow, @function\n\
+                   show:\n\
+                   \t# the code of `show` is not generated yet\n\
+                   \t.size\tshow, .-show\n\
+                   \t.data\n\
+                   \t.globl\tcount\n\
+                   \t.align\t4\n\
+                   \t.type\tcount, @object\n\
+                   \t.size\tcount, 4\n\
+                   count:\n\
+                   \t.long\t3\n\
+                   \t.globl\tname\n\
+                   \t.align\t4\n\
+                   \t.type\tname, @object\n\
+                   \t.size\tname, 4\n\
+                   name:\n\
+                   \t.long\t.LC0\n\
+                   \t.globl\tlast\n\
+                   \t.align\t4\n\
+                   \t.type\tlast, @object\n\
+                   \t.size\tlast, 4\n\
+                   last:\n\
+                   \t.long\ttable+12\n\
+                   \t.align\t4\n\
+                   \t.type\tcalls.0, @object\n\
+                   \t.size\tcalls.0, 4\n\
+                   calls.0:\n\
+                   \t.long\t0\n\
+                   \t.section\t.rodata\n\
+                   .LC0:\n\
+                   \t.string\t\"shapes\"\n\
+                   \t.bss\n\
+                   \t.align\t4\n\
+                   \t.type\thidden, @object\n\
+                   \t.size\thidden, 4\n\
+                   hidden:\n\
+                   \t.zero\t4\n\
+                   \t.globl\torigin\n\
+                   \t.align\t4\n\
+                   \t.type\torigin, @object\n\
+                   \t.size\torigin, 8\n\
+                   origin:\n\
+                   \t.zero\t8\n\
+                   \t.globl\ttable\n\
+                   \t.align\t4\n\
+                   \t.type\ttable, @object\n\
+                   \t.size\ttable, 16\n\
+                   table:\n\
+                   \t.zero\t16\n\
+                   \t.section\t.note.GNU-stack,\"\",@progbits\n");
+    }
+}
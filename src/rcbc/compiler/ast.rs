@@ -1,15 +1,59 @@
 use super::location::Location;
+use super::source_map::FileId;
 use super::type_::*;
+use super::entity::{EntityId, ScopeId};
 
 const INDENT_STRING: &'static str = "    ";
 
 pub trait Node {
     fn location(&self) -> Location;
     fn dump(&self, indent_level: usize) -> String;
+    fn node_ref(&self) -> NodeRef<'_>;
+    fn node_mut(&mut self) -> NodeMut<'_>;
+
+    /// the type of the expression after the type checking, `None` for the
+    /// statements.
+    fn expr_type(&self) -> Option<TypeId> {
+        match self.node_ref() {
+            NodeRef::IntegerLiteralNode(node) => node.expr_type,
+            NodeRef::BinaryOpNode(node) => node.expr_type,
+            NodeRef::StringLiteralNode(node) => node.expr_type,
+            NodeRef::UnaryOpNode(node) => node.expr_type,
+            NodeRef::VariableNode(node) => node.expr_type,
+            NodeRef::PrefixOpNode(node) => node.expr_type,
+            NodeRef::DereferenceNode(node) => node.expr_type,
+            NodeRef::AddressNode(node) => node.expr_type,
+            NodeRef::CastNode(node) => node.expr_type,
+            NodeRef::SizeofTypeNode(node) => node.expr_type,
+            NodeRef::SizeofExprNode(node) => node.expr_type,
+            NodeRef::SuffixOpNode(node) => node.expr_type,
+            NodeRef::ArefNode(node) => node.expr_type,
+            NodeRef::MemberNode(node) => node.expr_type,
+            NodeRef::PtrMemberNode(node) => node.expr_type,
+            NodeRef::FuncallNode(node) => node.expr_type,
+            NodeRef::LogicalAndNode(node) => node.expr_type,
+            NodeRef::LogicalOrNode(node) => node.expr_type,
+            NodeRef::CondExprNode(node) => node.expr_type,
+            NodeRef::AssignNode(node) => node.expr_type,
+            NodeRef::OpAssignNode(node) => node.expr_type,
+            _ => None,
+        }
+    }
+
+    /// whether the expression designates an object, which can be assigned
+    /// or have its address taken.
+    fn is_lvalue(&self) -> bool {
+        match self.node_ref() {
+            NodeRef::VariableNode(_) | NodeRef::DereferenceNode(_) |
+            NodeRef::ArefNode(_) | NodeRef::PtrMemberNode(_) => true,
+            NodeRef::MemberNode(node) => node.expr.is_lvalue(),
+            _ => false,
+        }
+    }
 }
 
 macro_rules! impl_node_trait {
-    ($t: ty, $self_: ident, $string: block) => (
+    ($t: ident, $self_: ident, $string: block) => (
         impl Node for $t {
             fn location(&self) -> Location {
                 self.location
@@ -22,25 +66,46 @@ macro_rules! impl_node_trait {
                 let $self_ = self;
                 format!("{}{}", indent, $string)
             }
+            fn node_ref(&self) -> NodeRef<'_> {
+                NodeRef::$t(self)
+            }
+            fn node_mut(&mut self) -> NodeMut<'_> {
+                NodeMut::$t(self)
+            }
         }
     )
 }
 
+// The second member list is for the attributes set by the later passes,
+// with their initial values.
 macro_rules! define_node {
     ($node_name: ident; {
         $($member_name: ident: $member_type: ty,)*
     }; $self_: ident, $string: block) => (
+        define_node!($node_name; { $($member_name: $member_type,)* }; {};
+                     $self_, $string);
+    );
+
+    ($node_name: ident; {
+        $($member_name: ident: $member_type: ty,)*
+    }; {
+        $($attr_name: ident: $attr_type: ty = $attr_value: expr,)*
+    }; $self_: ident, $string: block) => (
 
         pub struct $node_name {
-            location: Location,
-            $($member_name: $member_type),*
+            pub location: Location,
+            $(pub $member_name: $member_type,)*
+            $(pub $attr_name: $attr_type,)*
         }
 
         impl $node_name {
+            // the AST has a member for each kind of definition
+            #[allow(clippy::too_many_arguments)]
             pub fn new(location: Location, $($member_name: $member_type),*) -> Self {
                 $node_name {
                     location: location,
-                    $($member_name: $member_name),*
+                    $($member_name: $member_name,)*
+                    $($attr_name: $attr_value,)*
                 }
             }
         }
@@ -49,23 +114,99 @@ macro_rules! define_node {
     )
 }
 
+// A reference to a node of the concrete type, to match on.
+macro_rules! define_node_kinds {
+    ($($node_name: ident,)*) => (
+        pub enum NodeRef<'a> {
+            $($node_name(&'a $node_name),)*
+        }
+
+        pub enum NodeMut<'a> {
+            $($node_name(&'a mut $node_name),)*
+        }
+    )
+}
+
+define_node_kinds!(
+    AST,
+    IntegerLiteralNode,
+    BinaryOpNode,
+    StringLiteralNode,
+    UnaryOpNode,
+    VariableNode,
+    PrefixOpNode,
+    DereferenceNode,
+    AddressNode,
+    CastNode,
+    SizeofTypeNode,
+    SizeofExprNode,
+    SuffixOpNode,
+    ArefNode,
+    MemberNode,
+    PtrMemberNode,
+    FuncallNode,
+    LogicalAndNode,
+    LogicalOrNode,
+    CondExprNode,
+    AssignNode,
+    OpAssignNode,
+    BlockNode,
+    ExprStmtNode,
+    IfNode,
+    WhileNode,
+    DoWhileNode,
+    ForNode,
+    SwitchNode,
+    BreakNode,
+    ContinueNode,
+    GotoNode,
+    ReturnNode,
+    LabelNode,
+    CasesNode,
+    CaseNode,
+    DefaultCaseNode,
+);
+
 
 define_node!(
     AST;
-    {};
+    {
+        defvars: Vec<DefinedVariable>,
+        defuns: Vec<DefinedFunction>,
+        funcdecls: Vec<UndefinedFunction>,
+        vardecls: Vec<UndefinedVariable>,
+        defstructs: Vec<StructNode>,
+        defunions: Vec<UnionNode>,
+        typedefs: Vec<TypedefNode>,
+        defenums: Vec<EnumNode>,
+        imports: Vec<ImportNode>,
+        constants: Vec<Box<Node>>, // the array lengths, by `ConstantId`
+    };
     self_, {
         format!("<<AST>> ({})\n", self_.location) +
-        "variable: \n" +
-        "function: \n"
+        &format!("variable: {}\n", self_.defvars.len()) +
+        &format!("function: {}\n", self_.defuns.len())
     }
 );
 
+impl AST {
+    /// the AST without any definition.
+    pub fn empty(location: Location) -> AST {
+        AST::new(location, Vec::new(), Vec::new(), Vec::new(), Vec::new(),
+                 Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(),
+                 Vec::new())
+    }
+}
+
 define_node!(
     IntegerLiteralNode;
     {
         type_: IntegerTypeRef,
         value: i64,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<IntegerLiteralNode>> ({})\n", self_.location) +
         &format!("typeNode: {:?}", self_.type_) +
@@ -80,6 +221,9 @@ define_node!(
         type_: BinaryOpType,
         right: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<BinaryOpNode>> ({})\n", self_.location)
     }
@@ -90,6 +234,9 @@ define_node!(
     {
         value: String,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<StringLiteralNode>> ({})\n", self_.location)
     }
@@ -101,6 +248,9 @@ define_node!(
         type_: UnaryOpType,
         node: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<UnaryOpNode>> ({})\n", self_.location)
     }
@@ -111,6 +261,10 @@ define_node!(
     {
         name: String,
     };
+    {
+        entity: Option<EntityId> = None, // by `LocalResolver`
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<VariableNode>> ({})\n", self_.location)
     }
@@ -122,6 +276,10 @@ define_node!(
         type_: PrefixOpType,
         node: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+        amount: usize = 1, // the size pointed, for a pointer
+    };
     self_, {
         format!("<<PrefixOpNode>> ({})\n", self_.location)
     }
@@ -132,6 +290,9 @@ define_node!(
     {
         node: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<DereferenceNode>> ({})\n", self_.location)
     }
@@ -142,6 +303,9 @@ define_node!(
     {
         node: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<AddressNode>> ({})\n", self_.location)
     }
@@ -150,9 +314,13 @@ define_node!(
 define_node!(
     CastNode;
     {
-        type_: Box<TypeRef>,
+        type_: TypeNode,
         node: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+        is_implicit: bool = false, // inserted by `TypeChecker`
+    };
     self_, {
         format!("<<CastNode>> ({})\n", self_.location)
     }
@@ -161,8 +329,11 @@ define_node!(
 define_node!(
     SizeofTypeNode;
     {
-        type_: Box<TypeRef>,
-        size: i64,
+        type_: TypeNode,
+    };
+    {
+        size: Option<usize> = None, // by `SizeofEvaluator`
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
     };
     self_, {
         format!("<<SizeofTypeNode>> ({})\n", self_.location)
@@ -173,10 +344,13 @@ define_node!(
     SizeofExprNode;
     {
         node: Box<Node>,
-        size: i64,
+    };
+    {
+        size: Option<usize> = None, // by `SizeofEvaluator`
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
     };
     self_, {
-        format!("<<SizeofTypeNode>> ({})\n", self_.location)
+        format!("<<SizeofExprNode>> ({})\n", self_.location)
     }
 );
 
@@ -186,6 +360,10 @@ define_node!(
         type_: SuffixOpType,
         expr: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+        amount: usize = 1, // the size pointed, for a pointer
+    };
     self_, {
         format!("<<SuffixOpNode>> ({})\n", self_.location)
     }
@@ -197,6 +375,9 @@ define_node!(
         expr: Box<Node>,
         idx: Box<Node>, // another expr
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<ArefNode>> ({})\n", self_.location)
     }
@@ -206,7 +387,10 @@ define_node!(
     MemberNode;
     {
         expr: Box<Node>,
-        memb: Box<Node>, // another expr
+        memb: String,
+    };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
     };
     self_, {
         format!("<<MemberNode>> ({})\n", self_.location)
@@ -217,7 +401,10 @@ define_node!(
     PtrMemberNode;
     {
         expr: Box<Node>,
-        memb: Box<Node>, // another expr
+        memb: String,
+    };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
     };
     self_, {
         format!("<<PtrMemberNode>> ({})\n", self_.location)
@@ -230,6 +417,9 @@ define_node!(
         expr: Box<Node>,
         args: Vec<Box<Node>>, // another expr
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<FuncallNode>> ({})\n", self_.location)
     }
@@ -241,6 +431,9 @@ define_node!(
         left: Box<Node>,
         right: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<LogicalAndNode>> ({})\n", self_.location)
     }
@@ -252,6 +445,9 @@ define_node!(
         left: Box<Node>,
         right: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<LogicalOrNode>> ({})\n", self_.location)
     }
@@ -264,6 +460,9 @@ define_node!(
         then_clause: Box<Node>,
         else_clause: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<CondExprNode>> ({})\n", self_.location)
     }
@@ -275,6 +474,9 @@ define_node!(
         lhs: Box<Node>,
         rhs: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<AssignNode>> ({})\n", self_.location)
     }
@@ -287,11 +489,38 @@ define_node!(
         type_: OpAssignType,
         rhs: Box<Node>,
     };
+    {
+        expr_type: Option<TypeId> = None, // by `TypeChecker`
+    };
     self_, {
         format!("<<OpAssignNode>> ({})\n", self_.location)
     }
 );
 
+define_node!(
+    BlockNode;
+    {
+        variables: Vec<DefinedVariable>,
+        stmts: Vec<Box<Node>>,
+    };
+    {
+        scope: Option<ScopeId> = None, // by `LocalResolver`
+    };
+    self_, {
+        format!("<<BlockNode>> ({})\n", self_.location)
+    }
+);
+
+define_node!(
+    ExprStmtNode;
+    {
+        expr: Box<Node>,
+    };
+    self_, {
+        format!("<<ExprStmtNode>> ({})\n", self_.location)
+    }
+);
+
 define_node!(
     IfNode;
     {
@@ -414,6 +643,9 @@ define_node!(
         expr: Box<Node>, // should be constant expr
         stmt: Vec<Box<Node>>,
     };
+    {
+        value: Option<i64> = None, // by `ConstantChecker`
+    };
     self_, {
         format!("<<CaseNode>> ({})\n", self_.location)
     }
@@ -481,6 +713,68 @@ pub enum OpAssignType {
     RightShiftAssignment,
 }
 
+impl UnaryOpType {
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            UnaryOpType::Plus => "+",
+            UnaryOpType::Hyphen => "-",
+            UnaryOpType::ExclamationMark => "!",
+            UnaryOpType::Tilde => "~",
+        }
+    }
+}
+
+impl BinaryOpType {
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            BinaryOpType::Multiplication => "*",
+            BinaryOpType::Division => "/",
+            BinaryOpType::Modulo => "%",
+            BinaryOpType::Addition => "+",
+            BinaryOpType::Subtraction => "-",
+            BinaryOpType::LeftShift => "<<",
+            BinaryOpType::RightShift => ">>",
+            BinaryOpType::BitAnd => "&",
+            BinaryOpType::BitOr => "|",
+            BinaryOpType::BitExclusiveOr => "^",
+            BinaryOpType::GreaterThan => ">",
+            BinaryOpType::LessThan => "<",
+            BinaryOpType::DoubleEquals => "==",
+            BinaryOpType::NotEqualTo => "!=",
+            BinaryOpType::LessThanOrEqualTo => "<=",
+            BinaryOpType::GreaterThanOrEqualTo => ">=",
+        }
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        match *self {
+            BinaryOpType::GreaterThan | BinaryOpType::LessThan |
+            BinaryOpType::DoubleEquals | BinaryOpType::NotEqualTo |
+            BinaryOpType::LessThanOrEqualTo |
+            BinaryOpType::GreaterThanOrEqualTo => true,
+            _ => false,
+        }
+    }
+}
+
+impl OpAssignType {
+    /// the binary operator, `+` of `+=`.
+    pub fn binary_op(&self) -> BinaryOpType {
+        match *self {
+            OpAssignType::AddAssignment => BinaryOpType::Addition,
+            OpAssignType::SubtractAssignment => BinaryOpType::Subtraction,
+            OpAssignType::MultiplyAssignment => BinaryOpType::Multiplication,
+            OpAssignType::DivideAssignment => BinaryOpType::Division,
+            OpAssignType::ModuloAssignment => BinaryOpType::Modulo,
+            OpAssignType::AndAssignment => BinaryOpType::BitAnd,
+            OpAssignType::ExclusiveOrAssignment => BinaryOpType::BitExclusiveOr,
+            OpAssignType::OrAssignment => BinaryOpType::BitOr,
+            OpAssignType::LeftShiftAssignment => BinaryOpType::LeftShift,
+            OpAssignType::RightShiftAssignment => BinaryOpType::RightShift,
+        }
+    }
+}
+
 trait UnaryArithmeticOpNode {}
 
 #[derive(Debug, Copy, Clone)]
@@ -495,30 +789,168 @@ pub enum SuffixOpType {
     Decrement,
 }
 
-pub struct Slot {}
-
 trait StmtNode: Node {}
 
-pub struct BlockNode {}
+/// `import stdio;`, with the dotted name as written, or `import c
+/// "mylib.h";` with the path of the C header.
+pub struct ImportNode {
+    pub location: Location,
+    pub kind: ImportKind,
+    pub name: String,
+    pub file: Option<FileId>, // of the header, once it is loaded
+    pub headers: Vec<FileId>, // imported by the header, directly or not
+}
 
-pub struct ExprStmtNode {}
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImportKind {
+    Cb,      // a `.hb` header by the dotted name
+    C,       // a C header by the path
+    Include, // a C header by `#include` in another one
+}
 
-trait TypeDefinition: Node {}
+/// A type written in the source, and the type it is resolved into.
+pub struct TypeNode {
+    pub location: Location,
+    pub type_ref: TypeRef,
+    pub type_: Option<TypeId>,
+}
 
-trait CompositeTypeDefinition: TypeDefinition {}
+/// A member in the definition of a struct or a union.
+pub struct Slot {
+    pub location: Location,
+    pub type_: TypeNode,
+    pub name: String,
+}
 
-pub struct StructNode {}
+pub struct StructNode {
+    pub location: Location,
+    pub name: String,
+    pub members: Vec<Slot>,
+    pub is_incomplete: bool, // declared but not defined, `struct tag;` of a C header
+}
 
-pub struct UnionNode {}
+pub struct UnionNode {
+    pub location: Location,
+    pub name: String,
+    pub members: Vec<Slot>,
+    pub is_incomplete: bool, // declared but not defined, `union tag;` of a C header
+}
 
-trait TypedefNode: TypeDefinition {}
+pub struct TypedefNode {
+    pub location: Location,
+    pub real: TypeNode,
+    pub name: String,
+}
+
+/// `enum name { A, B = 2 };`, where the name is optional.
+pub struct EnumNode {
+    pub location: Location,
+    pub name: Option<String>,
+    pub members: Vec<Enumerator>,
+}
+
+pub struct Enumerator {
+    pub location: Location,
+    pub name: String,
+    pub value: Option<Box<Node>>, // the previous one plus 1 if `None`
+    pub entity: Option<EntityId>, // by `TypeResolver`
+}
+
+/// A variable definition, global or in a block.
+pub struct DefinedVariable {
+    pub location: Location,
+    pub is_private: bool,
+    pub type_: TypeNode,
+    pub name: String,
+    pub initializer: Option<Box<Node>>,
+    pub entity: Option<EntityId>, // by `LocalResolver`
+}
+
+pub struct DefinedFunction {
+    pub location: Location,
+    pub is_private: bool,
+    pub type_: TypeNode, // of the function type
+    pub name: String,
+    pub params: Params,
+    pub body: BlockNode,
+    pub entity: Option<EntityId>, // by `LocalResolver`
+}
+
+/// `extern int printf(char* fmt, ...);`, a function defined elsewhere.
+pub struct UndefinedFunction {
+    pub location: Location,
+    pub type_: TypeNode, // of the function type
+    pub name: String,
+    pub params: Params,
+    pub format: Option<FormatAttribute>,
+    pub entity: Option<EntityId>, // by `LocalResolver`
+}
+
+/// `extern int errno;`, a variable defined elsewhere.
+pub struct UndefinedVariable {
+    pub location: Location,
+    pub type_: TypeNode,
+    pub name: String,
+    pub entity: Option<EntityId>, // by `LocalResolver`
+}
+
+/// `__attribute__((format(printf, 1, 2)))` after a declaration, the
+/// function takes a `printf` format string in the parameter `format_index`
+/// and the arguments it converts from the one at `first_arg`, counting from
+/// 1. The arguments are not checked if `first_arg` is 0.
+pub struct FormatAttribute {
+    pub location: Location,
+    pub format_index: usize,
+    pub first_arg: usize,
+}
+
+pub struct Params {
+    pub location: Location,
+    pub params: Vec<Parameter>,
+    pub is_variadic: bool,
+}
+
+pub struct Parameter {
+    pub location: Location,
+    pub type_: TypeNode,
+    pub name: String,
+    pub entity: Option<EntityId>, // by `LocalResolver`
+}
+
+impl TypeNode {
+    pub fn new(location: Location, type_ref: TypeRef) -> TypeNode {
+        TypeNode {
+            location: location,
+            type_ref: type_ref,
+            type_: None,
+        }
+    }
+
+    /// the resolved type, only after the type resolution.
+    pub fn type_id(&self) -> TypeId {
+        self.type_.expect("the type is not resolved yet")
+    }
+}
+
+impl Params {
+    /// the types of the parameters as written, for the function type.
+    pub fn param_typerefs(&self) -> ParamTypeRefs {
+        ParamTypeRefs {
+            types: self.params.iter()
+                              .map(|param| param.type_.type_ref.clone())
+                              .collect(),
+            is_variadic: self.is_variadic,
+        }
+    }
+}
 
 
 pub mod helper {
     use super::*;
+    use std::char;
 
     pub fn integer_node(location: Location, value: String) -> IntegerLiteralNode {
-        let i: i64 = integer_value(value.clone());
+        let i: i64 = integer_value(&value);
         if value.ends_with("UL") {
             IntegerLiteralNode::new(location, IntegerTypeRef::UnsignedLong, i)
         } else if value.ends_with("L") {
@@ -530,16 +962,71 @@ pub mod helper {
         }
     }
 
-    fn integer_value(val: String) -> i64 {
-        val.replace("U", "")
-            .replace("L", "")
-            .parse::<i64>()
-            .unwrap()
+    // The literal wraps around when it is too large, as the value is
+    // truncated to its type later anyway.
+    fn integer_value(val: &str) -> i64 {
+        let digits = val.trim_end_matches(['U', 'L']);
+        let (digits, radix) = if digits.starts_with("0x") || digits.starts_with("0X") {
+            (&digits[2..], 16)
+        } else if digits.len() > 1 && digits.starts_with("0") {
+            (&digits[1..], 8)
+        } else {
+            (digits, 10)
+        };
+        digits.chars().fold(0u64, |acc, c| {
+            acc.wrapping_mul(radix as u64)
+               .wrapping_add(c.to_digit(radix).unwrap() as u64)
+        }) as i64
     }
 
+    /// the code of a character literal with its quotes, such as `'\n'`.
     pub fn character_code(val: String) -> i64 {
-        assert!(val.len() == 1);
-        val.chars().next().unwrap() as i64
+        let image = &val[1..val.len() - 1];
+        let codes = unescape(image);
+        assert!(codes.len() == 1);
+        codes[0] as i64
     }
-}
 
+    /// the content of a string literal with its quotes.
+    pub fn string_value(val: String) -> String {
+        let image = &val[1..val.len() - 1];
+        unescape(image).into_iter()
+                       .map(|c| char::from_u32(c).unwrap_or('?'))
+                       .collect()
+    }
+
+    fn unescape(image: &str) -> Vec<u32> {
+        let mut codes = Vec::new();
+        let mut chars = image.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                codes.push(c as u32);
+                continue;
+            }
+            let code = match chars.next() {
+                Some(d @ '0'..='7') => {
+                    let mut code = d.to_digit(8).unwrap();
+                    for _ in 0..2 {
+                        match chars.peek().and_then(|c| c.to_digit(8)) {
+                            Some(n) => code = code * 8 + n,
+                            None => break,
+                        }
+                        chars.next();
+                    }
+                    code
+                }
+                Some('n') => '\n' as u32,
+                Some('t') => '\t' as u32,
+                Some('r') => '\r' as u32,
+                Some('b') => 0x08,
+                Some('f') => 0x0c,
+                Some('v') => 0x0b,
+                Some('a') => 0x07,
+                Some(c) => c as u32, // `\\`, `\'`, `\"` and the others
+                None => '\\' as u32,
+            };
+            codes.push(code);
+        }
+        codes
+    }
+}
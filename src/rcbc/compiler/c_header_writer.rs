@@ -0,0 +1,275 @@
+//! The C header of a compilation unit, for `--emit=c-header`.
+//!
+//! It declares the interface of the module for the C code linked with it,
+//! in the declarator syntax of C, such as `int (*f)(int)` for the Cb
+//! `int(int)* f`. The standard headers imported are included as their C
+//! ones, and the others as the C headers of the same path, `mylib/util.h`
+//! of `import mylib.util;`. A C header imported is included as it is.
+
+use super::ast::*;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::entity::EntityTable;
+use super::headers;
+use super::interface::{self, Interface, TypeDefinition};
+
+pub struct CHeaderWriter<'a> {
+    table: &'a TypeTable,
+    entities: &'a EntityTable,
+}
+
+impl<'a> CHeaderWriter<'a> {
+    pub fn new(table: &'a TypeTable, entities: &'a EntityTable) -> CHeaderWriter<'a> {
+        CHeaderWriter {
+            table: table,
+            entities: entities,
+        }
+    }
+
+    /// the header `name` of the interface, such as `foo.h`, guarded by
+    /// `FOO_H`.
+    pub fn write(&self, interface: &Interface, name: &str) -> String {
+        let guard = guard(name);
+        let mut sections = vec![format!("/* {}, generated by rcbc --emit=c-header. */\n\
+                                         #ifndef {}\n#define {}\n", name, guard, guard)];
+
+        if !interface.imports.is_empty() {
+            sections.push(interface.imports.iter()
+                                   .map(|&import| include(import))
+                                   .collect());
+        }
+        sections.push("#ifdef __cplusplus\nextern \"C\" {\n#endif\n".to_string());
+        if !interface.types.is_empty() {
+            sections.push(interface.types.iter()
+                                   .map(|&definition| self.type_definition(definition))
+                                   .collect());
+        }
+        if !interface.variables.is_empty() {
+            sections.push(interface.variables.iter()
+                                   .map(|var| format!("extern {};\n",
+                                                      self.declare(&var.type_.type_ref,
+                                                                   &var.name)))
+                                   .collect());
+        }
+        if !interface.functions.is_empty() {
+            sections.push(interface.functions.iter()
+                                   .map(|func| self.function(func))
+                                   .collect());
+        }
+        sections.push("#ifdef __cplusplus\n}\n#endif\n".to_string());
+        sections.push(format!("#endif /* {} */\n", guard));
+        sections.join("\n")
+    }
+
+    fn type_definition(&self, definition: TypeDefinition) -> String {
+        match definition {
+            TypeDefinition::Struct(node) =>
+                format!("struct {} {{\n{}}};\n", node.name, self.members(&node.members)),
+            TypeDefinition::Union(node) =>
+                format!("union {} {{\n{}}};\n", node.name, self.members(&node.members)),
+            TypeDefinition::Typedef(node) =>
+                format!("typedef {};\n", self.declare(&node.real.type_ref, &node.name)),
+            TypeDefinition::Enum(node) =>
+                format!("enum {} {{ {} }};\n", node.name.as_ref().map_or("", |n| n),
+                        interface::enumerators(node, self.entities)),
+        }
+    }
+
+    fn members(&self, members: &[Slot]) -> String {
+        members.iter()
+               .map(|member| format!("    {};\n", self.declare(&member.type_.type_ref,
+                                                               &member.name)))
+               .collect()
+    }
+
+    fn function(&self, func: &DefinedFunction) -> String {
+        let mut params: Vec<String> = func.params.params.iter()
+                                          .map(|param| self.declare(&param.type_.type_ref,
+                                                                    &param.name))
+                                          .collect();
+        if func.params.is_variadic {
+            params.push("...".to_string());
+        }
+        if params.is_empty() {
+            params.push("void".to_string());
+        }
+        let declarator = format!("{}({})", func.name, params.join(", "));
+        match func.type_.type_ref {
+            TypeRef::Function(ref function) =>
+                format!("{};\n", self.declare(&function.return_type, &declarator)),
+            ref typeref => format!("{};\n", self.declare(typeref, &declarator)),
+        }
+    }
+
+    // the type without a name, such as `int *`.
+    fn type_name(&self, typeref: &TypeRef) -> String {
+        self.declare(typeref, "")
+    }
+
+    // the C declaration of `name` with the type, as `TypeTable::declare` but
+    // with the typedef and the enum names as written.
+    fn declare(&self, typeref: &TypeRef, name: &str) -> String {
+        let declaration = match *typeref {
+            TypeRef::Void => format!("void {}", name),
+            TypeRef::Integer(integer) => format!("{} {}", integer.name(), name),
+            TypeRef::Struct(ref struct_) => format!("struct {} {}", struct_.name, name),
+            TypeRef::Union(ref union) => format!("union {} {}", union.name, name),
+            TypeRef::Enum(ref enum_) => format!("enum {} {}", enum_.name, name),
+            TypeRef::User(ref user) => format!("{} {}", user.name, name),
+            TypeRef::Const(ref const_) => match *const_.base_type {
+                // `int *const p`, the pointer itself is const
+                TypeRef::Pointer(_) =>
+                    return self.declare(&const_.base_type, &format!("const {}", name)),
+                // a const array is an array of the const elements in C
+                TypeRef::Array(ref array) => {
+                    let elements = TypeRef::const_((*array.base_type).clone());
+                    return self.declare(&TypeRef::array(elements, array.length), name);
+                }
+                ref base_type => format!("const {}", self.declare(base_type, name)),
+            },
+            TypeRef::Pointer(ref pointer) => {
+                let declarator = match *unqualified(&pointer.base_type) {
+                    TypeRef::Array(_) | TypeRef::Function(_) => format!("(*{})", name),
+                    _ => format!("*{}", name),
+                };
+                return self.declare(&pointer.base_type, &declarator);
+            }
+            TypeRef::Array(ref array) => {
+                let declarator = match interface::array_length(array, self.table) {
+                    Some(length) => format!("{}[{}]", name, length),
+                    None => format!("{}[]", name),
+                };
+                return self.declare(&array.base_type, &declarator);
+            }
+            TypeRef::Function(ref function) => {
+                let mut params: Vec<String> = function.params.types.iter()
+                                                      .map(|param| self.type_name(param))
+                                                      .collect();
+                if function.params.is_variadic {
+                    params.push("...".to_string());
+                }
+                if params.is_empty() {
+                    params.push("void".to_string());
+                }
+                let declarator = format!("{}({})", name, params.join(", "));
+                return self.declare(&function.return_type, &declarator);
+            }
+        };
+        declaration.trim_end().to_string()
+    }
+}
+
+// the type under the `const`s.
+fn unqualified(typeref: &TypeRef) -> &TypeRef {
+    match *typeref {
+        TypeRef::Const(ref const_) => unqualified(&const_.base_type),
+        _ => typeref,
+    }
+}
+
+// the include guard of the header, as `FOO_UTIL_H` of `foo_util.h`.
+fn guard(name: &str) -> String {
+    let guard: String = name.chars()
+                            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() }
+                                     else { '_' })
+                            .collect();
+    match guard.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", guard),
+        _ => guard,
+    }
+}
+
+// the `#include` of the C header for the import.
+fn include(import: &ImportNode) -> String {
+    if import.kind == ImportKind::C {
+        return format!("#include \"{}\"\n", import.name);
+    }
+    match headers::standard_c_header(&import.name) {
+        Some(c_header) => format!("#include <{}>\n", c_header),
+        None => format!("#include \"{}.h\"\n", import.name.replace('.', "/")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Emit;
+    use super::super::testing::{emit, MODULE};
+
+    #[test]
+    fn the_c_header_of_a_module() {
+        // the arrays and the functions in the declarators of C
+        assert_eq!(emit(MODULE, Emit::CHeader).output(), r#"/* test.h, generated by rcbc --emit=c-header. */
+#ifndef TEST_H
+#define TEST_H
+
+#include <stdio.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct point point_t;
+struct point {
+    int x;
+    int y;
+};
+union value {
+    int i;
+    char *s;
+};
+
+extern int count;
+extern const char *name;
+extern point_t origin;
+extern int table[4];
+extern int *last;
+
+int area(point_t *p, int scale[], int f(int));
+int show(FILE *out, union value v);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TEST_H */
+"#);
+    }
+
+    #[test]
+    fn structs_held_by_value_are_defined_first() {
+        // `struct b` is reached first, but it holds a `struct a`, which only
+        // points to it
+        let src = "struct a { struct b* next; int x; };\n\
+                   struct b { struct a inner; int y; };\n\
+                   struct c { struct b[2] pair; };\n\
+                   struct b* first(struct c* list) { return list->pair[0].inner.next; }\n";
+        assert_eq!(emit(src, Emit::CHeader).output(), r#"/* test.h, generated by rcbc --emit=c-header. */
+#ifndef TEST_H
+#define TEST_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+struct a {
+    struct b *next;
+    int x;
+};
+struct b {
+    struct a inner;
+    int y;
+};
+struct c {
+    struct b pair[2];
+};
+
+struct b *first(struct c *list);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TEST_H */
+"#);
+    }
+}
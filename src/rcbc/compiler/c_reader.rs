@@ -0,0 +1,1427 @@
+//! The reader of the C headers imported by `import c "mylib.h";`.
+//!
+//! It reads the declarations of C which Cb can represent: the prototypes,
+//! the `extern` variables, the structs, the unions, the enums, the typedefs,
+//! and the `#define`s of integer constants, which become enumerators. The
+//! preprocessor is a small one: the conditionals are evaluated, the macros
+//! without parameters are expanded, and an `#include` imports the header it
+//! names. Anything else, as `double`, a bit-field or a function-like macro,
+//! is skipped with a warning, and the rest of the header is read still.
+
+use std::collections::{HashMap, HashSet};
+use std::result;
+use super::ast::*;
+use super::location::Location;
+use super::source_map::FileId;
+use super::type_::*;
+use super::diagnostic::{Diagnostic, DiagnosticHandler};
+use super::warning::Warning;
+
+/// What the C headers of a compilation share: the macros, the integer
+/// constants, and the tags of the structs and unions they refer to.
+pub struct CContext {
+    macros: HashMap<String, Macro>,
+    constants: HashMap<String, i64>, // the integer macros and the enumerators
+    tags: Vec<(Tag, Location)>, // referred to, maybe never defined
+    anonymous: usize, // the structs, unions and enums named so far
+}
+
+/// A C header preprocessed, whose `#include`s are loaded before its
+/// declarations are read.
+pub struct CHeader {
+    tokens: Vec<CToken>,
+    pub includes: Vec<ImportNode>,
+    defines: Vec<(String, Location, i64)>, // the integer constants in order
+}
+
+pub struct CReader<'a> {
+    tokens: Vec<CToken>,
+    pos: usize,
+    defines: Vec<(String, Location, i64)>,
+    handler: &'a mut DiagnosticHandler,
+    context: &'a mut CContext,
+    typedefs: HashSet<String>,
+    ast: AST,
+}
+
+enum Macro {
+    Object(Vec<CToken>), // expanded where it is used
+    Function,            // never expanded
+}
+
+#[derive(PartialEq, Eq)]
+enum Tag {
+    Struct(String),
+    Union(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Identifier,
+    Number,
+    String,
+    Character,
+    Punctuator,
+}
+
+#[derive(Debug, Clone)]
+struct CToken {
+    kind: Kind,
+    text: String,
+    location: Location,
+    is_line_start: bool, // the first token of a line, where a directive may be
+}
+
+// the longest first
+const PUNCTUATORS: &'static [&'static str] = &[
+    "...", "<<=", ">>=", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=",
+    "&&", "||", "##", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+];
+
+// the words which can not be the name in a declarator
+const KEYWORDS: &'static [&'static str] = &[
+    "void", "char", "short", "int", "long", "float", "double", "signed", "unsigned",
+    "_Bool", "_Complex", "struct", "union", "enum", "const", "volatile", "restrict",
+    "typedef", "extern", "static", "inline", "register", "auto", "__signed__",
+    "__const", "__const__", "__restrict", "__restrict__", "__volatile__",
+    "__inline", "__inline__", "__extension__", "_Noreturn",
+];
+
+// the binary operators of the constant expressions, by precedence
+const OPERATORS: &'static [&'static [&'static str]] = &[
+    &["||"], &["&&"], &["|"], &["^"], &["&"], &["==", "!="],
+    &["<", ">", "<=", ">="], &["<<", ">>"], &["+", "-"], &["*", "/", "%"],
+];
+
+impl CContext {
+    pub fn new() -> CContext {
+        CContext {
+            macros: HashMap::new(),
+            constants: HashMap::new(),
+            tags: Vec::new(),
+            anonymous: 0,
+        }
+    }
+
+    /// Declare the structs and unions which the headers refer to but do not
+    /// define, as `FILE` of `typedef struct _IO_FILE FILE;`. They are
+    /// incomplete, so can only be used through pointers.
+    pub fn opaque_types(&self, ast: &mut AST) {
+        for &(ref tag, location) in &self.tags {
+            match *tag {
+                Tag::Struct(ref name) => if !ast.defstructs.iter().any(|s| &s.name == name) {
+                    ast.defstructs.push(StructNode {
+                        location: location,
+                        name: name.clone(),
+                        members: Vec::new(),
+                        is_incomplete: true,
+                    });
+                },
+                Tag::Union(ref name) => if !ast.defunions.iter().any(|u| &u.name == name) {
+                    ast.defunions.push(UnionNode {
+                        location: location,
+                        name: name.clone(),
+                        members: Vec::new(),
+                        is_incomplete: true,
+                    });
+                },
+            }
+        }
+    }
+}
+
+// ---------- Preprocessor ----------
+
+struct Preprocessor<'a> {
+    handler: &'a mut DiagnosticHandler,
+    context: &'a mut CContext,
+    conditions: Vec<Condition>, // of the `#if`s the line is in
+    tokens: Vec<CToken>,
+    includes: Vec<ImportNode>,
+    defines: Vec<(String, Location, i64)>,
+}
+
+struct Condition {
+    is_active: bool,       // the lines are read
+    is_taken: bool,        // one of the branches is active
+    is_outer_active: bool, // the `#if` itself is read
+}
+
+/// Preprocess the C header `src`, the macros it defines are added to the
+/// context.
+pub fn preprocess(src: &str, file: FileId, handler: &mut DiagnosticHandler,
+                  context: &mut CContext) -> CHeader {
+    let tokens = lex(src, file);
+    let mut preprocessor = Preprocessor {
+        handler: handler,
+        context: context,
+        conditions: Vec::new(),
+        tokens: Vec::new(),
+        includes: Vec::new(),
+        defines: Vec::new(),
+    };
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].is_line_start && tokens[i].text == "#" {
+            let end = tokens[i + 1..].iter()
+                                     .position(|token| token.is_line_start)
+                                     .map_or(tokens.len(), |n| i + 1 + n);
+            preprocessor.directive(&tokens[i], &tokens[i + 1..end]);
+            i = end;
+        } else {
+            if preprocessor.is_active() {
+                expand(&preprocessor.context.macros, &tokens[i], &mut Vec::new(),
+                       &mut preprocessor.tokens);
+            }
+            i += 1;
+        }
+    }
+    CHeader {
+        tokens: preprocessor.tokens,
+        includes: preprocessor.includes,
+        defines: preprocessor.defines,
+    }
+}
+
+impl<'a> Preprocessor<'a> {
+    fn is_active(&self) -> bool {
+        self.conditions.last().is_none_or(|c| c.is_active)
+    }
+
+    fn directive(&mut self, hash: &CToken, line: &[CToken]) {
+        let name = match line.first() {
+            Some(name) => name.text.as_str(),
+            None => return, // `#` alone
+        };
+        let args = &line[1..];
+        match name {
+            "ifdef" | "ifndef" => {
+                let is_defined = args.first()
+                                     .is_some_and(|m| self.context.macros.contains_key(&m.text));
+                self.push_condition(is_defined == (name == "ifdef"));
+            }
+            "if" => {
+                let value = self.is_active() && self.condition(hash, args);
+                self.push_condition(value);
+            }
+            "elif" => {
+                let is_read = self.conditions.last()
+                                  .is_some_and(|c| c.is_outer_active && !c.is_taken);
+                let value = is_read && self.condition(hash, args);
+                if let Some(condition) = self.conditions.last_mut() {
+                    condition.is_active = value;
+                    condition.is_taken |= value;
+                }
+            }
+            "else" => if let Some(condition) = self.conditions.last_mut() {
+                condition.is_active = condition.is_outer_active && !condition.is_taken;
+                condition.is_taken = true;
+            },
+            "endif" => {
+                self.conditions.pop();
+            }
+            _ if !self.is_active() => {}
+            "define" => self.define(args),
+            "undef" => if let Some(name) = args.first() {
+                self.context.macros.remove(&name.text);
+            },
+            "include" => self.include(hash, args),
+            _ => {} // `#pragma`, `#error` and the like
+        }
+    }
+
+    fn push_condition(&mut self, value: bool) {
+        let is_outer_active = self.is_active();
+        self.conditions.push(Condition {
+            is_active: is_outer_active && value,
+            is_taken: value,
+            is_outer_active: is_outer_active,
+        });
+    }
+
+    // the value of `#if`, the identifiers not defined are 0.
+    fn condition(&mut self, hash: &CToken, args: &[CToken]) -> bool {
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            if args[i].text == "defined" {
+                let is_parenthesized = args.get(i + 1).is_some_and(|t| t.text == "(");
+                let (name, next) = if is_parenthesized { (args.get(i + 2), i + 4) }
+                                   else { (args.get(i + 1), i + 2) };
+                let is_defined = name.is_some_and(|n| self.context.macros.contains_key(&n.text));
+                tokens.push(CToken {
+                    kind: Kind::Number,
+                    text: if is_defined { "1" } else { "0" }.to_string(),
+                    location: args[i].location,
+                    is_line_start: false,
+                });
+                i = next;
+            } else {
+                expand(&self.context.macros, &args[i], &mut Vec::new(), &mut tokens);
+                i += 1;
+            }
+        }
+        match evaluate(&tokens, None) {
+            Some(value) => value != 0,
+            None => {
+                let location = args.last().map_or(hash.location,
+                                                  |last| Location::range(hash.location,
+                                                                         last.location));
+                warn(self.handler, location,
+                     "can not evaluate the condition, which is taken as false");
+                false
+            }
+        }
+    }
+
+    fn define(&mut self, args: &[CToken]) {
+        let name = match args.first() {
+            Some(name) if name.kind == Kind::Identifier => name,
+            _ => return,
+        };
+        let body = &args[1..];
+        // `#define MAX(a, b) ...`, the `(` right after the name
+        if body.first().is_some_and(|t| t.text == "(" &&
+                                          t.location.begin == name.location.end) {
+            self.context.macros.insert(name.text.clone(), Macro::Function);
+            warn(self.handler, name.location,
+                 format!("the function-like macro `{}` is not imported", name.text));
+            return;
+        }
+        self.context.macros.insert(name.text.clone(), Macro::Object(body.to_vec()));
+        if body.is_empty() {
+            return;
+        }
+
+        let mut tokens = Vec::new();
+        for token in body {
+            expand(&self.context.macros, token, &mut vec![name.text.clone()], &mut tokens);
+        }
+        match evaluate(&tokens, Some(&self.context.constants)) {
+            Some(value) => {
+                // a header may define the same constant as another
+                let is_new = self.context.constants.insert(name.text.clone(), value).is_none();
+                if is_new {
+                    self.defines.push((name.text.clone(), name.location, value));
+                }
+            }
+            // a string or a floating constant, the others may be a part of
+            // the declarations, as a type or an attribute
+            None if body.len() == 1 && body[0].kind != Kind::Identifier => {
+                warn(self.handler, name.location,
+                     format!("the macro `{}` is not an integer constant, so it is \
+                              not imported", name.text));
+            }
+            None => {}
+        }
+    }
+
+    // `#include "mylib/util.h"` or `#include <stdio.h>`
+    fn include(&mut self, hash: &CToken, args: &[CToken]) {
+        let location = args.last().map_or(hash.location,
+                                          |last| Location::range(hash.location, last.location));
+        let name = match args.first() {
+            Some(token) if token.kind == Kind::String && args.len() == 1 =>
+                token.text[1..token.text.len() - 1].to_string(),
+            Some(token) if token.text == "<" && args.last().is_some_and(|t| t.text == ">") =>
+                args[1..args.len() - 1].iter().map(|t| t.text.as_str()).collect(),
+            _ => {
+                warn(self.handler, location,
+                     "the `#include` of a macro is not supported, so it is skipped");
+                return;
+            }
+        };
+        self.includes.push(ImportNode {
+            location: location,
+            kind: ImportKind::Include,
+            name: name,
+            file: None,
+            headers: Vec::new(),
+        });
+    }
+}
+
+// expand the macros in the token to `tokens`, except the ones `expanding`
+// already.
+fn expand(macros: &HashMap<String, Macro>, token: &CToken, expanding: &mut Vec<String>,
+          tokens: &mut Vec<CToken>) {
+    if token.kind == Kind::Identifier && !expanding.contains(&token.text) {
+        if let Some(Macro::Object(body)) = macros.get(&token.text) {
+            expanding.push(token.text.clone());
+            for t in body {
+                // at where the macro is used
+                let t = CToken {
+                    location: token.location,
+                    is_line_start: false,
+                    ..t.clone()
+                };
+                expand(macros, &t, expanding, tokens);
+            }
+            expanding.pop();
+            return;
+        }
+    }
+    tokens.push(token.clone());
+}
+
+fn lex(src: &str, file: FileId) -> Vec<CToken> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut is_line_start = true;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c == b'\n' {
+            is_line_start = true;
+            i += 1;
+            continue;
+        }
+        // the lines joined by `\`
+        if src[i..].starts_with("\\\n") || src[i..].starts_with("\\\r\n") {
+            i += if bytes[i + 1] == b'\n' { 2 } else { 3 };
+            continue;
+        }
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if src[i..].starts_with("//") {
+            i = src[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            continue;
+        }
+        if src[i..].starts_with("/*") {
+            i = src[i + 2..].find("*/").map_or(bytes.len(), |n| i + 2 + n + 2);
+            continue;
+        }
+
+        let begin = i;
+        let kind = if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            Kind::Identifier
+        } else if c.is_ascii_digit() ||
+                  (c == b'.' && bytes.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+            // a preprocessing number, as `0x1fUL` or `1.5e+3`
+            i += 1;
+            while i < bytes.len() {
+                let d = bytes[i];
+                if d.is_ascii_alphanumeric() || d == b'_' || d == b'.' ||
+                   ((d == b'+' || d == b'-') && b"eEpP".contains(&bytes[i - 1])) {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            Kind::Number
+        } else if c == b'"' || c == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != c && bytes[i] != b'\n' {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(bytes.len());
+            while !src.is_char_boundary(i) {
+                i -= 1;
+            }
+            if c == b'"' { Kind::String } else { Kind::Character }
+        } else {
+            i += PUNCTUATORS.iter()
+                            .find(|p| src[i..].starts_with(*p))
+                            .map_or(src[i..].chars().next().map_or(1, |c| c.len_utf8()),
+                                    |p| p.len());
+            Kind::Punctuator
+        };
+        tokens.push(CToken {
+            kind: kind,
+            text: src[begin..i].to_string(),
+            location: Location::new(file, begin, i),
+            is_line_start: is_line_start,
+        });
+        is_line_start = false;
+    }
+    tokens
+}
+
+// ---------- Constant expressions ----------
+
+struct Evaluator<'a> {
+    tokens: &'a [CToken],
+    pos: usize,
+    constants: Option<&'a HashMap<String, i64>>, // `None` in `#if`
+}
+
+// the value of the integer constant expression, `None` if it is not one.
+fn evaluate(tokens: &[CToken], constants: Option<&HashMap<String, i64>>) -> Option<i64> {
+    let mut evaluator = Evaluator {
+        tokens: tokens,
+        pos: 0,
+        constants: constants,
+    };
+    let value = evaluator.conditional()?;
+    if evaluator.pos == tokens.len() { Some(value) } else { None }
+}
+
+impl<'a> Evaluator<'a> {
+    fn accept(&mut self, text: &str) -> bool {
+        if self.tokens.get(self.pos).is_some_and(|t| t.text == text) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn conditional(&mut self) -> Option<i64> {
+        let condition = self.binary(0)?;
+        if !self.accept("?") {
+            return Some(condition);
+        }
+        let then = self.conditional()?;
+        if !self.accept(":") {
+            return None;
+        }
+        let else_ = self.conditional()?;
+        Some(if condition != 0 { then } else { else_ })
+    }
+
+    fn binary(&mut self, level: usize) -> Option<i64> {
+        if level == OPERATORS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        loop {
+            let op = match self.tokens.get(self.pos) {
+                Some(token) if OPERATORS[level].contains(&token.text.as_str()) => token,
+                _ => return Some(left),
+            };
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            left = match op.text.as_str() {
+                "||" => (left != 0 || right != 0) as i64,
+                "&&" => (left != 0 && right != 0) as i64,
+                "|" => left | right,
+                "^" => left ^ right,
+                "&" => left & right,
+                "==" => (left == right) as i64,
+                "!=" => (left != right) as i64,
+                "<" => (left < right) as i64,
+                ">" => (left > right) as i64,
+                "<=" => (left <= right) as i64,
+                ">=" => (left >= right) as i64,
+                "<<" => left.checked_shl(right as u32)?,
+                ">>" => left.checked_shr(right as u32)?,
+                "+" => left.wrapping_add(right),
+                "-" => left.wrapping_sub(right),
+                "*" => left.wrapping_mul(right),
+                "/" => left.checked_div(right)?,
+                _ => left.checked_rem(right)?,
+            };
+        }
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        match (token.kind, token.text.as_str()) {
+            (Kind::Punctuator, "-") => self.unary().map(|v| v.wrapping_neg()),
+            (Kind::Punctuator, "+") => self.unary(),
+            (Kind::Punctuator, "~") => self.unary().map(|v| !v),
+            (Kind::Punctuator, "!") => self.unary().map(|v| (v == 0) as i64),
+            (Kind::Punctuator, "(") => {
+                let value = self.conditional()?;
+                if self.accept(")") { Some(value) } else { None }
+            }
+            (Kind::Number, text) => integer_value(text),
+            (Kind::Character, text) => character_value(text),
+            (Kind::Identifier, name) => match self.constants {
+                Some(constants) => constants.get(name).cloned(),
+                None => Some(0),
+            },
+            _ => None,
+        }
+    }
+}
+
+// `0x1f`, `017` or `42`, with the suffixes as `UL`.
+fn integer_value(text: &str) -> Option<i64> {
+    let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    let value = if digits.starts_with("0x") || digits.starts_with("0X") {
+        u64::from_str_radix(&digits[2..], 16)
+    } else if digits.starts_with('0') && digits.len() > 1 {
+        u64::from_str_radix(&digits[1..], 8)
+    } else {
+        digits.parse::<u64>()
+    };
+    value.ok().map(|v| v as i64)
+}
+
+// `'a'` or `'\n'`
+fn character_value(text: &str) -> Option<i64> {
+    let image = text.get(1..text.len() - 1)?;
+    let mut chars = image.chars();
+    let value = match chars.next()? {
+        '\\' => match chars.next()? {
+            'n' => 10,
+            't' => 9,
+            'r' => 13,
+            'a' => 7,
+            'b' => 8,
+            'f' => 12,
+            'v' => 11,
+            'x' => return i64::from_str_radix(chars.as_str(), 16).ok(),
+            d @ '0'..='7' => {
+                let digits = format!("{}{}", d, chars.as_str());
+                return i64::from_str_radix(&digits, 8).ok().filter(|_| digits.len() <= 3);
+            }
+            c => c as i64,
+        },
+        c => c as i64,
+    };
+    if chars.next().is_none() { Some(value) } else { None }
+}
+
+// ---------- Declarations ----------
+
+// why a declaration is skipped
+struct Skip {
+    location: Location,
+    reason: String,
+}
+
+type Read<T> = result::Result<T, Skip>;
+
+fn skip<T, S: Into<String>>(location: Location, reason: S) -> Read<T> {
+    Err(Skip {
+        location: location,
+        reason: reason.into(),
+    })
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Storage {
+    Typedef,
+    Extern,
+    Static,
+}
+
+struct Specifiers {
+    location: Location,
+    storage: Option<Storage>,
+    type_: TypeRef,
+}
+
+struct Declarator {
+    name: Option<(String, Location)>,
+    type_: TypeRef,
+    params: Option<Params>, // of the function the name is declared
+    end: Location,
+}
+
+enum Suffix {
+    Array(Option<ArrayLength>),
+    Function(Params),
+}
+
+impl<'a> CReader<'a> {
+    pub fn new(header: CHeader, handler: &'a mut DiagnosticHandler,
+               context: &'a mut CContext) -> CReader<'a> {
+        CReader {
+            tokens: header.tokens,
+            pos: 0,
+            defines: header.defines,
+            handler: handler,
+            context: context,
+            typedefs: HashSet::new(),
+            ast: AST::empty(Location::default()),
+        }
+    }
+
+    /// Read the declarations of the header after the ones `imported` before
+    /// it, the declarations skipped are warned of.
+    pub fn read(mut self, imported: AST) -> AST {
+        self.typedefs = imported.typedefs.iter().map(|t| t.name.clone()).collect();
+        self.ast = imported;
+        // `#define BUFSIZE 1024` as `enum { BUFSIZE = 1024 };`
+        for (name, location, value) in self.defines.drain(..).collect::<Vec<_>>() {
+            let enumerator = enumerator(name, location, value);
+            self.ast.defenums.push(EnumNode {
+                location: location,
+                name: None,
+                members: vec![enumerator],
+            });
+        }
+
+        while self.pos < self.tokens.len() {
+            let begin = self.pos;
+            if let Err(skipped) = self.external_declaration() {
+                warn(self.handler, skipped.location,
+                     format!("{}, so the declaration is skipped", skipped.reason));
+                self.pos = begin;
+                self.recover();
+            }
+        }
+        self.ast
+    }
+
+    fn peek(&self) -> Option<&CToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_text(&self, n: usize) -> &str {
+        self.tokens.get(self.pos + n).map_or("", |t| t.text.as_str())
+    }
+
+    fn is(&self, text: &str) -> bool {
+        self.peek_text(0) == text
+    }
+
+    fn accept(&mut self, text: &str) -> bool {
+        if self.is(text) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Read<Location> {
+        if self.is(text) {
+            self.pos += 1;
+            Ok(self.tokens[self.pos - 1].location)
+        } else {
+            skip(self.location(), format!("`{}` is expected", text))
+        }
+    }
+
+    // of the next token, or the end of the last one.
+    fn location(&self) -> Location {
+        match self.peek() {
+            Some(token) => token.location,
+            None => self.previous_location(),
+        }
+    }
+
+    fn previous_location(&self) -> Location {
+        let previous = self.pos.min(self.tokens.len()).checked_sub(1);
+        previous.map(|i| self.tokens[i].location).unwrap_or_default()
+    }
+
+    // the index of the bracket closing the one at `open`.
+    fn closing(&self, open: usize) -> usize {
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate().skip(open) {
+            match token.text.as_str() {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return i;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.tokens.len()
+    }
+
+    // skip to the end of the declaration, a `;` or the `}` of a function
+    // body.
+    fn recover(&mut self) {
+        let mut depth = 0;
+        let mut is_body = false;
+        while self.pos < self.tokens.len() {
+            self.pos += 1;
+            match self.tokens[self.pos - 1].text.as_str() {
+                "{" => {
+                    if depth == 0 {
+                        is_body = self.pos >= 2 && self.tokens[self.pos - 2].text == ")";
+                    }
+                    depth += 1;
+                }
+                "}" if depth == 0 => return,
+                "}" => {
+                    depth -= 1;
+                    if depth == 0 && is_body {
+                        return;
+                    }
+                }
+                ";" if depth == 0 => return,
+                _ => {}
+            }
+        }
+    }
+
+    fn external_declaration(&mut self) -> Read<()> {
+        // `extern "C" {` of the headers for C++, and its `}`
+        if self.is("extern") && self.tokens.get(self.pos + 1)
+                                           .is_some_and(|t| t.kind == Kind::String) {
+            self.pos += 2;
+            self.accept("{");
+            return Ok(());
+        }
+        if self.accept(";") || self.accept("}") {
+            return Ok(());
+        }
+
+        let specifiers = self.specifiers()?;
+        if self.accept(";") {
+            return Ok(()); // only the tag, as `struct point { ... };`
+        }
+        loop {
+            let declarator = self.declarator(specifiers.type_.clone())?;
+            let format = self.attributes();
+            let (name, location) = match declarator.name.clone() {
+                Some(name) => name,
+                None => return skip(self.location(), "a name is expected"),
+            };
+            if self.is("{") {
+                return skip(location, format!("the function `{}` is defined in the header",
+                                              name));
+            }
+            if self.is("=") {
+                return skip(self.location(),
+                            format!("`{}` is initialized in the header", name));
+            }
+            self.declare(&specifiers, declarator, name, location, format)?;
+            if !self.accept(",") {
+                break;
+            }
+        }
+        self.expect(";")?;
+        Ok(())
+    }
+
+    fn declare(&mut self, specifiers: &Specifiers, declarator: Declarator, name: String,
+               location: Location, format: Option<FormatAttribute>) -> Read<()> {
+        match specifiers.storage {
+            Some(Storage::Typedef) => {
+                if let Some(typedef) = self.ast.typedefs.iter().find(|t| t.name == name) {
+                    // repeated, as C11 allows
+                    if typedef.real.type_ref == declarator.type_ {
+                        return Ok(());
+                    }
+                    return skip(location, format!("`{}` is defined already as another type",
+                                                  name));
+                }
+                self.typedefs.insert(name.clone());
+                self.ast.typedefs.push(TypedefNode {
+                    location: location,
+                    real: TypeNode::new(specifiers.location, declarator.type_),
+                    name: name,
+                });
+                return Ok(());
+            }
+            Some(Storage::Static) =>
+                return skip(location, format!("`{}` is `static`, which is not defined out \
+                                               of the header", name)),
+            _ => {}
+        }
+
+        let location = Location::range(specifiers.location, declarator.end);
+        if let TypeRef::Function(_) = declarator.type_ {
+            if self.ast.funcdecls.iter().any(|f| f.name == name) {
+                return Ok(()); // declared again
+            }
+            let params = match declarator.params {
+                Some(params) => params,
+                None => unnamed_params(&declarator.type_, location),
+            };
+            self.ast.funcdecls.push(UndefinedFunction {
+                location: location,
+                type_: TypeNode::new(specifiers.location, declarator.type_),
+                name: name,
+                params: params,
+                format: format,
+                entity: None,
+            });
+        } else {
+            if self.ast.vardecls.iter().any(|v| v.name == name) {
+                return Ok(());
+            }
+            self.ast.vardecls.push(UndefinedVariable {
+                location: location,
+                type_: TypeNode::new(specifiers.location, declarator.type_),
+                name: name,
+                entity: None,
+            });
+        }
+        Ok(())
+    }
+
+    fn specifiers(&mut self) -> Read<Specifiers> {
+        let begin = self.location();
+        let mut storage = None;
+        let mut base = None;
+        let mut words = Vec::new(); // of the integer types and `void`
+        let mut is_const = false;
+        while let Some(token) = self.peek().cloned() {
+            if token.kind != Kind::Identifier {
+                break;
+            }
+            let is_first = base.is_none() && words.is_empty();
+            match token.text.as_str() {
+                "typedef" | "extern" | "static" => {
+                    if storage.is_some() {
+                        return skip(token.location, "more than one storage class is given");
+                    }
+                    storage = Some(match token.text.as_str() {
+                        "typedef" => Storage::Typedef,
+                        "extern" => Storage::Extern,
+                        _ => Storage::Static,
+                    });
+                }
+                "const" | "__const" | "__const__" => is_const = true,
+                "volatile" | "__volatile__" | "inline" | "__inline" | "__inline__" |
+                "_Noreturn" | "register" | "auto" | "__extension__" => {}
+                "__attribute__" | "__attribute" | "__declspec" => {
+                    self.attributes();
+                    continue;
+                }
+                "signed" | "__signed__" | "unsigned" | "char" | "short" | "int" | "long" |
+                "void" if base.is_none() => words.push(token.text.trim_matches('_').to_string()),
+                "float" | "double" | "_Bool" | "_Complex" | "__int128" =>
+                    return skip(token.location, format!("`{}` is not a type of Cb",
+                                                        token.text)),
+                "struct" | "union" if is_first => {
+                    base = Some(self.record()?);
+                    continue;
+                }
+                "enum" if is_first => {
+                    base = Some(self.enum_()?);
+                    continue;
+                }
+                name if is_first && self.typedefs.contains(name) =>
+                    base = Some(TypeRef::User(UserTypeRef { name: name.to_string() })),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        let type_ = match base {
+            Some(base) => base,
+            None => self.integer_type(&words, begin)?,
+        };
+        Ok(Specifiers {
+            location: Location::range(begin, self.previous_location()),
+            storage: storage,
+            type_: if is_const { TypeRef::const_(type_) } else { type_ },
+        })
+    }
+
+    // the type of the words, as `unsigned long` of `long unsigned int`.
+    fn integer_type(&self, words: &[String], location: Location) -> Read<TypeRef> {
+        if words.is_empty() {
+            return match self.peek() {
+                Some(token) if token.kind == Kind::Identifier =>
+                    skip(token.location, format!("the type `{}` is not known", token.text)),
+                _ => skip(location, "a type is expected"),
+            };
+        }
+        let count = |word: &str| words.iter().filter(|w| *w == word).count();
+        if count("void") > 0 {
+            return if words.len() == 1 { Ok(TypeRef::Void) }
+                   else { skip(location, "`void` is combined with another type") };
+        }
+        if count("long") > 1 {
+            return skip(location, "`long long` is not a type of Cb");
+        }
+        if count("signed") + count("unsigned") > 1 || count("int") > 1 ||
+           count("char") + count("short") + count("long") > 1 ||
+           (count("char") > 0 && count("int") > 0) {
+            return skip(location, "the type is not a valid one");
+        }
+        let integer = if count("char") > 0 { IntegerTypeRef::Char }
+                      else if count("short") > 0 { IntegerTypeRef::Short }
+                      else if count("long") > 0 { IntegerTypeRef::Long }
+                      else { IntegerTypeRef::Int };
+        Ok(TypeRef::Integer(if count("unsigned") > 0 { integer.to_unsigned() } else { integer }))
+    }
+
+    // `struct point { int x; int y; }` or `union value`
+    fn record(&mut self) -> Read<TypeRef> {
+        let keyword = self.tokens[self.pos].clone();
+        self.pos += 1;
+        self.attributes();
+        let tag = match self.peek() {
+            Some(token) if token.kind == Kind::Identifier => Some((token.text.clone(),
+                                                                   token.location)),
+            _ => None,
+        };
+        if tag.is_some() {
+            self.pos += 1;
+        }
+        let is_union = keyword.text == "union";
+        let tag_ref = |name: String| if is_union { TypeRef::Union(UnionTypeRef { name: name }) }
+                                     else { TypeRef::Struct(StructTypeRef { name: name }) };
+
+        if !self.is("{") {
+            let (name, location) = match tag {
+                Some(tag) => tag,
+                None => return skip(keyword.location, "the tag is expected"),
+            };
+            let tag = if is_union { Tag::Union(name.clone()) } else { Tag::Struct(name.clone()) };
+            if !self.context.tags.iter().any(|(t, _)| t == &tag) {
+                self.context.tags.push((tag, location));
+            }
+            return Ok(tag_ref(name));
+        }
+
+        let (name, location) = match tag {
+            Some(tag) => tag,
+            None => (self.anonymous_name(), keyword.location),
+        };
+        let what = format!("{} {}", keyword.text, name);
+        let is_defined = if is_union { self.ast.defunions.iter().any(|u| u.name == name) }
+                         else { self.ast.defstructs.iter().any(|s| s.name == name) };
+        if is_defined {
+            return skip(location, format!("`{}` is defined already", what));
+        }
+
+        self.pos += 1; // `{`
+        let mut members = Vec::new();
+        while !self.accept("}") {
+            if self.peek().is_none() {
+                return skip(location, format!("`}}` of `{}` is expected", what));
+            }
+            let specifiers = self.specifiers()?;
+            if self.is(";") {
+                return skip(self.location(), format!("the anonymous member of `{}` is not \
+                                                      supported", what));
+            }
+            loop {
+                let declarator = self.declarator(specifiers.type_.clone())?;
+                if self.is(":") {
+                    return skip(self.location(), format!("the bit-field of `{}` is not \
+                                                          supported", what));
+                }
+                self.attributes();
+                let (member, member_location) = match declarator.name {
+                    Some(name) => name,
+                    None => return skip(self.location(), "the name of the member is expected"),
+                };
+                members.push(Slot {
+                    location: member_location,
+                    type_: TypeNode::new(specifiers.location, declarator.type_),
+                    name: member,
+                });
+                if !self.accept(",") {
+                    break;
+                }
+            }
+            self.expect(";")?;
+        }
+        self.attributes();
+
+        if is_union {
+            self.ast.defunions.push(UnionNode {
+                location: location,
+                name: name.clone(),
+                members: members,
+                is_incomplete: false,
+            });
+        } else {
+            self.ast.defstructs.push(StructNode {
+                location: location,
+                name: name.clone(),
+                members: members,
+                is_incomplete: false,
+            });
+        }
+        Ok(tag_ref(name))
+    }
+
+    // `enum color { RED, GREEN = 2 }`, an anonymous one is `int` unless a
+    // name follows it.
+    fn enum_(&mut self) -> Read<TypeRef> {
+        let keyword = self.tokens[self.pos].clone();
+        self.pos += 1;
+        self.attributes();
+        let tag = match self.peek() {
+            Some(token) if token.kind == Kind::Identifier => Some((token.text.clone(),
+                                                                   token.location)),
+            _ => None,
+        };
+        if tag.is_some() {
+            self.pos += 1;
+        }
+        if !self.is("{") {
+            return match tag {
+                Some((name, _)) => Ok(TypeRef::Enum(EnumTypeRef { name: name })),
+                None => skip(keyword.location, "the tag is expected"),
+            };
+        }
+        let (name, location) = match tag {
+            Some((name, location)) => (Some(name), location),
+            None => (self.name_after(self.pos), keyword.location),
+        };
+
+        self.pos += 1; // `{`
+        let mut members = Vec::new();
+        let mut next = 0i64;
+        while !self.accept("}") {
+            let token = match self.peek().cloned() {
+                Some(token) if token.kind == Kind::Identifier => token,
+                _ => return skip(self.location(), "an enumerator is expected"),
+            };
+            self.pos += 1;
+            let value = if self.accept("=") {
+                let begin = self.pos;
+                let mut depth = 0;
+                while let Some(text) = self.tokens.get(self.pos).map(|t| t.text.as_str()) {
+                    match text {
+                        "(" => depth += 1,
+                        ")" => depth -= 1,
+                        "," | "}" if depth == 0 => break,
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+                match evaluate(&self.tokens[begin..self.pos], Some(&self.context.constants)) {
+                    Some(value) => value,
+                    None => return skip(token.location,
+                                        format!("the value of `{}` is not a constant",
+                                                token.text)),
+                }
+            } else {
+                next
+            };
+            self.context.constants.insert(token.text.clone(), value);
+            members.push(enumerator(token.text, token.location, value));
+            next = value.wrapping_add(1);
+            if !self.accept(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        self.attributes();
+
+        self.ast.defenums.push(EnumNode {
+            location: location,
+            name: name.clone(),
+            members: members,
+        });
+        Ok(match name {
+            Some(name) => TypeRef::Enum(EnumTypeRef { name: name }),
+            None => TypeRef::Integer(IntegerTypeRef::Int),
+        })
+    }
+
+    // the name of an anonymous struct or union, the one it is typedef'ed to
+    // as `point` of `typedef struct { ... } point;` if any.
+    fn anonymous_name(&mut self) -> String {
+        let open = self.pos;
+        match self.name_after(open) {
+            Some(name) => name,
+            None => {
+                self.context.anonymous += 1;
+                format!("__anonymous{}", self.context.anonymous)
+            }
+        }
+    }
+
+    // the identifier after the body at `open`.
+    fn name_after(&self, open: usize) -> Option<String> {
+        match self.tokens.get(self.closing(open) + 1) {
+            Some(token) if token.kind == Kind::Identifier &&
+                           !KEYWORDS.contains(&token.text.as_str()) => Some(token.text.clone()),
+            _ => None,
+        }
+    }
+
+    // `*const p`, `a[10]`, `f(int x)` or `(*fp)(void)`, of the base type.
+    fn declarator(&mut self, base: TypeRef) -> Read<Declarator> {
+        let mut type_ = base;
+        while self.accept("*") {
+            type_ = TypeRef::pointer(type_);
+            loop {
+                match self.peek_text(0) {
+                    "const" | "__const" | "__const__" => if let TypeRef::Pointer(_) = type_ {
+                        type_ = TypeRef::const_(type_);
+                    },
+                    "volatile" | "__volatile__" | "restrict" | "__restrict" |
+                    "__restrict__" => {}
+                    _ => break,
+                }
+                self.pos += 1;
+            }
+        }
+        self.attributes();
+
+        // the declarator in the parentheses applies after the suffixes
+        let mut nested = None;
+        let mut name = None;
+        if self.is("(") && self.is_nested() {
+            nested = Some((self.pos + 1, self.closing(self.pos)));
+            self.pos = self.closing(self.pos) + 1;
+        } else if let Some(token) = self.peek().cloned() {
+            if token.kind == Kind::Identifier && !KEYWORDS.contains(&token.text.as_str()) {
+                name = Some((token.text, token.location));
+                self.pos += 1;
+            }
+        }
+
+        let mut suffixes = Vec::new();
+        loop {
+            if self.is("[") {
+                suffixes.push(Suffix::Array(self.array_length()?));
+            } else if self.is("(") {
+                suffixes.push(Suffix::Function(self.params()?));
+            } else {
+                break;
+            }
+        }
+        let mut params = None;
+        for (i, suffix) in suffixes.into_iter().enumerate().rev() {
+            type_ = match suffix {
+                Suffix::Array(length) => TypeRef::array(type_, length),
+                Suffix::Function(function_params) => {
+                    let function = TypeRef::function(type_, function_params.param_typerefs());
+                    if i == 0 && nested.is_none() {
+                        params = Some(function_params);
+                    }
+                    function
+                }
+            };
+        }
+        let end = self.previous_location();
+
+        match nested {
+            Some((begin, close)) => {
+                let after = self.pos;
+                self.pos = begin;
+                let declarator = self.declarator(type_)?;
+                if self.pos != close {
+                    return skip(self.location(), "`)` is expected");
+                }
+                self.pos = after;
+                Ok(Declarator { end: end, ..declarator })
+            }
+            None => Ok(Declarator {
+                name: name,
+                type_: type_,
+                params: params,
+                end: end,
+            }),
+        }
+    }
+
+    // whether the `(` opens a declarator, not the parameters.
+    fn is_nested(&self) -> bool {
+        match self.tokens.get(self.pos + 1) {
+            Some(token) => match token.text.as_str() {
+                "*" | "(" | "^" => true,
+                text => token.kind == Kind::Identifier && !KEYWORDS.contains(&text) &&
+                        !self.typedefs.contains(text),
+            },
+            None => false,
+        }
+    }
+
+    fn array_length(&mut self) -> Read<Option<ArrayLength>> {
+        let open = self.pos;
+        let close = self.closing(open);
+        self.pos = close + 1;
+        if close == open + 1 {
+            return Ok(None);
+        }
+        let tokens = &self.tokens[open + 1..close.min(self.tokens.len())];
+        match evaluate(tokens, Some(&self.context.constants)) {
+            Some(length) if length >= 0 => Ok(Some(ArrayLength::Fixed(length as usize))),
+            _ => skip(self.tokens[open].location, "the length of the array is not a constant"),
+        }
+    }
+
+    fn params(&mut self) -> Read<Params> {
+        let begin = self.expect("(")?;
+        let mut params = Vec::new();
+        let mut is_variadic = false;
+        if self.is("void") && self.peek_text(1) == ")" {
+            self.pos += 1;
+        }
+        while !self.is(")") {
+            if self.accept("...") {
+                is_variadic = true;
+                break;
+            }
+            let specifiers = self.specifiers()?;
+            let declarator = self.declarator(specifiers.type_)?;
+            self.attributes();
+            let (name, location) = match declarator.name {
+                Some(name) => name,
+                None => (format!("arg{}", params.len()), specifiers.location),
+            };
+            params.push(Parameter {
+                location: location,
+                type_: TypeNode::new(specifiers.location, adjust_param(declarator.type_)),
+                name: name,
+                entity: None,
+            });
+            if !self.accept(",") {
+                break;
+            }
+        }
+        let end = self.expect(")")?;
+        Ok(Params {
+            location: Location::range(begin, end),
+            params: params,
+            is_variadic: is_variadic,
+        })
+    }
+
+    // skip the `__attribute__((...))`s and the `__asm__("name")`s, and
+    // return the format attribute among them.
+    fn attributes(&mut self) -> Option<FormatAttribute> {
+        let mut format = None;
+        loop {
+            match self.peek_text(0) {
+                "__attribute__" | "__attribute" => {
+                    let begin = self.pos;
+                    self.pos += 1;
+                    if self.is("(") {
+                        self.pos = self.closing(self.pos) + 1;
+                    }
+                    let end = self.pos.min(self.tokens.len());
+                    format = format.or_else(|| format_attribute(&self.tokens[begin..end]));
+                }
+                "__asm__" | "__asm" | "asm" | "__declspec" => {
+                    self.pos += 1;
+                    if self.is("(") {
+                        self.pos = self.closing(self.pos) + 1;
+                    }
+                }
+                _ => return format,
+            }
+        }
+    }
+}
+
+// `format(printf, 1, 2)` in the attribute
+fn format_attribute(tokens: &[CToken]) -> Option<FormatAttribute> {
+    let texts: Vec<&str> = tokens.iter().map(|t| t.text.as_str()).collect();
+    texts.windows(8).find(|w| {
+        (w[0] == "format" || w[0] == "__format__") && w[1] == "(" &&
+        (w[2] == "printf" || w[2] == "__printf__") && w[3] == "," && w[5] == "," &&
+        w[7] == ")"
+    }).and_then(|w| {
+        Some(FormatAttribute {
+            location: Location::range(tokens[0].location, tokens[tokens.len() - 1].location),
+            format_index: w[4].parse().ok()?,
+            first_arg: w[6].parse().ok()?,
+        })
+    })
+}
+
+// an array or a function parameter is a pointer
+fn adjust_param(type_: TypeRef) -> TypeRef {
+    match type_ {
+        TypeRef::Array(array) => TypeRef::pointer(*array.base_type),
+        TypeRef::Function(_) => TypeRef::pointer(type_),
+        _ => type_,
+    }
+}
+
+// the parameters named `arg0`, `arg1`, ... of the function type
+fn unnamed_params(type_: &TypeRef, location: Location) -> Params {
+    let (types, is_variadic) = match *type_ {
+        TypeRef::Function(ref function) => (function.params.types.clone(),
+                                            function.params.is_variadic),
+        _ => (Vec::new(), false),
+    };
+    Params {
+        location: location,
+        params: types.into_iter().enumerate().map(|(i, type_)| Parameter {
+            location: location,
+            type_: TypeNode::new(location, type_),
+            name: format!("arg{}", i),
+            entity: None,
+        }).collect(),
+        is_variadic: is_variadic,
+    }
+}
+
+fn enumerator(name: String, location: Location, value: i64) -> Enumerator {
+    let type_ = if value as i32 as i64 == value { IntegerTypeRef::Int }
+                else { IntegerTypeRef::Long };
+    Enumerator {
+        location: location,
+        name: name,
+        value: Some(Box::new(IntegerLiteralNode::new(location, type_, value))),
+        entity: None,
+    }
+}
+
+fn warn<S: Into<String>>(handler: &mut DiagnosticHandler, location: Location, message: S) {
+    handler.warn(Warning::CImport, Diagnostic::warning(message).with_primary(location, ""));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{Compiled, TempDir};
+    use super::super::warning::Warning;
+
+    // compile the source importing the C header `lib.h`.
+    fn import(header: &str, src: &str) -> Compiled {
+        let dir = TempDir::new();
+        dir.write("lib.h", header);
+        dir.compile(&format!("import c \"lib.h\";\n{}", src))
+    }
+
+    #[test]
+    fn declarations() {
+        let compiled = import("#define SIZE 4\n\
+                               #define TWICE (SIZE * 2)\n\
+                               #if TWICE > 4 && defined(SIZE)\n\
+                               #define BIG 1\n\
+                               #else\n\
+                               #define BIG 0\n\
+                               #endif\n\
+                               #ifdef UNDEFINED\n\
+                               double skipped(void);\n\
+                               #endif\n\
+                               typedef unsigned long size_type;\n\
+                               struct node { struct node *next; const char *name; int tags[SIZE]; };\n\
+                               union value { int i; char *s; };\n\
+                               typedef enum { LOW = -1, HIGH = TWICE } level;\n\
+                               extern struct node *head;\n\
+                               int visit(struct node *n, int (*callback)(struct node *, void *), \
+                                         void *data);\n\
+                               int printf_like(const char *fmt, ...);\n\
+                               static const int hidden;\n",
+                              "int count(struct node* n, void* data) { return n->tags[SIZE - 1]; }\n\
+                               size_type big = BIG;\n\
+                               int twice = TWICE;\n\
+                               level low = LOW;\n\
+                               union value v;\n\
+                               int main(void) {\n\
+                                   return visit(head, count, 0) + printf_like(head->name, 1);\n\
+                               }\n");
+        assert_eq!(compiled.warnings(), [Warning::CImport]); // `static`
+        let output = compiled.output();
+        for value in &["big:\n\t.long\t1", "twice:\n\t.long\t8", "low:\n\t.long\t-1"] {
+            assert!(output.contains(value), "no `{}` in\n{}", value, output);
+        }
+    }
+
+    #[test]
+    fn skipped_declarations() {
+        let compiled = import("#define MAX(a, b) ((a) > (b) ? (a) : (b))\n\
+                               #define NAME \"lib\"\n\
+                               #if SOME_MACRO(1)\n\
+                               #endif\n\
+                               double ratio(void);\n\
+                               long long big(void);\n\
+                               struct flags { unsigned ready : 1; };\n\
+                               int twice(int x) { return 2 * x; }\n\
+                               int kept(void);\n",
+                              "int main(void) { return kept(); }\n");
+        assert!(compiled.errors().is_empty(), "{:#?}", compiled.diagnostics);
+        assert_eq!(compiled.warnings(), [Warning::CImport; 7]);
+    }
+
+    #[test]
+    fn skipped_names_are_undefined() {
+        let compiled = import("double ratio(void);\n",
+                              "int main(void) { return ratio(); }\n");
+        assert_eq!(compiled.errors(), ["E0061"]);
+    }
+}
@@ -0,0 +1,478 @@
+//! The evaluator of the constant expressions, and the pass checking the ones
+//! of the `case` labels and of the initializers of the static variables.
+//!
+//! A constant is an integer computed at compile time from the integer
+//! literals, the enumerators, `sizeof`, the casts to the integer types and the
+//! operators on them. Every operation wraps around and has the sign of its
+//! type on the target, as it would have when the program runs. An initializer
+//! of a static variable may also be an address known when the program is
+//! linked, as a string literal or `&g`.
+
+use super::ast::*;
+use super::location::Location;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::entity::{EntityTable, EntityKind};
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::DiagnosticHandler;
+use super::visitor::{self, Visitor};
+
+// the error is boxed, as it is much larger than a constant
+type Result<T> = ::std::result::Result<T, Box<SemanticError>>;
+
+/// The value of a constant expression, and its type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Constant {
+    pub value: i64, // the bits of an `unsigned long` which does not fit
+    pub type_: IntegerTypeRef,
+}
+
+pub struct ConstantEvaluator<'a> {
+    table: &'a TypeTable,
+    entities: &'a EntityTable,
+}
+
+impl<'a> ConstantEvaluator<'a> {
+    pub fn new(table: &'a TypeTable, entities: &'a EntityTable) -> ConstantEvaluator<'a> {
+        ConstantEvaluator {
+            table: table,
+            entities: entities,
+        }
+    }
+
+    /// Evaluate the expression, or return the error at the first operand
+    /// which is not a constant.
+    pub fn evaluate(&self, node: &Node) -> Result<Constant> {
+        match node.node_ref() {
+            NodeRef::IntegerLiteralNode(node) => Ok(self.convert(node.value, node.type_)),
+            NodeRef::VariableNode(node) => self.enumerator(node),
+            NodeRef::SizeofTypeNode(node) => {
+                let size = match node.size {
+                    Some(size) => size,
+                    None => match node.type_.type_ {
+                        Some(type_) => self.size_of(type_, node.location)?,
+                        None => return Err(Box::new(self.not_constant(node.location,
+                                                                      "`sizeof`"))),
+                    },
+                };
+                Ok(self.size(size))
+            }
+            NodeRef::SizeofExprNode(node) => {
+                match node.size {
+                    Some(size) => Ok(self.size(size)),
+                    // the types of the expressions are not known yet
+                    None => Err(Box::new(SemanticError::new(SemanticErrorKind::NotConstant,
+                        node.location, "`sizeof` on an expression is not a constant here")
+                        .with_help("take the size of its type instead"))),
+                }
+            }
+            NodeRef::CastNode(node) => {
+                let operand = self.evaluate(&*node.node)?;
+                let type_ = node.type_.type_.map(|type_| self.table.real_type(type_));
+                match type_.and_then(|type_| self.table.integer_ref(type_)) {
+                    Some(integer) => Ok(self.convert(operand.value, integer)),
+                    None => Err(Box::new(self.not_constant(node.location,
+                                                           "a cast to a non-integer type"))),
+                }
+            }
+            NodeRef::UnaryOpNode(node) => {
+                let operand = self.evaluate(&*node.node)?;
+                Ok(self.unary_op(node.type_, operand))
+            }
+            NodeRef::BinaryOpNode(node) => {
+                let left = self.evaluate(&*node.left)?;
+                let right = self.evaluate(&*node.right)?;
+                self.binary_op(node.type_, left, right, node.right.location())
+            }
+            NodeRef::LogicalAndNode(node) => {
+                let left = self.evaluate(&*node.left)?;
+                let right = self.evaluate(&*node.right)?;
+                Ok(self.boolean(left.value != 0 && right.value != 0))
+            }
+            NodeRef::LogicalOrNode(node) => {
+                let left = self.evaluate(&*node.left)?;
+                let right = self.evaluate(&*node.right)?;
+                Ok(self.boolean(left.value != 0 || right.value != 0))
+            }
+            NodeRef::CondExprNode(node) => {
+                let condition = self.evaluate(&*node.condition)?;
+                let then_clause = self.evaluate(&*node.then_clause)?;
+                let else_clause = self.evaluate(&*node.else_clause)?;
+                let type_ = self.common_type(then_clause.type_, else_clause.type_);
+                let value = if condition.value != 0 {
+                    then_clause.value
+                } else {
+                    else_clause.value
+                };
+                Ok(self.convert(value, type_))
+            }
+            node_ref => Err(Box::new(self.not_constant(node.location(),
+                                                       describe(node_ref)))),
+        }
+    }
+
+    /// the value converted to the integer type, wrapping around its range.
+    pub fn convert(&self, value: i64, type_: IntegerTypeRef) -> Constant {
+        let shift = 64 - self.width(type_);
+        let value = if type_.is_signed() {
+            value.wrapping_shl(shift) >> shift
+        } else {
+            ((value as u64).wrapping_shl(shift) >> shift) as i64
+        };
+        Constant {
+            value: value,
+            type_: type_,
+        }
+    }
+
+    fn width(&self, type_: IntegerTypeRef) -> u32 {
+        let size = self.table.size(self.table.integer(type_)).unwrap_or(8);
+        (size * 8) as u32
+    }
+
+    fn size(&self, size: usize) -> Constant {
+        self.convert(size as i64, IntegerTypeRef::UnsignedLong)
+    }
+
+    fn boolean(&self, value: bool) -> Constant {
+        self.convert(value as i64, IntegerTypeRef::Int)
+    }
+
+    fn promote(&self, type_: IntegerTypeRef) -> IntegerTypeRef {
+        let promoted = self.table.integer_promotion(self.table.integer(type_));
+        self.table.integer_ref(promoted).unwrap_or(type_)
+    }
+
+    fn common_type(&self, left: IntegerTypeRef, right: IntegerTypeRef) -> IntegerTypeRef {
+        let common = self.table.usual_arithmetic_conversion(self.table.integer(left),
+                                                            self.table.integer(right));
+        self.table.integer_ref(common).unwrap_or(left)
+    }
+
+    fn size_of(&self, type_: TypeId, location: Location) -> Result<usize> {
+        if self.table.is_function(type_) {
+            return Err(Box::new(SemanticError::new(SemanticErrorKind::SizeofFunction,
+                location, format!("`sizeof` on the function type `{}`",
+                                  self.table.type_name(type_)))));
+        }
+        self.table.size(type_).ok_or_else(|| {
+            Box::new(SemanticError::new(SemanticErrorKind::IncompleteType, location,
+                format!("`sizeof` on the incomplete type `{}`",
+                        self.table.type_name(type_))))
+        })
+    }
+
+    fn enumerator(&self, node: &VariableNode) -> Result<Constant> {
+        // the names in an array length are not bound yet, but the only ones
+        // which can be used there are the enumerators, all in the toplevel
+        let entity = node.entity.or_else(|| {
+            self.entities.lookup(self.entities.toplevel(), &node.name)
+        });
+        match entity.map(|entity| self.entities.get(entity)) {
+            Some(entity) if entity.kind == EntityKind::Enumerator => {
+                Ok(self.convert(entity.value.unwrap_or(0), IntegerTypeRef::Int))
+            }
+            Some(entity) => {
+                Err(Box::new(self.not_constant(node.location, &format!("`{}`", node.name))
+                        .with_secondary(entity.location,
+                                        format!("`{}` is defined here", node.name))))
+            }
+            None => Err(Box::new(self.not_constant(node.location, &format!("`{}`", node.name))
+                            .with_help("only the enumerators can be used by name"))),
+        }
+    }
+
+    fn unary_op(&self, op: UnaryOpType, operand: Constant) -> Constant {
+        let type_ = self.promote(operand.type_);
+        match op {
+            UnaryOpType::Plus => self.convert(operand.value, type_),
+            UnaryOpType::Hyphen => self.convert(operand.value.wrapping_neg(), type_),
+            UnaryOpType::Tilde => self.convert(!operand.value, type_),
+            UnaryOpType::ExclamationMark => self.boolean(operand.value == 0),
+        }
+    }
+
+    // `location` is of the right operand, which can be a zero divisor or an
+    // invalid shift count.
+    fn binary_op(&self, op: BinaryOpType, left: Constant, right: Constant,
+                 location: Location) -> Result<Constant> {
+        if let BinaryOpType::LeftShift | BinaryOpType::RightShift = op {
+            return self.shift(op, left, right, location);
+        }
+
+        let type_ = self.common_type(left.type_, right.type_);
+        let signed = type_.is_signed();
+        let l = self.convert(left.value, type_).value;
+        let r = self.convert(right.value, type_).value;
+        let value = match op {
+            BinaryOpType::Multiplication => l.wrapping_mul(r),
+            BinaryOpType::Addition => l.wrapping_add(r),
+            BinaryOpType::Subtraction => l.wrapping_sub(r),
+            BinaryOpType::BitAnd => l & r,
+            BinaryOpType::BitOr => l | r,
+            BinaryOpType::BitExclusiveOr => l ^ r,
+            BinaryOpType::Division | BinaryOpType::Modulo => {
+                if r == 0 {
+                    return Err(Box::new(SemanticError::new(
+                        SemanticErrorKind::InvalidConstantOperation, location,
+                        format!("`{}` by zero in a constant expression", op.symbol()))));
+                }
+                match (op, signed) {
+                    (BinaryOpType::Division, true) => l.wrapping_div(r),
+                    (BinaryOpType::Division, false) => ((l as u64) / (r as u64)) as i64,
+                    (_, true) => l.wrapping_rem(r),
+                    (_, false) => ((l as u64) % (r as u64)) as i64,
+                }
+            }
+            _ => {
+                let ordering = if signed {
+                    l.cmp(&r)
+                } else {
+                    (l as u64).cmp(&(r as u64))
+                };
+                let result = match op {
+                    BinaryOpType::GreaterThan => ordering.is_gt(),
+                    BinaryOpType::LessThan => ordering.is_lt(),
+                    BinaryOpType::DoubleEquals => ordering.is_eq(),
+                    BinaryOpType::NotEqualTo => ordering.is_ne(),
+                    BinaryOpType::LessThanOrEqualTo => ordering.is_le(),
+                    _ => ordering.is_ge(),
+                };
+                return Ok(self.boolean(result));
+            }
+        };
+        Ok(self.convert(value, type_))
+    }
+
+    fn shift(&self, op: BinaryOpType, left: Constant, right: Constant,
+             location: Location) -> Result<Constant> {
+        let type_ = self.promote(left.type_);
+        let width = self.width(type_);
+        let count = right.value as u64;
+        let is_negative = right.type_.is_signed() && right.value < 0;
+        if is_negative || count >= width as u64 {
+            return Err(Box::new(SemanticError::new(
+                SemanticErrorKind::InvalidConstantOperation, location,
+                format!("`{}` by {} in a constant of `{}`, which has {} bits",
+                        op.symbol(), right.value, type_.name(), width))));
+        }
+        let count = count as u32;
+        let value = match op {
+            BinaryOpType::LeftShift => left.value.wrapping_shl(count),
+            _ if type_.is_signed() => left.value >> count,
+            _ => ((left.value as u64) >> count) as i64,
+        };
+        Ok(self.convert(value, type_))
+    }
+
+    fn not_constant(&self, location: Location, what: &str) -> SemanticError {
+        SemanticError::new(SemanticErrorKind::NotConstant, location,
+                           format!("{} is not a constant", what))
+    }
+}
+
+// what the expression is, as "a function call".
+fn describe(node: NodeRef) -> &'static str {
+    match node {
+        NodeRef::StringLiteralNode(_) => "a string literal",
+        NodeRef::FuncallNode(_) => "a function call",
+        NodeRef::AssignNode(_) | NodeRef::OpAssignNode(_) => "an assignment",
+        NodeRef::PrefixOpNode(_) | NodeRef::SuffixOpNode(_) => "an increment or a decrement",
+        NodeRef::DereferenceNode(_) => "a dereference",
+        NodeRef::AddressNode(_) => "an address",
+        NodeRef::ArefNode(_) => "an array element",
+        NodeRef::MemberNode(_) | NodeRef::PtrMemberNode(_) => "a member",
+        _ => "this expression",
+    }
+}
+
+/// The pass evaluating the `case` labels and checking that the initializers
+/// of the global and `static` variables are constants.
+pub struct ConstantChecker<'a> {
+    entities: &'a EntityTable,
+    table: &'a TypeTable,
+    handler: &'a mut DiagnosticHandler,
+    in_function: bool,
+}
+
+impl<'a> ConstantChecker<'a> {
+    pub fn new(entities: &'a EntityTable, table: &'a TypeTable,
+               handler: &'a mut DiagnosticHandler) -> ConstantChecker<'a> {
+        ConstantChecker {
+            entities: entities,
+            table: table,
+            handler: handler,
+            in_function: false,
+        }
+    }
+
+    /// Check all the constant expressions, the errors are reported to the
+    /// handler.
+    pub fn check(&mut self, ast: &mut AST) {
+        self.visit_ast(ast);
+    }
+
+    fn evaluate(&mut self, node: &Node, context: &str) -> Option<Constant> {
+        match ConstantEvaluator::new(self.table, self.entities).evaluate(node) {
+            Ok(constant) => Some(constant),
+            Err(error) => {
+                let error = *error;
+                let error = if error.kind() == SemanticErrorKind::NotConstant {
+                    error.with_note(format!("{} must be a constant", context))
+                } else {
+                    error
+                };
+                self.handler.emit(error.to_diagnostic());
+                None
+            }
+        }
+    }
+
+    // whether the initializer is an address known when the program is linked.
+    fn is_address_constant(&self, node: &Node) -> bool {
+        match node.node_ref() {
+            NodeRef::StringLiteralNode(_) => true,
+            NodeRef::AddressNode(node) => self.is_static_object(&*node.node),
+            // a function or an array is its address
+            NodeRef::VariableNode(_) => {
+                self.is_static_object(node) &&
+                node.expr_type().is_some_and(|type_| {
+                    self.table.is_function(type_) || self.table.is_array(type_)
+                })
+            }
+            NodeRef::CastNode(node) => self.is_address_constant(&*node.node),
+            NodeRef::BinaryOpNode(node) => {
+                let is_offset = |node: &Node| {
+                    ConstantEvaluator::new(self.table, self.entities).evaluate(node).is_ok()
+                };
+                match node.type_ {
+                    BinaryOpType::Addition => {
+                        (self.is_address_constant(&*node.left) && is_offset(&*node.right)) ||
+                        (is_offset(&*node.left) && self.is_address_constant(&*node.right))
+                    }
+                    BinaryOpType::Subtraction => {
+                        self.is_address_constant(&*node.left) && is_offset(&*node.right)
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    // whether the object has the static storage, so its address is constant.
+    fn is_static_object(&self, node: &Node) -> bool {
+        match node.node_ref() {
+            NodeRef::VariableNode(node) => {
+                node.entity.is_some_and(|entity| {
+                    let entity = self.entities.get(entity);
+                    match entity.kind {
+                        EntityKind::GlobalVariable | EntityKind::Function => true,
+                        EntityKind::LocalVariable => entity.is_private,
+                        _ => false,
+                    }
+                })
+            }
+            NodeRef::MemberNode(node) => self.is_static_object(&*node.expr),
+            NodeRef::ArefNode(node) => {
+                let is_array = node.expr.expr_type()
+                                   .is_some_and(|type_| self.table.is_array(type_));
+                is_array && self.is_static_object(&*node.expr) &&
+                ConstantEvaluator::new(self.table, self.entities).evaluate(&*node.idx)
+                                                                   .is_ok()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Visitor for ConstantChecker<'a> {
+    fn visit_defun(&mut self, func: &mut DefinedFunction) {
+        self.in_function = true;
+        visitor::walk_defun(self, func);
+        self.in_function = false;
+    }
+
+    fn visit_defvar(&mut self, var: &mut DefinedVariable) {
+        if let Some(ref initializer) = var.initializer {
+            if (!self.in_function || var.is_private) &&
+                    !self.is_address_constant(&**initializer) {
+                let context = if self.in_function {
+                    "the initializer of a `static` variable"
+                } else {
+                    "the initializer of a global variable"
+                };
+                self.evaluate(&**initializer, context);
+            }
+        }
+        visitor::walk_defvar(self, var);
+    }
+
+    fn visit_case(&mut self, node: &mut CaseNode) {
+        node.value = self.evaluate(&*node.expr, "a `case` label")
+                         .map(|constant| constant.value);
+        visitor::walk_case(self, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::compile;
+
+    #[test]
+    fn initializers() {
+        let compiled = compile("struct pair { int a; int b; };\n\
+                                enum { SIZE = 4 };\n\
+                                struct pair pair;\n\
+                                int[SIZE] xs;\n\
+                                int n = SIZE * 2 + sizeof(struct pair) - (long)1;\n\
+                                char* s = \"text\";\n\
+                                int* pb = &pair.b;\n\
+                                int* last = xs + SIZE - 1;\n\
+                                int* second = &xs[1];\n\
+                                int main(void) {\n\
+                                    static int* counter = &n;\n\
+                                    int local = n;\n\
+                                    switch (local) { case SIZE << 1: return 1; default: return 0; }\n\
+                                }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn values() {
+        let compiled = compile("unsigned char c = 300;\n\
+                                int wrapped = 2147483647 + 1;\n\
+                                unsigned int u = -1;\n\
+                                int negative = -7 / 2;\n\
+                                long shifted = 1 << 4 | 3;\n");
+        let output = compiled.output();
+        for value in &[".byte\t44", ".long\t-2147483648", ".long\t4294967295",
+                       ".long\t-3", ".long\t19"] {
+            assert!(output.contains(value), "no `{}` in\n{}", value, output);
+        }
+    }
+
+    #[test]
+    fn not_constant() {
+        let compiled = compile("int f(void) { return 1; }\n\
+                                int g = f();\n\
+                                int h = g;\n\
+                                int main(int argc, char** argv) {\n\
+                                    static int s = argc;\n\
+                                    static int* p = &argc;\n\
+                                    switch (argc) { case argc: return 1; default: return 0; }\n\
+                                }\n");
+        assert_eq!(compiled.errors(), ["E0077"; 5]);
+        assert!(compiled.has_message("a function call is not a constant"));
+    }
+
+    #[test]
+    fn invalid_constant_operation() {
+        let compiled = compile("int a = 1 / 0;\n\
+                                int b = 1 % (2 - 2);\n\
+                                int c = 1 << 32;\n\
+                                int d = 1 >> -1;\n\
+                                long e = 1L << 31;\n");
+        assert_eq!(compiled.errors(), ["E0078"; 4]);
+    }
+}
@@ -0,0 +1,356 @@
+//! The pass checking the jumps and the ends of the functions.
+//!
+//! A `break` must be in a loop or a `switch`, a `continue` in a loop, and a
+//! `goto` must jump to a label of its function, which are all different. The
+//! values of the `case` labels of a `switch` are all different. A function
+//! returning a value is warned if the control can reach its end.
+
+use std::collections::HashMap;
+use super::ast::*;
+use super::location::Location;
+use super::type_table::TypeTable;
+use super::entity::EntityTable;
+use super::constant_evaluator::ConstantEvaluator;
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::{Diagnostic, DiagnosticHandler};
+use super::warning::Warning;
+use super::visitor::{self, Visitor};
+
+pub struct ControlFlowChecker<'a> {
+    entities: &'a EntityTable,
+    table: &'a TypeTable,
+    handler: &'a mut DiagnosticHandler,
+    loop_depth: usize,
+    switch_depth: usize,
+    labels: HashMap<String, Location>, // of the current function
+    gotos: Vec<(String, Location)>,
+    jumps: Vec<Jumps>, // of the statements around, for `can_complete`
+}
+
+// the jumps out of a loop or a `switch`.
+struct Jumps {
+    is_loop: bool,
+    has_break: bool,
+    has_continue: bool,
+}
+
+impl<'a> ControlFlowChecker<'a> {
+    pub fn new(entities: &'a EntityTable, table: &'a TypeTable,
+               handler: &'a mut DiagnosticHandler) -> ControlFlowChecker<'a> {
+        ControlFlowChecker {
+            entities: entities,
+            table: table,
+            handler: handler,
+            loop_depth: 0,
+            switch_depth: 0,
+            labels: HashMap::new(),
+            gotos: Vec::new(),
+            jumps: Vec::new(),
+        }
+    }
+
+    /// Check all the functions, the errors and the warnings are reported to
+    /// the handler.
+    pub fn check(&mut self, ast: &mut AST) {
+        self.visit_ast(ast);
+    }
+
+    fn error(&mut self, error: SemanticError) {
+        self.handler.emit(error.to_diagnostic());
+    }
+
+    fn check_gotos(&mut self) {
+        for (label, location) in ::std::mem::take(&mut self.gotos) {
+            if !self.labels.contains_key(&label) {
+                let error = SemanticError::new(SemanticErrorKind::UndefinedLabel,
+                    location, format!("undefined label `{}`", label))
+                    .with_note("a `goto` can only jump to a label of its function");
+                self.error(error);
+            }
+        }
+    }
+
+    fn check_end(&mut self, func: &DefinedFunction) {
+        let returns_value = func.type_.type_
+            .and_then(|type_| self.table.function_type(type_))
+            .is_some_and(|function| !self.table.is_void(function.return_type));
+        // `main` returns 0 at its end
+        if !returns_value || func.name == "main" {
+            return;
+        }
+        if self.can_complete_all(&func.body.stmts) {
+            let end = func.body.location;
+            let end = Location::new(end.file, end.end - 1, end.end); // `}`
+            self.handler.warn(Warning::ReturnType,
+                Diagnostic::warning(format!("control can reach the end of `{}`, \
+                                             which returns a value", func.name))
+                    .with_primary(end, "")
+                    .with_secondary(func.type_.location, "the return type is here"));
+        }
+    }
+
+    // whether the control can go past the end of the statements.
+    fn can_complete_all(&mut self, stmts: &[Box<Node>]) -> bool {
+        let mut is_reachable = true;
+        for stmt in stmts {
+            if let NodeRef::LabelNode(_) = stmt.node_ref() {
+                is_reachable = true; // by a `goto`
+            }
+            // the jumps of the unreachable statements are also counted
+            let can_complete = self.can_complete(&**stmt);
+            is_reachable = is_reachable && can_complete;
+        }
+        is_reachable
+    }
+
+    // whether the control can go past the end of the statement.
+    fn can_complete(&mut self, node: &Node) -> bool {
+        match node.node_ref() {
+            NodeRef::BlockNode(node) => self.can_complete_all(&node.stmts),
+            NodeRef::ReturnNode(_) | NodeRef::GotoNode(_) => false,
+            NodeRef::BreakNode(_) => {
+                if let Some(jumps) = self.jumps.last_mut() {
+                    jumps.has_break = true;
+                }
+                false
+            }
+            NodeRef::ContinueNode(_) => {
+                if let Some(jumps) = self.jumps.iter_mut().rev().find(|j| j.is_loop) {
+                    jumps.has_continue = true;
+                }
+                false
+            }
+            NodeRef::LabelNode(node) => self.can_complete(&*node.stmt),
+            NodeRef::IfNode(node) => {
+                let then_clause = self.can_complete(&*node.then_clause);
+                let else_clause = match node.else_clause {
+                    Some(ref else_clause) => self.can_complete(&**else_clause),
+                    None => true,
+                };
+                then_clause || else_clause
+            }
+            NodeRef::WhileNode(node) => {
+                let is_infinite = self.is_always_true(Some(&*node.condition));
+                let jumps = self.in_jumps(true, |this| { this.can_complete(&*node.body); });
+                !is_infinite || jumps.has_break
+            }
+            NodeRef::ForNode(node) => {
+                let is_infinite = self.is_always_true(node.cond_expr.as_deref());
+                let jumps = self.in_jumps(true, |this| { this.can_complete(&*node.body); });
+                !is_infinite || jumps.has_break
+            }
+            NodeRef::DoWhileNode(node) => {
+                let is_infinite = self.is_always_true(Some(&*node.condition));
+                let mut body = false;
+                let jumps = self.in_jumps(true, |this| body = this.can_complete(&*node.body));
+                ((body || jumps.has_continue) && !is_infinite) || jumps.has_break
+            }
+            NodeRef::SwitchNode(node) => {
+                let mut can_complete = true;
+                let jumps = self.in_jumps(false, |this| {
+                    if let Some(ref cases) = node.cases {
+                        can_complete = this.can_complete(&**cases);
+                    }
+                });
+                can_complete || jumps.has_break
+            }
+            NodeRef::CasesNode(node) => {
+                // the control falls through the clauses
+                let mut is_reachable = true;
+                for clause in node.normal_cases.iter().chain(&node.default_case) {
+                    let stmts = match clause.node_ref() {
+                        NodeRef::CaseNode(case) => &case.stmt,
+                        NodeRef::DefaultCaseNode(default) => &default.stmt,
+                        _ => continue,
+                    };
+                    is_reachable = self.can_complete_all(stmts);
+                }
+                // without `default`, no clause may be taken
+                is_reachable || node.default_case.is_none()
+            }
+            _ => true,
+        }
+    }
+
+    fn in_jumps<F: FnOnce(&mut Self)>(&mut self, is_loop: bool, f: F) -> Jumps {
+        self.jumps.push(Jumps {
+            is_loop: is_loop,
+            has_break: false,
+            has_continue: false,
+        });
+        f(self);
+        self.jumps.pop().unwrap()
+    }
+
+    // whether the condition is a constant other than 0, where a missing one
+    // of `for` is true.
+    fn is_always_true(&self, condition: Option<&Node>) -> bool {
+        match condition {
+            Some(condition) => ConstantEvaluator::new(self.table, self.entities)
+                                   .evaluate(condition)
+                                   .is_ok_and(|constant| constant.value != 0),
+            None => true,
+        }
+    }
+
+    fn check_cases(&mut self, cases: &CasesNode) {
+        let mut values: HashMap<i64, Location> = HashMap::new();
+        for case in &cases.normal_cases {
+            if let NodeRef::CaseNode(case) = case.node_ref() {
+                let value = match case.value {
+                    Some(value) => value,
+                    None => continue,
+                };
+                if let Some(&first) = values.get(&value) {
+                    let error = SemanticError::new(SemanticErrorKind::DuplicatedCase,
+                        case.expr.location(), format!("duplicated `case` value `{}`",
+                                                      value))
+                        .with_secondary(first, "first used here");
+                    self.error(error);
+                } else {
+                    values.insert(value, case.expr.location());
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Visitor for ControlFlowChecker<'a> {
+    fn visit_defun(&mut self, func: &mut DefinedFunction) {
+        self.labels.clear();
+        visitor::walk_defun(self, func);
+        self.check_gotos();
+        self.check_end(func);
+    }
+
+    fn visit_while(&mut self, node: &mut WhileNode) {
+        self.loop_depth += 1;
+        visitor::walk_while(self, node);
+        self.loop_depth -= 1;
+    }
+
+    fn visit_dowhile(&mut self, node: &mut DoWhileNode) {
+        self.loop_depth += 1;
+        visitor::walk_dowhile(self, node);
+        self.loop_depth -= 1;
+    }
+
+    fn visit_for(&mut self, node: &mut ForNode) {
+        self.loop_depth += 1;
+        visitor::walk_for(self, node);
+        self.loop_depth -= 1;
+    }
+
+    fn visit_switch(&mut self, node: &mut SwitchNode) {
+        self.switch_depth += 1;
+        visitor::walk_switch(self, node);
+        self.switch_depth -= 1;
+    }
+
+    fn visit_cases(&mut self, node: &mut CasesNode) {
+        self.check_cases(node);
+        visitor::walk_cases(self, node);
+    }
+
+    fn visit_break(&mut self, node: &mut BreakNode) {
+        if self.loop_depth == 0 && self.switch_depth == 0 {
+            let error = SemanticError::new(SemanticErrorKind::BreakOutsideLoop,
+                node.location, "`break` outside of a loop or a `switch`");
+            self.error(error);
+        }
+    }
+
+    fn visit_continue(&mut self, node: &mut ContinueNode) {
+        if self.loop_depth == 0 {
+            let error = SemanticError::new(SemanticErrorKind::ContinueOutsideLoop,
+                node.location, "`continue` outside of a loop");
+            self.error(error);
+        }
+    }
+
+    fn visit_goto(&mut self, node: &mut GotoNode) {
+        self.gotos.push((node.label.clone(), node.location));
+    }
+
+    fn visit_label(&mut self, node: &mut LabelNode) {
+        let location = Location::new(node.location.file, node.location.begin,
+                                     node.location.begin + node.label.len());
+        if let Some(&first) = self.labels.get(&node.label) {
+            let error = SemanticError::new(SemanticErrorKind::DuplicatedLabel,
+                location, format!("label `{}` is defined more than once", node.label))
+                .with_secondary(first, "first defined here");
+            self.error(error);
+        } else {
+            self.labels.insert(node.label.clone(), location);
+        }
+        visitor::walk_label(self, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::compile;
+    use super::super::warning::Warning;
+
+    #[test]
+    fn jumps() {
+        let compiled = compile("int f(int n) {\n\
+                                    while (n > 0) { if (n == 5) break; n--; continue; }\n\
+                                    do { n++; } while (n < 3);\n\
+                                    for (;;) { if (n) break; }\n\
+                                    switch (n) { case 1: break; case 2: n = 0; default: n++; }\n\
+                                    goto done;\n\
+                                done:\n\
+                                    return n;\n\
+                                }\n\
+                                int main(void) { return f(1); }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn break_outside_loop() {
+        let compiled = compile("int f(int n) { if (n < 0) { break; } return n; }\n");
+        assert_eq!(compiled.errors(), ["E0083"]);
+    }
+
+    #[test]
+    fn continue_outside_loop() {
+        let compiled = compile("void f(int n) { switch (n) { case 0: continue; } }\n");
+        assert_eq!(compiled.errors(), ["E0084"]);
+    }
+
+    #[test]
+    fn labels() {
+        let compiled = compile("void f(void) { goto end; }\n\
+                                void g(void) { retry: f(); retry: f(); }\n\
+                                void h(void) { end: f(); }\n");
+        assert_eq!(compiled.errors(), ["E0085", "E0086"]);
+    }
+
+    #[test]
+    fn duplicated_case() {
+        let compiled = compile("enum { READ = 1, WRITE = 1 };\n\
+                                int f(int n) {\n\
+                                    switch (n) { case READ: return 1; case WRITE: return 2; \
+                                                 case 2 - 1: return 3; default: return 0; }\n\
+                                }\n");
+        assert_eq!(compiled.errors(), ["E0087", "E0087"]);
+    }
+
+    #[test]
+    fn control_reaches_the_end() {
+        let compiled = compile("int returns(int n) { if (n) return 1; else return 0; }\n\
+                                int loops(void) { while (1) { } }\n\
+                                int jumps(int n) { switch (n) { case 0: return 1; \
+                                                                default: return 2; } }\n\
+                                int main(void) { }\n\
+                                void nothing(void) { }\n");
+        compiled.assert_clean();
+
+        let compiled = compile("int f(int n) { if (n) return 1; }\n\
+                                int g(void) { while (1) { break; } }\n\
+                                int h(int n) { switch (n) { case 0: return 1; } }\n\
+                                int i(int n) { do { continue; } while (n); }\n");
+        assert_eq!(compiled.warnings(), [Warning::ReturnType; 4]);
+    }
+}
@@ -0,0 +1,308 @@
+//! The pass checking the uses of the objects, after the `TypeChecker`.
+//!
+//! Only an lvalue can be assigned, incremented or have its address taken,
+//! and an array or a `const` object cannot be assigned. Only a pointer can
+//! be dereferenced or indexed, and `.` and `->` need a struct or a union
+//! with the member, directly or through a pointer.
+
+use super::ast::*;
+use super::location::Location;
+use super::type_::TypeId;
+use super::type_table::TypeTable;
+use super::entity::{EntityKind, EntityTable};
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::DiagnosticHandler;
+use super::suggestion;
+use super::visitor::{self, Visitor};
+
+pub struct DereferenceChecker<'a> {
+    entities: &'a EntityTable,
+    table: &'a mut TypeTable,
+    handler: &'a mut DiagnosticHandler,
+}
+
+impl<'a> DereferenceChecker<'a> {
+    pub fn new(entities: &'a EntityTable, table: &'a mut TypeTable,
+               handler: &'a mut DiagnosticHandler) -> DereferenceChecker<'a> {
+        DereferenceChecker {
+            entities: entities,
+            table: table,
+            handler: handler,
+        }
+    }
+
+    /// Check all the expressions, the errors are reported to the handler.
+    pub fn check(&mut self, ast: &mut AST) {
+        self.visit_ast(ast);
+    }
+
+    fn error(&mut self, error: SemanticError) {
+        self.handler.emit(error.to_diagnostic());
+    }
+
+    // check that the expression is an object which can be modified, where
+    // `what` is as "assign to".
+    fn check_modifiable(&mut self, node: &Node, what: &str) {
+        let type_ = match node.expr_type() {
+            Some(type_) => type_,
+            None => return,
+        };
+        if !self.is_lvalue(node) || self.table.is_function(type_) {
+            let error = SemanticError::new(SemanticErrorKind::NotAnLvalue,
+                node.location(), format!("cannot {} this expression, which is \
+                                          not an lvalue", what))
+                .with_help("only a variable, `*p`, `a[i]`, `s.m` and `p->m` \
+                            can be modified");
+            self.error(error);
+        } else if self.table.is_array(type_) {
+            let error = SemanticError::new(SemanticErrorKind::AssignToArray,
+                node.location(), format!("cannot {} the array of type `{}`", what,
+                                         self.table.type_name(type_)))
+                .with_help("modify the elements one by one");
+            self.error(error);
+        } else if self.is_const_object(node) {
+            let message = if self.table.is_const(type_) {
+                format!("cannot {} a `const` object of type `{}`",
+                        what, self.table.type_name(type_))
+            } else {
+                format!("cannot {} a member of a `const` object", what)
+            };
+            let mut error = SemanticError::new(SemanticErrorKind::AssignToConst,
+                                               node.location(), message);
+            if let NodeRef::VariableNode(var) = node.node_ref() {
+                if let Some(entity) = var.entity {
+                    error = error.with_secondary(self.entities.get(entity).location,
+                                                 format!("`{}` is declared `const` \
+                                                          here", var.name));
+                }
+            }
+            self.error(error);
+        }
+    }
+
+    // an enumerator is a name but not an object.
+    fn is_lvalue(&self, node: &Node) -> bool {
+        if let NodeRef::VariableNode(var) = node.node_ref() {
+            let kind = var.entity.map(|entity| self.entities.get(entity).kind);
+            if kind == Some(EntityKind::Enumerator) {
+                return false;
+            }
+        }
+        node.is_lvalue()
+    }
+
+    // whether the object is `const`, or a member of a `const` one.
+    fn is_const_object(&mut self, node: &Node) -> bool {
+        if node.expr_type().is_some_and(|type_| self.table.is_const(type_)) {
+            return true;
+        }
+        match node.node_ref() {
+            NodeRef::MemberNode(node) => self.is_const_object(&*node.expr),
+            NodeRef::PtrMemberNode(node) => node.expr.expr_type()
+                .map(|type_| self.table.decay(type_))
+                .and_then(|type_| self.table.base_type(type_))
+                .is_some_and(|type_| self.table.is_const(type_)),
+            _ => false,
+        }
+    }
+
+    // the type of the expression as a value, where an array is a pointer.
+    fn value_type(&mut self, node: &Node) -> Option<TypeId> {
+        node.expr_type().map(|type_| self.table.decay(type_))
+    }
+
+    fn check_member(&mut self, location: Location, composite: TypeId, memb: &str) {
+        if self.table.member(composite, memb).is_none() {
+            let name = self.table.type_name(composite);
+            let mut error = SemanticError::new(SemanticErrorKind::UnknownMember,
+                location, format!("no member `{}` in `{}`", memb, name));
+            let real_type = self.table.real_type(composite);
+            if let Some(defined) = self.table.location(real_type) {
+                error = error.with_secondary(defined, format!("`{}` is defined here",
+                                                               name));
+            }
+            let members = self.table.members(composite).unwrap_or(&[]);
+            let names = members.iter().map(|member| member.name.as_str());
+            if let Some(member) = suggestion::suggest(memb, names) {
+                error = error.with_help(suggestion::did_you_mean(member));
+            }
+            self.error(error);
+        }
+    }
+}
+
+impl<'a> Visitor for DereferenceChecker<'a> {
+    fn visit_assign(&mut self, node: &mut AssignNode) {
+        visitor::walk_assign(self, node);
+        self.check_modifiable(&*node.lhs, "assign to");
+    }
+
+    fn visit_op_assign(&mut self, node: &mut OpAssignNode) {
+        visitor::walk_op_assign(self, node);
+        self.check_modifiable(&*node.lhs, "assign to");
+    }
+
+    fn visit_prefix_op(&mut self, node: &mut PrefixOpNode) {
+        visitor::walk_prefix_op(self, node);
+        let what = match node.type_ {
+            PrefixOpType::Increment => "increment",
+            PrefixOpType::Decrement => "decrement",
+        };
+        self.check_modifiable(&*node.node, what);
+    }
+
+    fn visit_suffix_op(&mut self, node: &mut SuffixOpNode) {
+        visitor::walk_suffix_op(self, node);
+        let what = match node.type_ {
+            SuffixOpType::Increment => "increment",
+            SuffixOpType::Decrement => "decrement",
+        };
+        self.check_modifiable(&*node.expr, what);
+    }
+
+    fn visit_address(&mut self, node: &mut AddressNode) {
+        visitor::walk_address(self, node);
+        if node.node.expr_type().is_some() && !self.is_lvalue(&*node.node) {
+            let error = SemanticError::new(SemanticErrorKind::NotAnLvalue,
+                node.node.location(),
+                "cannot take the address of this expression, which is not an lvalue");
+            self.error(error);
+        }
+    }
+
+    fn visit_dereference(&mut self, node: &mut DereferenceNode) {
+        visitor::walk_dereference(self, node);
+        if let Some(type_) = self.value_type(&*node.node) {
+            if !self.table.is_pointer(type_) {
+                let error = SemanticError::new(SemanticErrorKind::InvalidDereference,
+                    node.location, format!("cannot dereference `{}`, which is not \
+                                            a pointer", self.table.type_name(type_)));
+                self.error(error);
+            }
+        }
+    }
+
+    fn visit_aref(&mut self, node: &mut ArefNode) {
+        visitor::walk_aref(self, node);
+        if let Some(type_) = self.value_type(&*node.expr) {
+            if !self.table.is_pointer(type_) {
+                let error = SemanticError::new(SemanticErrorKind::InvalidDereference,
+                    node.location, format!("cannot index `{}`, which is not an \
+                                            array or a pointer",
+                                           self.table.type_name(type_)));
+                self.error(error);
+            }
+        }
+    }
+
+    fn visit_member(&mut self, node: &mut MemberNode) {
+        visitor::walk_member(self, node);
+        let type_ = match node.expr.expr_type() {
+            Some(type_) => type_,
+            None => return,
+        };
+        if self.table.is_composite(type_) {
+            self.check_member(node.location, type_, &node.memb);
+            return;
+        }
+        let mut error = SemanticError::new(SemanticErrorKind::InvalidMemberAccess,
+            node.location, format!("`.{}` on `{}`, which is not a struct or a union",
+                                   node.memb, self.table.type_name(type_)));
+        let is_pointer_to_composite = self.table.is_pointer(type_) &&
+            self.table.base_type(type_).is_some_and(|t| self.table.is_composite(t));
+        if is_pointer_to_composite {
+            error = error.with_help(format!("use `->{}` to access the member through \
+                                             the pointer", node.memb));
+        }
+        self.error(error);
+    }
+
+    fn visit_ptr_member(&mut self, node: &mut PtrMemberNode) {
+        visitor::walk_ptr_member(self, node);
+        let type_ = match self.value_type(&*node.expr) {
+            Some(type_) => type_,
+            None => return,
+        };
+        let base_type = self.table.base_type(type_)
+                            .filter(|&t| self.table.is_pointer(type_) &&
+                                         self.table.is_composite(t));
+        if let Some(base_type) = base_type {
+            self.check_member(node.location, base_type, &node.memb);
+            return;
+        }
+        let mut error = SemanticError::new(SemanticErrorKind::InvalidMemberAccess,
+            node.location, format!("`->{}` on `{}`, which is not a pointer to a \
+                                    struct or a union",
+                                   node.memb, self.table.type_name(type_)));
+        if self.table.is_composite(type_) {
+            error = error.with_help(format!("use `.{}` to access the member of the \
+                                             value", node.memb));
+        }
+        self.error(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::compile;
+
+    const POINT: &'static str = "struct point { int x; int y; };\n";
+
+    fn check(body: &str) -> Vec<&'static str> {
+        compile(&format!("{}int f(struct point p, struct point* pp, int* q, \
+                                    int const c, int[2] a) {{\n\
+                                    int[3] xs; int n;\n{}\nreturn 0;\n}}\n", POINT, body))
+            .errors()
+    }
+
+    #[test]
+    fn objects() {
+        assert_eq!(check("n = 1; n++; *q = n; xs[0] = *q; a[1] = 2;\n\
+                          p.x = pp->y; pp = &p; q = &xs[1]; q = &p.y; a = q;"),
+                   Vec::<&str>::new());
+    }
+
+    #[test]
+    fn not_an_lvalue() {
+        assert_eq!(check("(n + 1) = 2;"), ["E0071"]);
+        assert_eq!(check("f(p, pp, q, c, a)++;"), ["E0071"]);
+        assert_eq!(check("q = &(n + 1);"), ["E0071"]);
+        assert_eq!(check("f = 0;"), ["E0071"]);
+    }
+
+    #[test]
+    fn assign_to_array() {
+        assert_eq!(check("xs = a;"), ["E0072"]);
+    }
+
+    #[test]
+    fn assign_to_const() {
+        assert_eq!(check("c = 1;"), ["E0073"]);
+        let compiled = compile(&format!("{}struct point const origin;\n\
+                                         int main(void) {{ origin.x = 1; return 0; }}\n",
+                                        POINT));
+        assert_eq!(compiled.errors(), ["E0073"]);
+        assert!(compiled.has_message("cannot assign to a member of a `const` object"));
+    }
+
+    #[test]
+    fn invalid_dereference() {
+        assert_eq!(check("n = *n;"), ["E0074"]);
+        assert_eq!(check("n = n[0];"), ["E0074"]);
+    }
+
+    #[test]
+    fn invalid_member_access() {
+        assert_eq!(check("n = n.x;"), ["E0075"]);
+        assert_eq!(check("n = pp.x;"), ["E0075"]);
+        assert_eq!(check("n = p->x;"), ["E0075"]);
+    }
+
+    #[test]
+    fn unknown_member() {
+        let compiled = compile(&format!("{}int f(struct point p) {{ return p.z + p.xx; }}\n",
+                                        POINT));
+        assert_eq!(compiled.errors(), ["E0076", "E0076"]);
+        assert!(compiled.has_message("no member `z` in `struct point`"));
+    }
+}
@@ -0,0 +1,565 @@
+//! Diagnostics reported by every stage of the compiler.
+//!
+//! A `Diagnostic` has a severity, a message, labels pointing into the source
+//! and some notes and help text. The stages push them into a
+//! `DiagnosticHandler`, and the `Emitter` renders them for humans (with the
+//! source snippet and `^~~~` underlines) or as one JSON object per line.
+
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use super::location::Location;
+use super::source_map::SourceMap;
+use super::warning::{Warning, WarningLevel, WarningOptions};
+
+const TAB_WIDTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub location: Location,
+    pub message: String,
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>, // such as `E0012`
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub helps: Vec<String>,
+    pub warning: Option<Warning>, // the warning it comes from
+    is_warning_error: bool,        // the warning is turned into an error
+}
+
+/// Collect the diagnostics of a compilation, the driver emits them.
+#[derive(Debug)]
+pub struct DiagnosticHandler {
+    diagnostics: Vec<Diagnostic>,
+    error_count: usize,
+    warning_count: usize,
+    warning_options: WarningOptions,
+    error_codes: Vec<&'static str>, // of the errors reported, no duplicates
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorConfig {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Show the diagnostics on the standard error.
+pub struct Emitter {
+    format: ErrorFormat,
+    color: bool,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: severity,
+            code: None,
+            message: message,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            helps: Vec::new(),
+            warning: None,
+            is_warning_error: false,
+        }
+    }
+
+    pub fn error<S: Into<String>>(message: S) -> Diagnostic {
+        Diagnostic::new(Severity::Error, message.into())
+    }
+
+    pub fn warning<S: Into<String>>(message: S) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, message.into())
+    }
+
+    /// the stable code of the error, see `error_codes`.
+    pub fn with_code(mut self, code: &'static str) -> Diagnostic {
+        self.code = Some(code);
+        self
+    }
+
+    /// the place where the problem is, an empty message is allowed.
+    pub fn with_primary<S: Into<String>>(mut self, location: Location, message: S) -> Diagnostic {
+        self.labels.push(Label {
+            location: location,
+            message: message.into(),
+            is_primary: true,
+        });
+        self
+    }
+
+    /// another place which helps to understand the problem.
+    pub fn with_secondary<S: Into<String>>(mut self, location: Location, message: S) -> Diagnostic {
+        self.labels.push(Label {
+            location: location,
+            message: message.into(),
+            is_primary: false,
+        });
+        self
+    }
+
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help<S: Into<String>>(mut self, help: S) -> Diagnostic {
+        self.helps.push(help.into());
+        self
+    }
+
+    pub fn primary_location(&self) -> Option<Location> {
+        self.labels.iter()
+                   .find(|label| label.is_primary)
+                   .or(self.labels.first())
+                   .map(|label| label.location)
+    }
+}
+
+impl DiagnosticHandler {
+    pub fn new() -> DiagnosticHandler {
+        DiagnosticHandler {
+            diagnostics: Vec::new(),
+            error_count: 0,
+            warning_count: 0,
+            warning_options: WarningOptions::new(),
+            error_codes: Vec::new(),
+        }
+    }
+
+    pub fn set_warning_options(&mut self, options: WarningOptions) {
+        self.warning_options = options;
+    }
+
+    /// report the `warning`, it may be dropped or turned into an error by
+    /// the `-W` flags.
+    pub fn warn(&mut self, warning: Warning, mut diagnostic: Diagnostic) {
+        diagnostic.warning = Some(warning);
+        match self.warning_options.level(warning) {
+            WarningLevel::Allow => return,
+            WarningLevel::Warn => diagnostic.severity = Severity::Warning,
+            WarningLevel::Deny => {
+                diagnostic.severity = Severity::Error;
+                diagnostic.is_warning_error = true;
+            }
+        }
+        self.emit(diagnostic);
+    }
+
+    pub fn emit(&mut self, diagnostic: Diagnostic) {
+        if let (Severity::Error, Some(code)) = (diagnostic.severity, diagnostic.code) {
+            if !self.error_codes.contains(&code) {
+                self.error_codes.push(code);
+            }
+        }
+        match diagnostic.severity {
+            Severity::Error => self.error_count += 1,
+            Severity::Warning => self.warning_count += 1,
+            _ => { /* not counted */ },
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count > 0
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.error_count
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.warning_count
+    }
+
+    /// the codes of the errors reported, in the order they are first seen.
+    pub fn error_codes(&self) -> &[&'static str] {
+        &self.error_codes
+    }
+
+    /// take the diagnostics reported since the last call, the counts are kept.
+    pub fn take(&mut self) -> Vec<Diagnostic> {
+        self.diagnostics.drain(..).collect()
+    }
+}
+
+impl Emitter {
+    pub fn new(format: ErrorFormat, color: ColorConfig) -> Emitter {
+        let color = match color {
+            ColorConfig::Always => true,
+            ColorConfig::Never => false,
+            ColorConfig::Auto => io::stderr().is_terminal(),
+        };
+        Emitter {
+            format: format,
+            color: color && format == ErrorFormat::Human,
+        }
+    }
+
+    pub fn emit(&mut self, diagnostic: &Diagnostic, source_map: &SourceMap) {
+        let text = match self.format {
+            ErrorFormat::Human => self.render(diagnostic, source_map),
+            ErrorFormat::Json => {
+                let rendered = self.render(diagnostic, source_map);
+                json::diagnostic(diagnostic, source_map, &rendered) + "\n"
+            }
+        };
+        // nothing more could be done if the diagnostics could not be shown.
+        let _ = io::stderr().write_all(text.as_bytes());
+    }
+
+    /// render a diagnostic like this:
+    ///
+    /// ```text
+    /// error: need a semicolon after the expression statement
+    ///   --> test.cb:10:27
+    ///    |
+    /// 10 |         return (j * 1 - j)
+    ///    |                           ^
+    /// ```
+    pub fn render(&self, diagnostic: &Diagnostic, source_map: &SourceMap) -> String {
+        let mut text = String::new();
+        let severity_style = Style::of(diagnostic.severity);
+
+        let title = match diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity, code),
+            None => diagnostic.severity.to_string(),
+        };
+        text += &self.paint(severity_style, &title);
+        text += &self.paint(Style::Bold, &format!(": {}", diagnostic.message));
+        if let Some(warning) = diagnostic.warning {
+            let flag = if diagnostic.is_warning_error { "-Werror=" } else { "-W" };
+            text += &format!(" [{}{}]", flag, warning);
+        }
+        text += "\n";
+
+        let gutter_width = diagnostic.labels.iter()
+            .map(|label| source_map.lookup(label.location).line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(gutter_width);
+        let bar = self.paint(Style::Gutter, "|");
+
+        let mut last_line = None;
+        for (i, label) in diagnostic.labels.iter().enumerate() {
+            let file = source_map.file(label.location.file);
+            let begin = source_map.lookup(label.location);
+            let position = (label.location.file, begin.line);
+
+            if last_line != Some(position) {
+                let arrow = if i == 0 { "-->" } else { ":::" };
+                if i > 0 {
+                    text += &format!("{} {}\n", gutter, bar);
+                }
+                text += &format!("{}{} {}\n", gutter,
+                                 self.paint(Style::Gutter, arrow),
+                                 source_map.span_to_string(label.location));
+                text += &format!("{} {}\n", gutter, bar);
+                text += &format!("{} {} {}\n",
+                                 self.paint(Style::Gutter, &format!(
+                                     "{:>width$}", begin.line, width = gutter_width)),
+                                 bar,
+                                 expand_tabs(file.line(begin.line)));
+                last_line = Some(position);
+            }
+
+            // underline to the end of the span, or the end of the first line
+            // if the span goes across lines.
+            let line_begin = file.line_start(begin.line);
+            let line_end = line_begin + file.line(begin.line).len();
+            let start = label.location.begin.min(line_end);
+            let end = label.location.end.max(start).min(line_end);
+            let prefix = &file.src()[line_begin..start];
+            let marked = &file.src()[start..end];
+            let indent = expand_tabs(prefix).chars().count();
+            let width = expand_tabs(marked).chars().count().max(1);
+
+            let (style, underline) = if label.is_primary {
+                (severity_style, String::from("^") + &"~".repeat(width - 1))
+            } else {
+                (Style::Secondary, "-".repeat(width))
+            };
+            let mut marker = self.paint(style, &underline);
+            if !label.message.is_empty() {
+                marker += " ";
+                marker += &self.paint(style, &label.message);
+            }
+            text += &format!("{} {} {}{}\n", gutter, bar, " ".repeat(indent), marker);
+        }
+
+        for note in &diagnostic.notes {
+            text += &format!("{} {} {}: {}\n", gutter, self.paint(Style::Gutter, "="),
+                             self.paint(Style::Bold, "note"), note);
+        }
+        for help in &diagnostic.helps {
+            text += &format!("{} {} {}: {}\n", gutter, self.paint(Style::Gutter, "="),
+                             self.paint(Style::Bold, "help"), help);
+        }
+        text
+    }
+
+    fn paint(&self, style: Style, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", style.ansi(), text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Style {
+    Error,
+    Warning,
+    Note,
+    Help,
+    Secondary,
+    Gutter,
+    Bold,
+}
+
+impl Style {
+    fn of(severity: Severity) -> Style {
+        match severity {
+            Severity::Error => Style::Error,
+            Severity::Warning => Style::Warning,
+            Severity::Note => Style::Note,
+            Severity::Help => Style::Help,
+        }
+    }
+
+    fn ansi(&self) -> &'static str {
+        match *self {
+            Style::Error => "1;31",
+            Style::Warning => "1;33",
+            Style::Note => "1;32",
+            Style::Help => "1;36",
+            Style::Secondary | Style::Gutter => "1;34",
+            Style::Bold => "1",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => "error".fmt(f),
+            Severity::Warning => "warning".fmt(f),
+            Severity::Note => "note".fmt(f),
+            Severity::Help => "help".fmt(f),
+        }
+    }
+}
+
+
+/// The JSON output for tools, one object per diagnostic:
+///
+/// ```text
+/// {"severity":"error","code":"E0006","message":"...","warning":null,
+///  "spans":[{"file":"a.cb",
+///  "byte_start":12,"byte_end":13,"line_start":2,"column_start":7,
+///  "line_end":2,"column_end":8,"is_primary":true,"label":""}],
+///  "notes":[],"helps":[],"rendered":"..."}
+/// ```
+mod json {
+    use super::*;
+
+    pub fn diagnostic(diagnostic: &Diagnostic, source_map: &SourceMap,
+                      rendered: &str) -> String {
+        let spans: Vec<String> = diagnostic.labels.iter()
+                                                  .map(|label| span(label, source_map))
+                                                  .collect();
+        let code = diagnostic.code.map_or(String::from("null"), string);
+        let warning = diagnostic.warning.map_or(String::from("null"),
+                                                |warning| string(warning.name()));
+        format!("{{\"severity\":{},\"code\":{},\"message\":{},\"warning\":{},\
+                 \"spans\":[{}],\"notes\":{},\"helps\":{},\"rendered\":{}}}",
+                string(&diagnostic.severity.to_string()),
+                code,
+                string(&diagnostic.message),
+                warning,
+                spans.join(","),
+                strings(&diagnostic.notes),
+                strings(&diagnostic.helps),
+                string(rendered))
+    }
+
+    fn span(label: &Label, source_map: &SourceMap) -> String {
+        let file = source_map.file(label.location.file);
+        let begin = file.line_col(label.location.begin);
+        let end = file.line_col(label.location.end);
+        format!("{{\"file\":{},\"byte_start\":{},\"byte_end\":{},\
+                 \"line_start\":{},\"column_start\":{},\
+                 \"line_end\":{},\"column_end\":{},\
+                 \"is_primary\":{},\"label\":{}}}",
+                string(&file.path().display().to_string()),
+                label.location.begin, label.location.end,
+                begin.line, begin.column, end.line, end.column,
+                label.is_primary, string(&label.message))
+    }
+
+    fn strings(items: &[String]) -> String {
+        let items: Vec<String> = items.iter().map(|x| string(x)).collect();
+        format!("[{}]", items.join(","))
+    }
+
+    fn string(s: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in s.chars() {
+            match c {
+                '"' => quoted += "\\\"",
+                '\\' => quoted += "\\\\",
+                '\n' => quoted += "\\n",
+                '\r' => quoted += "\\r",
+                '\t' => quoted += "\\t",
+                c if (c as u32) < 0x20 => quoted += &format!("\\u{:04x}", c as u32),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::source_map::FileId;
+    use super::super::warning::{Warning, WarningOptions};
+
+    const SRC: &'static str = "int main(void) {\n\tint x = y;\n    return x\n}\n";
+
+    fn source() -> (SourceMap, FileId) {
+        let mut map = SourceMap::new();
+        let id = map.add_file("test.cb", SRC.to_string());
+        (map, id)
+    }
+
+    fn at(file: FileId, text: &str) -> Location {
+        let begin = SRC.find(text).unwrap();
+        Location::new(file, begin, begin + text.len())
+    }
+
+    #[test]
+    fn render() {
+        let (map, file) = source();
+        let diagnostic = Diagnostic::error("undefined name `y`")
+            .with_code("E0061")
+            .with_primary(at(file, "y"), "not found in this scope")
+            .with_secondary(at(file, "main"), "in this function")
+            .with_note("names are defined before their use")
+            .with_help("did you mean `x`?");
+        let emitter = Emitter::new(ErrorFormat::Human, ColorConfig::Never);
+        assert_eq!(emitter.render(&diagnostic, &map),
+                   "error[E0061]: undefined name `y`\n \
+                    --> test.cb:2:10\n  \
+                    |\n\
+                    2 |     int x = y;\n  \
+                    |             ^ not found in this scope\n  \
+                    |\n \
+                    ::: test.cb:1:5\n  \
+                    |\n\
+                    1 | int main(void) {\n  \
+                    |     ---- in this function\n  \
+                    = note: names are defined before their use\n  \
+                    = help: did you mean `x`?\n");
+    }
+
+    #[test]
+    fn render_across_lines() {
+        let (map, file) = source();
+        let begin = SRC.find("return").unwrap();
+        let diagnostic = Diagnostic::error("need a semicolon")
+            .with_primary(Location::new(file, begin, SRC.len()), "");
+        let emitter = Emitter::new(ErrorFormat::Human, ColorConfig::Never);
+        assert_eq!(emitter.render(&diagnostic, &map),
+                   "error: need a semicolon\n \
+                    --> test.cb:3:5\n  \
+                    |\n\
+                    3 |     return x\n  \
+                    |     ^~~~~~~~\n");
+    }
+
+    #[test]
+    fn render_color() {
+        let (map, file) = source();
+        let diagnostic = Diagnostic::warning("unused").with_primary(at(file, "x"), "");
+        let emitter = Emitter::new(ErrorFormat::Human, ColorConfig::Always);
+        let text = emitter.render(&diagnostic, &map);
+        assert!(text.starts_with("\x1b[1;33mwarning\x1b[0m\x1b[1m: unused\x1b[0m\n"), "{}", text);
+    }
+
+    #[test]
+    fn json() {
+        let (map, file) = source();
+        let diagnostic = Diagnostic::warning("`x` is not a format string")
+            .with_primary(at(file, "x"), "");
+        let mut handler = DiagnosticHandler::new();
+        handler.warn(Warning::Format, diagnostic);
+        let diagnostic = &handler.take()[0];
+        assert_eq!(json::diagnostic(diagnostic, &map, "a \"rendered\"\ttext\n"),
+                   "{\"severity\":\"warning\",\"code\":null,\
+                    \"message\":\"`x` is not a format string\",\"warning\":\"format\",\
+                    \"spans\":[{\"file\":\"test.cb\",\"byte_start\":22,\"byte_end\":23,\
+                    \"line_start\":2,\"column_start\":6,\"line_end\":2,\"column_end\":7,\
+                    \"is_primary\":true,\"label\":\"\"}],\"notes\":[],\"helps\":[],\
+                    \"rendered\":\"a \\\"rendered\\\"\\ttext\\n\"}");
+    }
+
+    #[test]
+    fn warning_levels() {
+        let (map, file) = source();
+        let mut options = WarningOptions::new();
+        options.apply("error=unused-variable").unwrap();
+        let mut handler = DiagnosticHandler::new();
+        handler.set_warning_options(options);
+        handler.warn(Warning::Shadow, Diagnostic::warning("shadowed"));
+        handler.warn(Warning::Format, Diagnostic::warning("format"));
+        handler.warn(Warning::UnusedVariable,
+                     Diagnostic::warning("unused").with_primary(at(file, "x"), ""));
+        assert_eq!((handler.error_count(), handler.warning_count()), (1, 1));
+
+        let diagnostics = handler.take();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        let emitter = Emitter::new(ErrorFormat::Human, ColorConfig::Never);
+        assert!(emitter.render(&diagnostics[0], &map)
+                       .starts_with("warning: format [-Wformat]\n"));
+        assert!(emitter.render(&diagnostics[1], &map)
+                       .starts_with("error: unused [-Werror=unused-variable]\n"));
+    }
+
+    #[test]
+    fn error_codes() {
+        let mut handler = DiagnosticHandler::new();
+        for &code in &["E0061", "E0065", "E0061"] {
+            handler.emit(Diagnostic::error("error").with_code(code));
+        }
+        assert!(handler.has_errors());
+        assert_eq!(handler.error_count(), 3);
+        assert_eq!(handler.error_codes(), ["E0061", "E0065"]);
+    }
+}
@@ -0,0 +1,132 @@
+//! The entities named in a program, and the scopes they are defined in.
+//!
+//! An entity is a variable, a parameter, a function or an enumerator. The
+//! `TypeResolver` defines the enumerators, as their values are needed by the
+//! array types, and the `LocalResolver` defines one for every other
+//! declaration and binds the names used in the expressions to them, so the
+//! later passes can look up what a name is.
+
+use std::collections::HashMap;
+use super::location::Location;
+use super::type_::TypeId;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EntityId(pub usize);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScopeId(pub usize);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EntityKind {
+    GlobalVariable,
+    LocalVariable,
+    Parameter,
+    Function,
+    Enumerator,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entity {
+    pub name: String,
+    pub kind: EntityKind,
+    pub type_: Option<TypeId>, // `None` if the type has an error
+    pub location: Location,
+    pub is_private: bool, // `static`
+    pub scope: ScopeId,
+    pub value: Option<i64>, // of an enumerator
+}
+
+#[derive(Debug)]
+pub struct Scope {
+    pub parent: Option<ScopeId>, // `None` for the toplevel
+    pub children: Vec<ScopeId>,
+    pub entities: HashMap<String, EntityId>,
+}
+
+/// All the entities and the scope tree of a compilation unit.
+#[derive(Debug)]
+pub struct EntityTable {
+    entities: Vec<Entity>,
+    scopes: Vec<Scope>,
+}
+
+impl EntityTable {
+    pub fn new() -> EntityTable {
+        EntityTable {
+            entities: Vec::new(),
+            scopes: vec![Scope {
+                parent: None,
+                children: Vec::new(),
+                entities: HashMap::new(),
+            }],
+        }
+    }
+
+    /// the scope of the global variables and the functions.
+    pub fn toplevel(&self) -> ScopeId {
+        ScopeId(0)
+    }
+
+    pub fn push_scope(&mut self, parent: ScopeId) -> ScopeId {
+        let id = ScopeId(self.scopes.len());
+        self.scopes.push(Scope {
+            parent: Some(parent),
+            children: Vec::new(),
+            entities: HashMap::new(),
+        });
+        self.scopes[parent.0].children.push(id);
+        id
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    pub fn get(&self, id: EntityId) -> &Entity {
+        &self.entities[id.0]
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> &mut Entity {
+        &mut self.entities[id.0]
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// define the entity in its scope, or return the one of the same name
+    /// already defined there.
+    pub fn define(&mut self, entity: Entity) -> Result<EntityId, EntityId> {
+        let scope = entity.scope;
+        if let Some(&id) = self.scopes[scope.0].entities.get(&entity.name) {
+            return Err(id);
+        }
+        let id = EntityId(self.entities.len());
+        self.scopes[scope.0].entities.insert(entity.name.clone(), id);
+        self.entities.push(entity);
+        Ok(id)
+    }
+
+    /// the entity of the name seen from the scope, searching outwards.
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<EntityId> {
+        let mut scope = Some(scope);
+        while let Some(id) = scope {
+            if let Some(&entity) = self.scopes[id.0].entities.get(name) {
+                return Some(entity);
+            }
+            scope = self.scopes[id.0].parent;
+        }
+        None
+    }
+
+    /// all the names seen from the scope, for the suggestions.
+    pub fn visible_names(&self, scope: ScopeId) -> Vec<&str> {
+        let mut names = Vec::new();
+        let mut scope = Some(scope);
+        while let Some(id) = scope {
+            names.extend(self.scopes[id.0].entities.keys().map(|name| name.as_str()));
+            scope = self.scopes[id.0].parent;
+        }
+        names
+    }
+}
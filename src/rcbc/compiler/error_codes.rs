@@ -0,0 +1,1801 @@
+//! The stable codes of the errors and their long explanations, shown by
+//! `rcbc --explain <code>`.
+//!
+//! Every error kind maps each of its variants to a code with an exhaustive
+//! `match`, and lists its variants in `ALL`. `codes()` collects the codes of
+//! all the kinds, so a code without an entry in `EXPLANATIONS` is found by
+//! `undocumented_codes()`.
+
+use super::scanner::ScanErrorKind;
+use super::parser::ParseErrorKind;
+use super::type_table::TypeErrorKind;
+use super::semantic_error::SemanticErrorKind;
+
+/// An error kind with a stable code such as `E0012`.
+pub trait ErrorCode {
+    fn code(&self) -> &'static str;
+}
+
+/// the codes of every variant of every error kind.
+pub fn codes() -> Vec<&'static str> {
+    let mut codes = Vec::new();
+    codes.extend(ScanErrorKind::ALL.iter().map(|kind| kind.code()));
+    codes.extend(ParseErrorKind::ALL.iter().map(|kind| kind.code()));
+    codes.extend(TypeErrorKind::ALL.iter().map(|kind| kind.code()));
+    codes.extend(SemanticErrorKind::ALL.iter().map(|kind| kind.code()));
+    codes
+}
+
+/// the long explanation of `code`, with an erroneous example and its fix.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS.iter()
+                .find(|&&(c, _)| c == code)
+                .map(|&(_, explanation)| explanation.trim_start_matches('\n'))
+}
+
+/// the codes used by some error kind but without an explanation.
+pub fn undocumented_codes() -> Vec<&'static str> {
+    codes().into_iter().filter(|code| explain(code).is_none()).collect()
+}
+
+const EXPLANATIONS: &'static [(&'static str, &'static str)] = &[
+
+("E0001", r##"
+A block comment is opened with `/*` but never closed with `*/`.
+
+Block comments do not nest, everything after the `/*` until the end of the
+file becomes the comment.
+
+Erroneous code example:
+
+    int main(void) {
+        /* the comment never ends
+        return 0;
+    }
+
+Close the comment with `*/`:
+
+    int main(void) {
+        /* the comment ends here */
+        return 0;
+    }
+"##),
+
+("E0002", r##"
+An octal escape sequence in a character or string literal does not have
+exactly three octal digits.
+
+Erroneous code example:
+
+    char c = '\18';
+
+Write three digits from `0` to `7`:
+
+    char c = '\012';
+"##),
+
+("E0003", r##"
+A character literal contains a line break.
+
+Erroneous code example:
+
+    char c = '
+    ';
+
+Use the escape sequence `\n` for a line break:
+
+    char c = '\n';
+"##),
+
+("E0004", r##"
+A character literal is not closed by a single quote. A character literal
+holds exactly one character or one escape sequence.
+
+Erroneous code example:
+
+    char c = 'ab';
+
+Write a single character, or use a string literal for more:
+
+    char c = 'a';
+    char *s = "ab";
+"##),
+
+("E0005", r##"
+A string literal is not closed by a double quote on the same line.
+
+Erroneous code example:
+
+    printf("hello, world\n);
+
+Close the string, and use `\n` instead of a line break inside it:
+
+    printf("hello, world\n");
+"##),
+
+("E0006", r##"
+A character which can not begin any token is found in the program.
+
+Erroneous code example:
+
+    int main(void) {
+        int @count = 0;
+        return 0;
+    }
+
+Remove the character, identifiers only contain letters, digits and `_`:
+
+    int main(void) {
+        int count = 0;
+        return 0;
+    }
+"##),
+
+("E0007", r##"
+An `import` statement is not ended by a semicolon.
+
+Erroneous code example:
+
+    import stdio
+    import stdlib;
+
+Add the semicolon:
+
+    import stdio;
+    import stdlib;
+"##),
+
+("E0008", r##"
+An identifier is expected, for example as the name of a variable, a function,
+a member or after `struct` and `union`.
+
+Erroneous code example:
+
+    int 1st = 1;
+
+Names begin with a letter or `_`:
+
+    int first = 1;
+"##),
+
+("E0009", r##"
+The parameter list of a function definition is not closed by `)`.
+
+Erroneous code example:
+
+    int add(int a, int b {
+        return a + b;
+    }
+
+Close the parameter list:
+
+    int add(int a, int b) {
+        return a + b;
+    }
+"##),
+
+("E0010", r##"
+A variable definition is not ended by a semicolon.
+
+Erroneous code example:
+
+    int main(void) {
+        int i = 0
+        return i;
+    }
+
+Add the semicolon:
+
+    int main(void) {
+        int i = 0;
+        return i;
+    }
+"##),
+
+("E0011", r##"
+The body of a function or a block is expected to begin with `{`.
+
+Erroneous code example:
+
+    int main(void)
+        return 0;
+
+Put the body in braces:
+
+    int main(void) {
+        return 0;
+    }
+"##),
+
+("E0012", r##"
+A block is not closed by `}`. This often happens when a statement in the
+block has a syntax error, so the parser stops before the end of the block.
+
+Erroneous code example:
+
+    int main(void) {
+        return 0;
+
+Close the block:
+
+    int main(void) {
+        return 0;
+    }
+"##),
+
+("E0013", r##"
+A struct definition is not ended by a semicolon.
+
+Erroneous code example:
+
+    struct point {
+        int x;
+        int y;
+    }
+
+Add the semicolon after the closing brace:
+
+    struct point {
+        int x;
+        int y;
+    };
+"##),
+
+("E0014", r##"
+A union definition is not ended by a semicolon.
+
+Erroneous code example:
+
+    union value {
+        int i;
+        char *s;
+    }
+
+Add the semicolon after the closing brace:
+
+    union value {
+        int i;
+        char *s;
+    };
+"##),
+
+("E0015", r##"
+The member list of a struct or union definition is expected to begin
+with `{`. A definition is told from a variable of the struct type by the
+`{` after the name, so without it the example is read as a variable and
+reported as E0008.
+
+Erroneous code example:
+
+    struct point
+        int x;
+        int y;
+    };
+
+Put the members in braces:
+
+    struct point {
+        int x;
+        int y;
+    };
+"##),
+
+("E0016", r##"
+The member list of a struct or union definition is not closed by `}` at
+the end of the file.
+
+Erroneous code example:
+
+    struct point {
+        int x;
+        int y;
+
+Close the member list:
+
+    struct point {
+        int x;
+        int y;
+    };
+"##),
+
+("E0017", r##"
+A member in a struct or union definition is not ended by a semicolon.
+
+Erroneous code example:
+
+    struct point {
+        int x, int y;
+    };
+
+Declare each member with its own type and semicolon:
+
+    struct point {
+        int x;
+        int y;
+    };
+"##),
+
+("E0018", r##"
+A `typedef` statement is expected here. A definition is read as a
+`typedef` only if it begins with the keyword, so the example is read as a
+variable of the unknown type `define` and reported as E0022.
+
+Erroneous code example:
+
+    define int length;
+
+Use `typedef` to give a type another name:
+
+    typedef int length;
+"##),
+
+("E0019", r##"
+A `typedef` statement is not ended by a semicolon.
+
+Erroneous code example:
+
+    typedef unsigned long size_t
+
+Add the semicolon:
+
+    typedef unsigned long size_t;
+"##),
+
+("E0020", r##"
+The length of an array type is not closed by `]`, or something else than an
+integer constant is found in the brackets.
+
+Erroneous code example:
+
+    int[10 numbers;
+
+Close the brackets:
+
+    int[10] numbers;
+"##),
+
+("E0021", r##"
+The parameter types of a function type are not closed by `)`.
+
+Erroneous code example:
+
+    typedef int (int, int compare;
+
+Close the parameter types:
+
+    typedef int(int, int) compare;
+"##),
+
+("E0022", r##"
+A type is expected but the name is not a type. Only `void`, the integer types,
+`struct` and `union` types and the names defined by `typedef` are types.
+
+Erroneous code example:
+
+    integer count;
+
+Use a type, or define the name with `typedef` first:
+
+    typedef int integer;
+    integer count;
+"##),
+
+("E0023", r##"
+An expression statement is not ended by a semicolon.
+
+Erroneous code example:
+
+    int main(void) {
+        puts("hello")
+        return 0;
+    }
+
+Add the semicolon:
+
+    int main(void) {
+        puts("hello");
+        return 0;
+    }
+"##),
+
+("E0024", r##"
+The condition of an `if` statement is expected to be in parentheses.
+
+Erroneous code example:
+
+    if x > 0 {
+        return x;
+    }
+
+Put the condition in parentheses:
+
+    if (x > 0) {
+        return x;
+    }
+"##),
+
+("E0025", r##"
+The condition of an `if` statement is not closed by `)`.
+
+Erroneous code example:
+
+    if (x > 0 {
+        return x;
+    }
+
+Close the condition:
+
+    if (x > 0) {
+        return x;
+    }
+"##),
+
+("E0026", r##"
+The condition of a `while` or `do`-`while` statement is expected to be in
+parentheses.
+
+Erroneous code example:
+
+    while i < 10 {
+        i++;
+    }
+
+Put the condition in parentheses:
+
+    while (i < 10) {
+        i++;
+    }
+"##),
+
+("E0027", r##"
+The condition of a `while` or `do`-`while` statement is not closed by `)`.
+
+Erroneous code example:
+
+    while (i < 10 {
+        i++;
+    }
+
+Close the condition:
+
+    while (i < 10) {
+        i++;
+    }
+"##),
+
+("E0028", r##"
+The head of a `for` statement is expected to be in parentheses.
+
+Erroneous code example:
+
+    for i = 0; i < 10; i++ {
+        sum += i;
+    }
+
+Put the three expressions in parentheses:
+
+    for (i = 0; i < 10; i++) {
+        sum += i;
+    }
+"##),
+
+("E0029", r##"
+The head of a `for` statement is not closed by `)`.
+
+Erroneous code example:
+
+    for (i = 0; i < 10; i++ {
+        sum += i;
+    }
+
+Close the head:
+
+    for (i = 0; i < 10; i++) {
+        sum += i;
+    }
+"##),
+
+("E0030", r##"
+The three expressions in the head of a `for` statement are separated by
+semicolons, even if some of them are empty.
+
+Erroneous code example:
+
+    for (i = 0, i < 10, i++) {
+        sum += i;
+    }
+
+Separate them with semicolons:
+
+    for (i = 0; i < 10; i++) {
+        sum += i;
+    }
+"##),
+
+("E0031", r##"
+A labeled statement needs an identifier as its label. A statement is read
+as labeled only if it begins with a name and `:`, so the example is read
+as an expression and reported as E0023.
+
+Erroneous code example:
+
+    10: i++;
+
+Use an identifier as the label:
+
+    again: i++;
+"##),
+
+("E0032", r##"
+The body of a `do` statement is expected to be followed by `while` and the
+condition.
+
+Erroneous code example:
+
+    do {
+        i++;
+    } until (i == 10);
+
+Use `while` with the condition of going on:
+
+    do {
+        i++;
+    } while (i != 10);
+"##),
+
+("E0033", r##"
+A `do`-`while` statement is not ended by a semicolon.
+
+Erroneous code example:
+
+    do {
+        i++;
+    } while (i < 10)
+
+Add the semicolon after the condition:
+
+    do {
+        i++;
+    } while (i < 10);
+"##),
+
+("E0034", r##"
+The expression of a `switch` statement is expected to be in parentheses.
+
+Erroneous code example:
+
+    switch c {
+    case 'a': return 1;
+    }
+
+Put the expression in parentheses:
+
+    switch (c) {
+    case 'a': return 1;
+    }
+"##),
+
+("E0035", r##"
+The expression of a `switch` statement is not closed by `)`.
+
+Erroneous code example:
+
+    switch (c {
+    case 'a': return 1;
+    }
+
+Close the expression:
+
+    switch (c) {
+    case 'a': return 1;
+    }
+"##),
+
+("E0036", r##"
+The case clauses of a `switch` statement are expected to be in braces.
+
+Erroneous code example:
+
+    switch (c)
+    case 'a': return 1;
+
+Put the case clauses in braces:
+
+    switch (c) {
+    case 'a': return 1;
+    }
+"##),
+
+("E0037", r##"
+The case clauses of a `switch` statement are not closed by `}`. Only `case`
+and `default` clauses may be written in the braces, so a statement before
+the first clause ends them.
+
+Erroneous code example:
+
+    int kind(int c) {
+        switch (c) {
+        c++;
+        case 'a': return 1;
+        }
+        return 0;
+    }
+
+Put the statement in a clause, or before the `switch`:
+
+    int kind(int c) {
+        c++;
+        switch (c) {
+        case 'a': return 1;
+        }
+        return 0;
+    }
+"##),
+
+("E0038", r##"
+A `break` statement is not ended by a semicolon.
+
+Erroneous code example:
+
+    while (1) {
+        break
+    }
+
+Add the semicolon:
+
+    while (1) {
+        break;
+    }
+"##),
+
+("E0039", r##"
+The value of a `case` clause, or the `default` keyword, is expected to be
+followed by a colon.
+
+Erroneous code example:
+
+    switch (c) {
+    case 'a' return 1;
+    default return 0;
+    }
+
+Add the colons:
+
+    switch (c) {
+    case 'a': return 1;
+    default: return 0;
+    }
+"##),
+
+("E0040", r##"
+A `goto` statement needs the name of a label to jump to.
+
+Erroneous code example:
+
+    goto;
+
+Name the label:
+
+    goto done;
+"##),
+
+("E0041", r##"
+A `goto` statement is not ended by a semicolon.
+
+Erroneous code example:
+
+    goto done
+    i++;
+
+Add the semicolon:
+
+    goto done;
+    i++;
+"##),
+
+("E0042", r##"
+A `return` statement is not ended by a semicolon.
+
+Erroneous code example:
+
+    int main(void) {
+        return 0
+    }
+
+Add the semicolon:
+
+    int main(void) {
+        return 0;
+    }
+"##),
+
+("E0043", r##"
+A `continue` statement is not ended by a semicolon.
+
+Erroneous code example:
+
+    while (i < 10) {
+        i++;
+        continue
+    }
+
+Add the semicolon:
+
+    while (i < 10) {
+        i++;
+        continue;
+    }
+"##),
+
+("E0044", r##"
+The two branches of a conditional expression `a ? b : c` are separated by
+a colon.
+
+Erroneous code example:
+
+    int max = a > b ? a, b;
+
+Separate them with a colon:
+
+    int max = a > b ? a : b;
+"##),
+
+("E0045", r##"
+The type of a cast, or of `sizeof`, is not closed by `)`.
+
+Erroneous code example:
+
+    long l = (long i;
+
+Close the type:
+
+    long l = (long)i;
+"##),
+
+("E0046", r##"
+The index of an array reference is not closed by `]`.
+
+Erroneous code example:
+
+    int first = numbers[0;
+
+Close the index:
+
+    int first = numbers[0];
+"##),
+
+("E0047", r##"
+The argument list of a function call is not closed by `)`, the arguments are
+separated by commas.
+
+Erroneous code example:
+
+    printf("%d\n" count);
+
+Separate the arguments with commas and close the list:
+
+    printf("%d\n", count);
+"##),
+
+("E0048", r##"
+An expression in parentheses is not closed by `)`.
+
+Erroneous code example:
+
+    int n = (a + b * c;
+
+Close the parentheses:
+
+    int n = (a + b) * c;
+"##),
+
+("E0049", r##"
+An expression is expected here, but the token can not begin one. An
+expression begins with a literal, a name, an opening parenthesis or a unary
+operator.
+
+Erroneous code example:
+
+    int n = * ;
+    int m = 1 + ;
+
+Complete the expressions:
+
+    int n = *p;
+    int m = 1 + 2;
+"##),
+
+("E0050", r##"
+A struct is used but no struct of the name is defined.
+
+Erroneous code example:
+
+    struct point *origin;
+
+Define the struct in the file, or import the header which defines it:
+
+    struct point {
+        int x;
+        int y;
+    };
+
+    struct point *origin;
+"##),
+
+("E0051", r##"
+A union is used but no union of the name is defined.
+
+Erroneous code example:
+
+    union value v;
+
+Define the union in the file, or import the header which defines it:
+
+    union value {
+        int i;
+        char *s;
+    };
+
+    union value v;
+"##),
+
+("E0052", r##"
+A type name is used, but its typedef is not defined. The parser reads the
+name as a type since a `typedef` gives it, but the typedef is not defined if
+its type has an error, as the undefined struct here.
+
+Erroneous code example:
+
+    typedef struct buffer buffer_t;
+    buffer_t buf;
+
+Define the type the typedef names:
+
+    struct buffer { int length; };
+    typedef struct buffer buffer_t;
+    buffer_t buf;
+"##),
+
+("E0053", r##"
+A struct, union or typedef of the same name is defined more than once.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point { long x; long y; };
+
+Remove one of the definitions, or give it another name:
+
+    struct point { int x; int y; };
+    struct lpoint { long x; long y; };
+"##),
+
+("E0054", r##"
+A struct or union contains itself by value, directly or through other
+members, so its size would be infinite.
+
+Erroneous code example:
+
+    struct node {
+        int value;
+        struct node next;
+    };
+
+Refer to it by a pointer instead:
+
+    struct node {
+        int value;
+        struct node *next;
+    };
+"##),
+
+("E0055", r##"
+The elements of an array must be objects with a size, so an array of `void`
+or of functions can not be made.
+
+Erroneous code example:
+
+    void[16] buf;
+    int(int)[4] table;
+
+Use an array of `char` for raw memory, and an array of function pointers:
+
+    char[16] buf;
+    int(int)*[4] table;
+"##),
+
+("E0056", r##"
+A function can not return an array or a function.
+
+Erroneous code example:
+
+    typedef int[4] row;
+    extern row make_row(void);
+
+Return a pointer instead:
+
+    extern int* make_row(void);
+"##),
+
+("E0057", r##"
+A member of a struct or union has a type without a size, such as `void`, a
+function or an array without a length, so the layout can not be made.
+
+Erroneous code example:
+
+    struct buffer {
+        int length;
+        void data;
+    };
+
+Give the member a complete type, or make it a pointer:
+
+    struct buffer {
+        int length;
+        char* data;
+    };
+"##),
+
+("E0058", r##"
+Two members of a struct or union have the same name.
+
+Erroneous code example:
+
+    struct point {
+        int x;
+        int x;
+    };
+
+Rename one of them:
+
+    struct point {
+        int x;
+        int y;
+    };
+"##),
+
+("E0059", r##"
+`sizeof` is applied to a function type, or to a function, but a function
+has no size.
+
+Erroneous code example:
+
+    int f(int x) { return x; }
+    unsigned long n = sizeof f;
+
+Take the size of a pointer to the function instead:
+
+    unsigned long n = sizeof(int(int)*);
+"##),
+
+("E0060", r##"
+`sizeof` is applied to a type without a size, or a variable or a parameter
+is defined with one. `void`, an array without a length, a struct whose
+definition has an error and a struct declared but not defined by a C
+header, as `struct tag;`, have no size.
+
+Erroneous code example:
+
+    unsigned long n = sizeof(void);
+
+Take the size of a complete type:
+
+    unsigned long n = sizeof(char);
+
+A struct which a C header does not define is used through pointers:
+
+    // in tag.h
+    struct tag;
+    struct tag* make(void);
+
+    // in main.cb
+    import c "tag.h";
+
+    struct tag* t;
+"##),
+
+("E0061", r##"
+A name is used but no variable, parameter or function of the name is
+defined in the scope.
+
+Erroneous code example:
+
+    int main(void) {
+        return count;
+    }
+
+Define the name before, or fix its spelling:
+
+    int count = 0;
+
+    int main(void) {
+        return count;
+    }
+"##),
+
+("E0062", r##"
+A variable, a parameter or a function of the same name is defined more
+than once in the same scope. The variables defined at the beginning of
+the body of a function are in the same scope as its parameters.
+
+Erroneous code example:
+
+    int f(int x) {
+        int x = 1;
+        return x;
+    }
+
+Remove one of the definitions, or give it another name:
+
+    int f(int x) {
+        int y = 1;
+        return x + y;
+    }
+"##),
+
+("E0063", r##"
+An operator is applied to operands of types it does not work on, such as
+adding two pointers, or multiplying a pointer.
+
+Erroneous code example:
+
+    int f(int* p, int* q) {
+        return p + q;
+    }
+
+A pointer can only be added to an integer, and subtracted from a pointer
+of the same type, which gives the number of elements between them:
+
+    long f(int* p, int* q) {
+        return p - q;
+    }
+"##),
+
+("E0064", r##"
+A pointer is used where an integer is expected, or an integer other than
+`0` is used where a pointer is expected, without a cast.
+
+Erroneous code example:
+
+    int* p = 16;
+
+Cast the value if the conversion is intended:
+
+    int* p = (int*)16;
+"##),
+
+("E0065", r##"
+A value is given where a value of another type is expected, as in an
+assignment, an argument, a return value or a branch of `?:`, and the two
+types do not convert into each other. The pointers of different types
+only convert through `void*`.
+
+Erroneous code example:
+
+    int* p;
+    char* s = p;
+
+Cast the pointer if the conversion is intended:
+
+    int* p;
+    char* s = (char*)p;
+"##),
+
+("E0066", r##"
+An expression is called, but it is not a function or a pointer to a
+function.
+
+Erroneous code example:
+
+    int f(int n) {
+        return n(1);
+    }
+
+Only a function or a pointer to one can be called, such as the one passed
+as a parameter:
+
+    int f(int(int)* g) {
+        return g(1);
+    }
+"##),
+
+("E0067", r##"
+A function is called with a wrong number of arguments. A variadic
+function, declared with `...`, takes at least the arguments before the
+`...`.
+
+Erroneous code example:
+
+    int add(int a, int b) { return a + b; }
+    int x = add(1);
+
+Give all the arguments:
+
+    int x = add(1, 2);
+"##),
+
+("E0068", r##"
+A value is cast to a type it cannot be converted into. Only the integers
+and the pointers can be cast to each other, and anything can be cast to
+`void`.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n = (int)p;
+
+Convert a member instead:
+
+    int n = (int)p.x;
+"##),
+
+("E0069", r##"
+A `return` statement has a value in a function returning `void`, or has
+no value in a function returning another type.
+
+Erroneous code example:
+
+    int f(void) {
+        return;
+    }
+
+Return a value of the return type:
+
+    int f(void) {
+        return 0;
+    }
+"##),
+
+("E0070", r##"
+The condition of `if`, `while`, `do while`, `for`, `?:`, `&&`, `||` or `!`
+is not an integer or a pointer, or the condition of `switch` is not an
+integer.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+
+    int is_set(struct point p) {
+        if (p) { return 1; }
+        return 0;
+    }
+
+Test a member instead:
+
+    struct point { int x; int y; };
+
+    int is_set(struct point p) {
+        if (p.x) { return 1; }
+        return 0;
+    }
+"##),
+
+("E0071", r##"
+An expression which is not an lvalue is assigned, incremented,
+decremented or has its address taken. An lvalue designates an object in
+the memory: a variable, `*p`, `a[i]`, `s.m` or `p->m`.
+
+Erroneous code example:
+
+    int x;
+    (x + 1) = 2;
+
+Assign to the object instead:
+
+    int x;
+    x = 2 - 1;
+"##),
+
+("E0072", r##"
+An array is assigned, incremented or decremented as a whole. An array is
+not a value which can be stored.
+
+Erroneous code example:
+
+    int[3] a;
+    int[3] b;
+    a = b;
+
+Assign the elements one by one:
+
+    int i;
+    for (i = 0; i < 3; i++) {
+        a[i] = b[i];
+    }
+"##),
+
+("E0073", r##"
+An object declared `const`, or a member of one, is modified after its
+definition.
+
+Erroneous code example:
+
+    const int limit = 10;
+
+    void reset(void) {
+        limit = 0;
+    }
+
+Remove the `const` if the object needs to change, or give its value in
+the definition only:
+
+    int limit = 10;
+
+    void reset(void) {
+        limit = 0;
+    }
+"##),
+
+("E0074", r##"
+An expression which is not a pointer is dereferenced with `*`, or one
+which is not an array or a pointer is indexed with `[]`.
+
+Erroneous code example:
+
+    int x;
+    int y = *x;
+
+Dereference a pointer to the object:
+
+    int x;
+    int* p = &x;
+    int y = *p;
+"##),
+
+("E0075", r##"
+`.` is used on an expression which is not a struct or a union, or `->`
+on one which is not a pointer to a struct or a union.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point* p;
+    int n = p.x;
+
+Use `->` through a pointer, and `.` on the value:
+
+    int n = p->x;
+"##),
+
+("E0076", r##"
+A member which is not in the struct or the union is accessed with `.` or
+`->`.
+
+Erroneous code example:
+
+    struct point { int x; int y; };
+    struct point p;
+    int n = p.z;
+
+Access one of the members of the definition:
+
+    int n = p.x;
+"##),
+
+("E0077", r##"
+A value which must be known at compile time is computed from something which
+is not a constant.
+
+The labels of `case`, the lengths of arrays, the values of enumerators and
+the initializers of global and `static` variables are computed when the
+program is compiled. They can only use the integer literals, the
+enumerators, `sizeof`, the casts to the integer types and the operators on
+them. An initializer may also be a string literal or the address of a global
+variable or function.
+
+Erroneous code example:
+
+    int size = 16;
+    char[size] buffer;
+
+Use an enumerator, or write the value:
+
+    enum { SIZE = 16 };
+    char[SIZE] buffer;
+"##),
+
+("E0078", r##"
+An operation in a constant expression has no value, as a division by zero or
+a shift by a negative count or by the width of the type or more.
+
+Erroneous code example:
+
+    enum { ZERO, ONE };
+    char[ONE / ZERO] buffer;
+
+Fix the operands:
+
+    char[ZERO / ONE] buffer;
+"##),
+
+("E0079", r##"
+The length of an array type is negative.
+
+Erroneous code example:
+
+    enum { SIZE = 4 };
+    int[SIZE - 8] values;
+
+The length must be zero or more:
+
+    int[8 - SIZE] values;
+"##),
+
+("E0080", r##"
+An enum is used but no enum of the name is defined.
+
+Erroneous code example:
+
+    enum color c;
+
+Define the enum in the file, or import the header which defines it:
+
+    enum color { RED, GREEN, BLUE };
+
+    enum color c;
+"##),
+
+("E0081", r##"
+An enum definition is not ended by a semicolon.
+
+Erroneous code example:
+
+    enum color { RED, GREEN, BLUE }
+
+Add the semicolon after the closing brace:
+
+    enum color { RED, GREEN, BLUE };
+"##),
+
+("E0082", r##"
+The list of the enumerators of an enum definition is not closed by `}`.
+The enumerators are separated by commas, and can be followed by one.
+
+Erroneous code example:
+
+    enum color { RED GREEN };
+
+Separate the enumerators with commas:
+
+    enum color { RED, GREEN };
+"##),
+
+("E0083", r##"
+A `break` is not in a loop or a `switch`, so there is nothing to leave.
+
+Erroneous code example:
+
+    int f(int n)
+    {
+        if (n < 0) {
+            break;
+        }
+        return n;
+    }
+
+Use `return` to leave the function:
+
+    int f(int n)
+    {
+        if (n < 0) {
+            return 0;
+        }
+        return n;
+    }
+"##),
+
+("E0084", r##"
+A `continue` is not in a loop. A `switch` is not a loop, so a `continue`
+in a `switch` must also be in a loop.
+
+Erroneous code example:
+
+    void f(int n)
+    {
+        switch (n) {
+        case 0:
+            continue;
+        }
+    }
+
+Use `break` to leave the `switch`:
+
+    void f(int n)
+    {
+        switch (n) {
+        case 0:
+            break;
+        }
+    }
+"##),
+
+("E0085", r##"
+A `goto` jumps to a label which is not defined in its function. The labels
+of the other functions can not be reached.
+
+Erroneous code example:
+
+    void f(void)
+    {
+        goto end;
+    }
+
+Define the label in the function:
+
+    void f(void)
+    {
+        goto end;
+    end:
+        return;
+    }
+"##),
+
+("E0086", r##"
+The same label is defined twice in a function, so a `goto` to it would be
+ambiguous.
+
+Erroneous code example:
+
+    void g(void) {}
+    void h(void) {}
+
+    void f(void)
+    {
+    retry:
+        g();
+    retry:
+        h();
+    }
+
+Give the labels different names:
+
+    void g(void) {}
+    void h(void) {}
+
+    void f(void)
+    {
+    retry_g:
+        g();
+    retry_h:
+        h();
+    }
+"##),
+
+("E0087", r##"
+Two `case` labels of a `switch` have the same value, after their constant
+expressions are evaluated.
+
+Erroneous code example:
+
+    enum { READ = 1, WRITE = 1 };
+
+    int access(int mode) {
+        switch (mode) {
+        case READ: return 1;
+        case WRITE: return 2;
+        }
+        return 0;
+    }
+
+Make the values different, or merge the clauses:
+
+    enum { READ = 1, WRITE = 2 };
+
+    int access(int mode) {
+        switch (mode) {
+        case READ: return 1;
+        case WRITE: return 2;
+        }
+        return 0;
+    }
+"##),
+
+("E0088", r##"
+An `extern` declaration is not closed by a semicolon.
+
+Erroneous code example:
+
+    extern int errno
+    extern int puts(char* s)
+
+Put a semicolon after each declaration:
+
+    extern int errno;
+    extern int puts(char* s);
+"##),
+
+("E0089", r##"
+The attribute after a function declaration is not a format attribute. The
+only attribute is `__attribute__((format(printf, N, M)))`, which tells that
+the parameter N is a `printf` format string and the arguments from the M-th
+are converted by it.
+
+Erroneous code example:
+
+    extern int printf(char* fmt, ...) __attribute__((noreturn));
+    extern int scanf(char* fmt, ...) __attribute__((format(scanf, 1, 2)));
+
+Write the format attribute of `printf`, or remove the attribute:
+
+    extern int printf(char* fmt, ...) __attribute__((format(printf, 1, 2)));
+    extern int scanf(char* fmt, ...);
+"##),
+
+("E0090", r##"
+The parameters named by a format attribute do not fit the function. The
+format string must be a `char*` parameter, and the converted arguments must
+be the variadic ones after the last parameter, or 0 if they are not checked,
+as for a function taking a `va_list`.
+
+Erroneous code example:
+
+    extern int printf(char* fmt, ...) __attribute__((format(printf, 2, 1)));
+
+Count the parameters from 1:
+
+    extern int printf(char* fmt, ...) __attribute__((format(printf, 1, 2)));
+"##),
+
+("E0091", r##"
+A header (`.hb` file) defines a function or a variable. A header only has
+the declarations shared by the source files importing it: `extern`
+functions and variables, structs, unions, enums and typedefs.
+
+Erroneous code example:
+
+    // in mylib.hb
+    int counter;
+    int twice(int x) { return x * 2; }
+
+Declare them with `extern`, and define them in a source file:
+
+    // in mylib.hb
+    extern int counter;
+    extern int twice(int x);
+"##),
+
+("E0092", r##"
+The header of an import is not found. `import sys.io;` imports the header
+`sys/io.hb`, which is searched in the directories given by `-I` in order,
+then in the directory of the source file, then in the directory of the
+standard headers. `import c "mylib/util.h";` imports the C header by its
+path, searched in the same directories.
+
+Erroneous code example:
+
+    import mylib.util;   // mylib/util.hb is in ../include
+
+Give the directory to search with `-I`:
+
+    rcbc -I ../include main.cb
+"##),
+
+("E0093", r##"
+A header imports itself, directly or through the headers it imports. The
+declarations of a header must be loaded before the ones which use them, so
+the imports can not make a cycle.
+
+Erroneous code example:
+
+    // in a.hb
+    import b;
+
+    // in b.hb
+    import a;
+
+Move the declarations both headers need into a third one, which both
+import:
+
+    // in common.hb
+    struct shared { int n; };
+
+    // in a.hb
+    import common;
+
+    // in b.hb
+    import common;
+"##),
+
+("E0094", r##"
+A definition does not match its declaration in the header of the module.
+The header `foo.hb` of `foo.cb` is what the other modules see, so a
+function or a variable it declares must be defined in `foo.cb` with the
+same type, and not `static`. A struct, a union, a typedef or an enum
+defined in both must be defined the same.
+
+Erroneous code example:
+
+    // in foo.hb
+    extern long count(char* s);
+
+    // in foo.cb
+    static int count(char* s) { return 0; }
+
+Make the definition and the declaration agree:
+
+    // in foo.cb
+    long count(char* s) { return 0; }
+"##),
+
+("E0095", r##"
+A function or a variable declared in the header of the module is not
+defined in the module. The header `foo.hb` of `foo.cb` promises the
+definitions to the modules importing it, which would fail to link.
+
+Erroneous code example:
+
+    // in foo.hb
+    extern int twice(int x);
+
+    // in foo.cb, which does not define `twice`
+    int thrice(int x) { return 3 * x; }
+
+Define it in the module, or remove the declaration from the header:
+
+    // in foo.cb
+    int twice(int x) { return 2 * x; }
+    int thrice(int x) { return 3 * x; }
+"##),
+
+("E0096", r##"
+The length of an array is a constant expression which needs the type or
+the enumerator being defined with the array, so it has no value.
+
+Erroneous code example:
+
+    typedef char[sizeof(struct packet)] raw_packet;
+    struct packet { int length; raw_packet data; };
+
+    enum { N = sizeof(struct slot) };
+    struct slot { char[N] data; };
+
+Compute the length from the types it does not depend on:
+
+    typedef char[64] raw_packet;
+    struct packet { int length; raw_packet data; };
+"##),
+
+];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use super::{codes, explain, undocumented_codes, EXPLANATIONS};
+    use super::super::CompileOptionBuilder;
+    use super::super::testing::{compile_at, Compiled, TempDir};
+
+    // the codes no input reports, with the code their examples report
+    // instead, as their explanations say.
+    const UNREACHABLE: &'static [(&'static str, &'static str)] = &[
+        ("E0015", "E0008"),
+        ("E0018", "E0022"),
+        ("E0031", "E0023"),
+    ];
+
+    #[test]
+    fn every_code_is_documented() {
+        assert_eq!(undocumented_codes(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn codes_are_unique() {
+        let mut seen = HashSet::new();
+        for code in codes() {
+            assert!(seen.insert(code), "`{}` is the code of two error kinds", code);
+        }
+    }
+
+    #[test]
+    fn explanations_are_of_used_codes() {
+        let codes = codes();
+        let mut seen = HashSet::new();
+        for &(code, _) in EXPLANATIONS {
+            assert!(codes.contains(&code), "`{}` is explained but not used", code);
+            assert!(seen.insert(code), "`{}` is explained twice", code);
+        }
+    }
+
+    #[test]
+    fn explain_a_code() {
+        let explanation = explain("E0001").unwrap();
+        assert!(explanation.starts_with("A block comment is opened"));
+        assert_eq!(explain("E9999"), None);
+    }
+
+    // the code blocks of the explanation, indented by four spaces.
+    fn examples(explanation: &str) -> Vec<String> {
+        let mut examples = Vec::new();
+        let mut example: Option<String> = None;
+        for line in explanation.lines() {
+            if line.starts_with("    ") || (line.is_empty() && example.is_some()) {
+                let example = example.get_or_insert_with(String::new);
+                example.push_str(line.get(4..).unwrap_or(""));
+                example.push('\n');
+            } else if let Some(example) = example.take() {
+                examples.push(example.trim_end().to_string() + "\n");
+            }
+        }
+        examples.extend(example.map(|example| example.trim_end().to_string() + "\n"));
+        examples
+    }
+
+    // the files of the example, each after a `// in foo.hb` line, or the
+    // example as `test.cb`.
+    fn files(example: &str) -> Vec<(String, String)> {
+        let mut files: Vec<(String, String)> = Vec::new();
+        for line in example.lines() {
+            if let Some(name) = line.strip_prefix("// in ") {
+                let name = name.split(|c: char| c == ',' || c.is_whitespace())
+                               .next().unwrap_or("");
+                files.push((name.to_string(), String::new()));
+            } else if let Some(file) = files.last_mut() {
+                file.1.push_str(line);
+                file.1.push('\n');
+            }
+        }
+        if files.is_empty() {
+            files.push(("test.cb".to_string(), example.to_string()));
+        }
+        files
+    }
+
+    // compile the source among the files, or one importing the first
+    // header, in a function if it is statements.
+    fn compile_files(files: &[(String, String)], in_function: bool) -> Compiled {
+        let dir = TempDir::new();
+        for (name, text) in files {
+            dir.write(name, text);
+        }
+        let (name, src) = match files.iter().find(|(name, _)| name.ends_with(".cb")) {
+            Some((name, src)) => (name.clone(), src.clone()),
+            None => ("test.cb".to_string(), format!("import {};\n",
+                                                    files[0].0.trim_end_matches(".hb"))),
+        };
+        let src = if in_function {
+            let body: String = src.lines().map(|line| format!("    {}\n", line)).collect();
+            format!("import stdio;\n\nint example(void) {{\n{}    return 0;\n}}\n", body)
+        } else {
+            src
+        };
+        compile_at(&dir.path().join(name), &src, &CompileOptionBuilder::new().finalize())
+    }
+
+    #[test]
+    fn examples_report_their_codes() {
+        let mut failures = Vec::new();
+        for &(code, explanation) in EXPLANATIONS {
+            let reported = UNREACHABLE.iter().find(|&&(c, _)| c == code)
+                                      .map_or(code, |&(_, reported)| reported);
+            let examples = examples(explanation);
+            if examples.len() < 2 {
+                failures.push(format!("{} has no erroneous example and fix", code));
+                continue;
+            }
+            let erroneous = files(&examples[0]);
+            let in_function = match [false, true].iter().find(|&&in_function| {
+                compile_files(&erroneous, in_function).errors().contains(&reported)
+            }) {
+                Some(&in_function) => in_function,
+                None => {
+                    failures.push(format!("the example of {} reports {:?}", code,
+                                          compile_files(&erroneous, false).errors()));
+                    continue;
+                }
+            };
+            // a fix keeps the headers of the erroneous example it does not replace
+            for fix in examples[1..].iter().filter(|fix| !fix.starts_with("rcbc ")) {
+                let mut files = files(fix);
+                for file in &erroneous {
+                    if !file.0.ends_with(".cb") && !files.iter().any(|f| f.0 == file.0) {
+                        files.push(file.clone());
+                    }
+                }
+                if compile_files(&files, in_function).errors().contains(&reported) {
+                    failures.push(format!("the fix of {} reports it:\n{}", code, fix));
+                }
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,430 @@
+//! The pass checking the calls of the `printf`-style functions.
+//!
+//! A function declared with `__attribute__((format(printf, N, M)))` takes a
+//! format string in its parameter N. When the argument is a string literal,
+//! its conversion specifications are parsed, and the arguments from the M-th
+//! must be as many as the conversions and of the types they expect, after
+//! the default argument promotion.
+
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+use super::ast::*;
+use super::location::Location;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::entity::EntityId;
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::{Diagnostic, DiagnosticHandler};
+use super::warning::Warning;
+use super::visitor::{self, Visitor};
+
+pub struct FormatChecker<'a> {
+    table: &'a TypeTable,
+    handler: &'a mut DiagnosticHandler,
+    formats: HashMap<EntityId, (usize, usize)>, // the format and the first argument
+}
+
+// A conversion specification such as `%-8.3ld`, or its `*`, and the
+// argument it converts.
+struct Conversion {
+    spec: String, // as written
+    is_star: bool, // the width or the precision
+    argument: Argument,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Argument {
+    Integer(Length),  // `%d`, `%i`, `%c` and `*`
+    Unsigned(Length), // `%o`, `%u`, `%x` and `%X`
+    String,          // `%s`
+    Pointer,         // `%p`
+    Count(Length),   // `%n`
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Length {
+    Default,
+    Char,  // `hh`
+    Short, // `h`
+    Long,  // `l`
+    Size,  // `z`, of a `size_t`, which is an `unsigned long`
+}
+
+impl<'a> FormatChecker<'a> {
+    pub fn new(table: &'a TypeTable,
+               handler: &'a mut DiagnosticHandler) -> FormatChecker<'a> {
+        FormatChecker {
+            table: table,
+            handler: handler,
+            formats: HashMap::new(),
+        }
+    }
+
+    /// Check all the calls of the functions with a format attribute, the
+    /// errors and the warnings are reported to the handler.
+    pub fn check(&mut self, ast: &mut AST) {
+        for func in &ast.funcdecls {
+            if let (Some(format), Some(entity)) = (func.format.as_ref(), func.entity) {
+                if self.check_attribute(func, format) {
+                    self.formats.insert(entity, (format.format_index, format.first_arg));
+                }
+            }
+        }
+        self.visit_ast(ast);
+    }
+
+    fn check_attribute(&mut self, func: &UndefinedFunction,
+                       format: &FormatAttribute) -> bool {
+        let params = &func.params;
+        let message = match params.params.get(format.format_index.wrapping_sub(1)) {
+            None => format!("the format attribute of `{}` refers to the parameter {}, \
+                             but it has {}", func.name, format.format_index,
+                            params.params.len()),
+            Some(param) if !self.is_string(param.type_.type_) =>
+                format!("the format string of `{}` is the parameter `{}`, \
+                         which is not a `char *`", func.name, param.name),
+            _ if format.first_arg != 0 &&
+                 (!params.is_variadic || format.first_arg != params.params.len() + 1) =>
+                format!("the format arguments of `{}` must be its variadic ones, \
+                         from the argument {}", func.name, params.params.len() + 1),
+            _ => return true,
+        };
+        let error = SemanticError::new(SemanticErrorKind::InvalidFormatParameter,
+            format.location, message)
+            .with_secondary(params.location, "the parameters are here");
+        self.handler.emit(error.to_diagnostic());
+        false
+    }
+
+    fn is_string(&self, type_: Option<TypeId>) -> bool {
+        type_.is_some_and(|type_| {
+            (self.table.is_pointer(type_) || self.table.is_array(type_)) &&
+            self.table.base_type(type_).is_some_and(|base| self.is_char(base))
+        })
+    }
+
+    fn is_char(&self, type_: TypeId) -> bool {
+        match self.table.integer_ref(self.table.real_type(type_)) {
+            Some(IntegerTypeRef::Char) | Some(IntegerTypeRef::UnsignedChar) => true,
+            _ => false,
+        }
+    }
+
+    fn warn(&mut self, message: String, location: Location, format: Location) {
+        self.handler.warn(Warning::Format,
+            Diagnostic::warning(message)
+                .with_primary(location, "")
+                .with_secondary(format, "the format string is here"));
+    }
+
+    fn check_call(&mut self, node: &FuncallNode, format_index: usize, first_arg: usize) {
+        let format = match node.args.get(format_index - 1) {
+            Some(format) => format,
+            None => return,
+        };
+        // only a literal is known before running
+        let (string, format) = match format.node_ref() {
+            NodeRef::StringLiteralNode(literal) => (&literal.value, literal.location),
+            _ => return,
+        };
+        let conversions = match parse_format(string) {
+            Ok(conversions) => conversions,
+            Err(message) => {
+                self.handler.warn(Warning::Format,
+                    Diagnostic::warning(message).with_primary(format, ""));
+                return;
+            }
+        };
+        if first_arg == 0 {
+            return;
+        }
+
+        let args = node.args.get(first_arg - 1..).unwrap_or(&[]);
+        for (i, conversion) in conversions.iter().enumerate() {
+            let what = if conversion.is_star {
+                format!("the `*` in `{}`", conversion.spec)
+            } else {
+                format!("`{}`", conversion.spec)
+            };
+            let arg = match args.get(i) {
+                Some(arg) => arg,
+                None => {
+                    let message = format!("{} expects a matching `{}` argument",
+                                          what, conversion.argument.expected());
+                    self.warn(message, node.location, format);
+                    return;
+                }
+            };
+            let type_ = match arg.expr_type() {
+                Some(type_) => type_,
+                None => continue,
+            };
+            if !self.matches(conversion.argument, type_) {
+                let message = format!("{} expects an argument of type `{}`, but the \
+                                       argument {} has type `{}`", what,
+                                      conversion.argument.expected(), first_arg + i,
+                                      self.table.type_name(type_));
+                self.warn(message, arg.location(), format);
+            }
+        }
+        if let Some(arg) = args.get(conversions.len()) {
+            let message = format!("too many arguments for the format, which converts {}",
+                                  conversions.len());
+            self.warn(message, arg.location(), format);
+        }
+    }
+
+    // whether the promoted argument is of the type the conversion expects.
+    fn matches(&self, argument: Argument, type_: TypeId) -> bool {
+        match argument {
+            Argument::Integer(length) |
+            Argument::Unsigned(length) => self.table.integer_ref(self.table.real_type(type_))
+                                             .is_some_and(|integer| length.matches(integer)),
+            Argument::String => self.is_string(Some(type_)),
+            Argument::Pointer => self.table.is_pointer(type_) || self.table.is_array(type_) ||
+                                 self.table.is_function(type_),
+            Argument::Count(length) => {
+                let base = match self.table.base_type(type_) {
+                    Some(base) if self.table.is_pointer(type_) => base,
+                    _ => return false,
+                };
+                let integer = self.table.integer_ref(self.table.real_type(base));
+                integer.is_some_and(|integer| length.counts(integer))
+            }
+        }
+    }
+}
+
+impl<'a> Visitor for FormatChecker<'a> {
+    fn visit_funcall(&mut self, node: &mut FuncallNode) {
+        visitor::walk_funcall(self, node);
+        let format = match node.expr.node_ref() {
+            NodeRef::VariableNode(var) => var.entity.and_then(|e| self.formats.get(&e)),
+            _ => None,
+        };
+        if let Some(&(format_index, first_arg)) = format {
+            self.check_call(node, format_index, first_arg);
+        }
+    }
+}
+
+impl Argument {
+    // the type of the argument as written in the messages.
+    fn expected(&self) -> &'static str {
+        match *self {
+            Argument::Integer(Length::Long) => "long",
+            Argument::Integer(Length::Size) => "ssize_t",
+            Argument::Integer(_) => "int",
+            Argument::Unsigned(Length::Long) => "unsigned long",
+            Argument::Unsigned(Length::Size) => "size_t",
+            Argument::Unsigned(_) => "unsigned int",
+            Argument::String => "char *",
+            Argument::Pointer => "void *",
+            Argument::Count(Length::Default) => "int *",
+            Argument::Count(Length::Char) => "char *",
+            Argument::Count(Length::Short) => "short *",
+            Argument::Count(Length::Long) => "long *",
+            Argument::Count(Length::Size) => "ssize_t *",
+        }
+    }
+}
+
+impl Length {
+    // the integers smaller than `int` are promoted, and the sign of the
+    // argument does not matter.
+    fn matches(&self, integer: IntegerTypeRef) -> bool {
+        match *self {
+            Length::Long | Length::Size => integer == IntegerTypeRef::Long ||
+                                           integer == IntegerTypeRef::UnsignedLong,
+            _ => integer == IntegerTypeRef::Int || integer == IntegerTypeRef::UnsignedInt,
+        }
+    }
+
+    // the integer pointed by the argument of `%n`, which is not promoted.
+    fn counts(&self, integer: IntegerTypeRef) -> bool {
+        let signed = match *self {
+            Length::Default => IntegerTypeRef::Int,
+            Length::Char => IntegerTypeRef::Char,
+            Length::Short => IntegerTypeRef::Short,
+            Length::Long | Length::Size => IntegerTypeRef::Long,
+        };
+        integer == signed || integer == signed.to_unsigned()
+    }
+}
+
+// the conversions of the format in the order of their arguments, or the
+// message of the first invalid one.
+fn parse_format(format: &str) -> Result<Vec<Conversion>, String> {
+    let mut conversions = Vec::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        let mut spec = String::from("%");
+        let mut stars = 0;
+        while let Some(flag) = next_if(&mut chars, |c| "-+ #0".contains(c)) {
+            spec.push(flag);
+        }
+        stars += width(&mut chars, &mut spec);
+        if let Some(dot) = next_if(&mut chars, |c| c == '.') {
+            spec.push(dot);
+            stars += width(&mut chars, &mut spec);
+        }
+        let mut length = Length::Default;
+        if let Some(h) = next_if(&mut chars, |c| c == 'h') {
+            spec.push(h);
+            length = Length::Short;
+            if let Some(h) = next_if(&mut chars, |c| c == 'h') {
+                spec.push(h);
+                length = Length::Char;
+            }
+        } else if let Some(l) = next_if(&mut chars, |c| c == 'l') {
+            spec.push(l);
+            length = Length::Long;
+        } else if let Some(z) = next_if(&mut chars, |c| c == 'z') {
+            spec.push(z);
+            length = Length::Size;
+        }
+
+        let conversion = match chars.next() {
+            Some(conversion) => conversion,
+            None => return Err(format!("the format ends in the middle of `{}`", spec)),
+        };
+        spec.push(conversion);
+        let argument = match conversion {
+            'd' | 'i' => Argument::Integer(length),
+            'o' | 'u' | 'x' | 'X' => Argument::Unsigned(length),
+            'n' => Argument::Count(length),
+            'c' => Argument::Integer(Length::Default),
+            's' => Argument::String,
+            'p' => Argument::Pointer,
+            '%' if spec == "%%" => continue,
+            _ => return Err(format!("unknown conversion `{}` in the format", spec)),
+        };
+        let has_length = match argument {
+            Argument::Integer(_) | Argument::Unsigned(_) | Argument::Count(_) => true,
+            _ => false,
+        };
+        if length != Length::Default && (!has_length || conversion == 'c') {
+            return Err(format!("the length modifier of `{}` is not for `%{}`",
+                               spec, conversion));
+        }
+        for _ in 0..stars {
+            conversions.push(Conversion {
+                spec: spec.clone(),
+                is_star: true,
+                argument: Argument::Integer(Length::Default),
+            });
+        }
+        conversions.push(Conversion {
+            spec: spec,
+            is_star: false,
+            argument: argument,
+        });
+    }
+    Ok(conversions)
+}
+
+// a width or a precision, which are digits or `*`, and the number of `*`.
+fn width(chars: &mut Peekable<Chars>, spec: &mut String) -> usize {
+    if let Some(star) = next_if(chars, |c| c == '*') {
+        spec.push(star);
+        return 1;
+    }
+    while let Some(digit) = next_if(chars, |c| c.is_digit(10)) {
+        spec.push(digit);
+    }
+    0
+}
+
+fn next_if<F: Fn(char) -> bool>(chars: &mut Peekable<Chars>, f: F) -> Option<char> {
+    match chars.peek() {
+        Some(&c) if f(c) => {}
+        _ => return None,
+    }
+    chars.next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{compile, TempDir};
+    use super::super::warning::Warning;
+
+    fn warnings(call: &str) -> Vec<Warning> {
+        let compiled = compile(&format!("import stdio;\nimport stddef;\n\
+                                         int main(int argc, char** argv) {{\n\
+                                             long l = argc; char c = 'c'; char* fmt = argv[0];\n\
+                                             size_t n = argc;\n\
+                                             {}\n\
+                                             return 0;\n\
+                                         }}\n", call));
+        assert!(compiled.errors().is_empty(), "{:#?}", compiled.diagnostics);
+        compiled.warnings()
+    }
+
+    #[test]
+    fn matching_arguments() {
+        for call in &["printf(\"%d %s %c\\n\", argc, argv[0], c);",
+                      "printf(\"%ld %lu %x %5.2s %% %p\\n\", l, l, argc, fmt, fmt);",
+                      "printf(\"%*d\\n\", argc, argc);",
+                      "printf(\"%zu %zx %zd\\n\", n, n, sizeof(int));",
+                      "fprintf(stderr, \"%s\\n\", fmt);",
+                      "printf(fmt, argc);"] {
+            assert_eq!(warnings(call), [], "{}", call);
+        }
+    }
+
+    #[test]
+    fn mismatched_arguments() {
+        for call in &["printf(\"%d %d\\n\", argc);",
+                      "printf(\"%d\\n\", argc, argc);",
+                      "printf(\"%s\\n\", argc);",
+                      "printf(\"%d\\n\", l);",
+                      "printf(\"%zu\\n\", argc);",
+                      "printf(\"%zs\\n\", fmt);",
+                      "printf(\"%*d\\n\", l, argc);",
+                      "printf(\"%q\\n\", argc);",
+                      "printf(\"%\");",
+                      "printf(\"%hs\\n\", fmt);"] {
+            assert_eq!(warnings(call), [Warning::Format], "{}", call);
+        }
+    }
+
+    #[test]
+    fn expected_types_follow_the_sign() {
+        for &(call, expected) in &[("\"%lu\", argc", "`%lu` expects an argument of type \
+                                                     `unsigned long`, but the argument 2 \
+                                                     has type `int`"),
+                                   ("\"%ld\", argc", "`%ld` expects an argument of type \
+                                                     `long`, but the argument 2 has type \
+                                                     `int`"),
+                                   ("\"%x\", l", "`%x` expects an argument of type \
+                                                 `unsigned int`, but the argument 2 has \
+                                                 type `long`"),
+                                   ("\"%zu\"", "`%zu` expects a matching `size_t` argument")] {
+            let compiled = compile(&format!("import stdio;\n\
+                                             void f(int argc, long l) {{ printf({}); }}\n",
+                                            call));
+            assert!(compiled.has_message(expected), "{:#?}", compiled.diagnostics);
+        }
+    }
+
+    #[test]
+    fn invalid_format_parameter() {
+        let dir = TempDir::new();
+        dir.write("log.hb", "extern int log(int level, char* fmt, ...) \
+                                 __attribute__((format(printf, 2, 3)));\n\
+                             extern int lines(char* fmt, char* arg) \
+                                 __attribute__((format(printf, 1, 0)));\n");
+        dir.compile("import log;\nint main(void) { return log(1, \"%d\", 2) + lines(\"%s\", \"\"); }\n")
+           .assert_clean();
+
+        dir.write("log.hb", "extern int far(char* fmt, ...) __attribute__((format(printf, 3, 4)));\n\
+                             extern int level(int fmt, ...) __attribute__((format(printf, 1, 2)));\n\
+                             extern int fixed(char* fmt, int x) \
+                                 __attribute__((format(printf, 1, 2)));\n");
+        assert_eq!(dir.compile("import log;\n").errors(), ["E0090"; 3]);
+    }
+}
@@ -0,0 +1,276 @@
+//! The pass checking the source against the header of its module.
+//!
+//! `foo.hb` is the interface of `foo.cb`, so each function and variable it
+//! declares must be defined in the source, with the same type and without
+//! `static`. Otherwise the other modules importing the header are compiled
+//! against the declarations which the definitions do not match.
+//!
+//! The types the header defines may be defined in the source as well, as in
+//! the headers written by `--emit=header`. Such a type is taken from the
+//! source, and it must be defined the same in both.
+
+use std::collections::HashMap;
+use super::ast::*;
+use super::location::Location;
+use super::source_map::FileId;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::DiagnosticHandler;
+
+pub struct HeaderChecker<'a> {
+    table: &'a TypeTable,
+    handler: &'a mut DiagnosticHandler,
+}
+
+/// Remove from the AST the types defined by the header which the source
+/// file defines too, and report those defined differently.
+pub fn merge_types(ast: &mut AST, source: FileId, header: FileId,
+                   handler: &mut DiagnosticHandler) {
+    let mut redefined = Vec::new();
+    {
+        let in_source = |location: &Location| location.file == source;
+        let in_header = |location: &Location| location.file == header;
+        for node in ast.defstructs.iter().filter(|node| in_header(&node.location)) {
+            if let Some(source) = ast.defstructs.iter().find(|s| in_source(&s.location) &&
+                                                                 s.name == node.name) {
+                redefined.push((format!("struct {}", node.name), node.location,
+                                source.location, same_members(&node.members, &source.members)));
+            }
+        }
+        for node in ast.defunions.iter().filter(|node| in_header(&node.location)) {
+            if let Some(source) = ast.defunions.iter().find(|u| in_source(&u.location) &&
+                                                                u.name == node.name) {
+                redefined.push((format!("union {}", node.name), node.location,
+                                source.location, same_members(&node.members, &source.members)));
+            }
+        }
+        for node in ast.typedefs.iter().filter(|node| in_header(&node.location)) {
+            if let Some(source) = ast.typedefs.iter().find(|t| in_source(&t.location) &&
+                                                               t.name == node.name) {
+                redefined.push((node.name.clone(), node.location, source.location,
+                                same_typeref(&node.real.type_ref, &source.real.type_ref)));
+            }
+        }
+        for node in ast.defenums.iter().filter(|node| in_header(&node.location)) {
+            let name = match node.name {
+                Some(ref name) => name,
+                None => continue,
+            };
+            if let Some(source) = ast.defenums.iter().find(|e| in_source(&e.location) &&
+                                                               e.name.as_ref() == Some(name)) {
+                let same = node.members.len() == source.members.len() &&
+                           node.members.iter().zip(&source.members)
+                                              .all(|(h, s)| h.name == s.name);
+                redefined.push((format!("enum {}", name), node.location, source.location,
+                                same));
+            }
+        }
+    }
+
+    for &(ref name, location, source, same) in &redefined {
+        if !same {
+            let error = SemanticError::new(SemanticErrorKind::HeaderMismatch, source,
+                format!("`{}` is defined differently from the header", name))
+                .with_secondary(location, "defined in the header here");
+            handler.emit(error.to_diagnostic());
+        }
+    }
+    let is_redefined = |location: Location| redefined.iter().any(|r| r.1 == location);
+    ast.defstructs.retain(|node| !is_redefined(node.location));
+    ast.defunions.retain(|node| !is_redefined(node.location));
+    ast.typedefs.retain(|node| !is_redefined(node.location));
+    ast.defenums.retain(|node| !is_redefined(node.location));
+}
+
+fn same_members(header: &[Slot], source: &[Slot]) -> bool {
+    header.len() == source.len() &&
+    header.iter().zip(source).all(|(h, s)| {
+        h.name == s.name && same_typeref(&h.type_.type_ref, &s.type_.type_ref)
+    })
+}
+
+// whether the types are written the same. The length of an array may be a
+// constant expression, which is not evaluated yet, so it matches any.
+fn same_typeref(header: &TypeRef, source: &TypeRef) -> bool {
+    match (header, source) {
+        (TypeRef::Pointer(h), TypeRef::Pointer(s)) =>
+            same_typeref(&h.base_type, &s.base_type),
+        (TypeRef::Const(h), TypeRef::Const(s)) =>
+            same_typeref(&h.base_type, &s.base_type),
+        (TypeRef::Array(h), TypeRef::Array(s)) => {
+            let same_length = match (h.length, s.length) {
+                (Some(ArrayLength::Constant(_)), Some(_)) |
+                (Some(_), Some(ArrayLength::Constant(_))) => true,
+                (h, s) => h == s,
+            };
+            same_length && same_typeref(&h.base_type, &s.base_type)
+        }
+        (TypeRef::Function(h), TypeRef::Function(s)) =>
+            h.params.is_variadic == s.params.is_variadic &&
+            h.params.types.len() == s.params.types.len() &&
+            same_typeref(&h.return_type, &s.return_type) &&
+            h.params.types.iter().zip(&s.params.types).all(|(h, s)| same_typeref(h, s)),
+        (h, s) => h == s,
+    }
+}
+
+// a global definition of the source.
+struct Definition {
+    location: Location,
+    is_function: bool,
+    is_private: bool,
+    type_: Option<TypeId>,
+}
+
+impl<'a> HeaderChecker<'a> {
+    pub fn new(table: &'a TypeTable,
+               handler: &'a mut DiagnosticHandler) -> HeaderChecker<'a> {
+        HeaderChecker {
+            table: table,
+            handler: handler,
+        }
+    }
+
+    /// Check the declarations of the header against the definitions, the
+    /// errors are reported to the handler.
+    pub fn check(&mut self, ast: &AST, header: FileId) {
+        let mut definitions = HashMap::new();
+        for func in &ast.defuns {
+            definitions.insert(func.name.as_str(), Definition {
+                location: func.location,
+                is_function: true,
+                is_private: func.is_private,
+                type_: func.type_.type_,
+            });
+        }
+        for var in &ast.defvars {
+            definitions.insert(var.name.as_str(), Definition {
+                location: var.location,
+                is_function: false,
+                is_private: var.is_private,
+                type_: var.type_.type_,
+            });
+        }
+
+        for func in ast.funcdecls.iter().filter(|f| f.location.file == header) {
+            self.check_declaration(&func.name, func.location, true, func.type_.type_,
+                                   definitions.get(func.name.as_str()));
+        }
+        for var in ast.vardecls.iter().filter(|v| v.location.file == header) {
+            self.check_declaration(&var.name, var.location, false, var.type_.type_,
+                                   definitions.get(var.name.as_str()));
+        }
+    }
+
+    fn check_declaration(&mut self, name: &str, location: Location, is_function: bool,
+                         type_: Option<TypeId>, definition: Option<&Definition>) {
+        let what = if is_function { "function" } else { "variable" };
+        let definition = match definition {
+            Some(definition) => definition,
+            None => {
+                let error = SemanticError::new(SemanticErrorKind::MissingDefinition,
+                    location, format!("the {} `{}` is declared in the header of the \
+                                       module, but it is not defined", what, name))
+                    .with_help(format!("define `{}` in the source, or remove the \
+                                        declaration", name));
+                self.handler.emit(error.to_diagnostic());
+                return;
+            }
+        };
+
+        let error = if definition.is_function != is_function {
+            let defined = if definition.is_function { "function" } else { "variable" };
+            SemanticError::new(SemanticErrorKind::HeaderMismatch, definition.location,
+                format!("`{}` is defined as a {}, but the header declares a {}",
+                        name, defined, what))
+        } else if definition.is_private {
+            SemanticError::new(SemanticErrorKind::HeaderMismatch, definition.location,
+                format!("`{}` is defined `static`, but the header declares it `extern`",
+                        name))
+                .with_help(format!("remove the `static`, or the declaration of `{}` \
+                                    from the header", name))
+        } else {
+            let (declared, defined) = match (type_, definition.type_) {
+                (Some(declared), Some(defined)) => (declared, defined),
+                _ => return, // not resolved, which is reported elsewhere
+            };
+            if self.is_same(declared, defined, is_function) {
+                return;
+            }
+            SemanticError::new(SemanticErrorKind::HeaderMismatch, definition.location,
+                format!("`{}` is defined with the type `{}`, but the header declares \
+                         `{}`", name, self.table.type_name(defined),
+                        self.table.type_name(declared)))
+        };
+        let error = error.with_secondary(location, "declared in the header here");
+        self.handler.emit(error.to_diagnostic());
+    }
+
+    // the `const` of a parameter is not a part of the function type, but the
+    // one of a variable is.
+    fn is_same(&self, declared: TypeId, defined: TypeId, is_function: bool) -> bool {
+        self.table.is_compatible(declared, defined) &&
+        (is_function || self.table.is_const(declared) == self.table.is_const(defined))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{Compiled, TempDir};
+
+    // compile `test.cb` with `test.hb`, the header of its module.
+    fn check(header: &str, src: &str) -> Compiled {
+        let dir = TempDir::new();
+        dir.write("test.hb", header);
+        dir.compile(src)
+    }
+
+    #[test]
+    fn matching_definitions() {
+        check("struct point { int x; int y; };\n\
+               enum color { RED, GREEN };\n\
+               extern int count;\n\
+               extern int twice(int x);\n\
+               extern int sum(int[] xs, int n);\n",
+              "struct point { int x; int y; };\n\
+               enum color { RED, GREEN };\n\
+               int count;\n\
+               int twice(int x) { return 2 * x + RED; }\n\
+               int sum(int* xs, int n) { return xs[n]; }\n\
+               static int helper(void) { return twice(count); }\n").assert_clean();
+    }
+
+    #[test]
+    fn missing_definition() {
+        let compiled = check("extern int count;\nextern int twice(int x);\n",
+                             "int count;\n");
+        assert_eq!(compiled.errors(), ["E0095"]);
+        assert!(compiled.has_message("the function `twice` is declared in the header of the \
+                                      module, but it is not defined"));
+    }
+
+    #[test]
+    fn header_mismatch() {
+        let compiled = check("extern long count;\n\
+                              extern int twice(int x);\n\
+                              extern int limit;\n\
+                              extern int size;\n",
+                             "int count;\n\
+                              static int twice(int x) { return 2 * x; }\n\
+                              int limit(void) { return 0; }\n\
+                              int size;\n");
+        assert_eq!(compiled.errors(), ["E0094"; 3]);
+    }
+
+    #[test]
+    fn types_defined_differently() {
+        let compiled = check("struct point { int x; int y; };\n\
+                              union value { int i; char* s; };\n\
+                              enum color { RED, GREEN };\n",
+                             "struct point { int x; long y; };\n\
+                              union value { int i; char* s; };\n\
+                              enum color { RED, BLUE };\n");
+        assert_eq!(compiled.errors(), ["E0094"; 2]);
+    }
+}
@@ -0,0 +1,170 @@
+//! The `.hb` header of a compilation unit, for `--emit=header`.
+//!
+//! The header imports what the interface needs from the imports of the
+//! source, defines the types of the interface in their order, and declares
+//! the functions and the variables `extern`. The types are written as in
+//! the source, except the lengths of the arrays, which are evaluated.
+
+use super::ast::*;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::entity::EntityTable;
+use super::interface::{self, Interface, TypeDefinition};
+
+pub struct HeaderWriter<'a> {
+    table: &'a TypeTable,
+    entities: &'a EntityTable,
+}
+
+impl<'a> HeaderWriter<'a> {
+    pub fn new(table: &'a TypeTable, entities: &'a EntityTable) -> HeaderWriter<'a> {
+        HeaderWriter {
+            table: table,
+            entities: entities,
+        }
+    }
+
+    /// the header `name` of the interface, such as `foo.hb`.
+    pub fn write(&self, interface: &Interface, name: &str) -> String {
+        let mut sections = vec![format!("// {}\n// Generated by rcbc --emit=header.\n", name)];
+
+        if !interface.imports.is_empty() {
+            sections.push(interface.imports.iter()
+                                   .map(|import| match import.kind {
+                                       ImportKind::C => format!("import c \"{}\";\n",
+                                                                import.name),
+                                       _ => format!("import {};\n", import.name),
+                                   })
+                                   .collect());
+        }
+        if !interface.types.is_empty() {
+            sections.push(interface.types.iter()
+                                   .map(|&definition| self.type_definition(definition))
+                                   .collect());
+        }
+        if !interface.variables.is_empty() {
+            sections.push(interface.variables.iter()
+                                   .map(|var| format!("extern {} {};\n",
+                                                      self.typeref(&var.type_.type_ref),
+                                                      var.name))
+                                   .collect());
+        }
+        if !interface.functions.is_empty() {
+            sections.push(interface.functions.iter()
+                                   .map(|func| self.function(func))
+                                   .collect());
+        }
+        sections.join("\n")
+    }
+
+    fn type_definition(&self, definition: TypeDefinition) -> String {
+        match definition {
+            TypeDefinition::Struct(node) =>
+                format!("struct {} {{\n{}}};\n", node.name, self.members(&node.members)),
+            TypeDefinition::Union(node) =>
+                format!("union {} {{\n{}}};\n", node.name, self.members(&node.members)),
+            TypeDefinition::Typedef(node) =>
+                format!("typedef {} {};\n", self.typeref(&node.real.type_ref), node.name),
+            TypeDefinition::Enum(node) =>
+                format!("enum {} {{ {} }};\n", node.name.as_ref().map_or("", |n| n),
+                        interface::enumerators(node, self.entities)),
+        }
+    }
+
+    fn members(&self, members: &[Slot]) -> String {
+        members.iter()
+               .map(|member| format!("    {} {};\n", self.typeref(&member.type_.type_ref),
+                                     member.name))
+               .collect()
+    }
+
+    fn function(&self, func: &DefinedFunction) -> String {
+        let return_type = match func.type_.type_ref {
+            TypeRef::Function(ref function) => self.typeref(&function.return_type),
+            ref typeref => self.typeref(typeref),
+        };
+        let mut params: Vec<String> = func.params.params.iter()
+                                          .map(|param| format!("{} {}",
+                                                               self.typeref(&param.type_.type_ref),
+                                                               param.name))
+                                          .collect();
+        if func.params.is_variadic {
+            params.push("...".to_string());
+        }
+        if params.is_empty() {
+            params.push("void".to_string());
+        }
+        format!("extern {} {}({});\n", return_type, func.name, params.join(", "))
+    }
+
+    // the type in the syntax of Cb, such as `int(char*, ...)*`.
+    fn typeref(&self, typeref: &TypeRef) -> String {
+        match *typeref {
+            TypeRef::Void => "void".to_string(),
+            TypeRef::Integer(integer) => integer.name().to_string(),
+            TypeRef::Struct(ref struct_) => format!("struct {}", struct_.name),
+            TypeRef::Union(ref union) => format!("union {}", union.name),
+            TypeRef::Enum(ref enum_) => format!("enum {}", enum_.name),
+            TypeRef::User(ref user) => user.name.clone(),
+            TypeRef::Pointer(ref pointer) => format!("{}*", self.typeref(&pointer.base_type)),
+            TypeRef::Array(ref array) => {
+                let length = interface::array_length(array, self.table);
+                format!("{}[{}]", self.typeref(&array.base_type),
+                        length.map_or(String::new(), |length| length.to_string()))
+            }
+            TypeRef::Function(ref function) => {
+                let mut params: Vec<String> = function.params.types.iter()
+                                                      .map(|param| self.typeref(param))
+                                                      .collect();
+                if function.params.is_variadic {
+                    params.push("...".to_string());
+                }
+                if params.is_empty() {
+                    params.push("void".to_string());
+                }
+                format!("{}({})", self.typeref(&function.return_type), params.join(", "))
+            }
+            TypeRef::Const(ref const_) => match *const_.base_type {
+                // `int* const`, the pointer itself is const
+                TypeRef::Pointer(_) | TypeRef::Array(_) | TypeRef::Function(_) =>
+                    format!("{} const", self.typeref(&const_.base_type)),
+                ref base_type => format!("const {}", self.typeref(base_type)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Emit;
+    use super::super::testing::{emit, MODULE};
+
+    #[test]
+    fn the_header_of_a_module() {
+        // only the types the definitions refer to, not `enum color`, and not the `static` ones
+        assert_eq!(emit(MODULE, Emit::Header).output(), r#"// test.hb
+// Generated by rcbc --emit=header.
+
+import stdio;
+
+typedef struct point point_t;
+struct point {
+    int x;
+    int y;
+};
+union value {
+    int i;
+    char* s;
+};
+
+extern int count;
+extern const char* name;
+extern point_t origin;
+extern int[4] table;
+extern int* last;
+
+extern int area(point_t* p, int[] scale, int(int) f);
+extern int show(FILE* out, union value v);
+"#);
+    }
+}
@@ -0,0 +1,64 @@
+//! The standard headers built in the compiler.
+//!
+//! They declare the functions, the types and the constants of the C library
+//! the programs are linked with, so `import stdio;` works without any `-I`.
+//! A header of the same name found in the search path is used instead.
+
+// the name to import, the C header of the same declarations, and the text
+const HEADERS: &'static [(&'static str, &'static str, &'static str)] = &[
+    ("stddef", "sys/types.h", include_str!("headers/stddef.hb")),
+    ("stdio", "stdio.h", include_str!("headers/stdio.hb")),
+    ("stdlib", "stdlib.h", include_str!("headers/stdlib.hb")),
+    ("string", "string.h", include_str!("headers/string.hb")),
+    ("ctype", "ctype.h", include_str!("headers/ctype.hb")),
+    ("unistd", "unistd.h", include_str!("headers/unistd.hb")),
+    ("errno", "errno.h", include_str!("headers/errno.hb")),
+    ("alloca", "alloca.h", include_str!("headers/alloca.hb")),
+];
+
+/// the text of the standard header imported by the name, as `stdio`.
+pub fn standard_header(name: &str) -> Option<&'static str> {
+    HEADERS.iter()
+           .find(|&&(header, _, _)| header == name)
+           .map(|&(_, _, src)| src)
+}
+
+/// the C header to include for the standard header, as `stdio.h`.
+pub fn standard_c_header(name: &str) -> Option<&'static str> {
+    HEADERS.iter()
+           .find(|&&(header, _, _)| header == name)
+           .map(|&(_, c_header, _)| c_header)
+}
+
+/// the names of all the standard headers.
+pub fn standard_headers() -> Vec<&'static str> {
+    HEADERS.iter().map(|&(name, _, _)| name).collect()
+}
+
+/// the standard header declaring what the C header does, as `stdio` of
+/// `stdio.h`, for the C headers importing it.
+pub fn standard_header_of(c_header: &str) -> Option<&'static str> {
+    let c_header = match c_header {
+        "stddef.h" => "sys/types.h",
+        c_header => c_header,
+    };
+    HEADERS.iter()
+           .find(|&&(_, c, _)| c == c_header)
+           .map(|&(name, _, _)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert!(standard_header("stdio").unwrap().starts_with("// stdio.hb"));
+        assert_eq!(standard_header("stdio.h"), None);
+        assert_eq!(standard_c_header("stddef"), Some("sys/types.h"));
+        assert_eq!(standard_header_of("stdio.h"), Some("stdio"));
+        assert_eq!(standard_header_of("stddef.h"), Some("stddef"));
+        assert_eq!(standard_header_of("sys/socket.h"), None);
+        assert_eq!(standard_headers().len(), HEADERS.len());
+    }
+}
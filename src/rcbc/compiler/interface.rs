@@ -0,0 +1,233 @@
+//! The interface of a compilation unit, what the other modules can use.
+//!
+//! It is the functions and the global variables which are not `static`, and
+//! the structs, unions, typedefs and enums their types refer to, directly or
+//! through the others. The types defined in the source are ordered so each
+//! typedef name and enum is defined before it is used, and each struct and
+//! union before it is held by value, as C needs it. The others are in the
+//! imports which are kept.
+
+use std::collections::{HashMap, HashSet};
+use super::ast::*;
+use super::location::Location;
+use super::source_map::FileId;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::entity::EntityTable;
+
+pub struct Interface<'a> {
+    pub functions: Vec<&'a DefinedFunction>,
+    pub variables: Vec<&'a DefinedVariable>,
+    pub types: Vec<TypeDefinition<'a>>, // in the order to define them
+    pub imports: Vec<&'a ImportNode>, // of the types not in the source
+}
+
+#[derive(Copy, Clone)]
+pub enum TypeDefinition<'a> {
+    Struct(&'a StructNode),
+    Union(&'a UnionNode),
+    Typedef(&'a TypedefNode),
+    Enum(&'a EnumNode),
+}
+
+// A type by its name as written, such as `struct point` or `size_t`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum TypeName<'a> {
+    Struct(&'a str),
+    Union(&'a str),
+    Typedef(&'a str),
+    Enum(&'a str),
+}
+
+struct Collector<'a> {
+    file: FileId, // of the source
+    definitions: HashMap<TypeName<'a>, TypeDefinition<'a>>,
+    seen: HashSet<TypeName<'a>>,
+    types: Vec<TypeDefinition<'a>>,
+    headers: HashSet<FileId>, // where the other types are defined
+}
+
+impl<'a> Interface<'a> {
+    /// the interface of the source `file`, whose definitions are in the AST.
+    pub fn new(ast: &'a AST, file: FileId) -> Interface<'a> {
+        let functions: Vec<&DefinedFunction> = ast.defuns.iter()
+                                                  .filter(|f| !f.is_private)
+                                                  .collect();
+        let variables: Vec<&DefinedVariable> = ast.defvars.iter()
+                                                  .filter(|v| !v.is_private)
+                                                  .collect();
+
+        let mut collector = Collector::new(ast, file);
+        for func in &functions {
+            collector.use_typeref(&func.type_.type_ref);
+        }
+        for var in &variables {
+            collector.use_typeref(&var.type_.type_ref);
+        }
+        let headers = collector.headers;
+        let imports = ast.imports.iter().filter(|import| {
+            import.file.into_iter().chain(import.headers.iter().cloned())
+                  .any(|file| headers.contains(&file))
+        }).collect();
+
+        Interface {
+            functions: functions,
+            variables: variables,
+            types: collector.types,
+            imports: imports,
+        }
+    }
+}
+
+impl<'a> TypeDefinition<'a> {
+    pub fn location(&self) -> Location {
+        match *self {
+            TypeDefinition::Struct(node) => node.location,
+            TypeDefinition::Union(node) => node.location,
+            TypeDefinition::Typedef(node) => node.location,
+            TypeDefinition::Enum(node) => node.location,
+        }
+    }
+}
+
+impl<'a> Collector<'a> {
+    fn new(ast: &'a AST, file: FileId) -> Collector<'a> {
+        let mut definitions = HashMap::new();
+        for node in &ast.defstructs {
+            definitions.insert(TypeName::Struct(&node.name), TypeDefinition::Struct(node));
+        }
+        for node in &ast.defunions {
+            definitions.insert(TypeName::Union(&node.name), TypeDefinition::Union(node));
+        }
+        for node in &ast.typedefs {
+            definitions.insert(TypeName::Typedef(&node.name), TypeDefinition::Typedef(node));
+        }
+        for node in &ast.defenums {
+            if let Some(ref name) = node.name {
+                definitions.insert(TypeName::Enum(name), TypeDefinition::Enum(node));
+            }
+        }
+        Collector {
+            file: file,
+            definitions: definitions,
+            seen: HashSet::new(),
+            types: Vec::new(),
+            headers: HashSet::new(),
+        }
+    }
+
+    // the named types in the type, each after the ones it needs.
+    fn use_typeref(&mut self, typeref: &'a TypeRef) {
+        match *typeref {
+            TypeRef::Struct(ref struct_) => self.use_type(TypeName::Struct(&struct_.name)),
+            TypeRef::Union(ref union) => self.use_type(TypeName::Union(&union.name)),
+            TypeRef::Enum(ref enum_) => self.use_type(TypeName::Enum(&enum_.name)),
+            TypeRef::User(ref user) => self.use_type(TypeName::Typedef(&user.name)),
+            TypeRef::Pointer(ref pointer) => self.use_typeref(&pointer.base_type),
+            TypeRef::Array(ref array) => self.use_typeref(&array.base_type),
+            TypeRef::Const(ref const_) => self.use_typeref(&const_.base_type),
+            TypeRef::Function(ref function) => {
+                self.use_typeref(&function.return_type);
+                for param in &function.params.types {
+                    self.use_typeref(param);
+                }
+            }
+            TypeRef::Void | TypeRef::Integer(_) => {}
+        }
+    }
+
+    fn use_type(&mut self, name: TypeName<'a>) {
+        let definition = match self.definitions.get(&name) {
+            Some(&definition) => definition,
+            None => return, // undefined, which is reported elsewhere
+        };
+        if definition.location().file != self.file {
+            self.headers.insert(definition.location().file);
+            return;
+        }
+        if !self.seen.insert(name) {
+            return;
+        }
+        // the types it needs are defined before it, and the others it
+        // refers to after it, since one of them may refer back to it
+        let mut needed = Vec::new();
+        match definition {
+            TypeDefinition::Typedef(node) => needs(&node.real.type_ref, false, &mut needed),
+            TypeDefinition::Struct(node) => {
+                for member in &node.members {
+                    needs(&member.type_.type_ref, true, &mut needed);
+                }
+            }
+            TypeDefinition::Union(node) => {
+                for member in &node.members {
+                    needs(&member.type_.type_ref, true, &mut needed);
+                }
+            }
+            TypeDefinition::Enum(_) => {}
+        }
+        for name in needed {
+            self.use_type(name);
+        }
+        self.types.push(definition);
+        match definition {
+            TypeDefinition::Typedef(node) => self.use_typeref(&node.real.type_ref),
+            TypeDefinition::Struct(node) => {
+                for member in &node.members {
+                    self.use_typeref(&member.type_.type_ref);
+                }
+            }
+            TypeDefinition::Union(node) => {
+                for member in &node.members {
+                    self.use_typeref(&member.type_.type_ref);
+                }
+            }
+            TypeDefinition::Enum(_) => {}
+        }
+    }
+}
+
+/// the length of the array type, `None` if it has none.
+pub fn array_length(array: &ArrayTypeRef, table: &TypeTable) -> Option<usize> {
+    match array.length {
+        Some(ArrayLength::Fixed(length)) => Some(length),
+        Some(ArrayLength::Constant(id)) => table.array_length(id),
+        None => None,
+    }
+}
+
+/// the members of the enum as written in Cb and C, as `A, B = 2, C`, where
+/// the values written are evaluated.
+pub fn enumerators(node: &EnumNode, entities: &EntityTable) -> String {
+    let members: Vec<String> = node.members.iter().map(|member| {
+        let value = member.entity.and_then(|e| entities.get(e).value);
+        match (&member.value, value) {
+            (&Some(_), Some(value)) => format!("{} = {}", member.name, value),
+            _ => member.name.clone(),
+        }
+    }).collect();
+    members.join(", ")
+}
+
+// the named types which are defined before a declaration of the type,
+// `complete` if it is of an object, as a member. A typedef name is defined
+// before it is used, as the parser needs it, and so is an enum, but a struct
+// or a union only if it is complete, held by value or as the elements of an
+// array, not through a pointer.
+fn needs<'a>(typeref: &'a TypeRef, complete: bool, names: &mut Vec<TypeName<'a>>) {
+    match *typeref {
+        TypeRef::Struct(ref struct_) if complete => names.push(TypeName::Struct(&struct_.name)),
+        TypeRef::Union(ref union) if complete => names.push(TypeName::Union(&union.name)),
+        TypeRef::Enum(ref enum_) => names.push(TypeName::Enum(&enum_.name)),
+        TypeRef::User(ref user) => names.push(TypeName::Typedef(&user.name)),
+        TypeRef::Pointer(ref pointer) => needs(&pointer.base_type, false, names),
+        TypeRef::Array(ref array) => needs(&array.base_type, true, names),
+        TypeRef::Const(ref const_) => needs(&const_.base_type, complete, names),
+        TypeRef::Function(ref function) => {
+            needs(&function.return_type, false, names);
+            for param in &function.params.types {
+                needs(param, false, names);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,374 @@
+//! The loading of the headers imported by a compilation unit.
+//!
+//! `import sys.io;` is the header `sys/io.hb`, searched in the directories
+//! of the `-I` options, the directory of the source file and the default
+//! one in order, and the standard headers built in the compiler are the
+//! last resort. The declarations of a header are put into the AST before
+//! the source is parsed, so its typedef names are types in the source. A
+//! header is loaded once however many times it is imported, and it can not
+//! import itself through the others.
+//!
+//! The header of the module, `foo.hb` of `foo.cb`, is loaded as well if it
+//! is in the search path, even if the source does not import it, so its
+//! declarations can be checked against the definitions of the source.
+//!
+//! `import c "mylib.h";` is a C header by its path in the search path, read
+//! by the `c_reader`. The headers it includes are searched in its directory
+//! first, and a standard C header is the standard Cb one declaring the same,
+//! `stdio.hb` of `stdio.h`. An `#include` not found is skipped with a
+//! warning, since a C header may include the system ones Cb can not read.
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::path::{Path, PathBuf};
+use super::Result;
+use super::ast::{AST, ImportNode, ImportKind};
+use super::location::Location;
+use super::source_map::{FileId, SourceMap};
+use super::scanner::Scanner;
+use super::token::Token;
+use super::parser::Parser;
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::{Diagnostic, DiagnosticHandler};
+use super::warning::Warning;
+use super::headers;
+use super::c_reader::{self, CContext, CReader};
+
+/// where the headers of the standard library are installed.
+pub const DEFAULT_IMPORT_DIR: &'static str = "/usr/local/lib/rcbc/import";
+
+const EXT_HEADER: &'static str = "hb";
+
+pub struct Loader<'a> {
+    paths: Vec<PathBuf>, // to search the headers in, in order
+    source_map: &'a mut SourceMap,
+    handler: &'a mut DiagnosticHandler,
+    tokens: &'a mut HashMap<FileId, Vec<Token>>, // of the headers in the run
+    loading: Vec<(FileId, String)>, // the headers being loaded and their names
+    loaded: HashSet<FileId>,
+    imports: HashMap<FileId, Vec<FileId>>, // of each header loaded
+    imported: AST, // the declarations of the headers loaded
+    c_context: CContext, // of the C headers loaded
+}
+
+impl<'a> Loader<'a> {
+    pub fn new(paths: Vec<PathBuf>, source_map: &'a mut SourceMap,
+               handler: &'a mut DiagnosticHandler,
+               tokens: &'a mut HashMap<FileId, Vec<Token>>) -> Loader<'a> {
+        Loader {
+            paths: paths,
+            source_map: source_map,
+            handler: handler,
+            tokens: tokens,
+            loading: Vec::new(),
+            loaded: HashSet::new(),
+            imports: HashMap::new(),
+            imported: AST::empty(Location::default()),
+            c_context: CContext::new(),
+        }
+    }
+
+    /// Load the headers of the imports and the ones they import, then the
+    /// header of the module if it is given, and return all their
+    /// declarations and the file of the module header if there is one. The
+    /// headers not found and the import cycles are reported to the handler.
+    pub fn load(mut self, imports: &mut [ImportNode],
+                module: Option<&str>) -> Result<(AST, Option<FileId>)> {
+        for import in imports.iter_mut() {
+            self.load_import(import)?;
+        }
+        for import in imports.iter_mut() {
+            if let Some(file) = import.file {
+                import.headers = self.imported_by(file);
+            }
+        }
+        let file = match module {
+            Some(module) => self.load_module_header(module)?,
+            None => None,
+        };
+        self.c_context.opaque_types(&mut self.imported);
+        Ok((self.imported, file))
+    }
+
+    fn load_module_header(&mut self, module: &str) -> Result<Option<FileId>> {
+        let mut header = PathBuf::from(module);
+        header.set_extension(EXT_HEADER);
+        let file = match self.find(&header) {
+            Some(path) => Some(self.open_file(path, None)?),
+            None => None,
+        };
+        if let Some(file) = file {
+            self.load_header(file, module)?;
+        }
+        Ok(file)
+    }
+
+    fn load_import(&mut self, import: &mut ImportNode) -> Result<()> {
+        let file = match self.open(import)? {
+            Some(file) => file,
+            None => {
+                self.report_not_found(import);
+                return Ok(());
+            }
+        };
+        import.file = Some(file);
+
+        if let Some(position) = self.loading.iter().position(|&(f, _)| f == file) {
+            // the include guards of a C header stop it including itself
+            if import.kind != ImportKind::Include {
+                self.report_cycle(import, position);
+            }
+            return Ok(());
+        }
+        let name = import.name.clone();
+        self.load_header(file, &name)
+    }
+
+    // parse the header and the ones it imports, unless it is loaded.
+    fn load_header(&mut self, file: FileId, name: &str) -> Result<()> {
+        if !self.loaded.insert(file) {
+            return Ok(());
+        }
+        if self.source_map.file(file).path().extension().is_none_or(|ext| ext != EXT_HEADER) {
+            return self.load_c_header(file, name);
+        }
+
+        let tokens = self.scan(file)?;
+        let mut parser = Parser::new(&tokens);
+        let mut imports = parser.parse_imports()?;
+        self.loading.push((file, name.to_string()));
+        for import in &mut imports {
+            self.load_import(import)?;
+        }
+        self.loading.pop();
+        self.imports.insert(file, imports.iter().filter_map(|i| i.file).collect());
+        let imported = mem::replace(&mut self.imported, AST::empty(Location::default()));
+        self.imported = parser.parse_header(imported)?;
+        Ok(())
+    }
+
+    // read the C header after the ones it includes.
+    fn load_c_header(&mut self, file: FileId, name: &str) -> Result<()> {
+        let src = self.source_map.file(file).src().to_string();
+        let mut header = c_reader::preprocess(&src, file, self.handler, &mut self.c_context);
+        let mut includes = mem::take(&mut header.includes);
+        self.loading.push((file, name.to_string()));
+        for include in &mut includes {
+            self.load_import(include)?;
+        }
+        self.loading.pop();
+        self.imports.insert(file, includes.iter().filter_map(|i| i.file).collect());
+        let imported = mem::replace(&mut self.imported, AST::empty(Location::default()));
+        self.imported = CReader::new(header, self.handler, &mut self.c_context).read(imported);
+        Ok(())
+    }
+
+    // the headers the header imports, directly or not.
+    fn imported_by(&self, file: FileId) -> Vec<FileId> {
+        let mut headers = Vec::new();
+        let mut stack = vec![file];
+        while let Some(file) = stack.pop() {
+            for &header in self.imports.get(&file).into_iter().flatten() {
+                if !headers.contains(&header) {
+                    headers.push(header);
+                    stack.push(header);
+                }
+            }
+        }
+        headers
+    }
+
+    // the header of the import in the source map, `None` if there is not
+    // such a header.
+    fn open(&mut self, import: &ImportNode) -> Result<Option<FileId>> {
+        let (found, name) = match import.kind {
+            ImportKind::Cb => (self.find(&header_path(&import.name)), Some(import.name.as_str())),
+            ImportKind::C | ImportKind::Include =>
+                (self.find_c(&import.name), headers::standard_header_of(&import.name)),
+        };
+        let (path, src) = match (found, name) {
+            (Some(path), _) => (path, None),
+            (None, None) => return Ok(None),
+            (None, Some(name)) => match headers::standard_header(name) {
+                Some(src) => (PathBuf::from(format!("<built-in>/{}.{}", name, EXT_HEADER)),
+                              Some(src)),
+                None => return Ok(None),
+            },
+        };
+        self.open_file(path, src).map(Some)
+    }
+
+    // the file in the source map, which is added if it is not yet.
+    fn open_file(&mut self, path: PathBuf, src: Option<&str>) -> Result<FileId> {
+        if let Some(file) = self.source_map.find_file(&path) {
+            return Ok(file);
+        }
+        Ok(match src {
+            Some(src) => self.source_map.add_file(path, src.to_string()),
+            None => self.source_map.load_file(path)?,
+        })
+    }
+
+    // the C header included by the one being loaded is searched in the
+    // directory of the latter first.
+    fn find_c(&self, name: &str) -> Option<PathBuf> {
+        let including = self.loading.last()
+                            .and_then(|&(file, _)| self.source_map.file(file).path().parent())
+                            .map(Path::to_path_buf);
+        including.into_iter()
+                 .chain(self.paths.iter().cloned())
+                 .map(|dir| dir.join(name))
+                 .find(|path| path.is_file())
+    }
+
+    // the first path of the relative one in the search path.
+    fn find(&self, relative: &Path) -> Option<PathBuf> {
+        self.paths.iter()
+                  .map(|dir| dir.join(relative))
+                  .find(|path| path.is_file())
+    }
+
+    // the tokens of the header, scanned once in a run.
+    fn scan(&mut self, file: FileId) -> Result<Vec<Token>> {
+        if let Some(tokens) = self.tokens.get(&file) {
+            return Ok(tokens.clone());
+        }
+        let src = self.source_map.file(file).src().to_string();
+        let tokens: Vec<Token> = Scanner::new(&src, file, self.handler).scan()?
+                                     .into_iter()
+                                     .filter(|token| !token.is_special())
+                                     .collect();
+        self.tokens.insert(file, tokens.clone());
+        Ok(tokens)
+    }
+
+    fn report_not_found(&mut self, import: &ImportNode) {
+        let searched: Vec<String> = self.paths.iter()
+                                        .map(|dir| format!("`{}`", dir.display()))
+                                        .collect();
+        let message = match import.kind {
+            ImportKind::Cb => format!("can not find the header `{}` of `import {}`",
+                                      header_path(&import.name).display(), import.name),
+            ImportKind::C => format!("can not find the C header `{}`", import.name),
+            ImportKind::Include => {
+                let warning = Diagnostic::warning(format!("can not find the C header `{}`, \
+                                                           so it is not included", import.name))
+                    .with_primary(import.location, "")
+                    .with_note(format!("searched in {}", searched.join(", ")));
+                self.handler.warn(Warning::CImport, warning);
+                return;
+            }
+        };
+        let error = SemanticError::new(SemanticErrorKind::ImportNotFound,
+            import.location, message)
+            .with_note(format!("searched in {}", searched.join(", ")))
+            .with_help("add the directory of the header with `-I`");
+        self.handler.emit(error.to_diagnostic());
+    }
+
+    // the import makes the header at `position` in `loading` import itself.
+    fn report_cycle(&mut self, import: &ImportNode, position: usize) {
+        let mut names: Vec<String> = self.loading[position..].iter()
+                                         .map(|(_, name)| format!("`{}`", name))
+                                         .collect();
+        names.push(format!("`{}`", import.name));
+        let error = SemanticError::new(SemanticErrorKind::ImportCycle,
+            import.location, format!("`{}` imports itself", import.name))
+            .with_note(format!("the cycle is {}", names.join(" -> ")));
+        self.handler.emit(error.to_diagnostic());
+    }
+}
+
+// `sys/io.hb` of `sys.io`
+fn header_path(name: &str) -> PathBuf {
+    let mut relative: PathBuf = name.split('.').collect();
+    relative.set_extension(EXT_HEADER);
+    relative
+}
+
+/// the directories to search the headers imported by the source file in.
+pub fn import_paths(dirs: &[PathBuf], src_file: &Path) -> Vec<PathBuf> {
+    let mut paths = dirs.to_vec();
+    let src_dir = src_file.parent().filter(|dir| !dir.as_os_str().is_empty());
+    paths.push(src_dir.unwrap_or(Path::new(".")).to_path_buf());
+    paths.push(PathBuf::from(DEFAULT_IMPORT_DIR));
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::CompileOptionBuilder;
+    use super::super::testing::{compile, TempDir};
+    use super::super::warning::Warning;
+
+    #[test]
+    fn search_path() {
+        let include = TempDir::new();
+        include.write("sys/io.hb", "import counter;\nextern int write_all(char* s);\n")
+               .write("counter.hb", "extern int count;\n");
+        let dir = TempDir::new();
+        // the header in the directory of the source is found after `-I`
+        dir.write("counter.hb", "extern int not_this_one;\n");
+        let opts = CompileOptionBuilder::new()
+                       .import_dirs(vec![include.path().to_path_buf()])
+                       .finalize();
+        let compiled = dir.compile_with("import sys.io;\n\
+                                         int main(void) { return write_all(\"\") + count; }\n",
+                                        &opts);
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn standard_headers() {
+        compile("import stddef; import stdio; import stdlib; import string;\n\
+                 import ctype; import unistd; import errno; import alloca;\n\
+                 int main(void) {\n\
+                     char* s = malloc(BUFSIZ);\n\
+                     size_t n = strlen(\"text\");\n\
+                     if (isdigit('1') && *__errno_location() != EINTR) printf(\"%s %lu\\n\", s, n);\n\
+                     free(s);\n\
+                     return EOF + EXIT_SUCCESS;\n\
+                 }\n").assert_clean();
+    }
+
+    #[test]
+    fn header_in_the_search_path_before_the_standard_one() {
+        let dir = TempDir::new();
+        dir.write("stdio.hb", "extern int mine(void);\n");
+        dir.compile("import stdio;\nint main(void) { return mine(); }\n").assert_clean();
+        assert_eq!(dir.compile("import stdio;\nint main(void) { return puts(\"\"); }\n")
+                      .errors(), ["E0061"]);
+    }
+
+    #[test]
+    fn import_not_found() {
+        let compiled = compile("import nowhere;\nimport c \"nowhere.h\";\n");
+        assert_eq!(compiled.errors(), ["E0092", "E0092"]);
+    }
+
+    #[test]
+    fn import_cycle() {
+        let dir = TempDir::new();
+        dir.write("a.hb", "import b;\n").write("b.hb", "import a;\n");
+        let compiled = dir.compile("import a;\n");
+        assert_eq!(compiled.errors(), ["E0093"]);
+        assert_eq!(compiled.diagnostics[0].notes, ["the cycle is `a` -> `b` -> `a`"]);
+    }
+
+    #[test]
+    fn c_includes() {
+        let dir = TempDir::new();
+        dir.write("lib.h", "#ifndef LIB_H\n#define LIB_H\n\
+                            #include <stdio.h>\n#include \"lib.h\"\n#include \"types.h\"\n\
+                            extern point_t origin;\nint show(FILE* out);\n#endif\n")
+           .write("types.h", "typedef struct { int x; int y; } point_t;\n");
+        dir.compile("import c \"lib.h\";\n\
+                     int main(void) { return show(stdout) + origin.x; }\n").assert_clean();
+
+        dir.write("types.h", "#include <sys/socket.h>\ntypedef int point_t;\n");
+        let compiled = dir.compile("import c \"lib.h\";\n\
+                                    int main(void) { return show(stdout) + origin; }\n");
+        assert!(compiled.errors().is_empty());
+        assert_eq!(compiled.warnings(), [Warning::CImport]);
+    }
+}
@@ -0,0 +1,246 @@
+//! The pass binding every name in the expressions to its entity.
+//!
+//! The global variables and the functions are all defined in the toplevel
+//! scope first, so a function can be called before its definition. An
+//! `extern` declaration and the definition of the same name, or two
+//! declarations, share one entity, which is at the definition. Then
+//! every function has a scope for its parameters, which is also the scope
+//! of the variables at the beginning of its body as in C, and every inner
+//! block has a scope of its own.
+
+use std::collections::HashSet;
+use super::ast::*;
+use super::location::Location;
+use super::type_::TypeId;
+use super::type_table::TypeTable;
+use super::entity::*;
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::{Diagnostic, DiagnosticHandler};
+use super::warning::Warning;
+use super::suggestion;
+use super::visitor::{self, Visitor};
+
+pub struct LocalResolver<'a> {
+    entities: &'a mut EntityTable,
+    table: &'a mut TypeTable,
+    handler: &'a mut DiagnosticHandler,
+    scope: ScopeId, // the current one
+    is_function_body: bool, // the next block shares the scope of the params
+    declarations: HashSet<EntityId>, // of the `extern` ones not defined yet
+}
+
+impl<'a> LocalResolver<'a> {
+    pub fn new(entities: &'a mut EntityTable, table: &'a mut TypeTable,
+               handler: &'a mut DiagnosticHandler) -> LocalResolver<'a> {
+        let toplevel = entities.toplevel();
+        LocalResolver {
+            entities: entities,
+            table: table,
+            handler: handler,
+            scope: toplevel,
+            is_function_body: false,
+            declarations: HashSet::new(),
+        }
+    }
+
+    /// Bind all the names, the errors are reported to the handler.
+    pub fn resolve(&mut self, ast: &mut AST) {
+        for var in &mut ast.vardecls {
+            var.entity = self.declare(&var.name, EntityKind::GlobalVariable,
+                                      var.type_.type_, var.location);
+        }
+        for func in &mut ast.funcdecls {
+            func.entity = self.declare(&func.name, EntityKind::Function,
+                                       func.type_.type_, func.location);
+        }
+        for var in &mut ast.defvars {
+            var.entity = self.define_declared(&var.name, EntityKind::GlobalVariable,
+                                              var.type_.type_, var.location,
+                                              var.is_private);
+        }
+        for func in &mut ast.defuns {
+            func.entity = self.define_declared(&func.name, EntityKind::Function,
+                                               func.type_.type_, func.location,
+                                               func.is_private);
+        }
+        self.visit_ast(ast);
+    }
+
+    // an `extern` declaration, which may be repeated.
+    fn declare(&mut self, name: &str, kind: EntityKind, type_: Option<TypeId>,
+               location: Location) -> Option<EntityId> {
+        if let Some(id) = self.entities.lookup(self.scope, name) {
+            if self.declarations.contains(&id) && self.entities.get(id).kind == kind {
+                return Some(id);
+            }
+        }
+        let id = self.define(name, kind, type_, location, false);
+        self.declarations.extend(id);
+        id
+    }
+
+    // a global definition, which takes the entity of its declaration.
+    fn define_declared(&mut self, name: &str, kind: EntityKind, type_: Option<TypeId>,
+                       location: Location, is_private: bool) -> Option<EntityId> {
+        if let Some(id) = self.entities.lookup(self.scope, name) {
+            if self.entities.get(id).kind == kind && self.declarations.remove(&id) {
+                let entity = self.entities.get_mut(id);
+                entity.type_ = type_;
+                entity.location = location;
+                entity.is_private = is_private;
+                return Some(id);
+            }
+        }
+        self.define(name, kind, type_, location, is_private)
+    }
+
+    fn define(&mut self, name: &str, kind: EntityKind, type_: Option<TypeId>,
+              location: Location, is_private: bool) -> Option<EntityId> {
+        let entity = Entity {
+            name: name.to_string(),
+            kind: kind,
+            type_: type_,
+            location: location,
+            is_private: is_private,
+            scope: self.scope,
+            value: None,
+        };
+        match self.entities.define(entity) {
+            Ok(id) => {
+                self.check_shadowing(id);
+                Some(id)
+            }
+            Err(first) => {
+                let error = SemanticError::new(SemanticErrorKind::DuplicatedDefinition,
+                    location, format!("`{}` is defined more than once", name))
+                    .with_secondary(self.entities.get(first).location,
+                                    "first defined here");
+                self.handler.emit(error.to_diagnostic());
+                None
+            }
+        }
+    }
+
+    // warn if the local entity hides another one of an outer scope.
+    fn check_shadowing(&mut self, id: EntityId) {
+        let entity = self.entities.get(id);
+        let parent = match self.entities.scope(entity.scope).parent {
+            Some(parent) => parent,
+            None => return,
+        };
+        if let Some(shadowed) = self.entities.lookup(parent, &entity.name) {
+            let shadowed = self.entities.get(shadowed);
+            let what = match shadowed.kind {
+                EntityKind::GlobalVariable => "global variable",
+                EntityKind::LocalVariable => "local variable",
+                EntityKind::Parameter => "parameter",
+                EntityKind::Function => "function",
+                EntityKind::Enumerator => "enumerator",
+            };
+            self.handler.warn(Warning::Shadow,
+                Diagnostic::warning(format!("`{}` shadows a {}", entity.name, what))
+                    .with_primary(entity.location, "")
+                    .with_secondary(shadowed.location, "shadowed declaration is here"));
+        }
+    }
+}
+
+impl<'a> Visitor for LocalResolver<'a> {
+    fn visit_defun(&mut self, func: &mut DefinedFunction) {
+        let toplevel = self.scope;
+        self.scope = self.entities.push_scope(toplevel);
+        for param in &mut func.params.params {
+            // an array parameter is a pointer
+            let type_ = param.type_.type_.map(|t| self.table.decay(t));
+            param.entity = self.define(&param.name, EntityKind::Parameter, type_,
+                                       param.location, false);
+        }
+        self.is_function_body = true;
+        self.visit_block(&mut func.body);
+        self.scope = toplevel;
+    }
+
+    fn visit_block(&mut self, node: &mut BlockNode) {
+        let outer = self.scope;
+        if !self.is_function_body {
+            self.scope = self.entities.push_scope(outer);
+        }
+        self.is_function_body = false;
+        node.scope = Some(self.scope);
+
+        for var in &mut node.variables {
+            // the variable is visible in its own initializer, as in C
+            var.entity = self.define(&var.name, EntityKind::LocalVariable,
+                                     var.type_.type_, var.location, var.is_private);
+            visitor::walk_defvar(self, var);
+        }
+        for stmt in &mut node.stmts {
+            self.visit_node(stmt);
+        }
+        self.scope = outer;
+    }
+
+    fn visit_variable(&mut self, node: &mut VariableNode) {
+        node.entity = self.entities.lookup(self.scope, &node.name);
+        if node.entity.is_none() {
+            let mut error = SemanticError::new(SemanticErrorKind::UndefinedName,
+                node.location, format!("undefined name `{}`", node.name));
+            let names = self.entities.visible_names(self.scope);
+            if let Some(name) = suggestion::suggest(&node.name, names) {
+                error = error.with_help(suggestion::did_you_mean(name));
+            }
+            self.handler.emit(error.to_diagnostic());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{compile, compile_warning};
+    use super::super::warning::Warning;
+
+    #[test]
+    fn scopes() {
+        let compiled = compile("extern int later(int x);\n\
+                                int count;\n\
+                                int main(void) {\n\
+                                    int x = later(count);\n\
+                                    { int y = x; x = y; }\n\
+                                    { int y = 2; x += y; }\n\
+                                    return x;\n\
+                                }\n\
+                                int later(int x) { return x; }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn undefined_name() {
+        let compiled = compile("int count;\n\
+                                int main(void) { { int inner; } return cuont + inner; }\n");
+        assert_eq!(compiled.errors(), ["E0061", "E0061"]);
+        assert_eq!(compiled.diagnostics[0].helps, ["did you mean `count`?"]);
+        assert!(compiled.diagnostics[1].helps.is_empty());
+    }
+
+    #[test]
+    fn duplicated_definition() {
+        let compiled = compile("int count;\n\
+                                long count;\n\
+                                int f(int x, int x) { return 0; }\n\
+                                int main(void) { int y; int y; return 0; }\n");
+        assert_eq!(compiled.errors(), ["E0062"; 3]);
+    }
+
+    #[test]
+    fn shadow() {
+        let src = "int count;\n\
+                   int main(int argc, char** argv) {\n\
+                       int count = argc;\n\
+                       { int argc = 0; return count + argc; }\n\
+                   }\n";
+        compile(src).assert_clean();
+        let compiled = compile_warning(src, &["all"]);
+        assert_eq!(compiled.warnings(), [Warning::Shadow, Warning::Shadow]);
+        assert!(compiled.has_message("`count` shadows a global variable"));
+    }
+}
@@ -1,61 +1,47 @@
 use std::fmt;
+use super::source_map::FileId;
 
-#[derive(Debug, Clone, Copy)]
+/// A span in one of the files registered in the `SourceMap`.
+///
+/// `begin` and `end` are byte offsets into the source text, `end` is
+/// exclusive. Ask the `SourceMap` to turn it into lines and columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Location {
-    pub begin: Position,
-    pub end: Position,
-}
-
-#[derive(Debug, Clone, Copy)]
-pub struct Position {
-    pub line: usize,
-    pub column: usize,
+    pub file: FileId,
+    pub begin: usize,
+    pub end: usize,
 }
 
 impl Location {
-    pub fn new(begin_line: usize,
-               begin_column: usize,
-               end_line: usize,
-               end_column: usize)
-               -> Location {
+    pub fn new(file: FileId, begin: usize, end: usize) -> Location {
         Location {
-            begin: Position::new(begin_line, begin_column),
-            end: Position::new(end_line, end_column),
+            file: file,
+            begin: begin,
+            end: end,
         }
     }
 
     pub fn range(left_location: Location, right_location: Location) -> Location {
         Location {
+            file: left_location.file,
             begin: left_location.begin,
             end: right_location.end,
         }
     }
-}
 
-impl Default for Location {
-    fn default() -> Location {
-        Location::new(1, 1, 1, 1)
+    pub fn len(&self) -> usize {
+        self.end - self.begin
     }
 }
 
-impl Position {
-    pub fn new(line: usize, column: usize) -> Position {
-        Position {
-            line: line,
-            column: column,
-        }
+impl Default for Location {
+    fn default() -> Location {
+        Location::new(FileId::default(), 0, 0)
     }
 }
 
 impl fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} ~ {}", self.begin, self.end)
-    }
-}
-
-impl fmt::Display for Position {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}:{}", self.line, self.column)
+        write!(f, "{}[{}..{}]", self.file, self.begin, self.end)
     }
 }
-
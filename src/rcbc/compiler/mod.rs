@@ -7,20 +7,82 @@
 
 #![allow(unused_variables, dead_code)]
 
-use std::path::Path;
-use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::io::{self, Write};
 use std::fs::File;
 use std::fmt;
 use std::result;
 use self::scanner::{Scanner, ScanError};
 use self::parser::{Parser, ParseError};
+use self::token::Token;
+use self::loader::Loader;
+use self::location::Location;
+use self::source_map::{FileId, SourceMap};
+use self::diagnostic::{Diagnostic, DiagnosticHandler, Emitter};
+use self::error_codes::ErrorCode;
+use self::type_table::TypeTable;
+use self::type_resolver::TypeResolver;
+use self::header_checker::HeaderChecker;
+use self::interface::Interface;
+use self::header_writer::HeaderWriter;
+use self::c_header_writer::CHeaderWriter;
+use self::rust_bindings_writer::RustBindingsWriter;
+use self::assembly_writer::AssemblyWriter;
+use self::entity::EntityTable;
+use self::local_resolver::LocalResolver;
+use self::type_checker::TypeChecker;
+use self::dereference_checker::DereferenceChecker;
+use self::sizeof_evaluator::SizeofEvaluator;
+use self::constant_evaluator::ConstantChecker;
+use self::control_flow_checker::ControlFlowChecker;
+use self::format_checker::FormatChecker;
+use self::uninitialized_checker::UninitializedChecker;
+use self::unused_checker::UnusedChecker;
+
+pub use self::diagnostic::{ErrorFormat, ColorConfig};
+pub use self::warning::WarningOptions;
+pub use self::error_codes::explain;
+pub use self::target::Target;
+pub use self::headers::{standard_header, standard_headers};
 
 mod location;
+mod source_map;
+mod diagnostic;
+mod warning;
+mod error_codes;
+mod suggestion;
 mod scanner;
 mod token;
 mod parser;
+mod loader;
+mod headers;
+mod c_reader;
 mod ast;
+mod target;
 mod type_;
+mod type_table;
+mod visitor;
+mod type_resolver;
+mod header_checker;
+mod semantic_error;
+mod entity;
+mod local_resolver;
+mod type_checker;
+mod dereference_checker;
+mod sizeof_evaluator;
+mod constant_evaluator;
+mod control_flow_checker;
+mod format_checker;
+mod uninitialized_checker;
+mod unused_checker;
+mod interface;
+mod header_writer;
+mod c_header_writer;
+mod rust_bindings_writer;
+mod assembly_writer;
+#[cfg(test)]
+mod testing;
 
 const COMPILER_NAME:    &'static str = "rcbc";
 const COMPILER_VERSION: &'static str = "0.0.1";
@@ -31,6 +93,9 @@ type Result<T> = result::Result<T, CompileError>;
 pub struct Compiler {
     name: &'static str,
     version: &'static str,
+    source_map: SourceMap,
+    handler: DiagnosticHandler,
+    headers: HashMap<FileId, Vec<Token>>, // the tokens of the headers loaded
 }
 
 #[derive(Debug)]
@@ -38,19 +103,43 @@ pub enum CompileError {
     IO(io::Error),
     Scan(ScanError),
     Parse(ParseError),
+    Reported(usize), // the number of errors already reported
+}
+
+/// What a compilation writes, by `--emit`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Emit {
+    Assembly,
+    Header, // the `.hb` header of the interface
+    CHeader, // the `.h` header of the interface, for C
+    RustBindings, // the `.rs` module declaring the interface, for Rust
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct CompileOption {
     is_dump_tokens: bool,
     is_dump_ast: bool,
     is_dump_asm: bool,
+    is_dump_record_layouts: bool,
+    target: Target,
+    error_format: ErrorFormat,
+    color: ColorConfig,
+    warnings: WarningOptions,
+    import_dirs: Vec<PathBuf>, // by `-I`
+    emit: Emit,
 }
 
 pub struct CompileOptionBuilder {
     is_dump_tokens: bool,
     is_dump_ast: bool,
     is_dump_asm: bool,
+    is_dump_record_layouts: bool,
+    target: Target,
+    error_format: ErrorFormat,
+    color: ColorConfig,
+    warnings: WarningOptions,
+    import_dirs: Vec<PathBuf>,
+    emit: Emit,
 }
 
 impl Compiler {
@@ -58,25 +147,60 @@ impl Compiler {
         Compiler {
             name: COMPILER_NAME,
             version: COMPILER_VERSION,
+            source_map: SourceMap::new(),
+            handler: DiagnosticHandler::new(),
+            headers: HashMap::new(),
         }
     }
 
-    pub fn compile(&self, src_file: &Path, asm_file: &Path, opts: CompileOption) -> Result<()> {
+    /// Compile a source file into the output of `opts.emit`, the diagnostics
+    /// are shown on the standard error in the format of the options.
+    pub fn compile(&mut self, src_file: &Path, out_file: &Path, opts: &CompileOption) -> Result<()> {
+        self.handler.set_warning_options(opts.warnings);
+
+        let result = self.compile_file(src_file, out_file, opts);
+
+        if let Err(ref err) = result {
+            if let Some(diagnostic) = err.to_diagnostic() {
+                self.handler.emit(diagnostic);
+            }
+        }
+
+        let mut emitter = Emitter::new(opts.error_format, opts.color);
+        for diagnostic in self.handler.take() {
+            emitter.emit(&diagnostic, &self.source_map);
+        }
+
+        result
+    }
+
+    fn compile_file(&mut self, src_file: &Path, out_file: &Path, opts: &CompileOption) -> Result<()> {
         println!("I will compile these files: {} to {}", 
-                 src_file.to_str().unwrap(), asm_file.to_str().unwrap());
+                 src_file.to_str().unwrap(), out_file.to_str().unwrap());
         
-        let mut char_stream = String::new();
+        let file = self.source_map.load_file(src_file) ?;
+        let out_name = out_file.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        if let Some(output) = self.compile_source(file, src_file, out_name, opts) ? {
+            File::create(out_file)
+                 .and_then(|mut out| out.write(output.as_bytes())) ?;
+        }
+        Ok(())
+    }
 
-        File::open(src_file)
-             .and_then(|mut src| src.read_to_string(&mut char_stream)) ?;
+    // compile the source `file` read from `src_file` into the output named
+    // `out_name`, `None` if only something is dumped.
+    fn compile_source(&mut self, file: FileId, src_file: &Path, out_name: &str,
+                      opts: &CompileOption) -> Result<Option<String>> {
+        let char_stream = self.source_map.file(file).src().to_string();
 
-        let mut scanner = Scanner::new(&char_stream);
+        let error_count = self.handler.error_count();
 
-        let token_stream = scanner.scan() ?;
+        let token_stream = Scanner::new(&char_stream, file, &mut self.handler).scan() ?;
+        self.check_errors(error_count) ?;
 
         if opts.is_dump_tokens {
             for i in &token_stream { println!("{}", i); }
-            return Ok(());
+            return Ok(None);
         }
 
         let token_stream = token_stream.into_iter()
@@ -84,12 +208,158 @@ impl Compiler {
                                        .collect();
 
         let mut parser = Parser::new(&token_stream);
+        let mut imports = parser.parse_imports() ?;
 
-        let ast = parser.parse() ?;
+        let error_count = self.handler.error_count();
+        let paths = loader::import_paths(&opts.import_dirs, src_file);
+        // the header being written is not checked against
+        let module = match opts.emit {
+            Emit::Header => None,
+            _ => src_file.file_stem().and_then(|stem| stem.to_str()),
+        };
+        let (imported, header) = Loader::new(paths, &mut self.source_map,
+                                             &mut self.handler, &mut self.headers)
+                                        .load(&mut imports, module) ?;
+        self.check_errors(error_count) ?;
 
-        File::create(src_file)
-             .and_then(|mut asm| asm.write("Something...".as_bytes())) ?;
-        Ok(())
+        let mut ast = parser.parse(imported) ?;
+        ast.imports = imports;
+
+        if let Some(header) = header {
+            let error_count = self.handler.error_count();
+            header_checker::merge_types(&mut ast, file, header, &mut self.handler);
+            self.check_errors(error_count) ?;
+        }
+
+        let error_count = self.handler.error_count();
+        let mut type_table = TypeTable::new(&opts.target);
+        let mut entities = EntityTable::new();
+        TypeResolver::new(&mut type_table, &mut entities, &mut self.handler)
+                     .resolve(&mut ast);
+        self.check_errors(error_count) ?;
+
+        if opts.is_dump_record_layouts {
+            print!("{}", type_table.dump_record_layouts());
+            return Ok(None);
+        }
+
+        if let Some(header) = header {
+            let error_count = self.handler.error_count();
+            HeaderChecker::new(&type_table, &mut self.handler).check(&ast, header);
+            self.check_errors(error_count) ?;
+        }
+
+        let error_count = self.handler.error_count();
+        LocalResolver::new(&mut entities, &mut type_table, &mut self.handler)
+                      .resolve(&mut ast);
+        self.check_errors(error_count) ?;
+
+        let error_count = self.handler.error_count();
+        TypeChecker::new(&entities, &mut type_table, &mut self.handler)
+                    .check(&mut ast);
+        DereferenceChecker::new(&entities, &mut type_table, &mut self.handler)
+                          .check(&mut ast);
+        self.check_errors(error_count) ?;
+
+        let error_count = self.handler.error_count();
+        SizeofEvaluator::new(&mut type_table, &mut self.handler).evaluate(&mut ast);
+        self.check_errors(error_count) ?;
+
+        let error_count = self.handler.error_count();
+        ConstantChecker::new(&entities, &type_table, &mut self.handler)
+                       .check(&mut ast);
+        self.check_errors(error_count) ?;
+
+        let error_count = self.handler.error_count();
+        ControlFlowChecker::new(&entities, &type_table, &mut self.handler)
+                          .check(&mut ast);
+        FormatChecker::new(&type_table, &mut self.handler).check(&mut ast);
+        self.check_errors(error_count) ?;
+
+        let error_count = self.handler.error_count();
+        UninitializedChecker::new(&entities, &type_table, &mut self.handler)
+                            .check(&mut ast);
+        UnusedChecker::new(&entities, &type_table, &mut self.handler)
+                     .check(&mut ast);
+        self.check_errors(error_count) ?;
+
+        let output = match opts.emit {
+            Emit::Assembly => {
+                let src_name = src_file.file_name().and_then(|name| name.to_str()).unwrap_or("");
+                AssemblyWriter::new(&type_table, &entities).write(&mut ast, src_name)
+            }
+            Emit::Header => HeaderWriter::new(&type_table, &entities)
+                                         .write(&Interface::new(&ast, file), out_name),
+            Emit::CHeader => CHeaderWriter::new(&type_table, &entities)
+                                           .write(&Interface::new(&ast, file), out_name),
+            Emit::RustBindings => RustBindingsWriter::new(&type_table, &entities)
+                                                     .write(&Interface::new(&ast, file), out_name),
+        };
+        Ok(Some(output))
+    }
+
+    /// how many warnings and errors are reported in all the compilations,
+    /// `None` if there is nothing.
+    pub fn summary(&self) -> Option<String> {
+        let plural = |n: usize, what: &str| {
+            format!("{} {}{}", n, what, if n == 1 { "" } else { "s" })
+        };
+        let warnings = self.handler.warning_count();
+        let errors = self.handler.error_count();
+        match (warnings, errors) {
+            (0, 0) => None,
+            (w, 0) => Some(format!("{} generated", plural(w, "warning"))),
+            (0, e) => Some(format!("{} generated", plural(e, "error"))),
+            (w, e) => Some(format!("{} and {} generated",
+                                   plural(w, "warning"), plural(e, "error"))),
+        }
+    }
+
+    /// the first code of the errors reported, to be explained.
+    pub fn first_error_code(&self) -> Option<&'static str> {
+        self.handler.error_codes().first().cloned()
+    }
+
+    /// fail if some errors are reported after there were `error_count` errors.
+    fn check_errors(&self, error_count: usize) -> Result<()> {
+        match self.handler.error_count() - error_count {
+            0 => Ok(()),
+            n => Err(CompileError::Reported(n)),
+        }
+    }
+}
+
+impl CompileError {
+    /// where the error occurs in the source, if it is about the source.
+    pub fn location(&self) -> Option<Location> {
+        match *self {
+            CompileError::IO(_) => None,
+            CompileError::Scan(ref err) => Some(err.location()),
+            CompileError::Parse(ref err) => Some(err.location()),
+            CompileError::Reported(_) => None,
+        }
+    }
+
+    /// `None` if the error has been reported as diagnostics.
+    pub fn to_diagnostic(&self) -> Option<Diagnostic> {
+        let diagnostic = match *self {
+            CompileError::IO(ref err) => Diagnostic::error(format!("io error: {}", err)),
+            CompileError::Scan(ref err) =>
+                Diagnostic::error(err.to_string()).with_code(err.kind().code()),
+            CompileError::Parse(ref err) => {
+                let diagnostic = Diagnostic::error(err.to_string())
+                                     .with_code(err.kind.code());
+                match err.help() {
+                    Some(help) => diagnostic.with_help(help.to_string()),
+                    None => diagnostic,
+                }
+            }
+            CompileError::Reported(_) => return None,
+        };
+        match self.location() {
+            Some(location) => Some(diagnostic.with_primary(location, "")),
+            None => Some(diagnostic),
+        }
     }
 }
 
@@ -120,6 +390,8 @@ impl fmt::Display for CompileError {
                 write!(f, "lexical error: {}", err),
             CompileError::Parse(ref err) =>
                 write!(f, "syntax error: {}", err),
+            CompileError::Reported(n) =>
+                write!(f, "aborting due to {} previous error(s)", n),
         }
     }
 }
@@ -131,6 +403,13 @@ impl CompileOptionBuilder {
             is_dump_tokens: false,
             is_dump_ast: false,
             is_dump_asm: false,
+            is_dump_record_layouts: false,
+            target: Target::default(),
+            error_format: ErrorFormat::Human,
+            color: ColorConfig::Auto,
+            warnings: WarningOptions::new(),
+            import_dirs: Vec::new(),
+            emit: Emit::Assembly,
         }
     }
 
@@ -139,11 +418,53 @@ impl CompileOptionBuilder {
         self
     }
 
+    pub fn is_dump_record_layouts(&mut self, is_dump: bool) -> &mut Self {
+        self.is_dump_record_layouts = is_dump;
+        self
+    }
+
+    pub fn target(&mut self, target: Target) -> &mut Self {
+        self.target = target;
+        self
+    }
+
+    pub fn error_format(&mut self, format: ErrorFormat) -> &mut Self {
+        self.error_format = format;
+        self
+    }
+
+    pub fn color(&mut self, color: ColorConfig) -> &mut Self {
+        self.color = color;
+        self
+    }
+
+    pub fn warnings(&mut self, warnings: WarningOptions) -> &mut Self {
+        self.warnings = warnings;
+        self
+    }
+
+    pub fn import_dirs(&mut self, dirs: Vec<PathBuf>) -> &mut Self {
+        self.import_dirs = dirs;
+        self
+    }
+
+    pub fn emit(&mut self, emit: Emit) -> &mut Self {
+        self.emit = emit;
+        self
+    }
+
     pub fn finalize(&self) -> CompileOption {
         CompileOption {
             is_dump_tokens: self.is_dump_tokens,
             is_dump_ast: self.is_dump_ast,
             is_dump_asm: self.is_dump_asm,
+            is_dump_record_layouts: self.is_dump_record_layouts,
+            target: self.target,
+            error_format: self.error_format,
+            color: self.color,
+            warnings: self.warnings,
+            import_dirs: self.import_dirs.clone(),
+            emit: self.emit,
         }
     }
 }
\ No newline at end of file
@@ -2,21 +2,29 @@ use super::token::{Token, TokenKind};
 use super::ast::*;
 use super::type_::*;
 use super::location::Location;
+use super::error_codes::ErrorCode;
+use super::suggestion;
 use std::result;
 use std::fmt;
 use std::slice::Iter;
 use std::mem;
+use std::collections::HashSet;
 
 type Result<'a, T> = result::Result<T, ParseError>;
 
 pub struct Parser<'a> {
+    tokens: &'a [Token],
     iter: Iter<'a, Token>,
     ast: AST,
+    typedefs: HashSet<String>, // the names which are types in `typeref_base`
+    is_header: bool, // without the definitions
 }
 
 #[derive(Debug)]
 pub struct ParseError {
     pub kind: ParseErrorKind,
+    location: Location,
+    help: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -64,6 +72,11 @@ pub enum ParseErrorKind {
     FunctionCallArgsTerminal,
     ExpectPrimaryRightBracket,
     InvalidPrimary,
+    EnumDefinitionTerminal,
+    LackOfEnumeratorListRightBracket,
+    DeclarationTerminal,
+    InvalidFormatAttribute,
+    DefinitionInHeader,
 }
 
 
@@ -77,7 +90,7 @@ macro_rules! expect {
         lookahead!($Iter, if $Kind {
             eat!($Iter)
         }, else {
-            return Err(ParseError::new(ParseErrorKind::$Errorkind));
+            return Err(ParseError::at(&$Iter, ParseErrorKind::$Errorkind));
         })
     });
     ($Iter: expr, $Kind: ident) => ({
@@ -149,14 +162,41 @@ impl<'a> Parser<'a> {
             .map(|x| x.location())
             .unwrap_or(Location::default());
         Parser {
+            tokens: token_stream,
             iter: token_stream.iter(),
-            ast: AST::new(location),
+            ast: AST::empty(location),
+            typedefs: HashSet::new(),
+            is_header: false,
         }
     }
 
-    pub fn parse(&mut self) -> Result<AST> {
+    /// Parse the import statements at the beginning, which are taken out of
+    /// the AST, so the headers can be loaded before the rest is parsed.
+    pub fn parse_imports(&mut self) -> Result<Vec<ImportNode>> {
+        self.import_stmts()?;
+        Ok(mem::take(&mut self.ast.imports))
+    }
+
+    /// Parse a source file, after the declarations imported, which begin the
+    /// AST and whose typedef names are types in the source.
+    pub fn parse(&mut self, imported: AST) -> Result<AST> {
+        self.import(imported);
         self.syntax_analysis()?;
-        Ok(mem::replace(&mut self.ast, AST::new(Location::default())))
+        Ok(mem::replace(&mut self.ast, AST::empty(Location::default())))
+    }
+
+    /// Parse a header, which has only declarations, after the declarations
+    /// imported as `parse`.
+    pub fn parse_header(&mut self, imported: AST) -> Result<AST> {
+        self.is_header = true;
+        self.parse(imported)
+    }
+
+    fn import(&mut self, mut imported: AST) {
+        self.typedefs.extend(imported.typedefs.iter().map(|typedef| typedef.name.clone()));
+        imported.location = self.ast.location;
+        imported.imports = mem::take(&mut self.ast.imports);
+        self.ast = imported;
     }
 
     fn syntax_analysis(&mut self) -> Result<()> {
@@ -170,213 +210,455 @@ impl<'a> Parser<'a> {
     }
 
     fn import_stmts(&mut self) -> Result<()> {
-        // let _stmts = Vec::new();
-
         lookahead!(self.iter,
                    while Import {
-                       self.import_stmt()?;
+                       let import = self.import_stmt()?;
+                       self.ast.imports.push(import);
                    });
 
-        println!("Import Statements Finished!");
-
         Ok(())
     }
 
     fn top_defs(&mut self) -> Result<()> {
-        lookahead!(self.iter,
-            Static => {
-                self.defun_or_defvars()
-            },
-            Struct => {
-                lookahead!(self.iter, 3, if LeftCurlyBracket {
-                    self.defstruct()
-                }, else {
-                    self.defun_or_defvars()
-                })
-            },
-            Union => {
-                lookahead!(self.iter, 3, if LeftCurlyBracket {
-                    self.defunion()
-                }, else {
-                    self.defun_or_defvars()
-                })
-            },
-            Typedef => {
-                self.typedef()
-            }
-            else {
-                self.defun_or_defvars()
-            }
-        )
+        loop {
+            lookahead!(self.iter,
+                EOF => {
+                    return Ok(());
+                },
+                Struct => {
+                    lookahead!(self.iter, 3, if LeftCurlyBracket {
+                        let defstruct = self.defstruct() ?;
+                        self.ast.defstructs.push(defstruct);
+                    }, else {
+                        self.defun_or_defvars() ?;
+                    })
+                },
+                Union => {
+                    lookahead!(self.iter, 3, if LeftCurlyBracket {
+                        let defunion = self.defunion() ?;
+                        self.ast.defunions.push(defunion);
+                    }, else {
+                        self.defun_or_defvars() ?;
+                    })
+                },
+                Enum => {
+                    // `enum {` or `enum name {`
+                    let is_definition = lookahead!(self.iter, 2, if LeftCurlyBracket {
+                        true
+                    }, else {
+                        lookahead!(self.iter, 3, if LeftCurlyBracket {
+                            true
+                        }, else {
+                            false
+                        })
+                    });
+                    if is_definition {
+                        let defenum = self.defenum() ?;
+                        self.ast.defenums.push(defenum);
+                    } else {
+                        self.defun_or_defvars() ?;
+                    }
+                },
+                Typedef => {
+                    let typedef = self.typedef() ?;
+                    self.ast.typedefs.push(typedef);
+                },
+                Extern => {
+                    self.declaration() ?;
+                }
+                else {
+                    self.defun_or_defvars() ?;
+                }
+            );
+        }
     }
 
     fn defun_or_defvars(&mut self) -> Result<()> {
-        lookahead!(self.iter,
-                   if Static {
-                       eat!(self.iter);
-                       // do someting...
-                   });
+        if self.is_header {
+            return Err(ParseError::at(&self.iter, ParseErrorKind::DefinitionInHeader));
+        }
+        let begin = self.next_location();
+        let is_private = lookahead!(self.iter, if Static {
+            eat!(self.iter);
+            true
+        }, else {
+            false
+        });
+
+        let type_ = self.type_()?;
+        let name = self.name()?;
+
+        lookahead!(self.iter, if OpenParentheses {
+            eat!(self.iter);
+            let params = self.params() ?;
+            expect!(self.iter, CloseParentheses else ParamsCloseBracket);
+            let body = self.block() ?;
+            let location = Location::range(begin, body.location());
+            let function_type = TypeNode::new(type_.location,
+                TypeRef::function(type_.type_ref, params.param_typerefs()));
+            self.ast.defuns.push(DefinedFunction {
+                location: location,
+                is_private: is_private,
+                type_: function_type,
+                name: name.image(),
+                params: params,
+                body: body,
+                entity: None,
+            });
+            Ok(())
+        }, else {
+            let defvars = self.defvars(is_private, type_, name) ?;
+            self.ast.defvars.extend(defvars);
+            Ok(())
+        })
+    }
 
-        self.typeref()?;
+    // `extern int f(int i);` or `extern int i;`, a function or a variable
+    // defined elsewhere.
+    fn declaration(&mut self) -> Result<()> {
+        let begin = expect!(self.iter, Extern).location();
 
-        self.name()?;
+        let type_ = self.type_()?;
+        let name = self.name()?;
 
         lookahead!(self.iter, if OpenParentheses {
             eat!(self.iter);
-            self.params() ?;
+            let params = self.params() ?;
             expect!(self.iter, CloseParentheses else ParamsCloseBracket);
-            self.block() ?;
-            println!("Function Definition Found!");
-            return Ok(());
+            let format = self.format_attribute() ?;
+            let end = expect!(self.iter, Semicolon else DeclarationTerminal);
+            let function_type = TypeNode::new(type_.location,
+                TypeRef::function(type_.type_ref, params.param_typerefs()));
+            self.ast.funcdecls.push(UndefinedFunction {
+                location: Location::range(begin, end.location()),
+                type_: function_type,
+                name: name.image(),
+                params: params,
+                format: format,
+                entity: None,
+            });
+            Ok(())
         }, else {
-            lookahead!(self.iter, if Equals {
+            expect!(self.iter, Semicolon else DeclarationTerminal);
+            self.ast.vardecls.push(UndefinedVariable {
+                location: name.location(),
+                type_: type_,
+                name: name.image(),
+                entity: None,
+            });
+            Ok(())
+        })
+    }
+
+    // `__attribute__((format(printf, 1, 2)))`, which is optional.
+    fn format_attribute(&mut self) -> Result<Option<FormatAttribute>> {
+        match self.iter.clone().next() {
+            Some(token) if token.value().is_some_and(|v| v == "__attribute__") => {}
+            _ => return Ok(None),
+        }
+        let begin = eat!(self.iter).location();
+
+        expect!(self.iter, OpenParentheses else InvalidFormatAttribute);
+        expect!(self.iter, OpenParentheses else InvalidFormatAttribute);
+        self.attribute_word("format")?;
+        expect!(self.iter, OpenParentheses else InvalidFormatAttribute);
+        self.attribute_word("printf")?;
+        expect!(self.iter, Comma else InvalidFormatAttribute);
+        let format_index = self.attribute_number()?;
+        expect!(self.iter, Comma else InvalidFormatAttribute);
+        let first_arg = self.attribute_number()?;
+        expect!(self.iter, CloseParentheses else InvalidFormatAttribute);
+        expect!(self.iter, CloseParentheses else InvalidFormatAttribute);
+        let end = expect!(self.iter, CloseParentheses else InvalidFormatAttribute);
+
+        Ok(Some(FormatAttribute {
+            location: Location::range(begin, end.location()),
+            format_index: format_index,
+            first_arg: first_arg,
+        }))
+    }
+
+    fn attribute_word(&mut self, word: &str) -> Result<()> {
+        lookahead!(self.iter, if Identifier {
+            if eat!(self.iter).image() == word {
+                return Ok(());
+            }
+            Err(ParseError::new(ParseErrorKind::InvalidFormatAttribute,
+                                self.last_location()))
+        }, else {
+            Err(ParseError::at(&self.iter, ParseErrorKind::InvalidFormatAttribute))
+        })
+    }
+
+    fn attribute_number(&mut self) -> Result<usize> {
+        let number = expect!(self.iter, Integer else InvalidFormatAttribute);
+        number.image().parse().map_err(|_| {
+            ParseError::new(ParseErrorKind::InvalidFormatAttribute, number.location())
+        })
+    }
+
+    // the rest of the variable definitions after the type and the first
+    // name, as in `int i = 1, j;`.
+    fn defvars(&mut self, is_private: bool, type_: TypeNode, name: &'a Token)
+            -> Result<Vec<DefinedVariable>> {
+        let mut defvars = Vec::new();
+        let mut name = name;
+        loop {
+            let initializer = lookahead!(self.iter, if Equals {
                 eat!(self.iter);
-                self.expr() ?;
+                Some(self.expr() ?)
+            }, else {
+                None
             });
-            lookahead!(self.iter, while Comma {
+            defvars.push(DefinedVariable {
+                location: name.location(),
+                is_private: is_private,
+                type_: TypeNode::new(type_.location, type_.type_ref.clone()),
+                name: name.image(),
+                initializer: initializer,
+                entity: None,
+            });
+            lookahead!(self.iter, if Comma {
                 eat!(self.iter);
-                self.name() ?;
-                lookahead!(self.iter, if Equals {
-                    eat!(self.iter);
-                    self.expr() ?;
-                });
+                name = self.name() ?;
+            }, else {
+                break;
             });
-            expect!(self.iter, Semicolon else VarDefTerminal);
-            println!("Variables Definition Found!");
-            return Ok(());
-        });
+        }
+        expect!(self.iter, Semicolon else VarDefTerminal);
+
+        Ok(defvars)
     }
 
-    fn defstruct(&mut self) -> Result<()> {
-        expect!(self.iter, Struct);
+    fn defstruct(&mut self) -> Result<StructNode> {
+        let begin = expect!(self.iter, Struct).location();
 
-        self.name()?;
-        self.member_list()?;
+        let name = self.name()?;
+        let members = self.member_list()?;
 
-        expect!(self.iter, Semicolon else StructDefinitionTermial);
+        let end = expect!(self.iter, Semicolon else StructDefinitionTermial);
 
-        println!("Structure definition Found!");
-        Ok(())
+        Ok(StructNode {
+            location: Location::range(begin, end.location()),
+            name: name.image(),
+            members: members,
+            is_incomplete: false,
+        })
     }
 
-    fn defunion(&mut self) -> Result<()> {
-        expect!(self.iter, Union);
+    fn defunion(&mut self) -> Result<UnionNode> {
+        let begin = expect!(self.iter, Union).location();
 
-        self.name()?;
-        self.member_list()?;
+        let name = self.name()?;
+        let members = self.member_list()?;
 
-        expect!(self.iter, Semicolon else UnionDefinitionTermial);
+        let end = expect!(self.iter, Semicolon else UnionDefinitionTermial);
 
-        println!("Union definition Found!");
-        Ok(())
+        Ok(UnionNode {
+            location: Location::range(begin, end.location()),
+            name: name.image(),
+            members: members,
+            is_incomplete: false,
+        })
     }
 
-    fn member_list(&mut self) -> Result<()> {
-        expect!(self.iter, LeftCurlyBracket else LackOfMemberListLeftBracket);
+    fn defenum(&mut self) -> Result<EnumNode> {
+        let begin = expect!(self.iter, Enum).location();
+
+        let name = lookahead!(self.iter, if Identifier {
+            Some(eat!(self.iter).image())
+        }, else {
+            None
+        });
+        let members = self.enumerator_list()?;
+
+        let end = expect!(self.iter, Semicolon else EnumDefinitionTerminal);
+
+        Ok(EnumNode {
+            location: Location::range(begin, end.location()),
+            name: name,
+            members: members,
+        })
+    }
+
+    fn enumerator_list(&mut self) -> Result<Vec<Enumerator>> {
+        let mut members = Vec::new();
+        expect!(self.iter, LeftCurlyBracket);
 
         loop {
             lookahead!(self.iter,
                        if RightCurlyBracket {
                            break;
                        });
-            self.slot()?;
+            let name = self.name()?;
+            let value = lookahead!(self.iter, if Equals {
+                eat!(self.iter);
+                Some(self.expr_10(None) ?)
+            }, else {
+                None
+            });
+            members.push(Enumerator {
+                location: name.location(),
+                name: name.image(),
+                value: value,
+                entity: None,
+            });
+            // the comma after the last one is optional
+            lookahead!(self.iter, if Comma {
+                eat!(self.iter);
+            }, else {
+                break;
+            });
+        }
+
+        expect!(self.iter, RightCurlyBracket else LackOfEnumeratorListRightBracket);
+
+        Ok(members)
+    }
+
+    fn member_list(&mut self) -> Result<Vec<Slot>> {
+        let mut members = Vec::new();
+        expect!(self.iter, LeftCurlyBracket else LackOfMemberListLeftBracket);
+
+        loop {
+            lookahead!(self.iter,
+                RightCurlyBracket => {
+                    break;
+                },
+                EOF => {
+                    break;
+                } else {});
+            members.push(self.slot()?);
             expect!(self.iter, Semicolon else LackOfSlotTerminal);
         }
 
         expect!(self.iter, RightCurlyBracket else LackOfMemberListRightBracket);
 
-        println!("Member List Found!");
-        Ok(())
+        Ok(members)
     }
 
-    fn slot(&mut self) -> Result<()> {
-        self.type_()?;
-        self.name()?;
+    fn slot(&mut self) -> Result<Slot> {
+        let type_ = self.type_()?;
+        let name = self.name()?;
 
-        println!("Slot Found!");
-        Ok(())
+        Ok(Slot {
+            location: name.location(),
+            type_: type_,
+            name: name.image(),
+        })
     }
 
-    fn typedef(&mut self) -> Result<()> {
-        expect!(self.iter, Typedef else ExpectTypedef);
+    fn typedef(&mut self) -> Result<TypedefNode> {
+        let begin = expect!(self.iter, Typedef else ExpectTypedef).location();
 
-        self.typeref()?;
-        self.name()?; // Or Identifier?
+        let real = self.type_()?;
+        let name = self.name()?;
 
-        expect!(self.iter, Semicolon else TypedefTerminal);
+        let end = expect!(self.iter, Semicolon else TypedefTerminal);
 
-        println!("Typedef Statement Found!");
-        Ok(())
+        self.typedefs.insert(name.image());
+        Ok(TypedefNode {
+            location: Location::range(begin, end.location()),
+            real: real,
+            name: name.image(),
+        })
     }
 
     fn eof(&mut self) -> Result<()> {
-        unimplemented!()
+        expect!(self.iter, EOF);
+        Ok(())
     }
 
-    fn import_stmt(&mut self) -> Result<()> {
-        eat!(self.iter); // <Import>
+    fn import_stmt(&mut self) -> Result<ImportNode> {
+        let begin = eat!(self.iter).location(); // <Import>
 
-        self.name()?;
+        // `import c "mylib.h";`, a C header by its path
+        let is_c = lookahead!(self.iter, 2, if String { true }, else { false });
+        if is_c {
+            let error = ParseError::at(&self.iter, ParseErrorKind::InvalidIdentifier);
+            if self.name()?.image() != "c" {
+                return Err(error);
+            }
+            let name = helper::string_value(eat!(self.iter).image()); // <String>
+            let end = expect!(self.iter, Semicolon else ImportTerminalSign);
+            return Ok(ImportNode {
+                location: Location::range(begin, end.location()),
+                kind: ImportKind::C,
+                name: name,
+                file: None,
+                headers: Vec::new(),
+            });
+        }
+
+        let mut name = self.name()?.image();
 
         lookahead!(self.iter,
                    while Dot {
                        eat!(self.iter); // <Dot>
-                       self.name()?;
+                       name.push('.');
+                       name.push_str(&self.name()?.image());
                    });
 
-        expect!(self.iter, Semicolon else ImportTerminalSign);
-        println!("Import Statement Found!");
-        Ok(())
+        let end = expect!(self.iter, Semicolon else ImportTerminalSign);
+        Ok(ImportNode {
+            location: Location::range(begin, end.location()),
+            kind: ImportKind::Cb,
+            name: name,
+            file: None,
+            headers: Vec::new(),
+        })
     }
 
-    fn name(&mut self) -> Result<Box<Node>> {
+    fn name(&mut self) -> Result<&'a Token> {
         lookahead!(self.iter, if Identifier {
-            eat!(self.iter); // <Identifier>
-            println!("Identifier Found!");
-            // Ok(())
-            unimplemented!()
+            Ok(eat!(self.iter)) // <Identifier>
         }, else {
-            println!("Identifier Error!");
-            Err(ParseError::new(ParseErrorKind::InvalidIdentifier))
+            Err(ParseError::at(&self.iter, ParseErrorKind::InvalidIdentifier))
         })
     }
 
-    fn params(&mut self) -> Result<()> {
+    fn params(&mut self) -> Result<Params> {
+        let begin = self.next_location();
+        let mut params = Params {
+            location: begin,
+            params: Vec::new(),
+            is_variadic: false,
+        };
+
         lookahead!(self.iter,
                    if Void {
-                       lookahead!(self.iter,
+                       lookahead!(self.iter, 2,
                                   if CloseParentheses {
-                                      eat!(self.iter, 2); // <Void> and ')'
-                                      println!("Parameters with no element Found!");
-                                      return Ok(());
-                                  });
+                                      eat!(self.iter); // <Void>
+                                      return Ok(params);
+                                  }, else {});
                    });
 
-        self.param()?;
+        params.params.push(self.param()?);
 
         lookahead!(self.iter,
                    while Comma {
             eat!(self.iter); // ','
             lookahead!(self.iter, if Ellipsis {
                 eat!(self.iter);
-                println!("Variable parameter Found!");
-                return Ok(());
+                params.is_variadic = true;
+                break;
             }, else {
-                self.param() ?;
+                params.params.push(self.param() ?);
             });
         });
 
-        println!("Fixed parameter list Found!");
-        Ok(())
+        params.location = Location::range(begin, self.last_location());
+        Ok(params)
     }
 
-    fn block(&mut self) -> Result<()> {
-        expect!(self.iter, LeftCurlyBracket else LackOfBlockLeftBracket);
-        self.defvar_list()?;
-        self.stmts()?;
-        expect!(self.iter, RightCurlyBracket else LackOfBlockRightBracket);
-        println!("Block Found!");
-        Ok(())
+    fn block(&mut self) -> Result<BlockNode> {
+        let begin = expect!(self.iter, LeftCurlyBracket else LackOfBlockLeftBracket);
+        let variables = self.defvar_list()?;
+        let stmts = self.stmts()?;
+        let end = expect!(self.iter, RightCurlyBracket else LackOfBlockRightBracket);
+        let location = Location::range(begin.location(), end.location());
+        Ok(BlockNode::new(location, variables, stmts))
     }
 
     fn expr(&mut self) -> Result<Box<Node>> {
@@ -388,103 +670,57 @@ impl<'a> Parser<'a> {
                 eat!(self.iter);
                 let rhs = self.expr() ?;
                 let location = Location::range(lhs.location(), rhs.location());
-                println!("Assignment statement Found!");
                 Ok(Box::new(AssignNode::new(location, lhs, rhs)))
             },
             AddAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("Add assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::AddAssignment, rhs)))
+                self.op_assign(term, OpAssignType::AddAssignment)
             },
             SubtractAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("Subtract assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::SubtractAssignment, rhs)))
+                self.op_assign(term, OpAssignType::SubtractAssignment)
             },
             MultiplyAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("Multiply assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::MultiplyAssignment, rhs)))
+                self.op_assign(term, OpAssignType::MultiplyAssignment)
             },
             DivideAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("Divide assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::DivideAssignment, rhs)))
+                self.op_assign(term, OpAssignType::DivideAssignment)
             },
             ModuloAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("Modulo assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::ModuloAssignment, rhs)))
+                self.op_assign(term, OpAssignType::ModuloAssignment)
             },
             AndAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("And assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::AndAssignment, rhs)))
+                self.op_assign(term, OpAssignType::AndAssignment)
             },
             ExclusiveOrAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("ExclusiveOr assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::ExclusiveOrAssignment, rhs)))
+                self.op_assign(term, OpAssignType::ExclusiveOrAssignment)
             },
             OrAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("Or assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::OrAssignment, rhs)))
+                self.op_assign(term, OpAssignType::OrAssignment)
             },
             LeftShiftAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("LeftShift assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::LeftShiftAssignment, rhs)))
+                self.op_assign(term, OpAssignType::LeftShiftAssignment)
             },
             RightShiftAssign => {
-                let lhs = term;
-                eat!(self.iter);
-                let rhs = self.expr() ?;
-                let location = Location::range(lhs.location(), rhs.location());
-                println!("RightShift assignment statement Found!");
-                Ok(Box::new(OpAssignNode::new(location, lhs, OpAssignType::RightShiftAssignment, rhs)))
+                self.op_assign(term, OpAssignType::RightShiftAssignment)
             }
             else {
-                let expr = self.expr_10(Some(term)) ?;
-
-                println!("Expression Found!");
-                Ok(expr)
+                self.expr_10(Some(term))
             }
         )
     }
 
+    fn op_assign(&mut self, lhs: Box<Node>, type_: OpAssignType) -> Result<Box<Node>> {
+        eat!(self.iter); // the operator
+        let rhs = self.expr() ?;
+        let location = Location::range(lhs.location(), rhs.location());
+        Ok(Box::new(OpAssignNode::new(location, lhs, type_, rhs)))
+    }
+
     fn expr_10(&mut self, term: Option<Box<Node>>) -> Result<Box<Node>> {
         let condition = self.expr_9(term)?;
 
         lookahead!(self.iter,
                    if QuestionMark {
+            eat!(self.iter);
             let then_clause = self.expr()?;
             expect!(self.iter, Colon else ExpectTernaryColon);
             let else_clause = self.expr_10(None)?;
@@ -700,16 +936,13 @@ impl<'a> Parser<'a> {
             let open = eat!(self.iter);
             match self.type_() { // just try
                 Ok(type_) => {
-                    let close: &Token = expect!(self.iter, CloseParentheses else
-                        ExpectCastRightBracket);
+                    expect!(self.iter, CloseParentheses else ExpectCastRightBracket);
                     let node = self.term() ?;
-                    println!("Casting Term Found!");
-                    let location = Location::range(open.location(), close.location());
+                    let location = Location::range(open.location(), node.location());
                     Ok(Box::new(CastNode::new(location, type_, node)))
                 },
-                Err(ParseError { kind: ParseErrorKind::InvalidTyperefBase }) => {
+                Err(ParseError { kind: ParseErrorKind::InvalidTyperefBase, .. }) => {
                     let node = self.unary(true) ?;
-                    println!("Unary Term Found!");
                     Ok(node) // TODO: should update location
                 },
                 Err(e) => {
@@ -717,75 +950,59 @@ impl<'a> Parser<'a> {
                 }
             }
         }, else {
-            let node = self.unary(false) ?;
-            println!("Unary Term Found!");
-            Ok(node)
+            self.unary(false)
         })
     }
 
     fn unary(&mut self, has_ate_left_bracket: bool) -> Result<Box<Node>> {
         if has_ate_left_bracket {
-            let node = self.postfix(has_ate_left_bracket)?;
-            println!("Unary Found!");
-            return Ok(node);
+            return self.postfix(has_ate_left_bracket);
         }
 
         lookahead!(self.iter,
             Increment => {
                 let token: &Token = eat!(self.iter);
                 let node = self.unary(false) ?;
-                return Ok(Box::new(
-                    PrefixOpNode::new(token.location(), PrefixOpType::Increment, node)
-                ));
+                let location = Location::range(token.location(), node.location());
+                Ok(Box::new(
+                    PrefixOpNode::new(location, PrefixOpType::Increment, node)
+                ))
             },
             Decrement => {
                 let token: &Token = eat!(self.iter);
                 let node = self.unary(false) ?;
-                return Ok(Box::new(
-                    PrefixOpNode::new(token.location(), PrefixOpType::Decrement, node)
-                ));
+                let location = Location::range(token.location(), node.location());
+                Ok(Box::new(
+                    PrefixOpNode::new(location, PrefixOpType::Decrement, node)
+                ))
             },
             Plus => {
-                let token: &Token = eat!(self.iter);
-                let node = self.term() ?;
-                return Ok(Box::new(
-                    UnaryOpNode::new(token.location(), UnaryOpType::Plus, node)
-                ));
+                self.unary_op(UnaryOpType::Plus)
             },
             Hyphen => {
-                let token: &Token = eat!(self.iter);
-                let node = self.term() ?;
-                return Ok(Box::new(
-                    UnaryOpNode::new(token.location(), UnaryOpType::Hyphen, node)
-                ));
+                self.unary_op(UnaryOpType::Hyphen)
             },
             ExclamationMark => {
-                let token: &Token = eat!(self.iter);
-                let node = self.term() ?;
-                return Ok(Box::new(
-                    UnaryOpNode::new(token.location(), UnaryOpType::ExclamationMark, node)
-                ));
+                self.unary_op(UnaryOpType::ExclamationMark)
             },
             Tilde => {
-                let token: &Token = eat!(self.iter);
-                let node = self.term() ?;
-                return Ok(Box::new(
-                    UnaryOpNode::new(token.location(), UnaryOpType::Tilde, node)
-                ));
+                self.unary_op(UnaryOpType::Tilde)
             },
             Asterisk => {
                 let token: &Token = eat!(self.iter);
                 let node = self.term() ?;
-                return Ok(Box::new(
-                    DereferenceNode::new(token.location(), node)
-                ));
+                let location = Location::range(token.location(), node.location());
+                Ok(Box::new(
+                    DereferenceNode::new(location, node)
+                ))
             },
             Ampersand => {
                 let token: &Token = eat!(self.iter);
                 let node = self.term() ?;
-                return Ok(Box::new(
-                    AddressNode::new(token.location(), node)
-                ));
+                let location = Location::range(token.location(), node.location());
+                Ok(Box::new(
+                    AddressNode::new(location, node)
+                ))
             },
             Sizeof => {
                 let left = eat!(self.iter);
@@ -794,34 +1011,35 @@ impl<'a> Parser<'a> {
                     match self.type_() { // just try
                         Ok(type_) => {
                             let right = expect!(self.iter, CloseParentheses else ExpectCastRightBracket);
-                            println!("Sizeof(type) Found!");
                             let location = Location::range(left.location(), right.location());
-                            return Ok(Box::new(SizeofTypeNode::new(location, type_, unimplemented!())));
+                            Ok(Box::new(SizeofTypeNode::new(location, type_)))
                         },
-                        Err(ParseError { kind: ParseErrorKind::InvalidTyperefBase }) => {
+                        Err(ParseError { kind: ParseErrorKind::InvalidTyperefBase, .. }) => {
                             let node = self.unary(true) ?;
-                            println!("Sizeof expression Found!");
-                            let location = Location::range(left.location(), node.location());
-                            return Ok(Box::new(SizeofExprNode::new(location, node, unimplemented!())));
+                            let location = Location::range(left.location(), self.last_location());
+                            Ok(Box::new(SizeofExprNode::new(location, node)))
                         },
                         Err(e) => {
-                            return Err(e); // real error
+                            Err(e) // real error
                         }
-                    };
+                    }
                 }, else {
                     let node = self.unary(false) ?;
-                    println!("Sizeof expression Found!");
                     let location = Location::range(left.location(), node.location());
-                    return Ok(Box::new(SizeofExprNode::new(location, node, unimplemented!())));
-                });
+                    Ok(Box::new(SizeofExprNode::new(location, node)))
+                })
             }
             else {
-                let node = self.postfix(false) ?;
-                return Ok(node)
+                self.postfix(false)
             }
-        );
+        )
+    }
 
-        println!("Unary Found!");
+    fn unary_op(&mut self, type_: UnaryOpType) -> Result<Box<Node>> {
+        let token: &Token = eat!(self.iter); // the operator
+        let node = self.term() ?;
+        let location = Location::range(token.location(), node.location());
+        Ok(Box::new(UnaryOpNode::new(location, type_, node)))
     }
 
     fn postfix(&mut self, has_ate_left_bracket: bool) -> Result<Box<Node>> {
@@ -842,187 +1060,213 @@ impl<'a> Parser<'a> {
                     eat!(self.iter);
                     let idx = self.expr() ?;
                     let right = expect!(self.iter, ClosingBracket else ArrayReferenceTerminal);
-                    println!("Array reference postfix Found!");
                     let location = Location::range(expr.location(), right.location());
                     expr = Box::new(ArefNode::new(location, expr, idx));
                 },
                 Dot => {
                     eat!(self.iter);
                     let memb = self.name() ?;
-                    println!("Structure or Union member reference postfix Found!");
                     let location = Location::range(expr.location(), memb.location());
-                    expr = Box::new(MemberNode::new(location, expr, memb));
+                    expr = Box::new(MemberNode::new(location, expr, memb.image()));
                 },
                 Arrow => {
                     eat!(self.iter);
                     let memb = self.name() ?;
-                    println!("Reference by pointer postfix Found!");
                     let location = Location::range(expr.location(), memb.location());
-                    expr = Box::new(PtrMemberNode::new(location, expr, memb));
+                    expr = Box::new(PtrMemberNode::new(location, expr, memb.image()));
                 },
                 OpenParentheses => {
                     eat!(self.iter);
                     let args: Vec<Box<Node>> = self.args() ?;
                     let right = expect!(self.iter, CloseParentheses else FunctionCallArgsTerminal);
                     let location = Location::range(expr.location(), right.location());
-                    println!("Function call postfix Found!");
                     expr = Box::new(FuncallNode::new(location, expr, args));
                 }
                 else { break; }
             );
         }
 
-        println!("postfix Found!");
         Ok(expr)
     }
 
     fn args(&mut self) -> Result<Vec<Box<Node>>> {
+        let mut args = Vec::new();
         lookahead!(self.iter, if CloseParentheses { /* Empty args */ }, else {
-            self.expr() ?;
+            args.push(self.expr() ?);
             lookahead!(self.iter, while Comma {
                 eat!(self.iter);
-                self.expr() ?;
+                args.push(self.expr() ?);
             });
         });
 
-        println!("Args Found!");
-        // Ok(())
-        unimplemented!()
+        Ok(args)
     }
 
-    fn param(&mut self) -> Result<()> {
-        self.type_()?;
-        self.name()?;
-        println!("A Parameter Found!");
-        Ok(())
+    fn param(&mut self) -> Result<Parameter> {
+        let type_ = self.type_()?;
+        let name = self.name()?;
+        Ok(Parameter {
+            location: name.location(),
+            type_: type_,
+            name: name.image(),
+            entity: None,
+        })
     }
 
     // crash the keyword `type`, so type_
-    fn type_(&mut self) -> Result<Box<TypeRef>> {
+    fn type_(&mut self) -> Result<TypeNode> {
+        let begin = self.next_location();
         let typeref = self.typeref()?;
-        println!("Type Found!");
-        Ok(typeref)
+        let location = Location::range(begin, self.last_location());
+        Ok(TypeNode::new(location, typeref))
     }
 
-    fn typeref(&mut self) -> Result<Box<TypeRef>> {
-        self.typeref_base()?;
+    fn typeref(&mut self) -> Result<TypeRef> {
+        let mut typeref = self.typeref_base()?;
         loop {
             lookahead!(self.iter,
-                ClosingBracket => {
+                OpeningBracket => {
                     eat!(self.iter); // '['
-                    lookahead!(self.iter,
-                        OpeningBracket => {
-                            // Variable-length array
-                        },
-                        Integer => {
-                            // Fixed-length array
-                            eat!(self.iter); // <Integer>
-                            expect!(self.iter, OpeningBracket else
-                                LackOfArrayCloseBracket);
-                        }
-                        else {
-                            return Err(ParseError::new(
-                                ParseErrorKind::LackOfArrayCloseBracket));
-                        }
-                    )
+                    lookahead!(self.iter, if ClosingBracket {
+                        // Variable-length array
+                        eat!(self.iter); // ']'
+                        typeref = TypeRef::array(typeref, None);
+                    }, else {
+                        // Fixed-length array, whose length is evaluated
+                        // with the types if it is not a literal
+                        let length = self.expr_10(None) ?;
+                        expect!(self.iter, ClosingBracket else
+                            LackOfArrayCloseBracket);
+                        let length = match length.node_ref() {
+                            NodeRef::IntegerLiteralNode(literal) if literal.value >= 0 =>
+                                ArrayLength::Fixed(literal.value as usize),
+                            _ => {
+                                let id = ConstantId(self.ast.constants.len());
+                                self.ast.constants.push(length);
+                                ArrayLength::Constant(id)
+                            }
+                        };
+                        typeref = TypeRef::array(typeref, Some(length));
+                    })
                 },
                 Asterisk => {
                     eat!(self.iter); // '*'
+                    typeref = TypeRef::pointer(typeref);
+                },
+                Const => {
+                    // `int* const`, the pointer is const
+                    eat!(self.iter); // <Const>
+                    typeref = TypeRef::const_(typeref);
                 },
                 OpenParentheses => {
-                    self.param_typerefs() ?;
+                    eat!(self.iter); // '('
+                    let params = self.param_typerefs() ?;
                     expect!(self.iter, CloseParentheses else
                         LackOfCloseParentheses);
+                    typeref = TypeRef::function(typeref, params);
                 }
                 else { break; }
             );
         }
 
-        println!("Typeref Found!");
-        unimplemented!()
+        Ok(typeref)
     }
 
-    fn typeref_base(&mut self) -> Result<()> {
+    fn typeref_base(&mut self) -> Result<TypeRef> {
         lookahead!(self.iter,
+            Const => {
+                eat!(self.iter); // <Const>
+                Ok(TypeRef::const_(self.typeref_base() ?))
+            },
             Void => {
                 eat!(self.iter);
+                Ok(TypeRef::Void)
             },
             Char => {
                 eat!(self.iter);
+                Ok(TypeRef::Integer(IntegerTypeRef::Char))
             },
             Short => {
                 eat!(self.iter);
+                Ok(TypeRef::Integer(IntegerTypeRef::Short))
             },
             Int => {
                 eat!(self.iter);
+                Ok(TypeRef::Integer(IntegerTypeRef::Int))
             },
             Long => {
                 eat!(self.iter);
+                Ok(TypeRef::Integer(IntegerTypeRef::Long))
             },
             Unsigned => {
                 eat!(self.iter); // <Unsigned>
-                lookahead!(self.iter,
+                let type_ = lookahead!(self.iter,
                     Char => {
                         eat!(self.iter); // <Char>
+                        IntegerTypeRef::UnsignedChar
                     },
                     Short => {
                         eat!(self.iter); // <Short>
+                        IntegerTypeRef::UnsignedShort
                     },
                     Int => {
                         eat!(self.iter); // <Int>
+                        IntegerTypeRef::UnsignedInt
                     },
                     Long => {
                         eat!(self.iter); // <Long>
+                        IntegerTypeRef::UnsignedLong
                     }
-                    else { /* Just Unsigned */ }
+                    else { /* Just Unsigned */ IntegerTypeRef::UnsignedInt }
                 );
+                Ok(TypeRef::Integer(type_))
             },
             Struct => {
                 eat!(self.iter);
-                lookahead!(self.iter, if Identifier {
-                    eat!(self.iter);
-                }, else {
-                    return Err(ParseError::new(
-                        ParseErrorKind::InvalidIdentifier));
-                });
+                let name = self.name() ?;
+                Ok(TypeRef::Struct(StructTypeRef { name: name.image() }))
             },
             Union => {
                 eat!(self.iter);
-                lookahead!(self.iter, if Identifier {
-                    eat!(self.iter);
-                }, else {
-                    return Err(ParseError::new(
-                        ParseErrorKind::InvalidIdentifier));
-                });
+                let name = self.name() ?;
+                Ok(TypeRef::Union(UnionTypeRef { name: name.image() }))
+            },
+            Enum => {
+                eat!(self.iter);
+                let name = self.name() ?;
+                Ok(TypeRef::Enum(EnumTypeRef { name: name.image() }))
             },
             Identifier => {
-                if !self.is_type(self.iter.clone().next().unwrap().image()) {
-                    return Err(ParseError::new(
-                        ParseErrorKind::InvalidTyperefBase));
+                let name = self.iter.clone().next().unwrap().image();
+                if !self.is_type(name.clone()) {
+                    let error = ParseError::at(&self.iter,
+                                               ParseErrorKind::InvalidTyperefBase);
+                    return Err(self.suggest_type(error, &name));
                 }
-                eat!(self.iter); // p78?
+                let name = eat!(self.iter); // p78?
+                Ok(TypeRef::User(UserTypeRef { name: name.image() }))
             }
             else {
-                return Err(ParseError::new(
-                    ParseErrorKind::InvalidTyperefBase));
+                Err(ParseError::at(&self.iter,
+                    ParseErrorKind::InvalidTyperefBase))
             }
-        );
-
-        println!("Typeref Base Found!");
-        Ok(())
+        )
     }
 
-    fn defvar_list(&mut self) -> Result<()> {
+    fn defvar_list(&mut self) -> Result<Vec<DefinedVariable>> {
+        let mut variables = Vec::new();
         loop {
-            lookahead!(self.iter,
-                       if Static {
-                           eat!(self.iter);
-                           // do someting...
-                       });
+            let is_private = lookahead!(self.iter, if Static {
+                eat!(self.iter);
+                true
+            }, else {
+                false
+            });
 
-            match self.typeref() {
-                Ok(_) => { /* is variable definition list */ }
-                Err(ParseError { kind: ParseErrorKind::InvalidTyperefBase }) => {
+            let type_ = match self.type_() {
+                Ok(type_) => type_, /* is variable definition list */
+                Err(ParseError { kind: ParseErrorKind::InvalidTyperefBase, .. })
+                        if !is_private && !self.is_misspelled_type() => {
                     break;
                 }
                 Err(e) => {
@@ -1030,96 +1274,85 @@ impl<'a> Parser<'a> {
                 }
             };
 
-            self.name()?;
-
-            lookahead!(self.iter,
-                       if Equals {
-                           eat!(self.iter);
-                           self.expr()?;
-                       });
-
-            lookahead!(self.iter,
-                       while Comma {
-                           eat!(self.iter);
-                           self.name()?;
-                           lookahead!(self.iter,
-                                      if Equals {
-                                          eat!(self.iter);
-                                          self.expr()?;
-                                      });
-                       });
-
-            expect!(self.iter, Semicolon else VarDefTerminal);
+            let name = self.name()?;
+            variables.extend(self.defvars(is_private, type_, name)?);
         }
 
-        println!("Variables Definition in function Found!");
-        Ok(())
+        Ok(variables)
     }
 
-    fn stmts(&mut self) -> Result<()> {
+    fn stmts(&mut self) -> Result<Vec<Box<Node>>> {
+        let mut stmts = Vec::new();
         loop {
+            // the missing `}` at the end of the file is reported by the block
             lookahead!(self.iter,
-                       if RightCurlyBracket {
-                           break;
-                       });
-            self.stmt()?;
+                RightCurlyBracket => {
+                    break;
+                },
+                EOF => {
+                    break;
+                } else {});
+            stmts.push(self.stmt()?);
         }
 
-        println!("Statements Found!");
-        Ok(())
+        Ok(stmts)
     }
 
     fn stmt(&mut self) -> Result<Box<Node>> {
         lookahead!(self.iter,
             Semicolon => {
-                eat!(self.iter);
+                let token = eat!(self.iter);
+                // the empty statement, as an empty block
+                Ok(Box::new(BlockNode::new(token.location(), Vec::new(), Vec::new())))
             },
             LeftCurlyBracket => { // block
-                self.block() ?;
+                Ok(Box::new(self.block() ?))
             },
             If => {
-                self.if_stmt() ?;
+                self.if_stmt()
             },
             While => {
-                self.while_stmt() ?;
+                self.while_stmt()
             },
             Do => {
-                self.dowhile_stmt() ?;
+                self.dowhile_stmt()
             },
             For => {
-                self.for_stmt() ?;
+                self.for_stmt()
             },
             Switch => {
-                self.switch_stmt() ?;
+                self.switch_stmt()
             },
             Break => {
-                self.break_stmt() ?;
+                self.break_stmt()
             },
             Continue => {
-                self.continue_stmt() ?;
+                self.continue_stmt()
             },
             Goto => {
-                self.goto_stmt() ?;
+                self.goto_stmt()
             },
             Return => {
-                self.return_stmt() ?;
+                self.return_stmt()
             },
             Identifier => {
                 lookahead!(self.iter, 2, if Colon {
-                    self.labeled_stmt() ?;
+                    self.labeled_stmt()
                 }, else {
-                    self.expr() ?;
-                    expect!(self.iter, Semicolon else ExpressionTerminal);
-                });
+                    self.expr_stmt()
+                })
             }
             else {
-                self.expr() ?;
-                expect!(self.iter, Semicolon else ExpressionTerminal);
+                self.expr_stmt()
             }
-        );
+        )
+    }
 
-        println!("Statment Found!");
-        unimplemented!()
+    fn expr_stmt(&mut self) -> Result<Box<Node>> {
+        let expr = self.expr() ?;
+        let end = expect!(self.iter, Semicolon else ExpressionTerminal);
+        let location = Location::range(expr.location(), end.location());
+        Ok(Box::new(ExprStmtNode::new(location, expr)))
     }
 
     fn if_stmt(&mut self) -> Result<Box<Node>> {
@@ -1136,7 +1369,6 @@ impl<'a> Parser<'a> {
                        return Ok(Box::new(IfNode::new(location, condition, then_clause, Some(else_clause))));
                    });
 
-        println!("If statement Found!");
         let location = Location::range(if_token.location(), then_clause.location());
         Ok(Box::new(IfNode::new(location, condition, then_clause, None)))
     }
@@ -1148,7 +1380,6 @@ impl<'a> Parser<'a> {
         expect!(self.iter, CloseParentheses else LackOfRightBracketAfterWhileCond);
         let body = self.stmt() ?;
 
-        println!("While statement Found!");
         let location = Location::range(while_token.location(), body.location());
         Ok(Box::new(WhileNode::new(location, condition, body)))
     }
@@ -1162,7 +1393,6 @@ impl<'a> Parser<'a> {
         expect!(self.iter, CloseParentheses else LackOfRightBracketAfterWhileCond);
         expect!(self.iter, Semicolon else DoWhileTerminal);
 
-        println!("Do-While statement Found!");
         let location = Location::range(do_token.location(), condition.location());
         Ok(Box::new(DoWhileNode::new(location, body, condition)))
     }
@@ -1184,7 +1414,6 @@ impl<'a> Parser<'a> {
         expect!(self.iter, CloseParentheses else LackOfRightBracketAfterForCond);
         let body = self.stmt()?;
 
-        println!("For statement Found!");
         let location = Location::range(for_token.location(), body.location());
         Ok(Box::new(ForNode::new(location, init_expr, cond_expr, step_expr, body)))
     }
@@ -1198,7 +1427,6 @@ impl<'a> Parser<'a> {
         let cases = self.case_clauses() ?;
         let close_token = expect!(self.iter, RightCurlyBracket else LackOfRightBracketAfterCaseClause);
 
-        println!("Switch statement Found!");
         let location = Location::range(switch_token.location(), close_token.location());
         Ok(Box::new(SwitchNode::new(location, expr, cases)))
     }
@@ -1207,7 +1435,6 @@ impl<'a> Parser<'a> {
         let break_token = expect!(self.iter, Break);
         let semicolon_token = expect!(self.iter, Semicolon else BreakStatementTerminal);
 
-        println!("Break statement Found!");
         let location = Location::range(break_token.location(), semicolon_token.location());
         Ok(Box::new(BreakNode::new(location)))
     }
@@ -1216,7 +1443,6 @@ impl<'a> Parser<'a> {
         let continue_token = expect!(self.iter, Continue);
         let semicolon_token = expect!(self.iter, Semicolon else ContinueStatementTerminal);
 
-        println!("Continue statement Found!");
         let location = Location::range(continue_token.location(), semicolon_token.location());
         Ok(Box::new(ContinueNode::new(location)))
     }
@@ -1226,11 +1452,11 @@ impl<'a> Parser<'a> {
         let label = lookahead!(self.iter, if Identifier {
             eat!(self.iter)
         }, else {
-            return Err(ParseError::new(ParseErrorKind::ExpectGotoLabel));
+            return Err(ParseError::at(&self.iter,
+                ParseErrorKind::ExpectGotoLabel));
         });
         let semicolon_token = expect!(self.iter, Semicolon else GotoStatementTerminal);
 
-        println!("Goto statement Found!");
         let location = Location::range(goto_token.location(), semicolon_token.location());
         Ok(Box::new(GotoNode::new(location, label.value().unwrap())))
     }
@@ -1243,7 +1469,6 @@ impl<'a> Parser<'a> {
         });
         let semicolon_token = expect!(self.iter, Semicolon else ReturnStatementTerminal);
 
-        println!("Return statement Found!");
         let location = Location::range(return_token.location(), semicolon_token.location());
         Ok(Box::new(ReturnNode::new(location, expr)))
     }
@@ -1252,12 +1477,12 @@ impl<'a> Parser<'a> {
         let label = lookahead!(self.iter, if Identifier {
             eat!(self.iter)
         }, else {
-            return Err(ParseError::new(ParseErrorKind::LackOfLabel));
+            return Err(ParseError::at(&self.iter,
+                ParseErrorKind::LackOfLabel));
         });
         expect!(self.iter, Colon);
         let stmt = self.stmt() ?;
 
-        println!("Labeled statement Found!");
         let location = Location::range(label.location(), stmt.location());
         Ok(Box::new(LabelNode::new(location, label.value().unwrap(), stmt)))
     }
@@ -1275,7 +1500,6 @@ impl<'a> Parser<'a> {
                        default_case = Some(self.default_clause() ?);
                    });
 
-        println!("Case clauses Found!");
         if normal_cases.len() == 0 && default_case.is_none() {
             Ok(None)
         } else {
@@ -1296,7 +1520,6 @@ impl<'a> Parser<'a> {
         let expr = self.case() ?;
         let stmts = self.case_body() ?;
 
-        println!("Case clause Found!");
         let right = if stmts.len() > 0 {
             stmts[stmts.len() - 1].location()
         } else {
@@ -1310,7 +1533,6 @@ impl<'a> Parser<'a> {
         let default_token = expect!(self.iter, Default);
         let colon_token = expect!(self.iter, Colon else ExpectCaseColon);
         let stmts = self.case_body() ?;
-        println!("Default clause Found!");
         let right = if stmts.len() > 0 {
             stmts[stmts.len() - 1].location()
         } else {
@@ -1321,11 +1543,10 @@ impl<'a> Parser<'a> {
     }
 
     fn case(&mut self) -> Result<Box<Node>> {
-        let case_token = expect!(self.iter, Case);
-        let expr = self.primary(false) ?;
+        expect!(self.iter, Case);
+        let expr = self.expr_10(None) ?; // a constant expression
         expect!(self.iter, Colon else ExpectCaseColon);
 
-        println!("Case head Found!");
         Ok(expr)
     }
 
@@ -1336,104 +1557,282 @@ impl<'a> Parser<'a> {
             lookahead!(self.iter,
                 Case => { break; },
                 Default => { break; },
-                RightCurlyBracket => { break; }
+                RightCurlyBracket => { break; },
+                EOF => { break; }
                 else { /* continue to get the stmt */ }
             );
         }
 
-        println!("Case body Found!");
         Ok(stmts)
     }
 
-    fn param_typerefs(&mut self) -> Result<()> {
+    fn param_typerefs(&mut self) -> Result<ParamTypeRefs> {
+        let mut params = ParamTypeRefs {
+            types: Vec::new(),
+            is_variadic: false,
+        };
+
         lookahead!(self.iter,
                    if Void {
-                       lookahead!(self.iter,
+                       lookahead!(self.iter, 2,
                                   if CloseParentheses {
-                                      eat!(self.iter, 2); // <Void> and ')'
-                                      println!("Typedef parameters with no element Found!");
-                                      return Ok(());
-                                  });
+                                      eat!(self.iter); // <Void>
+                                      return Ok(params);
+                                  }, else {});
                    });
 
-        self.typeref()?;
+        params.types.push(self.typeref()?);
 
         lookahead!(self.iter,
                    while Comma {
             eat!(self.iter); // ','
             lookahead!(self.iter, if Ellipsis {
                 eat!(self.iter);
-                println!("Variable typeref parameters Found!");
-                return Ok(());
+                params.is_variadic = true;
+                break;
             }, else {
-                self.typeref() ?;
+                params.types.push(self.typeref() ?);
             });
         });
 
-        println!("Fixed typeref parameter list Found!");
-        Ok(())
+        Ok(params)
     }
 
     fn primary(&mut self, has_ate_left_bracket: bool) -> Result<Box<Node>> {
         if has_ate_left_bracket {
-            self.expr()?;
+            let node = self.expr()?;
             expect!(self.iter, CloseParentheses else
                 ExpectPrimaryRightBracket);
-        } else {
-            lookahead!(self.iter,
-                Integer => {
-                    let token: &Token = eat!(self.iter);
-                    return Ok(Box::new(helper::integer_node(token.location(), token.value().unwrap())))
-                },
-                Character => {
-                    let token: &Token = eat!(self.iter);
-                    return Ok(Box::new(
-                        IntegerLiteralNode::new(
-                            token.location(),
-                            IntegerTypeRef::Char,
-                            helper::character_code(token.value().unwrap())
-                        )
-                    ))
-                },
-                String => {
-                    let token: &Token = eat!(self.iter);
-                    return Ok(Box::new(
-                        StringLiteralNode::new(token.location(), token.value().unwrap())
-                    ))
-                },
-                Identifier => {
-                    let token: &Token = eat!(self.iter);
-                    return Ok(Box::new(
-                        VariableNode::new(token.location(), token.value().unwrap())
-                    ))
-                },
-                OpenParentheses => {
-                    eat!(self.iter);
-                    let node = self.expr() ?;
-                    expect!(self.iter, CloseParentheses else
-                        ExpectPrimaryRightBracket);
-                    // return Ok(Box::new(node))
-                    unimplemented!()
-                }
-                else {
-                    return Err(ParseError::new(ParseErrorKind::InvalidPrimary));
-                }
-            );
+            return Ok(node);
         }
 
-        println!("Primary Found!");
-        unimplemented!()
+        lookahead!(self.iter,
+            Integer => {
+                let token: &Token = eat!(self.iter);
+                Ok(Box::new(helper::integer_node(token.location(), token.image())))
+            },
+            Character => {
+                let token: &Token = eat!(self.iter);
+                Ok(Box::new(
+                    IntegerLiteralNode::new(
+                        token.location(),
+                        IntegerTypeRef::Char,
+                        helper::character_code(token.image())
+                    )
+                ))
+            },
+            String => {
+                let token: &Token = eat!(self.iter);
+                Ok(Box::new(
+                    StringLiteralNode::new(token.location(),
+                                           helper::string_value(token.image()))
+                ))
+            },
+            Identifier => {
+                let token: &Token = eat!(self.iter);
+                Ok(Box::new(
+                    VariableNode::new(token.location(), token.image())
+                ))
+            },
+            OpenParentheses => {
+                eat!(self.iter);
+                let node = self.expr() ?;
+                expect!(self.iter, CloseParentheses else
+                    ExpectPrimaryRightBracket);
+                Ok(node)
+            }
+            else {
+                Err(ParseError::at(&self.iter,
+                    ParseErrorKind::InvalidPrimary))
+            }
+        )
     }
 
     fn is_type(&self, name: String) -> bool {
-        false // unimplemented!()
+        self.typedefs.contains(&name)
+    }
+
+    // whether the next name is a misspelled type, as `unsinged int i;` or
+    // `Point p;`, since no expression has a name followed by another.
+    fn is_misspelled_type(&self) -> bool {
+        let mut scout = self.iter.clone();
+        match (scout.next().map(|t| &t.kind), scout.next().map(|t| &t.kind)) {
+            (Some(&TokenKind::Identifier), Some(&TokenKind::Identifier)) |
+            (Some(&TokenKind::Identifier), Some(&TokenKind::Void)) |
+            (Some(&TokenKind::Identifier), Some(&TokenKind::Char)) |
+            (Some(&TokenKind::Identifier), Some(&TokenKind::Short)) |
+            (Some(&TokenKind::Identifier), Some(&TokenKind::Int)) |
+            (Some(&TokenKind::Identifier), Some(&TokenKind::Long)) => true,
+            _ => false,
+        }
+    }
+
+    // suggest the type keyword or the typedef name close to the name.
+    fn suggest_type(&self, error: ParseError, name: &str) -> ParseError {
+        let keywords = ["void", "char", "short", "int", "long", "unsigned",
+                        "struct", "union", "enum", "const"];
+        let names = keywords.iter().cloned()
+                            .chain(self.typedefs.iter().map(|name| name.as_str()));
+        match suggestion::suggest(name, names) {
+            Some(name) => error.with_help(suggestion::did_you_mean(name)),
+            None => error,
+        }
+    }
+
+    // the location of the token which the parser is looking at.
+    fn next_location(&self) -> Location {
+        self.iter.clone()
+                 .next()
+                 .map(|x| x.location())
+                 .unwrap_or(Location::default())
+    }
+
+    // the location of the token which the parser ate last.
+    fn last_location(&self) -> Location {
+        let consumed = self.tokens.len() - self.iter.as_slice().len();
+        if consumed == 0 {
+            return self.next_location();
+        }
+        self.tokens[consumed - 1].location()
     }
 }
 
 
 impl ParseError {
-    fn new(kind: ParseErrorKind) -> ParseError {
-        ParseError { kind: kind }
+    fn new(kind: ParseErrorKind, location: Location) -> ParseError {
+        ParseError { kind: kind, location: location, help: None }
+    }
+
+    fn with_help(mut self, help: String) -> ParseError {
+        self.help = Some(help);
+        self
+    }
+
+    /// an error at the token which the parser is looking at.
+    fn at(iter: &Iter<Token>, kind: ParseErrorKind) -> ParseError {
+        let location = iter.clone()
+                           .next()
+                           .map(|x| x.location())
+                           .unwrap_or(Location::default());
+        ParseError::new(kind, location)
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+}
+
+
+impl ParseErrorKind {
+    pub const ALL: &'static [ParseErrorKind] = &[
+        ParseErrorKind::ImportTerminalSign,
+        ParseErrorKind::InvalidIdentifier,
+        ParseErrorKind::ParamsCloseBracket,
+        ParseErrorKind::VarDefTerminal,
+        ParseErrorKind::LackOfBlockLeftBracket,
+        ParseErrorKind::LackOfBlockRightBracket,
+        ParseErrorKind::StructDefinitionTermial,
+        ParseErrorKind::UnionDefinitionTermial,
+        ParseErrorKind::LackOfMemberListLeftBracket,
+        ParseErrorKind::LackOfMemberListRightBracket,
+        ParseErrorKind::LackOfSlotTerminal,
+        ParseErrorKind::ExpectTypedef,
+        ParseErrorKind::TypedefTerminal,
+        ParseErrorKind::LackOfArrayCloseBracket,
+        ParseErrorKind::LackOfCloseParentheses,
+        ParseErrorKind::InvalidTyperefBase,
+        ParseErrorKind::ExpressionTerminal,
+        ParseErrorKind::LackOfLeftBracketBeforeIfCond,
+        ParseErrorKind::LackOfRightBracketAfterIfCond,
+        ParseErrorKind::LackOfLeftBracketBeforeWhileCond,
+        ParseErrorKind::LackOfRightBracketAfterWhileCond,
+        ParseErrorKind::LackOfLeftBracketBeforeForCond,
+        ParseErrorKind::LackOfRightBracketAfterForCond,
+        ParseErrorKind::ForExpressionSeparator,
+        ParseErrorKind::LackOfLabel,
+        ParseErrorKind::ExpectWhileinDoWhile,
+        ParseErrorKind::DoWhileTerminal,
+        ParseErrorKind::LackOfLeftBracketBeforeSwitchCond,
+        ParseErrorKind::LackOfRightBracketAfterSwitchCond,
+        ParseErrorKind::LackOfLeftBracketBeforeCaseClause,
+        ParseErrorKind::LackOfRightBracketAfterCaseClause,
+        ParseErrorKind::BreakStatementTerminal,
+        ParseErrorKind::ExpectCaseColon,
+        ParseErrorKind::ExpectGotoLabel,
+        ParseErrorKind::GotoStatementTerminal,
+        ParseErrorKind::ReturnStatementTerminal,
+        ParseErrorKind::ContinueStatementTerminal,
+        ParseErrorKind::ExpectTernaryColon,
+        ParseErrorKind::ExpectCastRightBracket,
+        ParseErrorKind::ArrayReferenceTerminal,
+        ParseErrorKind::FunctionCallArgsTerminal,
+        ParseErrorKind::ExpectPrimaryRightBracket,
+        ParseErrorKind::InvalidPrimary,
+        ParseErrorKind::EnumDefinitionTerminal,
+        ParseErrorKind::LackOfEnumeratorListRightBracket,
+        ParseErrorKind::DeclarationTerminal,
+        ParseErrorKind::InvalidFormatAttribute,
+        ParseErrorKind::DefinitionInHeader,
+    ];
+}
+
+
+impl ErrorCode for ParseErrorKind {
+    fn code(&self) -> &'static str {
+        match *self {
+            ParseErrorKind::ImportTerminalSign => "E0007",
+            ParseErrorKind::InvalidIdentifier => "E0008",
+            ParseErrorKind::ParamsCloseBracket => "E0009",
+            ParseErrorKind::VarDefTerminal => "E0010",
+            ParseErrorKind::LackOfBlockLeftBracket => "E0011",
+            ParseErrorKind::LackOfBlockRightBracket => "E0012",
+            ParseErrorKind::StructDefinitionTermial => "E0013",
+            ParseErrorKind::UnionDefinitionTermial => "E0014",
+            ParseErrorKind::LackOfMemberListLeftBracket => "E0015",
+            ParseErrorKind::LackOfMemberListRightBracket => "E0016",
+            ParseErrorKind::LackOfSlotTerminal => "E0017",
+            ParseErrorKind::ExpectTypedef => "E0018",
+            ParseErrorKind::TypedefTerminal => "E0019",
+            ParseErrorKind::LackOfArrayCloseBracket => "E0020",
+            ParseErrorKind::LackOfCloseParentheses => "E0021",
+            ParseErrorKind::InvalidTyperefBase => "E0022",
+            ParseErrorKind::ExpressionTerminal => "E0023",
+            ParseErrorKind::LackOfLeftBracketBeforeIfCond => "E0024",
+            ParseErrorKind::LackOfRightBracketAfterIfCond => "E0025",
+            ParseErrorKind::LackOfLeftBracketBeforeWhileCond => "E0026",
+            ParseErrorKind::LackOfRightBracketAfterWhileCond => "E0027",
+            ParseErrorKind::LackOfLeftBracketBeforeForCond => "E0028",
+            ParseErrorKind::LackOfRightBracketAfterForCond => "E0029",
+            ParseErrorKind::ForExpressionSeparator => "E0030",
+            ParseErrorKind::LackOfLabel => "E0031",
+            ParseErrorKind::ExpectWhileinDoWhile => "E0032",
+            ParseErrorKind::DoWhileTerminal => "E0033",
+            ParseErrorKind::LackOfLeftBracketBeforeSwitchCond => "E0034",
+            ParseErrorKind::LackOfRightBracketAfterSwitchCond => "E0035",
+            ParseErrorKind::LackOfLeftBracketBeforeCaseClause => "E0036",
+            ParseErrorKind::LackOfRightBracketAfterCaseClause => "E0037",
+            ParseErrorKind::BreakStatementTerminal => "E0038",
+            ParseErrorKind::ExpectCaseColon => "E0039",
+            ParseErrorKind::ExpectGotoLabel => "E0040",
+            ParseErrorKind::GotoStatementTerminal => "E0041",
+            ParseErrorKind::ReturnStatementTerminal => "E0042",
+            ParseErrorKind::ContinueStatementTerminal => "E0043",
+            ParseErrorKind::ExpectTernaryColon => "E0044",
+            ParseErrorKind::ExpectCastRightBracket => "E0045",
+            ParseErrorKind::ArrayReferenceTerminal => "E0046",
+            ParseErrorKind::FunctionCallArgsTerminal => "E0047",
+            ParseErrorKind::ExpectPrimaryRightBracket => "E0048",
+            ParseErrorKind::InvalidPrimary => "E0049",
+            ParseErrorKind::EnumDefinitionTerminal => "E0081",
+            ParseErrorKind::LackOfEnumeratorListRightBracket => "E0082",
+            ParseErrorKind::DeclarationTerminal => "E0088",
+            ParseErrorKind::InvalidFormatAttribute => "E0089",
+            ParseErrorKind::DefinitionInHeader => "E0091",
+        }
     }
 }
 
@@ -1444,7 +1843,7 @@ impl fmt::Display for ParseError {
             ParseErrorKind::ImportTerminalSign =>
                 "need a semicolon after the import sentence".fmt(f),
             ParseErrorKind::InvalidIdentifier =>
-                "need a valid identifier".fmt(f),
+                "need an identifier".fmt(f),
             ParseErrorKind::ParamsCloseBracket =>
                 "need a close bracket `)` for param list".fmt(f),
             ParseErrorKind::VarDefTerminal =>
@@ -1460,7 +1859,7 @@ impl fmt::Display for ParseError {
             ParseErrorKind::LackOfMemberListLeftBracket =>
                 "need a left curly bracket `{` before the Member List".fmt(f),
             ParseErrorKind::LackOfMemberListRightBracket =>
-                "need a right curly bracket `}` before the Member List".fmt(f),
+                "need a right curly bracket `}` after the Member List".fmt(f),
             ParseErrorKind::LackOfSlotTerminal =>
                 "need a semicolon after the slot in member list".fmt(f),
             ParseErrorKind::ExpectTypedef =>
@@ -1472,7 +1871,7 @@ impl fmt::Display for ParseError {
             ParseErrorKind::LackOfCloseParentheses =>
                 "need a close parentheses `)`".fmt(f),
             ParseErrorKind::InvalidTyperefBase =>
-                "need a valid typeref base part".fmt(f),
+                "need a type, such as `int`, `struct point` or a typedef name".fmt(f),
             ParseErrorKind::ExpressionTerminal =>
                 "need a semicolon after the expression statement".fmt(f),
             ParseErrorKind::LackOfLeftBracketBeforeIfCond =>
@@ -1530,9 +1929,135 @@ impl fmt::Display for ParseError {
                  argument list".fmt(f),
             ParseErrorKind::ExpectPrimaryRightBracket =>
                 "need a close parentheses after the expression".fmt(f),
-            ParseErrorKind::InvalidPrimary => // what?
-                "need a valid primary".fmt(f),
+            ParseErrorKind::InvalidPrimary =>
+                "need an expression, such as a literal, a name or `(...)`".fmt(f),
+            ParseErrorKind::EnumDefinitionTerminal =>
+                "need a semicolon after the enum definition".fmt(f),
+            ParseErrorKind::LackOfEnumeratorListRightBracket =>
+                "need a right curly bracket `}` after the enumerators".fmt(f),
+            ParseErrorKind::DeclarationTerminal =>
+                "need a semicolon after the `extern` declaration".fmt(f),
+            ParseErrorKind::InvalidFormatAttribute =>
+                "need a format attribute such as \
+                 `__attribute__((format(printf, 1, 2)))`".fmt(f),
+            ParseErrorKind::DefinitionInHeader =>
+                "need a declaration, a header can not define a function \
+                 or a variable".fmt(f),
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{compile, TempDir};
+
+    // the statements in a function, where the names used are defined.
+    fn in_function(stmts: &str) -> String {
+        format!("int f(int x, int i, int sum, char c) {{\n{}\nreturn 0;\n}}\n", stmts)
+    }
+
+    #[test]
+    fn definitions() {
+        let compiled = compile("import stdio;\n\
+                                struct point { int x; int y; };\n\
+                                union value { int i; char* s; };\n\
+                                typedef unsigned long length_t;\n\
+                                enum color { RED, GREEN = 3, BLUE };\n\
+                                static int[4] numbers;\n\
+                                extern int errno_like;\n\
+                                int apply(int(int, int)* op, int a, int b) { return op(a, b); }\n\
+                                int main(int argc, char** argv) {\n\
+                                    struct point p; int i; int sum = 0;\n\
+                                    p.x = argc > 1 ? 1 : 0;\n\
+                                    for (i = 0; i < 4; i++) { sum += numbers[i]; }\n\
+                                    while (sum > 10) { sum--; if (sum == 5) break; else continue; }\n\
+                                    do { sum++; } while (sum < 0);\n\
+                                    switch (sum) { case 1: sum = (int)(long)p.x; break; \
+                                                   default: sum = -sum; }\n\
+                                    goto done;\n\
+                                done:\n\
+                                    printf(\"%d %s\\n\", sum, argv[0]);\n\
+                                    return sizeof(union value) + RED;\n\
+                                }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn errors() {
+        for &(src, code) in &[
+            ("import stdio\nimport stdlib;\n", "E0007"),
+            ("int 1st = 1;\n", "E0008"),
+            ("int add(int a, int b {\n    return a + b;\n}\n", "E0009"),
+            ("int main(void) {\n    int i = 0\n    return i;\n}\n", "E0010"),
+            ("int main(void)\n    return 0;\n", "E0011"),
+            ("int main(void) {\n    return 0;\n", "E0012"),
+            ("int f(char c) {\n    switch (c) {\n    case 'a': return 1;\n", "E0037"),
+            ("struct point {\n    int x;\n    int y;\n}\n", "E0013"),
+            ("union value {\n    int i;\n    char* s;\n}\n", "E0014"),
+            ("struct point {\n    int x;\n    int y;\n", "E0016"),
+            ("struct point {\n    int x, int y;\n};\n", "E0017"),
+            ("typedef unsigned long size_t\n", "E0019"),
+            ("int[10 numbers;\n", "E0020"),
+            ("typedef int(int, int compare;\n", "E0021"),
+            ("integer count;\n", "E0022"),
+            ("enum color { RED, GREEN, BLUE }\n", "E0081"),
+            ("enum color { RED GREEN };\n", "E0082"),
+            ("extern int errno\nextern int puts(char* s);\n", "E0088"),
+        ] {
+            assert_eq!(compile(src).errors(), [code], "{}", src);
+        }
+    }
+
+    #[test]
+    fn statement_errors() {
+        for &(stmts, code) in &[
+            ("puts(\"hello\")\nreturn 0;", "E0023"),
+            ("if x > 0 { return x; }", "E0024"),
+            ("if (x > 0 { return x; }", "E0025"),
+            ("while i < 10 { i++; }", "E0026"),
+            ("while (i < 10 { i++; }", "E0027"),
+            ("for i = 0; i < 10; i++ { sum += i; }", "E0028"),
+            ("for (i = 0; i < 10; i++ { sum += i; }", "E0029"),
+            ("for (i = 0, i < 10, i++) { sum += i; }", "E0030"),
+            ("do { i++; } until (i == 10);", "E0032"),
+            ("do { i++; } while (i < 10)", "E0033"),
+            ("switch c { case 'a': return 1; }", "E0034"),
+            ("switch (c { case 'a': return 1; }", "E0035"),
+            ("switch (c) case 'a': return 1;", "E0036"),
+            ("while (1) { break }", "E0038"),
+            ("switch (c) { case 'a' return 1; }", "E0039"),
+            ("goto;", "E0040"),
+            ("goto done\ni++;\ndone: ;", "E0041"),
+            ("return 0\n", "E0042"),
+            ("while (i < 10) { i++; continue }", "E0043"),
+            ("sum = x > i ? x, i;", "E0044"),
+            ("sum = (long x;", "E0045"),
+            ("sum = (&x)[0;", "E0046"),
+            ("sum = f(x i, sum, c);", "E0047"),
+            ("sum = (x + i * sum;", "E0048"),
+            ("sum = 1 + ;", "E0049"),
+        ] {
+            let src = in_function(stmts);
+            assert_eq!(compile(&src).errors(), [code], "{}", src);
+        }
+    }
+
+    #[test]
+    fn format_attribute() {
+        let dir = TempDir::new();
+        dir.write("io.hb", "extern int log(char* fmt, ...) __attribute__((format(printf, 1, 2)));\n");
+        dir.compile("import io;\nint main(void) { log(\"%d\", 1); return 0; }\n")
+           .assert_clean();
+
+        dir.write("io.hb", "extern int log(char* fmt, ...) __attribute__((noreturn));\n");
+        assert_eq!(dir.compile("import io;\n").errors(), ["E0089"]);
+    }
+
+    #[test]
+    fn definition_in_header() {
+        let dir = TempDir::new();
+        dir.write("lib.hb", "int twice(int x) { return x * 2; }\n");
+        assert_eq!(dir.compile("import lib;\n").errors(), ["E0091"]);
+    }
+}
@@ -0,0 +1,389 @@
+//! The Rust bindings of a compilation unit, for `--emit=rust-bindings`.
+//!
+//! They are written from the resolved types of the interface, not as they
+//! are written in the source: each struct and union the interface reaches,
+//! from any header, is a `#[repr(C)]` one with the members at the offsets
+//! of the target, and each typedef name a type alias. The enumerators are
+//! constants, since an enum is an `int` in Cb. The layout tests assert the
+//! sizes, the alignments and the offsets Cb computed, so the bindings fail
+//! their tests when Rust lays them out otherwise. They are compiled only for
+//! the `target_arch` of the `--target`, whose layouts they are.
+
+use std::collections::HashSet;
+use super::ast::*;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::target::{Arch, Target};
+use super::entity::EntityTable;
+use super::interface::{Interface, TypeDefinition};
+
+pub struct RustBindingsWriter<'a> {
+    table: &'a TypeTable,
+    entities: &'a EntityTable,
+}
+
+// the words Rust reserves, which are renamed with `_`
+const RUST_KEYWORDS: &'static [&'static str] = &[
+    "as", "async", "await", "box", "const", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod",
+    "move", "mut", "pub", "ref", "self", "Self", "static", "struct", "super", "trait",
+    "true", "try", "type", "unsafe", "use", "where", "while", "abstract", "become",
+    "do", "final", "macro", "override", "priv", "typeof", "unsized", "virtual", "yield",
+];
+
+impl<'a> RustBindingsWriter<'a> {
+    pub fn new(table: &'a TypeTable, entities: &'a EntityTable) -> RustBindingsWriter<'a> {
+        RustBindingsWriter {
+            table: table,
+            entities: entities,
+        }
+    }
+
+    /// the Rust module `name` of the interface, such as `foo.rs`.
+    pub fn write(&self, interface: &Interface, name: &str) -> String {
+        let mut sections = vec![format!("// {}, generated by rcbc --emit=rust-bindings for \
+                                         {}.\n\n#![allow(non_camel_case_types, \
+                                         non_snake_case, non_upper_case_globals, dead_code)]\n\n\
+                                         use std::os::raw::*;\n",
+                                        name, self.table.target())];
+
+        let mut types = Vec::new();
+        let mut seen = HashSet::new();
+        for func in &interface.functions {
+            self.collect(func.type_.type_id(), &mut types, &mut seen);
+        }
+        for var in &interface.variables {
+            self.collect(var.type_.type_id(), &mut types, &mut seen);
+        }
+
+        let constants: String = interface.types.iter().filter_map(|&definition| {
+            match definition {
+                TypeDefinition::Enum(node) => Some(self.enumerators(node)),
+                _ => None,
+            }
+        }).collect();
+        if !constants.is_empty() {
+            sections.push(constants);
+        }
+        let definitions: Vec<String> = types.iter()
+                                            .map(|&id| self.type_definition(id))
+                                            .filter(|definition| !definition.is_empty())
+                                            .collect();
+        if !definitions.is_empty() {
+            sections.push(definitions.join("\n"));
+        }
+        if !interface.functions.is_empty() || !interface.variables.is_empty() {
+            let mut items: Vec<String> = interface.variables.iter()
+                                                  .map(|var| self.variable(var))
+                                                  .collect();
+            items.extend(interface.functions.iter().map(|func| self.function(func)));
+            sections.push(format!("extern \"C\" {{\n{}}}\n", items.concat()));
+        }
+        let tests: Vec<String> = types.iter().filter_map(|&id| self.layout_test(id)).collect();
+        if !tests.is_empty() {
+            sections.push(format!("#[cfg(all(test, target_arch = \"{}\"))]\n\
+                                   mod layout_tests {{\n    use super::*;\n    \
+                                   use std::mem;\n\n{}}}\n",
+                                  target_arch(self.table.target()), tests.join("\n")));
+        }
+        sections.join("\n")
+    }
+
+    // the structs, unions and typedef names the type reaches, each once.
+    fn collect(&self, id: TypeId, types: &mut Vec<TypeId>, seen: &mut HashSet<TypeId>) {
+        match *self.table.get(id) {
+            Type::Struct(_) | Type::Union(_) => {
+                if !seen.insert(id) {
+                    return;
+                }
+                types.push(id);
+                for member in self.table.members(id).unwrap_or(&[]) {
+                    self.collect(member.type_, types, seen);
+                }
+            }
+            Type::User(ref user) => {
+                if !seen.insert(id) {
+                    return;
+                }
+                types.push(id);
+                self.collect(user.real_type, types, seen);
+            }
+            Type::Pointer(ref pointer) => self.collect(pointer.base_type, types, seen),
+            Type::Array(ref array) => self.collect(array.base_type, types, seen),
+            Type::Const(ref const_) => self.collect(const_.base_type, types, seen),
+            Type::Function(ref function) => {
+                self.collect(function.return_type, types, seen);
+                for &param in &function.param_types {
+                    self.collect(param, types, seen);
+                }
+            }
+            Type::Void(_) | Type::Integer(_) => {}
+        }
+    }
+
+    fn enumerators(&self, node: &EnumNode) -> String {
+        node.members.iter().map(|member| {
+            let value = member.entity.and_then(|e| self.entities.get(e).value).unwrap_or(0);
+            format!("pub const {}: c_int = {};\n", ident(&member.name), value)
+        }).collect()
+    }
+
+    fn type_definition(&self, id: TypeId) -> String {
+        match *self.table.get(id) {
+            Type::Struct(ref struct_) =>
+                format!("#[repr(C)]\n#[derive(Copy, Clone)]\npub struct {} {{\n{}}}\n",
+                        ident(&struct_.name), self.members(&struct_.members)),
+            Type::Union(ref union) =>
+                format!("#[repr(C)]\n#[derive(Copy, Clone)]\npub union {} {{\n{}}}\n",
+                        ident(&union.name), self.members(&union.members)),
+            Type::User(ref user) => {
+                // `typedef struct point point;`, the struct is the type itself
+                if self.record_name(self.unqualified(user.real_type)) == Some(&user.name) {
+                    return String::new();
+                }
+                format!("pub type {} = {};\n", ident(&user.name), self.rust_type(user.real_type))
+            }
+            _ => String::new(),
+        }
+    }
+
+    // a struct without members is opaque, as the ones of the C headers.
+    fn members(&self, members: &[Member]) -> String {
+        if members.is_empty() {
+            return "    _private: [u8; 0],\n".to_string();
+        }
+        members.iter()
+               .map(|member| format!("    pub {}: {},\n", ident(&member.name),
+                                     self.rust_type(member.type_)))
+               .collect()
+    }
+
+    fn variable(&self, var: &DefinedVariable) -> String {
+        let id = var.type_.type_id();
+        let mutability = if self.table.is_const(id) { "" } else { "mut " };
+        format!("{}    pub static {}{}: {};\n", link_name(&var.name), mutability,
+                ident(&var.name), self.rust_type(id))
+    }
+
+    // the types of the parameters are the ones of the function type, where
+    // an array or a function is a pointer.
+    fn function(&self, func: &DefinedFunction) -> String {
+        let function = match self.table.function_type(func.type_.type_id()) {
+            Some(function) => function,
+            None => return String::new(),
+        };
+        let mut params: Vec<String> = func.params.params.iter()
+                                          .zip(&function.param_types)
+                                          .map(|(param, &type_)| format!("{}: {}",
+                                                                         ident(&param.name),
+                                                                         self.rust_type(type_)))
+                                          .collect();
+        if func.params.is_variadic {
+            params.push("...".to_string());
+        }
+        let return_type = self.return_type(function.return_type);
+        format!("{}    pub fn {}({}){};\n", link_name(&func.name), ident(&func.name),
+                params.join(", "), return_type)
+    }
+
+    fn return_type(&self, id: TypeId) -> String {
+        if self.table.is_void(id) { String::new() }
+        else { format!(" -> {}", self.rust_type(id)) }
+    }
+
+    // the Rust type of the Cb one, such as `*const c_char` of `char const*`.
+    fn rust_type(&self, id: TypeId) -> String {
+        match *self.table.get(id) {
+            Type::Void(_) => "c_void".to_string(),
+            Type::Integer(ref integer) => match integer.name {
+                "char" => "c_char",
+                "short" => "c_short",
+                "int" => "c_int",
+                "long" => "c_long",
+                "unsigned char" => "c_uchar",
+                "unsigned short" => "c_ushort",
+                "unsigned int" => "c_uint",
+                _ => "c_ulong",
+            }.to_string(),
+            Type::Struct(ref struct_) => ident(&struct_.name),
+            Type::Union(ref union) => ident(&union.name),
+            Type::User(ref user) => ident(&user.name),
+            Type::Const(ref const_) => self.rust_type(const_.base_type),
+            // a pointer to a function may be null
+            Type::Pointer(ref pointer) if self.table.is_function(pointer.base_type) =>
+                format!("Option<{}>", self.rust_type(pointer.base_type)),
+            Type::Pointer(ref pointer) => {
+                let mutability = if self.table.is_const(pointer.base_type) { "const" }
+                                 else { "mut" };
+                format!("*{} {}", mutability, self.rust_type(pointer.base_type))
+            }
+            Type::Array(ref array) => format!("[{}; {}]", self.rust_type(array.base_type),
+                                              array.length.unwrap_or(0)),
+            Type::Function(ref function) => {
+                let mut params: Vec<String> = function.param_types.iter()
+                                                      .map(|&param| self.rust_type(param))
+                                                      .collect();
+                if function.is_variadic {
+                    params.push("...".to_string());
+                }
+                format!("unsafe extern \"C\" fn({}){}", params.join(", "),
+                        self.return_type(function.return_type))
+            }
+        }
+    }
+
+    fn unqualified(&self, id: TypeId) -> TypeId {
+        match *self.table.get(id) {
+            Type::Const(ref const_) => self.unqualified(const_.base_type),
+            _ => id,
+        }
+    }
+
+    fn record_name(&self, id: TypeId) -> Option<&str> {
+        match *self.table.get(id) {
+            Type::Struct(ref struct_) => Some(&struct_.name),
+            Type::Union(ref union) => Some(&union.name),
+            _ => None,
+        }
+    }
+
+    // the size, the alignment and the offsets of the members, which are all
+    // 0 in a union.
+    fn layout_test(&self, id: TypeId) -> Option<String> {
+        let (name, members) = match *self.table.get(id) {
+            Type::Struct(ref struct_) => (ident(&struct_.name), &struct_.members[..]),
+            Type::Union(ref union) => (ident(&union.name), &[][..]),
+            _ => return None,
+        };
+        let mut asserts = vec![
+            format!("assert_eq!(mem::size_of::<{}>(), {});", name, self.table.size(id)?),
+            format!("assert_eq!(mem::align_of::<{}>(), {});", name, self.table.alignment(id)?),
+        ];
+        for member in members {
+            asserts.push(format!("assert_eq!(mem::offset_of!({}, {}), {});", name,
+                                 ident(&member.name), member.offset));
+        }
+        Some(format!("    #[test]\n    fn layout_of_{}() {{\n{}    }}\n", name,
+                     asserts.iter().map(|a| format!("        {}\n", a)).collect::<String>()))
+    }
+}
+
+// the `target_arch` of Rust for the target.
+fn target_arch(target: &Target) -> &'static str {
+    match target.arch {
+        Arch::I386 => "x86",
+        Arch::X86_64 => "x86_64",
+    }
+}
+
+// the Rust identifier of the name, `type_` of `type`.
+fn ident(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) { format!("{}_", name) } else { name.to_string() }
+}
+
+// the symbol of a function or a variable renamed by `ident`.
+fn link_name(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) { format!("    #[link_name = \"{}\"]\n", name) }
+    else { String::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{CompileOptionBuilder, Emit, Target};
+    use super::super::testing::{compile_with, emit, MODULE};
+
+    #[test]
+    fn array_and_function_parameters_are_pointers() {
+        let compiled = emit("int sum(int[] xs, int const[4] ys, int(int) f) { return 0; }\n",
+                            Emit::RustBindings);
+        assert!(compiled.output().contains(
+            "    pub fn sum(xs: *mut c_int, ys: *const c_int, \
+             f: Option<unsafe extern \"C\" fn(c_int) -> c_int>) -> c_int;\n"),
+            "{}", compiled.output());
+    }
+
+    #[test]
+    fn the_bindings_of_a_module() {
+        // `FILE` is an empty struct in the header of `stdio`, so it is one in Rust too
+        assert_eq!(emit(MODULE, Emit::RustBindings).output(), r#"// test.rs, generated by rcbc --emit=rust-bindings for i386.
+
+#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, dead_code)]
+
+use std::os::raw::*;
+
+pub type point_t = point;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct point {
+    pub x: c_int,
+    pub y: c_int,
+}
+
+pub type FILE = _IO_FILE;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct _IO_FILE {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union value {
+    pub i: c_int,
+    pub s: *mut c_char,
+}
+
+extern "C" {
+    pub static mut count: c_int;
+    pub static mut name: *const c_char;
+    pub static mut origin: point_t;
+    pub static mut table: [c_int; 4];
+    pub static mut last: *mut c_int;
+    pub fn area(p: *mut point_t, scale: *mut c_int, f: Option<unsafe extern "C" fn(c_int) -> c_int>) -> c_int;
+    pub fn show(out: *mut FILE, v: value) -> c_int;
+}
+
+#[cfg(all(test, target_arch = "x86"))]
+mod layout_tests {
+    use super::*;
+    use std::mem;
+
+    #[test]
+    fn layout_of_point() {
+        assert_eq!(mem::size_of::<point>(), 8);
+        assert_eq!(mem::align_of::<point>(), 4);
+        assert_eq!(mem::offset_of!(point, x), 0);
+        assert_eq!(mem::offset_of!(point, y), 4);
+    }
+
+    #[test]
+    fn layout_of__IO_FILE() {
+        assert_eq!(mem::size_of::<_IO_FILE>(), 0);
+        assert_eq!(mem::align_of::<_IO_FILE>(), 1);
+    }
+
+    #[test]
+    fn layout_of_value() {
+        assert_eq!(mem::size_of::<value>(), 4);
+        assert_eq!(mem::align_of::<value>(), 4);
+    }
+}
+"#);
+    }
+
+    #[test]
+    fn layout_tests_are_of_the_target() {
+        let opts = CompileOptionBuilder::new().emit(Emit::RustBindings)
+                                              .target(Target::x86_64())
+                                              .finalize();
+        let compiled = compile_with("struct node { long v; struct node* next; };\n\
+                                     struct node* head;\n", &opts);
+        let output = compiled.output();
+        assert!(output.contains("#[cfg(all(test, target_arch = \"x86_64\"))]\nmod layout_tests {"),
+                "{}", output);
+        assert!(output.contains("assert_eq!(mem::size_of::<node>(), 16);"), "{}", output);
+        assert!(output.contains("assert_eq!(mem::offset_of!(node, next), 8);"), "{}", output);
+    }
+}
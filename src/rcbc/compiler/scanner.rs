@@ -1,5 +1,9 @@
 use super::token::{Token, TokenKind};
 use super::location::Location;
+use super::source_map::FileId;
+use super::diagnostic::{Diagnostic, DiagnosticHandler};
+use super::warning::Warning;
+use super::error_codes::ErrorCode;
 use std::result;
 use std::fmt;
 use std::str::Chars;
@@ -8,20 +12,20 @@ type Result<T> = result::Result<T, ScanError>;
 
 pub struct Scanner<'a> {
     iter: Chars<'a>,
-    line: usize,
-    column: usize,
+    file: FileId,
+    offset: usize, // in bytes
     tokens: Vec<Token>,
+    handler: &'a mut DiagnosticHandler,
 }
 
 #[derive(Debug)]
 pub struct ScanError {
-    line: usize,
-    column: usize,
+    location: Location,
     kind: ScanErrorKind,
     stray: Option<char>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ScanErrorKind {
     CommentBlockNotClosing,
     InvalidOctalChar,
@@ -32,12 +36,14 @@ pub enum ScanErrorKind {
 }
 
 impl<'a> Scanner<'a> {
-    pub fn new(stream: &'a String) -> Scanner<'a> {
+    pub fn new(stream: &'a String, file: FileId,
+               handler: &'a mut DiagnosticHandler) -> Scanner<'a> {
         Scanner {
             iter: stream.chars(),
-            line: 1,
-            column: 1,
+            file: file,
+            offset: 0,
             tokens: Vec::new(),
+            handler: handler,
         }
     }
 
@@ -71,8 +77,8 @@ impl<'a> Scanner<'a> {
                     self.scan_integer(),
                 Some(ref c) => self.scan_operator(),
                 None => {
-                    let location = Location::new(self.line, self.column,
-                                                 self.line, self.column);
+                    let location = Location::new(self.file, self.offset,
+                                                 self.offset);
                     self.tokens.push(Token::new(TokenKind::EOF, None,
                                                 location));
                     break;
@@ -85,7 +91,7 @@ impl<'a> Scanner<'a> {
     fn scan_space(&mut self) -> Result<()> {
         let mut scout = self.iter.clone();
         let content;
-        let (ln, col) = (self.line, self.column);
+        let begin = self.offset;
 
         match scout.position(|c| !c.is_whitespace()) {
             Some(0) => unreachable!(),
@@ -99,7 +105,7 @@ impl<'a> Scanner<'a> {
             }
         };
 
-        let location = Location::new(ln, col, self.line, self.column);
+        let location = Location::new(self.file, begin, self.offset);
         self.tokens.push(Token::new(TokenKind::Space, Some(content),
                                     location));
 
@@ -119,21 +125,36 @@ impl<'a> Scanner<'a> {
                 Some(pos) => {
                     move_count += pos + 1;
                     if scout.clone().next() == Some('/') {
-                        let (ln, col) = (self.line, self.column);
+                        let begin = self.offset;
                         let comments = self.step(move_count + 1);
-                        let location = Location::new(ln, col, self.line,
-                                                     self.column);
+                        let location = Location::new(self.file, begin, self.offset);
+                        self.check_nested_comment(begin, &comments);
                         self.tokens.push(Token::new(TokenKind::BlockComment,
                             Some(comments), location));
                         return Ok(())
                     }
                 },
-                None => return Err(ScanError::new(self.line, self.column,
+                None => return Err(ScanError::new(self.here(),
                     ScanErrorKind::CommentBlockNotClosing, None)),
             };
         }
     }
 
+    // warn about `/*` in the comment, which usually means that the comment
+    // before is not closed.
+    fn check_nested_comment(&mut self, begin: usize, comments: &str) {
+        let body = &comments[2..comments.len() - 2];
+        for (pos, _) in body.match_indices("/*") {
+            let offset = begin + 2 + pos;
+            let location = Location::new(self.file, offset, offset + 2);
+            self.handler.warn(Warning::Comment,
+                Diagnostic::warning("`/*` within block comment")
+                    .with_primary(location, "")
+                    .with_secondary(Location::new(self.file, begin, begin + 2),
+                                    "the comment begins here"));
+        }
+    }
+
     fn scan_line_comment(&mut self) -> Result<()> {
         assert!(self.iter.as_str().starts_with("//"));
 
@@ -141,9 +162,9 @@ impl<'a> Scanner<'a> {
         let mut scout = self.iter.clone();
 
         if let Some(pos) = scout.position(|c| c == '\n') {
-            let (ln, col) = (self.line, self.column);
+            let begin = self.offset;
             let comment = self.step(pos + 1);
-            let location = Location::new(ln, col, self.line, self.column);
+            let location = Location::new(self.file, begin, self.offset);
             self.tokens.push(Token::new(TokenKind::LineComment, Some(comment),
                                         location));
         }
@@ -161,10 +182,9 @@ impl<'a> Scanner<'a> {
                         c @ None | c @ Some(_) if c.is_none() || 
                                 !c.unwrap().is_alphanumeric() &&
                                  c.unwrap() != '_' => {
-                            let (ln, col) = (self.line, self.column);
+                            let begin = self.offset;
                             self.step($Kw_str.len());
-                            let location = Location::new(ln, col, self.line,
-                                                         self.column);
+                            let location = Location::new(self.file, begin, self.offset);
                             self.tokens.push(Token::new(TokenKind::$Kw_kind,
                                                         None, location));
                             return Ok(());
@@ -216,17 +236,17 @@ impl<'a> Scanner<'a> {
 
         match scout.position(|c| !c.is_alphanumeric() && c != '_') {
             Some(pos) => {
-                let (ln, col) = (self.line, self.column);
+                let begin = self.offset;
                 let identifier = self.step(pos);
-                let location = Location::new(ln, col, self.line, self.column);
+                let location = Location::new(self.file, begin, self.offset);
                 self.tokens.push(Token::new(TokenKind::Identifier,
                                             Some(identifier), location));
             },
             None => { // EOF
                 let identifier = self.iter.as_str().to_string();
-                let (ln, col) = (self.line, self.column);
+                let begin = self.offset;
                 self.step(identifier.len()); // eat all chars
-                let location = Location::new(ln, col, self.line, self.column);
+                let location = Location::new(self.file, begin, self.offset);
                 self.tokens.push(Token::new(TokenKind::Identifier,
                     Some(identifier), location));
             }
@@ -263,9 +283,9 @@ impl<'a> Scanner<'a> {
             move_count += 1;
         }
 
-        let (ln, col) = (self.line, self.column);
+        let begin = self.offset;
         let integer = self.step(move_count);
-        let location = Location::new(ln, col, self.line, self.column);
+        let location = Location::new(self.file, begin, self.offset);
         self.tokens.push(Token::new(TokenKind::Integer, Some(integer),
                                     location));
 
@@ -318,8 +338,7 @@ impl<'a> Scanner<'a> {
                     Some('0' ... '7') => {
                         for _ in 0..2 { match scout.next() {
                             Some('0' ... '7') => { /* should be */ },
-                            _ => return Err(ScanError::new(self.line,
-                                self.column,
+                            _ => return Err(ScanError::new(self.here(),
                                 ScanErrorKind::InvalidOctalChar,
                                 None
                             )),
@@ -334,13 +353,13 @@ impl<'a> Scanner<'a> {
             },
             Some(ref c) => {
                 if *c == '\n' {
-                    return Err(ScanError::new(self.line, self.column,
+                    return Err(ScanError::new(self.here(),
                         ScanErrorKind::InvalidChar, None));
                 }
                 move_count = 3;
             }
             None => {
-                return Err(ScanError::new(self.line, self.column,
+                return Err(ScanError::new(self.here(),
                     ScanErrorKind::NotClosingSingalquote, None));
             }
         }
@@ -348,13 +367,13 @@ impl<'a> Scanner<'a> {
         // The closing single quote
         match scout.next() {
             Some('\'') => {
-                let (ln, col) = (self.line, self.column);
+                let begin = self.offset;
                 let character = self.step(move_count);
-                let location = Location::new(ln, col, self.line, self.column);
+                let location = Location::new(self.file, begin, self.offset);
                 self.tokens.push(Token::new(TokenKind::Character, 
                     Some(character), location));
             },
-            _ => return Err(ScanError::new(self.line, self.column,
+            _ => return Err(ScanError::new(self.here(),
                     ScanErrorKind::NotClosingSingalquote, None)),
         }
 
@@ -371,9 +390,9 @@ impl<'a> Scanner<'a> {
             // end of string
             Some('\"') => {
                 move_count += 1;
-                let (ln, col) = (self.line, self.column);
+                let begin = self.offset;
                 let string = self.step(move_count);
-                let location = Location::new(ln, col, self.line, self.column);
+                let location = Location::new(self.file, begin, self.offset);
                 self.tokens.push(Token::new(TokenKind::String, Some(string),
                                             location));
                 return Ok(());
@@ -385,8 +404,7 @@ impl<'a> Scanner<'a> {
                     Some('0' ... '7') => {
                         for _ in 0..2 { match scout.next() {
                             Some('0' ... '7') => { /* should be */ },
-                            _ => return Err(ScanError::new(self.line,
-                                self.column,
+                            _ => return Err(ScanError::new(self.here(),
                                 ScanErrorKind::InvalidOctalChar,
                                 None
                             )),
@@ -403,7 +421,7 @@ impl<'a> Scanner<'a> {
                 move_count += 1;
             },
             _ => {
-                return Err(ScanError::new(self.line, self.column,
+                return Err(ScanError::new(self.here(),
                     ScanErrorKind::NotClosingDoublequote, None));
             }
         }}
@@ -415,9 +433,9 @@ impl<'a> Scanner<'a> {
         macro_rules! match_operator {
             ($Kw_str: expr, $Kw_kind: ident) => (
                 if s.starts_with($Kw_str) {
-                    let (ln, col) = (self.line, self.column);
+                    let begin = self.offset;
                     self.step($Kw_str.len());
-                    let location = Location::new(ln, col, self.line, self.column);
+                    let location = Location::new(self.file, begin, self.offset);
                     self.tokens.push(Token::new(TokenKind::$Kw_kind, None,
                                                 location));                    
                     return Ok(())
@@ -481,49 +499,82 @@ impl<'a> Scanner<'a> {
         match_operator!("$", Dollar);
 
         let stray = s.chars().next().unwrap();
-        Err(ScanError::new(self.line, self.column,
-                           ScanErrorKind::Strays, Some(stray)))
+        Err(ScanError::new(self.here(), ScanErrorKind::Strays, Some(stray)))
     }
 
     fn step(&mut self, n: usize) -> String {
         let mut content = String::new();
         for _ in 0..n {
             match self.iter.next() {
-                Some(ref c) => {
-                    content.push(*c);
-                    if *c == '\n' {
-                        self.line += 1;
-                        self.column = 1;
-                    } else {
-                        self.column += 1;
-                    }
+                Some(c) => {
+                    content.push(c);
+                    self.offset += c.len_utf8();
                 }
                 _ => unreachable!()
             }
         }
         content
     }
+
+    /// the location of the char under the cursor.
+    fn here(&self) -> Location {
+        let len = self.iter.clone().next().map_or(0, |c| c.len_utf8());
+        Location::new(self.file, self.offset, self.offset + len)
+    }
 }
 
 
 impl ScanError {
-    fn new(line: usize, column: usize, kind: ScanErrorKind, 
+    fn new(location: Location, kind: ScanErrorKind,
            stray: Option<char>) -> ScanError {
-        ScanError { line: line, column: column, kind: kind, stray: stray }
+        ScanError { location: location, kind: kind, stray: stray }
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub fn kind(&self) -> ScanErrorKind {
+        self.kind
+    }
+}
+
+
+impl ScanErrorKind {
+    pub const ALL: &'static [ScanErrorKind] = &[
+        ScanErrorKind::CommentBlockNotClosing,
+        ScanErrorKind::InvalidOctalChar,
+        ScanErrorKind::InvalidChar,
+        ScanErrorKind::NotClosingSingalquote,
+        ScanErrorKind::NotClosingDoublequote,
+        ScanErrorKind::Strays,
+    ];
+}
+
+
+impl ErrorCode for ScanErrorKind {
+    fn code(&self) -> &'static str {
+        match *self {
+            ScanErrorKind::CommentBlockNotClosing => "E0001",
+            ScanErrorKind::InvalidOctalChar => "E0002",
+            ScanErrorKind::InvalidChar => "E0003",
+            ScanErrorKind::NotClosingSingalquote => "E0004",
+            ScanErrorKind::NotClosingDoublequote => "E0005",
+            ScanErrorKind::Strays => "E0006",
+        }
     }
 }
 
 
 impl fmt::Display for ScanError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Ln {}, Col {}: ", self.line, self.column) ?;
         match self.kind {
             ScanErrorKind::CommentBlockNotClosing =>
                 write!(f, "the comment block is not closing"),
             ScanErrorKind::InvalidOctalChar =>
-                write!(f, "invalid octal number represented char"),
+                write!(f, "invalid octal escape, need three octal digits like `\\101`"),
             ScanErrorKind::InvalidChar =>
-                write!(f, "invalid char"),
+                write!(f, "line break in a character literal"),
             ScanErrorKind::NotClosingSingalquote =>
                 write!(f, "need a closing single quote for the char"),
             ScanErrorKind::NotClosingDoublequote =>
@@ -532,4 +583,42 @@ impl fmt::Display for ScanError {
                 write!(f, "stray ‘{}’ in program", self.stray.unwrap()),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{compile, compile_warning};
+    use super::super::warning::Warning;
+
+    #[test]
+    fn tokens() {
+        let compiled = compile("// a line comment\n\
+                                /* a block comment */\n\
+                                char c = '\\101';\n\
+                                char n = '\\n';\n\
+                                char* s = \"a \\\"string\\\"\\n\";\n\
+                                long l = 0x1fL;\n\
+                                unsigned int u = 017U;\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn errors() {
+        for &(src, code) in &[
+            ("int main(void) {\n    /* the comment never ends\n    return 0;\n}\n", "E0001"),
+            ("char c = '\\18';\n", "E0002"),
+            ("char c = '\n';\n", "E0003"),
+            ("char c = 'ab';\n", "E0004"),
+            ("char* s = \"hello, world\n;\n", "E0005"),
+            ("int main(void) { int @count = 0; return 0; }\n", "E0006"),
+        ] {
+            assert_eq!(compile(src).errors(), [code], "{}", src);
+        }
+    }
+
+    #[test]
+    fn nested_comment() {
+        let src = "/* the first comment\n/* the second one */\nint x;\n";
+        compile(src).assert_clean();
+        assert_eq!(compile_warning(src, &["all"]).warnings(), [Warning::Comment]);
+    }
+}
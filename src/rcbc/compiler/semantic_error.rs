@@ -0,0 +1,185 @@
+//! The errors found by the semantic passes over the AST.
+
+use std::fmt;
+use super::location::Location;
+use super::diagnostic::Diagnostic;
+use super::error_codes::ErrorCode;
+
+#[derive(Debug)]
+pub struct SemanticError {
+    kind: SemanticErrorKind,
+    location: Location,
+    message: String,
+    secondaries: Vec<(Location, String)>, // other locations to show
+    notes: Vec<String>,
+    help: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemanticErrorKind {
+    SizeofFunction,
+    IncompleteType,
+    UndefinedName,
+    DuplicatedDefinition,
+    InvalidOperands,
+    PointerIntegerMismatch,
+    IncompatibleTypes,
+    NotAFunction,
+    ArgumentCount,
+    InvalidCast,
+    InvalidReturn,
+    NonScalarCondition,
+    NotAnLvalue,
+    AssignToArray,
+    AssignToConst,
+    InvalidDereference,
+    InvalidMemberAccess,
+    UnknownMember,
+    NotConstant,
+    InvalidConstantOperation,
+    InvalidArrayLength,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    UndefinedLabel,
+    DuplicatedLabel,
+    DuplicatedCase,
+    InvalidFormatParameter,
+    ImportNotFound,
+    ImportCycle,
+    HeaderMismatch,
+    MissingDefinition,
+}
+
+impl SemanticError {
+    pub fn new<S: Into<String>>(kind: SemanticErrorKind, location: Location,
+                                message: S) -> SemanticError {
+        SemanticError {
+            kind: kind,
+            location: location,
+            message: message.into(),
+            secondaries: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_secondary<S: Into<String>>(mut self, location: Location,
+                                           label: S) -> SemanticError {
+        self.secondaries.push((location, label.into()));
+        self
+    }
+
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> SemanticError {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help<S: Into<String>>(mut self, help: S) -> SemanticError {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn kind(&self) -> SemanticErrorKind {
+        self.kind
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(self.message.clone())
+                                 .with_code(self.kind.code())
+                                 .with_primary(self.location, "");
+        for &(location, ref label) in &self.secondaries {
+            diagnostic = diagnostic.with_secondary(location, label.clone());
+        }
+        for note in &self.notes {
+            diagnostic = diagnostic.with_note(note.clone());
+        }
+        match self.help {
+            Some(ref help) => diagnostic.with_help(help.clone()),
+            None => diagnostic,
+        }
+    }
+}
+
+impl SemanticErrorKind {
+    pub const ALL: &'static [SemanticErrorKind] = &[
+        SemanticErrorKind::SizeofFunction,
+        SemanticErrorKind::IncompleteType,
+        SemanticErrorKind::UndefinedName,
+        SemanticErrorKind::DuplicatedDefinition,
+        SemanticErrorKind::InvalidOperands,
+        SemanticErrorKind::PointerIntegerMismatch,
+        SemanticErrorKind::IncompatibleTypes,
+        SemanticErrorKind::NotAFunction,
+        SemanticErrorKind::ArgumentCount,
+        SemanticErrorKind::InvalidCast,
+        SemanticErrorKind::InvalidReturn,
+        SemanticErrorKind::NonScalarCondition,
+        SemanticErrorKind::NotAnLvalue,
+        SemanticErrorKind::AssignToArray,
+        SemanticErrorKind::AssignToConst,
+        SemanticErrorKind::InvalidDereference,
+        SemanticErrorKind::InvalidMemberAccess,
+        SemanticErrorKind::UnknownMember,
+        SemanticErrorKind::NotConstant,
+        SemanticErrorKind::InvalidConstantOperation,
+        SemanticErrorKind::InvalidArrayLength,
+        SemanticErrorKind::BreakOutsideLoop,
+        SemanticErrorKind::ContinueOutsideLoop,
+        SemanticErrorKind::UndefinedLabel,
+        SemanticErrorKind::DuplicatedLabel,
+        SemanticErrorKind::DuplicatedCase,
+        SemanticErrorKind::InvalidFormatParameter,
+        SemanticErrorKind::ImportNotFound,
+        SemanticErrorKind::ImportCycle,
+        SemanticErrorKind::HeaderMismatch,
+        SemanticErrorKind::MissingDefinition,
+    ];
+}
+
+impl ErrorCode for SemanticErrorKind {
+    fn code(&self) -> &'static str {
+        match *self {
+            SemanticErrorKind::SizeofFunction => "E0059",
+            SemanticErrorKind::IncompleteType => "E0060",
+            SemanticErrorKind::UndefinedName => "E0061",
+            SemanticErrorKind::DuplicatedDefinition => "E0062",
+            SemanticErrorKind::InvalidOperands => "E0063",
+            SemanticErrorKind::PointerIntegerMismatch => "E0064",
+            SemanticErrorKind::IncompatibleTypes => "E0065",
+            SemanticErrorKind::NotAFunction => "E0066",
+            SemanticErrorKind::ArgumentCount => "E0067",
+            SemanticErrorKind::InvalidCast => "E0068",
+            SemanticErrorKind::InvalidReturn => "E0069",
+            SemanticErrorKind::NonScalarCondition => "E0070",
+            SemanticErrorKind::NotAnLvalue => "E0071",
+            SemanticErrorKind::AssignToArray => "E0072",
+            SemanticErrorKind::AssignToConst => "E0073",
+            SemanticErrorKind::InvalidDereference => "E0074",
+            SemanticErrorKind::InvalidMemberAccess => "E0075",
+            SemanticErrorKind::UnknownMember => "E0076",
+            SemanticErrorKind::NotConstant => "E0077",
+            SemanticErrorKind::InvalidConstantOperation => "E0078",
+            SemanticErrorKind::InvalidArrayLength => "E0079",
+            SemanticErrorKind::BreakOutsideLoop => "E0083",
+            SemanticErrorKind::ContinueOutsideLoop => "E0084",
+            SemanticErrorKind::UndefinedLabel => "E0085",
+            SemanticErrorKind::DuplicatedLabel => "E0086",
+            SemanticErrorKind::DuplicatedCase => "E0087",
+            SemanticErrorKind::InvalidFormatParameter => "E0090",
+            SemanticErrorKind::ImportNotFound => "E0092",
+            SemanticErrorKind::ImportCycle => "E0093",
+            SemanticErrorKind::HeaderMismatch => "E0094",
+            SemanticErrorKind::MissingDefinition => "E0095",
+        }
+    }
+}
+
+impl fmt::Display for SemanticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
@@ -0,0 +1,183 @@
+//! The pass computing every `sizeof` at compile time.
+//!
+//! The size of `sizeof(type)` is the size of the resolved type, and the one
+//! of `sizeof expr` is the size of the type of the expression, which is not
+//! evaluated, given by the `TypeChecker`.
+//!
+//! A variable or a parameter defined with a type without a size, such as
+//! `void` or a struct declared by a C header but not defined, is reported
+//! here too, as its storage cannot be allocated.
+
+use super::ast::*;
+use super::location::Location;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::DiagnosticHandler;
+use super::visitor::{self, Visitor};
+
+pub struct SizeofEvaluator<'a> {
+    table: &'a mut TypeTable,
+    handler: &'a mut DiagnosticHandler,
+}
+
+impl<'a> SizeofEvaluator<'a> {
+    pub fn new(table: &'a mut TypeTable, handler: &'a mut DiagnosticHandler)
+            -> SizeofEvaluator<'a> {
+        SizeofEvaluator {
+            table: table,
+            handler: handler,
+        }
+    }
+
+    /// Compute the sizes, the errors are reported to the handler.
+    pub fn evaluate(&mut self, ast: &mut AST) {
+        self.visit_ast(ast);
+    }
+
+    // the size of a value of the type at `location`, reporting the types
+    // without a size.
+    fn size_of(&mut self, type_: TypeId, location: Location) -> Option<usize> {
+        if self.table.is_function(type_) {
+            let error = SemanticError::new(SemanticErrorKind::SizeofFunction, location,
+                format!("`sizeof` on the function type `{}`",
+                        self.table.type_name(type_)))
+                .with_help("take the size of a pointer to the function instead");
+            self.handler.emit(error.to_diagnostic());
+            return None;
+        }
+        let size = self.table.size(type_);
+        if size.is_none() {
+            let error = SemanticError::new(SemanticErrorKind::IncompleteType,
+                location, format!("`sizeof` on the incomplete type `{}`",
+                                  self.table.type_name(type_)));
+            self.handler.emit(error.to_diagnostic());
+        }
+        size
+    }
+
+    // report the object `name` defined with a type without a size, but an
+    // array without a length, which is allocated by its initializer.
+    fn check_object(&mut self, type_: &TypeNode, name: &str) {
+        let id = match type_.type_ {
+            Some(id) => id,
+            None => return,
+        };
+        if self.table.size(id).is_some() || self.table.is_function(id) {
+            return;
+        }
+        if let Type::Array(ArrayType { length: None, .. }) =
+                *self.table.get(self.table.real_type(id)) {
+            return;
+        }
+        let error = SemanticError::new(SemanticErrorKind::IncompleteType, type_.location,
+            format!("`{}` has the incomplete type `{}`", name, self.table.type_name(id)))
+            .with_help("refer to it by a pointer instead");
+        self.handler.emit(error.to_diagnostic());
+    }
+}
+
+impl<'a> Visitor for SizeofEvaluator<'a> {
+    fn visit_defun(&mut self, func: &mut DefinedFunction) {
+        for param in &func.params.params {
+            match param.type_.type_ {
+                // an array or a function parameter is a pointer
+                Some(id) if self.table.is_array(id) => {}
+                _ => self.check_object(&param.type_, &param.name),
+            }
+        }
+        visitor::walk_defun(self, func);
+    }
+
+    fn visit_defvar(&mut self, var: &mut DefinedVariable) {
+        self.check_object(&var.type_, &var.name);
+        visitor::walk_defvar(self, var);
+    }
+
+    fn visit_sizeof_type(&mut self, node: &mut SizeofTypeNode) {
+        if let Some(type_) = node.type_.type_ {
+            node.size = self.size_of(type_, node.location);
+        }
+    }
+
+    fn visit_sizeof_expr(&mut self, node: &mut SizeofExprNode) {
+        visitor::walk_sizeof_expr(self, node);
+        if let Some(type_) = node.node.expr_type() {
+            node.size = self.size_of(type_, node.location);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{compile, TempDir};
+
+    const FORWARD: &'static str = "struct fwd;\n\
+                                   struct fwd* make(void);\n\
+                                   typedef struct fwd fwd_t;\n";
+
+    #[test]
+    fn sizes() {
+        let compiled = compile("struct pair { char c; long l; };\n\
+                                unsigned long a = sizeof(struct pair);\n\
+                                unsigned long b = sizeof a;\n\
+                                char[] s = \"abc\";\n\
+                                int main(void) { return sizeof(int(int)*) != 8; }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn sizeof_function() {
+        let compiled = compile("unsigned long n = sizeof(int(int));\n");
+        assert_eq!(compiled.errors(), ["E0059"]);
+    }
+
+    #[test]
+    fn sizeof_incomplete_type() {
+        let compiled = compile("int main(void) { return sizeof(void); }\n");
+        assert_eq!(compiled.errors(), ["E0060"]);
+    }
+
+    #[test]
+    fn object_of_incomplete_type() {
+        let compiled = compile("void v;\n\
+                                int f(void x) { return 0; }\n\
+                                int main(void) { void l; return 0; }\n");
+        assert_eq!(compiled.errors(), ["E0060"; 3]);
+        assert!(compiled.has_message("`v` has the incomplete type `void`"));
+    }
+
+    #[test]
+    fn undefined_c_struct_through_pointers() {
+        let dir = TempDir::new();
+        dir.write("fwd.h", FORWARD);
+        let compiled = dir.compile("import c \"fwd.h\";\n\
+                                    extern struct fwd e;\n\
+                                    struct fwd* p;\n\
+                                    int use(fwd_t* f) { return f != 0; }\n\
+                                    int main(void) { p = make(); return use(p); }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn undefined_c_struct_by_value() {
+        let dir = TempDir::new();
+        dir.write("fwd.h", FORWARD);
+        let compiled = dir.compile("import c \"fwd.h\";\n\
+                                    struct fwd g;\n\
+                                    fwd_t[2] a;\n\
+                                    unsigned long n = sizeof(struct fwd);\n\
+                                    int use(struct fwd f) { return 0; }\n");
+        assert_eq!(compiled.errors(), ["E0060"; 4]);
+        assert!(compiled.has_message("`g` has the incomplete type `struct fwd`"));
+    }
+
+    #[test]
+    fn undefined_c_struct_as_member() {
+        let dir = TempDir::new();
+        dir.write("fwd.h", FORWARD);
+        let compiled = dir.compile("import c \"fwd.h\";\n\
+                                    struct holder { struct fwd f; };\n");
+        assert_eq!(compiled.errors(), ["E0057"]);
+    }
+}
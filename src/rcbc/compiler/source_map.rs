@@ -0,0 +1,181 @@
+//! All the source files loaded in one run of the compiler.
+//!
+//! Every file (the compiled source and the headers it imports) is registered
+//! once and gets a `FileId`. A `Location` only keeps the id and byte offsets,
+//! the `SourceMap` turns them back into `path:line:col` and source lines.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use super::location::Location;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct FileId(usize);
+
+#[derive(Debug)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug)]
+pub struct SourceFile {
+    path: PathBuf,
+    src: String,
+    line_starts: Vec<usize>, // byte offset of the first char of each line
+}
+
+/// A resolved position, both line and column count from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap { files: Vec::new() }
+    }
+
+    /// register the text of a file, the path is only used for display.
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P, src: String) -> FileId {
+        let id = FileId(self.files.len());
+        self.files.push(SourceFile::new(path.as_ref().to_path_buf(), src));
+        id
+    }
+
+    /// read the file in `path` and register it.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<FileId> {
+        let mut src = String::new();
+        File::open(path.as_ref())
+             .and_then(|mut file| file.read_to_string(&mut src)) ?;
+        Ok(self.add_file(path, src))
+    }
+
+    /// find a file registered before by its path.
+    pub fn find_file<P: AsRef<Path>>(&self, path: P) -> Option<FileId> {
+        self.files.iter()
+                  .position(|file| file.path == path.as_ref())
+                  .map(FileId)
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    pub fn lookup(&self, location: Location) -> LineCol {
+        self.file(location.file).line_col(location.begin)
+    }
+
+    /// `path:line:col` of the beginning of `location`.
+    pub fn span_to_string(&self, location: Location) -> String {
+        let file = self.file(location.file);
+        let pos = file.line_col(location.begin);
+        format!("{}:{}:{}", file.path.display(), pos.line, pos.column)
+    }
+
+    /// the text of the source line where `location` begins, without the
+    /// line break.
+    pub fn line_text(&self, location: Location) -> &str {
+        let file = self.file(location.file);
+        file.line(file.line_col(location.begin).line)
+    }
+}
+
+impl SourceFile {
+    fn new(path: PathBuf, src: String) -> SourceFile {
+        let mut line_starts = vec![0];
+        line_starts.extend(src.match_indices('\n').map(|(i, _)| i + 1));
+        SourceFile {
+            path: path,
+            src: src,
+            line_starts: line_starts,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// the line and column (counted in chars) of a byte offset.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.src.len());
+        let index = match self.line_starts.binary_search(&offset) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        };
+        let start = self.line_starts[index];
+        LineCol {
+            line: index + 1,
+            column: self.src[start..offset].chars().count() + 1,
+        }
+    }
+
+    /// the byte offset where line `line` (counted from 1) begins.
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_starts[line - 1]
+    }
+
+    /// the text of line `line` (counted from 1), without the line break.
+    pub fn line(&self, line: usize) -> &str {
+        let start = self.line_starts[line - 1];
+        let end = self.line_starts.get(line)
+                                  .cloned()
+                                  .unwrap_or(self.src.len());
+        self.src[start..end].trim_end_matches(&['\n', '\r'][..])
+    }
+}
+
+impl fmt::Display for FileId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl fmt::Display for LineCol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_and_columns() {
+        let mut map = SourceMap::new();
+        let id = map.add_file("a.cb", "int x;\nchar* s = \"é\"; int y;\n\nlast".to_string());
+        let file = map.file(id);
+        assert_eq!(file.line_count(), 4);
+        assert_eq!(file.line_col(0), LineCol { line: 1, column: 1 });
+        assert_eq!(file.line_col(7), LineCol { line: 2, column: 1 });
+        // `é` is two bytes but one column
+        let y = file.src().find("int y").unwrap();
+        assert_eq!(file.line_col(y), LineCol { line: 2, column: 16 });
+        assert_eq!(file.line(2), "char* s = \"é\"; int y;");
+        assert_eq!(file.line(3), "");
+        assert_eq!(file.line(4), "last");
+        assert_eq!(file.line_col(1000), LineCol { line: 4, column: 5 });
+    }
+
+    #[test]
+    fn locations() {
+        let mut map = SourceMap::new();
+        map.add_file("a.cb", String::new());
+        let id = map.add_file("dir/b.cb", "int x;\r\n  int y;\r\n".to_string());
+        let location = Location::new(id, 10, 15);
+        assert_eq!(map.span_to_string(location), "dir/b.cb:2:3");
+        assert_eq!(map.line_text(location), "  int y;");
+        assert_eq!(map.find_file("dir/b.cb"), Some(id));
+        assert_eq!(map.find_file("c.cb"), None);
+    }
+}
@@ -0,0 +1,76 @@
+//! The suggestions of the names close to a misspelled one.
+//!
+//! The distance between two names is the number of the characters to
+//! insert, delete or replace, or of the adjacent ones to swap, to make one
+//! the other, so `pritnf` is 1 from `printf`. A name is suggested only if it
+//! is at most a third of the length of the misspelled one away.
+
+/// the closest of the candidates to the name, the first in alphabetical
+/// order of the equally close ones.
+pub fn suggest<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+        where I: IntoIterator<Item = &'a str> {
+    let limit = ::std::cmp::max(1, name.chars().count() / 3);
+    candidates.into_iter()
+              .filter(|&candidate| candidate != name)
+              .map(|candidate| (distance(name, candidate), candidate))
+              .filter(|&(distance, _)| distance <= limit)
+              .min()
+              .map(|(_, candidate)| candidate)
+}
+
+/// the help of a diagnostic suggesting the name.
+pub fn did_you_mean(name: &str) -> String {
+    format!("did you mean `{}`?", name)
+}
+
+// the optimal string alignment distance.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // `d[i][j]` is the distance between the first i of `a` and j of `b`
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..a.len() + 1 {
+        for j in 1..b.len() + 1 {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = *[d[i - 1][j] + 1, d[i][j - 1] + 1, d[i - 1][j - 1] + cost]
+                          .iter().min().unwrap();
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = ::std::cmp::min(d[i][j], d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("printf", "printf"), 0);
+        assert_eq!(distance("pritnf", "printf"), 1);
+        assert_eq!(distance("prinf", "printf"), 1);
+        assert_eq!(distance("printff", "printf"), 1);
+        assert_eq!(distance("sprintf", "fprintf"), 1);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn suggestions() {
+        let names = ["printf", "sprintf", "puts", "count"];
+        assert_eq!(suggest("pritnf", names.iter().cloned()), Some("printf"));
+        assert_eq!(suggest("cont", names.iter().cloned()), Some("count"));
+        // too far from every name, and the name itself is not suggested
+        assert_eq!(suggest("malloc", names.iter().cloned()), None);
+        assert_eq!(suggest("puts", names.iter().cloned()), None);
+        // the first of the equally close ones
+        assert_eq!(suggest("ab", ["ac", "aa"].iter().cloned()), Some("aa"));
+    }
+}
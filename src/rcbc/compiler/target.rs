@@ -0,0 +1,143 @@
+//! The description of the target machine.
+//!
+//! The sizes and alignments of the basic types come from the data model of
+//! the target, ILP32 for i386 and LP64 for x86-64 as the System V ABI says.
+//! The type table, so `sizeof` and the layouts of structs, and the code
+//! generation all take them from here.
+
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Arch {
+    I386,
+    X86_64,
+}
+
+/// The size and the alignment of a basic type, in bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DataLayout {
+    pub size: usize,
+    pub alignment: usize,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Target {
+    pub arch: Arch,
+    pub char_: DataLayout,
+    pub short: DataLayout,
+    pub int: DataLayout,
+    pub long: DataLayout,
+    pub pointer: DataLayout,
+    pub stack_alignment: usize, // of the stack pointer at a call
+    pub registers: &'static [&'static str], // the general purpose ones
+    pub arg_registers: &'static [&'static str], // for the first arguments
+}
+
+const I386_REGISTERS: &'static [&'static str] = &[
+    "eax", "ebx", "ecx", "edx", "esi", "edi", "ebp", "esp",
+];
+
+const X86_64_REGISTERS: &'static [&'static str] = &[
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp",
+    "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15",
+];
+
+const X86_64_ARG_REGISTERS: &'static [&'static str] = &[
+    "rdi", "rsi", "rdx", "rcx", "r8", "r9",
+];
+
+impl Target {
+    /// 32-bit x86, the target of the original Cb compiler.
+    pub fn i386() -> Target {
+        Target {
+            arch: Arch::I386,
+            char_: DataLayout { size: 1, alignment: 1 },
+            short: DataLayout { size: 2, alignment: 2 },
+            int: DataLayout { size: 4, alignment: 4 },
+            long: DataLayout { size: 4, alignment: 4 },
+            pointer: DataLayout { size: 4, alignment: 4 },
+            stack_alignment: 4,
+            registers: I386_REGISTERS,
+            arg_registers: &[], // all on the stack
+        }
+    }
+
+    pub fn x86_64() -> Target {
+        Target {
+            arch: Arch::X86_64,
+            char_: DataLayout { size: 1, alignment: 1 },
+            short: DataLayout { size: 2, alignment: 2 },
+            int: DataLayout { size: 4, alignment: 4 },
+            long: DataLayout { size: 8, alignment: 8 },
+            pointer: DataLayout { size: 8, alignment: 8 },
+            stack_alignment: 16,
+            registers: X86_64_REGISTERS,
+            arg_registers: X86_64_ARG_REGISTERS,
+        }
+    }
+
+    /// the target of the name in `--target`, `i386` or `x86_64`.
+    pub fn from_name(name: &str) -> Option<Target> {
+        match name {
+            "i386" | "i686" | "x86" => Some(Target::i386()),
+            "x86_64" | "x86-64" | "amd64" => Some(Target::x86_64()),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.arch {
+            Arch::I386 => "i386",
+            Arch::X86_64 => "x86_64",
+        }
+    }
+
+    /// the size of a machine word, which is the size of a pointer.
+    pub fn word_size(&self) -> usize {
+        self.pointer.size
+    }
+}
+
+impl Default for Target {
+    fn default() -> Target {
+        Target::i386()
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Target;
+    use super::super::CompileOptionBuilder;
+    use super::super::testing::compile_with;
+
+    #[test]
+    fn names() {
+        for &(name, target) in &[("i686", "i386"), ("x86", "i386"),
+                                 ("amd64", "x86_64"), ("x86-64", "x86_64")] {
+            assert_eq!(Target::from_name(name).map(|t| t.name()), Some(target));
+        }
+        assert!(Target::from_name("arm").is_none());
+    }
+
+    #[test]
+    fn layouts() {
+        let src = "struct s { char c; long l; char* p; };\n\
+                   int size = sizeof(struct s);\n\
+                   long count;\n";
+        for &(target, size, count) in &[(Target::i386(), "12", "\t.size\tcount, 4\n"),
+                                        (Target::x86_64(), "24", "\t.size\tcount, 8\n")] {
+            let opts = CompileOptionBuilder::new().target(target).finalize();
+            let compiled = compile_with(src, &opts);
+            let output = compiled.output();
+            assert!(output.contains(&format!("size:\n\t.long\t{}\n", size)),
+                    "{}: {}", target, output);
+            assert!(output.contains(count), "{}: {}", target, output);
+        }
+    }
+}
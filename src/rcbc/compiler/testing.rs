@@ -0,0 +1,178 @@
+//! Compiling the sources of the unit tests in memory.
+//!
+//! A test compiles a source text as `test.cb` and looks at what the compiler
+//! reported and wrote. The headers it imports, other than the standard ones
+//! built in the compiler, are written in a `TempDir`, the directory of the
+//! source.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use super::{Compiler, CompileOption, CompileOptionBuilder, Emit};
+use super::diagnostic::{Diagnostic, Severity};
+use super::warning::{Warning, WarningOptions};
+
+/// What the compilation of a source reported and wrote.
+pub struct Compiled {
+    pub output: Option<String>, // `None` if it failed
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A directory of the files of a test, removed after it.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+static TEMP_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// A module with every kind of definition, for the tests of the writers.
+pub const MODULE: &'static str = r#"import stdio;
+
+struct point { int x; int y; };
+union value { int i; char* s; };
+typedef struct point point_t;
+enum color { RED, GREEN = 5, BLUE };
+
+int count = 3;
+char const* name = "shapes";
+static int hidden;
+point_t origin;
+int[4] table;
+int* last = table + 3;
+
+int area(point_t* p, int[] scale, int(int) f) {
+    static int calls = 0;
+    calls++;
+    return f(p->x * p->y * scale[0]);
+}
+
+static int helper(void) { return hidden + GREEN; }
+
+int show(FILE* out, union value v) {
+    return fprintf(out, "%d\n", v.i) + helper();
+}
+"#;
+
+impl Compiled {
+    /// the codes of the errors, in the order they are reported.
+    pub fn errors(&self) -> Vec<&'static str> {
+        self.diagnostics.iter()
+                        .filter(|d| d.severity == Severity::Error)
+                        .map(|d| d.code.unwrap_or(""))
+                        .collect()
+    }
+
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.diagnostics.iter()
+                        .filter(|d| d.severity == Severity::Warning)
+                        .filter_map(|d| d.warning)
+                        .collect()
+    }
+
+    /// whether a diagnostic has the message, to tell apart the ones of the
+    /// same code.
+    pub fn has_message(&self, message: &str) -> bool {
+        self.diagnostics.iter().any(|d| d.message == message)
+    }
+
+    /// the output, failing the test with the diagnostics if there is none.
+    pub fn output(&self) -> &str {
+        match self.output {
+            Some(ref output) => output,
+            None => panic!("the compilation failed: {:#?}", self.diagnostics),
+        }
+    }
+
+    /// fail the test if something is reported.
+    pub fn assert_clean(&self) {
+        assert!(self.diagnostics.is_empty(), "unexpected diagnostics: {:#?}",
+                self.diagnostics);
+    }
+}
+
+/// Compile the source for the default target, into assembly.
+pub fn compile(src: &str) -> Compiled {
+    compile_with(src, &CompileOptionBuilder::new().finalize())
+}
+
+/// Compile the source into `emit`.
+pub fn emit(src: &str, emit: Emit) -> Compiled {
+    compile_with(src, &CompileOptionBuilder::new().emit(emit).finalize())
+}
+
+/// Compile the source with the `-W` flags, such as `all` for `-Wall`.
+pub fn compile_warning(src: &str, flags: &[&str]) -> Compiled {
+    let mut warnings = WarningOptions::new();
+    for flag in flags {
+        warnings.apply(flag).expect("a valid warning flag");
+    }
+    compile_with(src, &CompileOptionBuilder::new().warnings(warnings).finalize())
+}
+
+pub fn compile_with(src: &str, opts: &CompileOption) -> Compiled {
+    // a directory which does not exist, so nothing is imported from the disk
+    compile_at(&Path::new("/nonexistent").join("test.cb"), src, opts)
+}
+
+/// Compile the source as the file `src_file`, which is not read.
+pub fn compile_at(src_file: &Path, src: &str, opts: &CompileOption) -> Compiled {
+    let mut compiler = Compiler::new();
+    compiler.handler.set_warning_options(opts.warnings);
+    let file = compiler.source_map.add_file(src_file, src.to_string());
+    let out_name = match opts.emit {
+        Emit::Assembly => "test.s",
+        Emit::Header => "test.hb",
+        Emit::CHeader => "test.h",
+        Emit::RustBindings => "test.rs",
+    };
+    let result = compiler.compile_source(file, src_file, out_name, opts);
+    if let Err(ref err) = result {
+        if let Some(diagnostic) = err.to_diagnostic() {
+            compiler.handler.emit(diagnostic);
+        }
+    }
+    Compiled {
+        output: result.ok().and_then(|output| output),
+        diagnostics: compiler.handler.take(),
+    }
+}
+
+impl TempDir {
+    pub fn new() -> TempDir {
+        let path = env::temp_dir().join(format!("rcbc-test-{}-{}", process::id(),
+                                                TEMP_DIRS.fetch_add(1, Ordering::SeqCst)));
+        fs::create_dir_all(&path).expect("create the directory of a test");
+        TempDir { path: path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write a file of the test, `name` may be in a subdirectory.
+    pub fn write(&self, name: &str, text: &str) -> &TempDir {
+        let path = self.path.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("create the directory of a file");
+        }
+        fs::write(&path, text).expect("write a file of a test");
+        self
+    }
+
+    /// Compile the source as `test.cb` in the directory.
+    pub fn compile(&self, src: &str) -> Compiled {
+        self.compile_with(src, &CompileOptionBuilder::new().finalize())
+    }
+
+    pub fn compile_with(&self, src: &str, opts: &CompileOption) -> Compiled {
+        compile_at(&self.path.join("test.cb"), src, opts)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
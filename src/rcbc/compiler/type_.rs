@@ -2,41 +2,121 @@ use super::location::Location;
 
 // ---------- Type ----------
 
-pub trait Type {}
+/// Index of a canonical `Type` in the `TypeTable`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypeId(pub usize);
 
-pub struct ArrayType {}
+/// The semantic type, which the `TypeTable` resolves a `TypeRef` into.
+#[derive(Debug, Clone)]
+pub enum Type {
+    Void(VoidType),
+    Integer(IntegerType),
+    Pointer(PointerType),
+    Array(ArrayType),
+    Function(FunctionType),
+    Struct(StructType),
+    Union(UnionType),
+    User(UserType),
+    Const(ConstType),
+}
 
-pub struct FunctionType {}
+#[derive(Debug, Clone)]
+pub struct VoidType {}
 
-pub struct IntegerType {}
+#[derive(Debug, Clone)]
+pub struct IntegerType {
+    pub size: usize,
+    pub alignment: usize,
+    pub is_signed: bool,
+    pub name: &'static str,
+}
 
-pub struct NamedType {}
+#[derive(Debug, Clone)]
+pub struct PointerType {
+    pub size: usize,
+    pub alignment: usize,
+    pub base_type: TypeId,
+}
 
-pub struct CompositeType {}
+#[derive(Debug, Clone)]
+pub struct ArrayType {
+    pub base_type: TypeId,
+    pub length: Option<usize>, // `None` for `int[]`
+    pub pointer_size: usize,
+}
 
-pub struct StructType {}
+#[derive(Debug, Clone)]
+pub struct FunctionType {
+    pub return_type: TypeId,
+    pub param_types: Vec<TypeId>,
+    pub is_variadic: bool,
+}
 
-pub struct UnionType {}
+#[derive(Debug, Clone)]
+pub struct StructType {
+    pub name: String,
+    pub members: Vec<Member>,
+    pub location: Location,
+    pub layout: Option<Layout>, // `None` until the members are laid out
+}
 
-pub struct UserType {}
+#[derive(Debug, Clone)]
+pub struct UnionType {
+    pub name: String,
+    pub members: Vec<Member>,
+    pub location: Location,
+    pub layout: Option<Layout>,
+}
 
-pub struct PointerType {
-    size: usize,
-    base_type: Box<Type>,
+/// The size and the alignment of a struct or a union, with the padding.
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub size: usize,
+    pub alignment: usize,
 }
 
-pub struct VoidType {}
+/// The name given by `typedef`.
+#[derive(Debug, Clone)]
+pub struct UserType {
+    pub name: String,
+    pub real_type: TypeId,
+    pub location: Location,
+}
 
+/// The type qualified by `const`, whose objects cannot be assigned.
+#[derive(Debug, Clone)]
+pub struct ConstType {
+    pub base_type: TypeId,
+}
 
-// ---------- TypeRef ----------
+/// A member of a struct or a union.
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub type_: TypeId,
+    pub location: Location,
+    pub offset: usize, // from the head of the struct, after the layout
+}
 
-pub trait TypeRef {}
 
-pub struct ArrayTypeRef {}
+// ---------- TypeRef ----------
 
-pub struct FunctionTypeRef {}
+/// The type written in the source, before it is resolved.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeRef {
+    Void,
+    Integer(IntegerTypeRef),
+    Struct(StructTypeRef),
+    Union(UnionTypeRef),
+    Enum(EnumTypeRef),
+    User(UserTypeRef),
+    Pointer(PointerTypeRef),
+    Array(ArrayTypeRef),
+    Function(FunctionTypeRef),
+    Const(ConstTypeRef),
+}
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum IntegerTypeRef {
     Char,
     Short,
@@ -48,21 +128,129 @@ pub enum IntegerTypeRef {
     UnsignedLong,
 }
 
-pub struct NamedTypeRef {}
-
-pub struct CompositeTypeRef {}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StructTypeRef {
+    pub name: String,
+}
 
-pub struct StructTypeRef {}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnionTypeRef {
+    pub name: String,
+}
 
-pub struct UnionTypeRef {}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnumTypeRef {
+    pub name: String,
+}
 
-pub struct UserTypeRef {}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserTypeRef {
+    pub name: String,
+}
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PointerTypeRef {
-    base_type: Box<TypeRef>,
+    pub base_type: Box<TypeRef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArrayTypeRef {
+    pub base_type: Box<TypeRef>,
+    pub length: Option<ArrayLength>,
+}
+
+/// The length of an array as written, a literal or a constant expression
+/// evaluated when the type is resolved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ArrayLength {
+    Fixed(usize),
+    Constant(ConstantId),
+}
+
+/// The index of a constant expression in `AST::constants`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ConstantId(pub usize);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionTypeRef {
+    pub return_type: Box<TypeRef>,
+    pub params: ParamTypeRefs,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConstTypeRef {
+    pub base_type: Box<TypeRef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParamTypeRefs {
+    pub types: Vec<TypeRef>,
+    pub is_variadic: bool,
+}
+
+impl TypeRef {
+    pub fn pointer(base_type: TypeRef) -> TypeRef {
+        TypeRef::Pointer(PointerTypeRef { base_type: Box::new(base_type) })
+    }
+
+    pub fn array(base_type: TypeRef, length: Option<ArrayLength>) -> TypeRef {
+        TypeRef::Array(ArrayTypeRef {
+            base_type: Box::new(base_type),
+            length: length,
+        })
+    }
+
+    pub fn function(return_type: TypeRef, params: ParamTypeRefs) -> TypeRef {
+        TypeRef::Function(FunctionTypeRef {
+            return_type: Box::new(return_type),
+            params: params,
+        })
+    }
+
+    pub fn const_(base_type: TypeRef) -> TypeRef {
+        TypeRef::Const(ConstTypeRef { base_type: Box::new(base_type) })
+    }
 }
 
-pub struct VoidTypeRef {}
+impl IntegerTypeRef {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            IntegerTypeRef::Char => "char",
+            IntegerTypeRef::Short => "short",
+            IntegerTypeRef::Int => "int",
+            IntegerTypeRef::Long => "long",
+            IntegerTypeRef::UnsignedChar => "unsigned char",
+            IntegerTypeRef::UnsignedShort => "unsigned short",
+            IntegerTypeRef::UnsignedInt => "unsigned int",
+            IntegerTypeRef::UnsignedLong => "unsigned long",
+        }
+    }
 
-impl TypeRef for IntegerTypeRef {}
+    /// the conversion rank, larger for the wider types.
+    pub fn rank(&self) -> usize {
+        match *self {
+            IntegerTypeRef::Char | IntegerTypeRef::UnsignedChar => 1,
+            IntegerTypeRef::Short | IntegerTypeRef::UnsignedShort => 2,
+            IntegerTypeRef::Int | IntegerTypeRef::UnsignedInt => 3,
+            IntegerTypeRef::Long | IntegerTypeRef::UnsignedLong => 4,
+        }
+    }
 
+    pub fn to_unsigned(self) -> IntegerTypeRef {
+        match self {
+            IntegerTypeRef::Char => IntegerTypeRef::UnsignedChar,
+            IntegerTypeRef::Short => IntegerTypeRef::UnsignedShort,
+            IntegerTypeRef::Int => IntegerTypeRef::UnsignedInt,
+            IntegerTypeRef::Long => IntegerTypeRef::UnsignedLong,
+            unsigned => unsigned,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        match *self {
+            IntegerTypeRef::Char | IntegerTypeRef::Short |
+            IntegerTypeRef::Int | IntegerTypeRef::Long => true,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,820 @@
+//! The pass giving every expression its type and checking the operands.
+//!
+//! The conversions done implicitly in C are made explicit in the AST: the
+//! integer operands are wrapped in the implicit `CastNode`s of the integer
+//! promotion and the usual arithmetic conversions, and the integer added to
+//! a pointer is multiplied by the size pointed. An array or a function used
+//! as a value has the type of a pointer to it.
+//!
+//! The operands of `*`, `[]`, `.` and `->` and the left-hand sides of the
+//! assignments are only given their types here, and the invalid ones are
+//! left to the `DereferenceChecker`.
+
+use std::mem;
+use super::ast::*;
+use super::location::Location;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::entity::EntityTable;
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::{Diagnostic, DiagnosticHandler};
+use super::warning::Warning;
+use super::visitor::{self, Visitor};
+
+pub struct TypeChecker<'a> {
+    entities: &'a EntityTable,
+    table: &'a mut TypeTable,
+    handler: &'a mut DiagnosticHandler,
+    function: Option<(String, TypeId)>, // the name and the return type
+    pointer_difference: Option<usize>, // the size to divide the node by
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(entities: &'a EntityTable, table: &'a mut TypeTable,
+               handler: &'a mut DiagnosticHandler) -> TypeChecker<'a> {
+        TypeChecker {
+            entities: entities,
+            table: table,
+            handler: handler,
+            function: None,
+            pointer_difference: None,
+        }
+    }
+
+    /// Check all the expressions, the errors are reported to the handler.
+    pub fn check(&mut self, ast: &mut AST) {
+        self.visit_ast(ast);
+    }
+
+    fn error(&mut self, error: SemanticError) {
+        self.handler.emit(error.to_diagnostic());
+    }
+
+    fn name(&self, type_: TypeId) -> String {
+        self.table.type_name(type_)
+    }
+
+    fn long(&self) -> TypeId {
+        self.table.integer(IntegerTypeRef::Long)
+    }
+
+    // check the expression and return its type as a value, where an array
+    // or a function is a pointer.
+    fn check_expr(&mut self, node: &mut Box<Node>) -> Option<TypeId> {
+        self.visit_node(node);
+        node.expr_type().map(|type_| self.table.decay(type_))
+    }
+
+    fn check_condition(&mut self, node: &mut Box<Node>, what: &str) {
+        if let Some(type_) = self.check_expr(node) {
+            if !self.table.is_scalar(type_) {
+                let error = SemanticError::new(SemanticErrorKind::NonScalarCondition,
+                    node.location(),
+                    format!("the condition of {} has type `{}`, which is not \
+                             an integer or a pointer", what, self.name(type_)));
+                self.error(error);
+            }
+        }
+    }
+
+    // check that the value of `from` can be stored in `to`, converting it.
+    fn check_assignable(&mut self, node: &mut Box<Node>, from: TypeId, to: TypeId,
+                        context: &str) {
+        let table = &*self.table;
+        let is_valid = if table.is_integer(to) && table.is_integer(from) {
+            true
+        } else if table.is_pointer(to) && table.is_pointer(from) {
+            table.is_compatible(to, from) ||
+            table.is_void_pointer(to) || table.is_void_pointer(from)
+        } else if table.is_pointer(to) && table.is_integer(from) {
+            if !is_null(&**node) {
+                let error = SemanticError::new(
+                    SemanticErrorKind::PointerIntegerMismatch, node.location(),
+                    format!("expected `{}` but found `{}` {}",
+                            self.name(to), self.name(from), context))
+                    .with_help("cast the integer if the conversion is intended");
+                self.error(error);
+            }
+            return;
+        } else if table.is_integer(to) && table.is_pointer(from) {
+            let error = SemanticError::new(
+                SemanticErrorKind::PointerIntegerMismatch, node.location(),
+                format!("expected `{}` but found `{}` {}",
+                        self.name(to), self.name(from), context))
+                .with_help("cast the pointer if the conversion is intended");
+            self.error(error);
+            return;
+        } else if table.is_array(to) && is_string(&**node) {
+            // `char[6] s = "hello";`
+            table.base_type(to).is_some_and(|base_type| table.is_integer(base_type))
+        } else {
+            !table.is_void(to) && table.is_compatible(to, from)
+        };
+
+        if is_valid {
+            if self.table.discards_const(to, from) {
+                let warning = Diagnostic::warning(
+                    format!("`{}` loses the `const` of `{}` {}",
+                            self.name(to), self.name(from), context))
+                    .with_primary(node.location(), "")
+                    .with_help("make the pointer `const`, or cast it if the object \
+                                is not modified");
+                self.handler.warn(Warning::DiscardedQualifiers, warning);
+            }
+            self.cast(node, to);
+        } else {
+            let error = SemanticError::new(SemanticErrorKind::IncompatibleTypes,
+                node.location(), format!("expected `{}` but found `{}` {}",
+                                         self.name(to), self.name(from), context));
+            self.error(error);
+        }
+    }
+
+    // wrap the integer expression in an implicit cast to the integer type.
+    fn cast(&mut self, node: &mut Box<Node>, type_: TypeId) {
+        let from = match node.expr_type() {
+            Some(from) => from,
+            None => return,
+        };
+        let real_type = self.table.real_type(type_);
+        if self.table.real_type(from) == real_type || !self.table.is_integer(from) {
+            return;
+        }
+        let integer = match self.table.integer_ref(real_type) {
+            Some(integer) => integer,
+            None => return,
+        };
+        let location = node.location();
+        let mut type_node = TypeNode::new(location, TypeRef::Integer(integer));
+        type_node.type_ = Some(type_);
+        let mut cast = CastNode::new(location, type_node, take(node));
+        cast.expr_type = Some(type_);
+        cast.is_implicit = true;
+        *node = Box::new(cast);
+    }
+
+    fn promote(&mut self, node: &mut Box<Node>, type_: TypeId) -> TypeId {
+        let promoted = self.table.integer_promotion(type_);
+        self.cast(node, promoted);
+        promoted
+    }
+
+    // convert both operands to their common type.
+    fn convert(&mut self, left: &mut Box<Node>, left_type: TypeId,
+               right: &mut Box<Node>, right_type: TypeId) -> TypeId {
+        let common = self.table.usual_arithmetic_conversion(left_type, right_type);
+        self.cast(left, common);
+        self.cast(right, common);
+        common
+    }
+
+    // the size pointed by the pointer in the arithmetic, `void*` points
+    // bytes as in GNU C.
+    fn pointed_size(&mut self, pointer: TypeId, location: Location) -> Option<usize> {
+        let base_type = self.table.base_type(pointer)?;
+        if self.table.is_void(base_type) {
+            return Some(1);
+        }
+        let size = self.table.size(base_type);
+        if size.is_none() {
+            let error = SemanticError::new(SemanticErrorKind::InvalidOperands, location,
+                format!("arithmetic on `{}`, a pointer to an incomplete type",
+                        self.name(pointer)));
+            self.error(error);
+        }
+        size
+    }
+
+    // multiply the integer added to a pointer by the size pointed.
+    fn scale(&mut self, index: &mut Box<Node>, size: usize) {
+        let long = self.long();
+        self.cast(index, long);
+        if size > 1 {
+            let location = index.location();
+            let size = integer_literal(location, IntegerTypeRef::Long, size as i64, long);
+            wrap(index, BinaryOpType::Multiplication, size, long);
+        }
+    }
+
+    fn invalid_operands(&mut self, location: Location, op: &str, left: TypeId,
+                        right: TypeId) {
+        let error = SemanticError::new(SemanticErrorKind::InvalidOperands, location,
+            format!("invalid operands to binary `{}` (`{}` and `{}`)",
+                    op, self.name(left), self.name(right)));
+        self.error(error);
+    }
+
+    fn invalid_operand(&mut self, location: Location, op: &str, type_: TypeId) {
+        let error = SemanticError::new(SemanticErrorKind::InvalidOperands, location,
+            format!("invalid operand to unary `{}` (`{}`)", op, self.name(type_)));
+        self.error(error);
+    }
+
+    fn check_additive(&mut self, node: &mut BinaryOpNode, left: TypeId,
+                      right: TypeId) -> Option<TypeId> {
+        let is_addition = match node.type_ {
+            BinaryOpType::Addition => true,
+            _ => false,
+        };
+        let (is_left_pointer, is_right_pointer) =
+            (self.table.is_pointer(left), self.table.is_pointer(right));
+        if self.table.is_integer(left) && self.table.is_integer(right) {
+            Some(self.convert(&mut node.left, left, &mut node.right, right))
+        } else if is_left_pointer && self.table.is_integer(right) {
+            let size = self.pointed_size(left, node.location)?;
+            self.scale(&mut node.right, size);
+            Some(left)
+        } else if is_addition && self.table.is_integer(left) && is_right_pointer {
+            let size = self.pointed_size(right, node.location)?;
+            self.scale(&mut node.left, size);
+            Some(right)
+        } else if !is_addition && is_left_pointer && is_right_pointer &&
+                  self.table.is_compatible(left, right) {
+            let size = self.pointed_size(left, node.location)?;
+            if size > 1 {
+                self.pointer_difference = Some(size);
+            }
+            Some(self.long())
+        } else {
+            self.invalid_operands(node.location, node.type_.symbol(), left, right);
+            None
+        }
+    }
+
+    fn check_comparison(&mut self, node: &mut BinaryOpNode, left: TypeId,
+                        right: TypeId) {
+        let table = &*self.table;
+        if table.is_integer(left) && table.is_integer(right) {
+            self.convert(&mut node.left, left, &mut node.right, right);
+        } else if table.is_pointer(left) && table.is_pointer(right) {
+            if !table.is_compatible(left, right) && !table.is_void_pointer(left) &&
+               !table.is_void_pointer(right) {
+                let error = SemanticError::new(SemanticErrorKind::IncompatibleTypes,
+                    node.location, format!("comparison of distinct pointer types \
+                                            `{}` and `{}`",
+                                           self.name(left), self.name(right)))
+                    .with_help("cast one of them if the comparison is intended");
+                self.error(error);
+            }
+        } else if (table.is_pointer(left) && table.is_integer(right)) ||
+                  (table.is_integer(left) && table.is_pointer(right)) {
+            if !is_null(&*node.left) && !is_null(&*node.right) {
+                let error = SemanticError::new(
+                    SemanticErrorKind::PointerIntegerMismatch, node.location,
+                    format!("comparison between `{}` and `{}`",
+                            self.name(left), self.name(right)))
+                    .with_help("cast one of them if the comparison is intended");
+                self.error(error);
+            }
+        } else {
+            self.invalid_operands(node.location, node.type_.symbol(), left, right);
+        }
+    }
+
+    // the type of `++` and `--` on the operand, setting the amount added.
+    fn check_increment(&mut self, operand: &mut Box<Node>, op: &str,
+                       amount: &mut usize) -> Option<TypeId> {
+        self.visit_node(operand);
+        let type_ = operand.expr_type()?;
+        if self.table.is_integer(type_) {
+            Some(type_)
+        } else if self.table.is_pointer(type_) {
+            *amount = self.pointed_size(type_, operand.location())?;
+            Some(type_)
+        } else {
+            self.invalid_operand(operand.location(), op, type_);
+            None
+        }
+    }
+}
+
+impl<'a> Visitor for TypeChecker<'a> {
+    fn visit_node(&mut self, node: &mut Box<Node>) {
+        visitor::walk_node(self, node);
+        if let Some(size) = self.pointer_difference.take() {
+            // the difference of the pointers is in the elements
+            let long = self.long();
+            let size = integer_literal(node.location(), IntegerTypeRef::Long,
+                                       size as i64, long);
+            wrap(node, BinaryOpType::Division, size, long);
+        }
+    }
+
+    fn visit_defvar(&mut self, var: &mut DefinedVariable) {
+        if let Some(ref mut initializer) = var.initializer {
+            let type_ = self.check_expr(initializer);
+            if let (Some(from), Some(to)) = (type_, var.type_.type_) {
+                let context = format!("in the initializer of `{}`", var.name);
+                self.check_assignable(initializer, from, to, &context);
+            }
+        }
+    }
+
+    fn visit_defun(&mut self, func: &mut DefinedFunction) {
+        self.function = func.type_.type_
+            .and_then(|type_| self.table.function_type(type_))
+            .map(|function| (func.name.clone(), function.return_type));
+        self.visit_block(&mut func.body);
+        self.function = None;
+    }
+
+    fn visit_if(&mut self, node: &mut IfNode) {
+        self.check_condition(&mut node.condition, "`if`");
+        self.visit_node(&mut node.then_clause);
+        if let Some(ref mut else_clause) = node.else_clause {
+            self.visit_node(else_clause);
+        }
+    }
+
+    fn visit_while(&mut self, node: &mut WhileNode) {
+        self.check_condition(&mut node.condition, "`while`");
+        self.visit_node(&mut node.body);
+    }
+
+    fn visit_dowhile(&mut self, node: &mut DoWhileNode) {
+        self.visit_node(&mut node.body);
+        self.check_condition(&mut node.condition, "`do while`");
+    }
+
+    fn visit_for(&mut self, node: &mut ForNode) {
+        if let Some(ref mut init_expr) = node.init_expr {
+            self.visit_node(init_expr);
+        }
+        if let Some(ref mut cond_expr) = node.cond_expr {
+            self.check_condition(cond_expr, "`for`");
+        }
+        if let Some(ref mut step_expr) = node.step_expr {
+            self.visit_node(step_expr);
+        }
+        self.visit_node(&mut node.body);
+    }
+
+    fn visit_switch(&mut self, node: &mut SwitchNode) {
+        if let Some(type_) = self.check_expr(&mut node.expr) {
+            if self.table.is_integer(type_) {
+                self.promote(&mut node.expr, type_);
+            } else {
+                let error = SemanticError::new(SemanticErrorKind::NonScalarCondition,
+                    node.expr.location(),
+                    format!("the condition of `switch` has type `{}`, which is \
+                             not an integer", self.name(type_)));
+                self.error(error);
+            }
+        }
+        if let Some(ref mut cases) = node.cases {
+            self.visit_node(cases);
+        }
+    }
+
+    fn visit_return(&mut self, node: &mut ReturnNode) {
+        let type_ = match node.expr {
+            Some(ref mut expr) => self.check_expr(expr),
+            None => None,
+        };
+        let (name, return_type) = match self.function {
+            Some((ref name, return_type)) => (name.clone(), return_type),
+            None => return,
+        };
+        let is_void = self.table.is_void(return_type);
+        match node.expr {
+            Some(ref mut expr) if is_void => {
+                let error = SemanticError::new(SemanticErrorKind::InvalidReturn,
+                    expr.location(),
+                    format!("`return` with a value in `{}`, which returns `void`", name));
+                self.error(error);
+            }
+            Some(ref mut expr) => if let Some(type_) = type_ {
+                let context = format!("in the return value of `{}`", name);
+                self.check_assignable(expr, type_, return_type, &context);
+            },
+            None if !is_void => {
+                let error = SemanticError::new(SemanticErrorKind::InvalidReturn,
+                    node.location,
+                    format!("`return` without a value in `{}`, which returns `{}`",
+                            name, self.name(return_type)));
+                self.error(error);
+            }
+            None => {}
+        }
+    }
+
+    fn visit_integer_literal(&mut self, node: &mut IntegerLiteralNode) {
+        node.expr_type = Some(self.table.integer(node.type_));
+    }
+
+    fn visit_string_literal(&mut self, node: &mut StringLiteralNode) {
+        let char_ = self.table.integer(IntegerTypeRef::Char);
+        node.expr_type = Some(self.table.pointer_to(char_));
+    }
+
+    fn visit_variable(&mut self, node: &mut VariableNode) {
+        node.expr_type = node.entity.and_then(|id| self.entities.get(id).type_);
+    }
+
+    fn visit_binary_op(&mut self, node: &mut BinaryOpNode) {
+        let left = self.check_expr(&mut node.left);
+        let right = self.check_expr(&mut node.right);
+        let (left, right) = match (left, right) {
+            (Some(left), Some(right)) => (left, right),
+            _ => return,
+        };
+        let int = self.table.integer(IntegerTypeRef::Int);
+        node.expr_type = match node.type_ {
+            BinaryOpType::Addition | BinaryOpType::Subtraction =>
+                self.check_additive(node, left, right),
+            op if op.is_comparison() => {
+                self.check_comparison(node, left, right);
+                Some(int)
+            }
+            _ if !self.table.is_integer(left) || !self.table.is_integer(right) => {
+                self.invalid_operands(node.location, node.type_.symbol(), left, right);
+                None
+            }
+            BinaryOpType::LeftShift | BinaryOpType::RightShift => {
+                // the type is of the left operand only
+                self.promote(&mut node.right, right);
+                Some(self.promote(&mut node.left, left))
+            }
+            _ => Some(self.convert(&mut node.left, left, &mut node.right, right)),
+        };
+    }
+
+    fn visit_logical_and(&mut self, node: &mut LogicalAndNode) {
+        self.check_condition(&mut node.left, "`&&`");
+        self.check_condition(&mut node.right, "`&&`");
+        node.expr_type = Some(self.table.integer(IntegerTypeRef::Int));
+    }
+
+    fn visit_logical_or(&mut self, node: &mut LogicalOrNode) {
+        self.check_condition(&mut node.left, "`||`");
+        self.check_condition(&mut node.right, "`||`");
+        node.expr_type = Some(self.table.integer(IntegerTypeRef::Int));
+    }
+
+    fn visit_unary_op(&mut self, node: &mut UnaryOpNode) {
+        if let UnaryOpType::ExclamationMark = node.type_ {
+            self.check_condition(&mut node.node, "`!`");
+            node.expr_type = Some(self.table.integer(IntegerTypeRef::Int));
+            return;
+        }
+        let type_ = match self.check_expr(&mut node.node) {
+            Some(type_) => type_,
+            None => return,
+        };
+        if self.table.is_integer(type_) {
+            node.expr_type = Some(self.promote(&mut node.node, type_));
+        } else {
+            self.invalid_operand(node.location, node.type_.symbol(), type_);
+        }
+    }
+
+    fn visit_prefix_op(&mut self, node: &mut PrefixOpNode) {
+        let op = match node.type_ {
+            PrefixOpType::Increment => "++",
+            PrefixOpType::Decrement => "--",
+        };
+        node.expr_type = self.check_increment(&mut node.node, op, &mut node.amount);
+    }
+
+    fn visit_suffix_op(&mut self, node: &mut SuffixOpNode) {
+        let op = match node.type_ {
+            SuffixOpType::Increment => "++",
+            SuffixOpType::Decrement => "--",
+        };
+        node.expr_type = self.check_increment(&mut node.expr, op, &mut node.amount);
+    }
+
+    fn visit_dereference(&mut self, node: &mut DereferenceNode) {
+        node.expr_type = self.check_expr(&mut node.node)
+                             .filter(|&type_| self.table.is_pointer(type_))
+                             .and_then(|type_| self.table.base_type(type_));
+    }
+
+    fn visit_address(&mut self, node: &mut AddressNode) {
+        self.visit_node(&mut node.node);
+        node.expr_type = node.node.expr_type().map(|type_| self.table.pointer_to(type_));
+    }
+
+    fn visit_cast(&mut self, node: &mut CastNode) {
+        let from = self.check_expr(&mut node.node);
+        let (from, to) = match (from, node.type_.type_) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return,
+        };
+        let table = &*self.table;
+        if table.is_void(to) || (table.is_scalar(from) && table.is_scalar(to)) ||
+           table.is_compatible(from, to) {
+            node.expr_type = Some(to);
+        } else {
+            let error = SemanticError::new(SemanticErrorKind::InvalidCast, node.location,
+                format!("cannot cast `{}` to `{}`", self.name(from), self.name(to)));
+            self.error(error);
+        }
+    }
+
+    fn visit_sizeof_type(&mut self, node: &mut SizeofTypeNode) {
+        node.expr_type = Some(self.table.integer(IntegerTypeRef::UnsignedLong));
+    }
+
+    fn visit_sizeof_expr(&mut self, node: &mut SizeofExprNode) {
+        // not a value, so the array is not a pointer
+        self.visit_node(&mut node.node);
+        node.expr_type = Some(self.table.integer(IntegerTypeRef::UnsignedLong));
+    }
+
+    fn visit_aref(&mut self, node: &mut ArefNode) {
+        let type_ = self.check_expr(&mut node.expr);
+        if let Some(index) = self.check_expr(&mut node.idx) {
+            if self.table.is_integer(index) {
+                self.promote(&mut node.idx, index);
+            } else {
+                let error = SemanticError::new(SemanticErrorKind::InvalidOperands,
+                    node.idx.location(),
+                    format!("the array subscript has type `{}`, which is not an \
+                             integer", self.name(index)));
+                self.error(error);
+            }
+        }
+        node.expr_type = type_.filter(|&type_| self.table.is_pointer(type_))
+                              .and_then(|type_| self.table.base_type(type_));
+    }
+
+    fn visit_member(&mut self, node: &mut MemberNode) {
+        self.visit_node(&mut node.expr);
+        node.expr_type = node.expr.expr_type()
+            .and_then(|type_| self.table.member(type_, &node.memb))
+            .map(|member| member.type_);
+    }
+
+    fn visit_ptr_member(&mut self, node: &mut PtrMemberNode) {
+        node.expr_type = self.check_expr(&mut node.expr)
+            .filter(|&type_| self.table.is_pointer(type_))
+            .and_then(|type_| self.table.base_type(type_))
+            .and_then(|type_| self.table.member(type_, &node.memb))
+            .map(|member| member.type_);
+    }
+
+    fn visit_funcall(&mut self, node: &mut FuncallNode) {
+        let callee = self.check_expr(&mut node.expr);
+        let mut arg_types = Vec::new();
+        for arg in &mut node.args {
+            arg_types.push(self.check_expr(arg));
+        }
+        let callee = match callee {
+            Some(callee) => callee,
+            None => return,
+        };
+        let function = match self.table.function_type(callee) {
+            Some(function) => function.clone(),
+            None => {
+                let error = SemanticError::new(SemanticErrorKind::NotAFunction,
+                    node.expr.location(),
+                    format!("`{}` is not a function or a pointer to a function",
+                            self.name(callee)));
+                self.error(error);
+                return;
+            }
+        };
+        node.expr_type = Some(function.return_type);
+
+        let (name, entity) = match node.expr.node_ref() {
+            NodeRef::VariableNode(var) => (format!("`{}`", var.name), var.entity),
+            _ => ("the function".to_string(), None),
+        };
+        let params = function.param_types.len();
+        if node.args.len() < params || (node.args.len() > params && !function.is_variadic) {
+            let plural = |n: usize| if n == 1 { "" } else { "s" };
+            let mut error = SemanticError::new(SemanticErrorKind::ArgumentCount,
+                node.location,
+                format!("{} takes {}{} argument{} but {} {} given", name,
+                        if function.is_variadic { "at least " } else { "" },
+                        params, plural(params), node.args.len(),
+                        if node.args.len() == 1 { "was" } else { "were" }));
+            if let Some(entity) = entity {
+                error = error.with_secondary(self.entities.get(entity).location,
+                                             "defined here");
+            }
+            self.error(error);
+            return;
+        }
+
+        for (i, (arg, arg_type)) in node.args.iter_mut().zip(arg_types).enumerate() {
+            let arg_type = match arg_type {
+                Some(arg_type) => arg_type,
+                None => continue,
+            };
+            match function.param_types.get(i) {
+                Some(&param_type) => {
+                    let context = format!("in the argument {} of {}", i + 1, name);
+                    self.check_assignable(arg, arg_type, param_type, &context);
+                }
+                // the default argument promotion for the variadic arguments
+                None => if self.table.is_integer(arg_type) {
+                    self.promote(arg, arg_type);
+                },
+            }
+        }
+    }
+
+    fn visit_cond_expr(&mut self, node: &mut CondExprNode) {
+        self.check_condition(&mut node.condition, "`?:`");
+        let then_type = self.check_expr(&mut node.then_clause);
+        let else_type = self.check_expr(&mut node.else_clause);
+        let (then_type, else_type) = match (then_type, else_type) {
+            (Some(then_type), Some(else_type)) => (then_type, else_type),
+            _ => return,
+        };
+        let table = &*self.table;
+        node.expr_type = if table.is_integer(then_type) && table.is_integer(else_type) {
+            Some(self.convert(&mut node.then_clause, then_type,
+                              &mut node.else_clause, else_type))
+        } else if table.is_pointer(then_type) && table.is_pointer(else_type) &&
+                  (table.is_compatible(then_type, else_type) ||
+                   table.is_void_pointer(else_type)) {
+            Some(else_type)
+        } else if table.is_pointer(then_type) &&
+                  ((table.is_pointer(else_type) && table.is_void_pointer(then_type)) ||
+                   is_null(&*node.else_clause)) {
+            Some(then_type)
+        } else if table.is_pointer(else_type) && is_null(&*node.then_clause) {
+            Some(else_type)
+        } else if table.is_compatible(then_type, else_type) {
+            Some(then_type)
+        } else {
+            let error = SemanticError::new(SemanticErrorKind::IncompatibleTypes,
+                node.location,
+                format!("the branches of `?:` have the incompatible types `{}` \
+                         and `{}`", self.name(then_type), self.name(else_type)));
+            self.error(error);
+            None
+        };
+    }
+
+    fn visit_assign(&mut self, node: &mut AssignNode) {
+        self.visit_node(&mut node.lhs);
+        let from = self.check_expr(&mut node.rhs);
+        node.expr_type = node.lhs.expr_type();
+        if let (Some(from), Some(to)) = (from, node.expr_type) {
+            // an array or a function is left to the `DereferenceChecker`
+            if !self.table.is_array(to) && !self.table.is_function(to) {
+                self.check_assignable(&mut node.rhs, from, to, "in the assignment");
+            }
+        }
+    }
+
+    fn visit_op_assign(&mut self, node: &mut OpAssignNode) {
+        self.visit_node(&mut node.lhs);
+        let right = self.check_expr(&mut node.rhs);
+        let (left, right) = match (node.lhs.expr_type(), right) {
+            (Some(left), Some(right)) => (left, right),
+            _ => return,
+        };
+        node.expr_type = Some(left);
+        if self.table.is_array(left) || self.table.is_function(left) {
+            return;
+        }
+        let op = node.type_.binary_op();
+        match op {
+            BinaryOpType::Addition | BinaryOpType::Subtraction
+                if self.table.is_pointer(left) && self.table.is_integer(right) => {
+                if let Some(size) = self.pointed_size(left, node.location) {
+                    self.scale(&mut node.rhs, size);
+                }
+            }
+            _ if !self.table.is_integer(left) || !self.table.is_integer(right) => {
+                let op = format!("{}=", op.symbol());
+                self.invalid_operands(node.location, &op, left, right);
+            }
+            BinaryOpType::LeftShift | BinaryOpType::RightShift => {
+                self.promote(&mut node.rhs, right);
+            }
+            _ => {
+                let common = self.table.usual_arithmetic_conversion(left, right);
+                self.cast(&mut node.rhs, common);
+            }
+        }
+    }
+}
+
+// whether the expression is `0`, which is also a null pointer.
+fn is_null(node: &Node) -> bool {
+    match node.node_ref() {
+        NodeRef::IntegerLiteralNode(node) => node.value == 0,
+        _ => false,
+    }
+}
+
+fn is_string(node: &Node) -> bool {
+    match node.node_ref() {
+        NodeRef::StringLiteralNode(_) => true,
+        _ => false,
+    }
+}
+
+fn integer_literal(location: Location, integer: IntegerTypeRef, value: i64,
+                   type_: TypeId) -> Box<Node> {
+    let mut literal = IntegerLiteralNode::new(location, integer, value);
+    literal.expr_type = Some(type_);
+    Box::new(literal)
+}
+
+// take the node out, leaving a placeholder to be replaced.
+fn take(node: &mut Box<Node>) -> Box<Node> {
+    let location = node.location();
+    mem::replace(node, Box::new(IntegerLiteralNode::new(location,
+                                                        IntegerTypeRef::Int, 0)))
+}
+
+// replace the node by `node <op> right`.
+fn wrap(node: &mut Box<Node>, op: BinaryOpType, right: Box<Node>, type_: TypeId) {
+    let location = node.location();
+    let mut binary = BinaryOpNode::new(location, take(node), op, right);
+    binary.expr_type = Some(type_);
+    *node = Box::new(binary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::compile;
+    use super::super::warning::Warning;
+
+    #[test]
+    fn array_parameter_is_a_pointer() {
+        let compiled = compile("int first(int[3] a) { return a[0]; }\n\
+                                int apply(int(int) f, int x) { return f(x); }\n\
+                                int twice(int x) { return 2 * x; }\n\
+                                int main(void) {\n\
+                                    int[3] xs; int* p = xs;\n\
+                                    xs[0] = 1;\n\
+                                    return first(p) + first(xs) + apply(twice, 1);\n\
+                                }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn pointer_losing_const() {
+        let compiled = compile("char* strip(char const* s) { return s; }\n\
+                                int main(void) {\n\
+                                    char const* c = \"const\";\n\
+                                    char* p = c;\n\
+                                    char const* q = p;\n\
+                                    char const** pp = &p;\n\
+                                    p = c;\n\
+                                    return strip(q) == q && pp != 0;\n\
+                                }\n");
+        assert!(compiled.errors().is_empty());
+        assert_eq!(compiled.warnings(), [Warning::DiscardedQualifiers; 3]);
+    }
+
+    #[test]
+    fn pointer_losing_const_of_the_pointed_pointer() {
+        let compiled = compile("int main(void) {\n\
+                                    char const* c = \"c\";\n\
+                                    char const** pp = &c;\n\
+                                    char** p = pp;\n\
+                                    return p != 0;\n\
+                                }\n");
+        assert_eq!(compiled.warnings(), [Warning::DiscardedQualifiers]);
+    }
+
+    #[test]
+    fn invalid_operands() {
+        let compiled = compile("struct s { int x; };\n\
+                                int main(void) { struct s a; return a + 1; }\n");
+        assert_eq!(compiled.errors(), ["E0063"]);
+    }
+
+    #[test]
+    fn pointer_integer_mismatch() {
+        compile("int main(void) { int* p = 0; return p == 0; }\n").assert_clean();
+        let compiled = compile("int main(void) { int* p = 1; int n = p; return n; }\n");
+        assert_eq!(compiled.errors(), ["E0064", "E0064"]);
+    }
+
+    #[test]
+    fn incompatible_types() {
+        let compiled = compile("int main(void) { int* p; long* q = p; return 0; }\n");
+        assert_eq!(compiled.errors(), ["E0065"]);
+    }
+
+    #[test]
+    fn calls() {
+        let compiled = compile("int f(int x, int y) { return x + y; }\n\
+                                int main(void) { int n; n(); return f(1); }\n");
+        assert_eq!(compiled.errors(), ["E0066", "E0067"]);
+    }
+
+    #[test]
+    fn returns() {
+        let compiled = compile("void f(void) { return 1; }\n\
+                                int main(void) { f(); return; }\n");
+        assert_eq!(compiled.errors(), ["E0069", "E0069"]);
+    }
+
+    #[test]
+    fn conditions() {
+        let compiled = compile("struct s { int x; };\n\
+                                int main(void) { struct s a; if (a) return 1; return 0; }\n");
+        assert_eq!(compiled.errors(), ["E0070"]);
+    }
+}
@@ -0,0 +1,535 @@
+//! The pass resolving every type in the AST with the `TypeTable`.
+//!
+//! The structs, unions, enums and typedefs are defined first, so the types
+//! used anywhere in the functions and variables can be resolved. Each of
+//! them is defined on its first use, so a constant expression can use the
+//! ones defined after it: the length of an array is evaluated just before
+//! its type is resolved, and the value of an enumerator in its order, with
+//! the enumerators and typedef names they use defined before, and the
+//! structs and unions laid out before they are given to `sizeof`.
+
+use std::mem;
+use std::collections::{HashMap, HashSet};
+use super::ast::*;
+use super::location::Location;
+use super::type_::*;
+use super::type_table::{TypeTable, TypeError, TypeErrorKind};
+use super::entity::{Entity, EntityKind, EntityTable};
+use super::constant_evaluator::{Constant, ConstantEvaluator};
+use super::semantic_error::{SemanticError, SemanticErrorKind};
+use super::diagnostic::DiagnosticHandler;
+use super::visitor::{self, Visitor};
+
+pub struct TypeResolver<'a> {
+    table: &'a mut TypeTable,
+    entities: &'a mut EntityTable,
+    handler: &'a mut DiagnosticHandler,
+    constants: Vec<Option<Box<Node>>>, // of the AST, `None` while evaluated
+    // the definitions of the types, out of the AST while they are defined
+    defstructs: Vec<StructNode>,
+    defunions: Vec<UnionNode>,
+    typedefs: Vec<TypedefNode>,
+    defenums: Vec<EnumNode>,
+    composites: HashMap<Definition, TypeId>, // of the structs and unions
+    typedef_names: HashMap<String, usize>,
+    enumerator_names: HashMap<String, usize>, // to their enums
+    defined: HashSet<Definition>, // or being defined
+    demands: Vec<Demand>, // what is being defined, the innermost last
+    cycles: usize, // the dependency cycles reported
+}
+
+// a struct, union, typedef or enum by its index in the AST.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Definition {
+    Struct(usize),
+    Union(usize),
+    Typedef(usize),
+    Enum(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Demand {
+    Definition(Definition),
+    Length(Location), // an array length being evaluated
+}
+
+impl<'a> TypeResolver<'a> {
+    pub fn new(table: &'a mut TypeTable, entities: &'a mut EntityTable,
+               handler: &'a mut DiagnosticHandler) -> TypeResolver<'a> {
+        TypeResolver {
+            table: table,
+            entities: entities,
+            handler: handler,
+            constants: Vec::new(),
+            defstructs: Vec::new(),
+            defunions: Vec::new(),
+            typedefs: Vec::new(),
+            defenums: Vec::new(),
+            composites: HashMap::new(),
+            typedef_names: HashMap::new(),
+            enumerator_names: HashMap::new(),
+            defined: HashSet::new(),
+            demands: Vec::new(),
+            cycles: 0,
+        }
+    }
+
+    /// Resolve all the types, the errors are reported to the handler.
+    pub fn resolve(&mut self, ast: &mut AST) {
+        self.constants = mem::take(&mut ast.constants)
+                             .into_iter().map(Some).collect();
+        self.defstructs = mem::take(&mut ast.defstructs);
+        self.defunions = mem::take(&mut ast.defunions);
+        self.typedefs = mem::take(&mut ast.typedefs);
+        self.defenums = mem::take(&mut ast.defenums);
+
+        self.declare_types();
+        for i in 0..self.defenums.len() {
+            self.define(Definition::Enum(i));
+        }
+        // a typedef comes before its uses, as the parser needs it.
+        for i in 0..self.typedefs.len() {
+            self.define(Definition::Typedef(i));
+        }
+        for i in 0..self.defstructs.len() {
+            self.define(Definition::Struct(i));
+        }
+        for i in 0..self.defunions.len() {
+            self.define(Definition::Union(i));
+        }
+        for error in self.table.semantic_check() {
+            self.error(error);
+        }
+        for error in self.table.compute_layouts() {
+            self.error(error);
+        }
+
+        ast.defstructs = mem::take(&mut self.defstructs);
+        ast.defunions = mem::take(&mut self.defunions);
+        ast.typedefs = mem::take(&mut self.typedefs);
+        ast.defenums = mem::take(&mut self.defenums);
+        self.visit_ast(ast);
+        ast.constants = self.constants.drain(..).map(Option::unwrap).collect();
+    }
+
+    // the names of the structs, unions and enums, and where the typedef
+    // names and the enumerators are defined.
+    fn declare_types(&mut self) {
+        for i in 0..self.defstructs.len() {
+            let result = self.table.define_struct(&self.defstructs[i].name,
+                                                  self.defstructs[i].location);
+            match result {
+                Ok(id) => { self.composites.insert(Definition::Struct(i), id); }
+                Err(err) => self.error(err),
+            }
+        }
+        for i in 0..self.defunions.len() {
+            let result = self.table.define_union(&self.defunions[i].name,
+                                                 self.defunions[i].location);
+            match result {
+                Ok(id) => { self.composites.insert(Definition::Union(i), id); }
+                Err(err) => self.error(err),
+            }
+        }
+        for (i, defenum) in self.defenums.iter().enumerate() {
+            if let Some(ref name) = defenum.name {
+                if let Err(err) = self.table.define_enum(name, defenum.location) {
+                    self.handler.emit(err.to_diagnostic());
+                }
+            }
+            for member in &defenum.members {
+                self.enumerator_names.entry(member.name.clone()).or_insert(i);
+            }
+        }
+        for (i, typedef) in self.typedefs.iter().enumerate() {
+            self.typedef_names.entry(typedef.name.clone()).or_insert(i);
+        }
+    }
+
+    // define the type, unless it is defined or being defined.
+    fn define(&mut self, definition: Definition) {
+        if !self.defined.insert(definition) {
+            return;
+        }
+        self.demands.push(Demand::Definition(definition));
+        match definition {
+            Definition::Struct(i) if self.defstructs[i].is_incomplete =>
+                self.declare_incomplete(definition),
+            Definition::Union(i) if self.defunions[i].is_incomplete =>
+                self.declare_incomplete(definition),
+            Definition::Struct(i) => {
+                let mut slots = mem::take(&mut self.defstructs[i].members);
+                self.define_members(definition, &mut slots);
+                self.defstructs[i].members = slots;
+            }
+            Definition::Union(i) => {
+                let mut slots = mem::take(&mut self.defunions[i].members);
+                self.define_members(definition, &mut slots);
+                self.defunions[i].members = slots;
+            }
+            Definition::Typedef(i) => self.define_typedef(i),
+            Definition::Enum(i) => self.define_enum(i),
+        }
+        self.demands.pop();
+    }
+
+    fn define_members(&mut self, definition: Definition, slots: &mut Vec<Slot>) {
+        let mut members = Vec::new();
+        for slot in slots.iter_mut() {
+            if let Some(type_) = self.resolve_type(&mut slot.type_) {
+                members.push(Member {
+                    name: slot.name.clone(),
+                    type_: type_,
+                    location: slot.location,
+                    offset: 0,
+                });
+            }
+        }
+        if let Some(&id) = self.composites.get(&definition) {
+            self.table.set_members(id, members);
+        }
+    }
+
+    fn declare_incomplete(&mut self, definition: Definition) {
+        if let Some(&id) = self.composites.get(&definition) {
+            self.table.set_incomplete(id);
+        }
+    }
+
+    fn define_typedef(&mut self, i: usize) {
+        let location = self.typedefs[i].location;
+        let mut real = mem::replace(&mut self.typedefs[i].real,
+                                    TypeNode::new(location, TypeRef::Void));
+        if let Some(real_type) = self.resolve_type(&mut real) {
+            if let Err(err) = self.table.define_typedef(&self.typedefs[i].name, real_type,
+                                                        location) {
+                self.error(err);
+            }
+        }
+        self.typedefs[i].real = real;
+    }
+
+    fn define_enum(&mut self, i: usize) {
+        let int = self.table.integer(IntegerTypeRef::Int);
+        let mut members = mem::take(&mut self.defenums[i].members);
+        let mut next = 0;
+        for member in &mut members {
+            let mut value = next;
+            if let Some(ref mut expr) = member.value {
+                let context = "the value of an enumerator";
+                if let Some(constant) = self.evaluate(expr, context) {
+                    value = constant.value;
+                }
+            }
+            let value = ConstantEvaluator::new(self.table, self.entities)
+                            .convert(value, IntegerTypeRef::Int).value;
+            let entity = Entity {
+                name: member.name.clone(),
+                kind: EntityKind::Enumerator,
+                type_: Some(int),
+                location: member.location,
+                is_private: false,
+                scope: self.entities.toplevel(),
+                value: Some(value),
+            };
+            match self.entities.define(entity) {
+                Ok(id) => member.entity = Some(id),
+                Err(first) => {
+                    let error = SemanticError::new(
+                        SemanticErrorKind::DuplicatedDefinition, member.location,
+                        format!("`{}` is defined more than once", member.name))
+                        .with_secondary(self.entities.get(first).location,
+                                        "first defined here");
+                    self.semantic_error(error);
+                }
+            }
+            next = value.wrapping_add(1);
+        }
+        self.defenums[i].members = members;
+    }
+
+    // define the type `name` used at `location` before it is resolved,
+    // false if it is being defined, which is reported.
+    fn demand(&mut self, definition: Definition, name: &str, location: Location) -> bool {
+        let demand = Demand::Definition(definition);
+        let position = match self.demands.iter().position(|&d| d == demand) {
+            Some(position) => position,
+            None => {
+                self.define(definition);
+                return true;
+            }
+        };
+        // an enum refers to its own enumerators after they are defined,
+        // and to the later ones only by mistake, reported as undefined
+        let length = self.demands[position..].iter().filter_map(|&d| match d {
+            Demand::Length(location) => Some(location),
+            _ => None,
+        }).next();
+        match length {
+            Some(length) => {
+                let error = TypeError::new(TypeErrorKind::RecursiveArrayLength, location,
+                    format!("`{}` depends on itself through the length of an array", name));
+                // `char[N]` where `N` is the one being defined
+                let error = if length == location {
+                    error
+                } else {
+                    error.with_secondary(length, "the length which needs it")
+                };
+                self.error(error);
+                self.cycles += 1;
+                false
+            }
+            None => true,
+        }
+    }
+
+    // lay out the struct or union of the type, and the ones it contains,
+    // for `sizeof` in a constant expression.
+    fn lay_out(&mut self, type_: TypeId) {
+        let id = self.table.value_type(type_);
+        if self.define_contained(id, &mut HashSet::new()) {
+            for error in self.table.compute_layout(id) {
+                self.error(error);
+            }
+        }
+    }
+
+    // define the struct or union `id` and the ones it contains by value,
+    // false if one of them is being defined, so has no members yet.
+    fn define_contained(&mut self, id: TypeId, seen: &mut HashSet<TypeId>) -> bool {
+        let definition = match self.composites.iter().find(|&(_, &c)| c == id) {
+            Some((&definition, _)) => definition,
+            None => return true, // not a struct or union
+        };
+        if !seen.insert(id) {
+            return true;
+        }
+        self.define(definition);
+        if self.demands.contains(&Demand::Definition(definition)) {
+            return false;
+        }
+        let members: Vec<TypeId> = self.table.members(id).unwrap_or(&[])
+                                       .iter().map(|member| member.type_).collect();
+        members.into_iter().all(|member| {
+            let member = self.table.value_type(member);
+            self.define_contained(member, seen)
+        })
+    }
+
+    // evaluate the constant expression, of which `context` must be one.
+    fn evaluate(&mut self, expr: &mut Box<Node>, context: &str) -> Option<Constant> {
+        let cycles = self.cycles;
+        // the types of the casts and `sizeof`, and the enumerators
+        self.visit_node(expr);
+        if self.cycles > cycles {
+            return None; // reported
+        }
+        let result = ConstantEvaluator::new(self.table, self.entities).evaluate(&**expr);
+        match result {
+            Ok(constant) => Some(constant),
+            Err(error) => {
+                let error = *error;
+                let error = if error.kind() == SemanticErrorKind::NotConstant {
+                    error.with_note(format!("{} must be a constant", context))
+                } else {
+                    error
+                };
+                self.semantic_error(error);
+                None
+            }
+        }
+    }
+
+    // define the typedef names the type uses and evaluate the lengths of
+    // its arrays, as `int[N]`, false if one of them is being defined.
+    fn prepare(&mut self, typeref: &TypeRef, location: Location) -> bool {
+        match *typeref {
+            TypeRef::Pointer(ref pointer) => self.prepare(&pointer.base_type, location),
+            TypeRef::Const(ref const_) => self.prepare(&const_.base_type, location),
+            TypeRef::Function(ref function) => {
+                let mut is_prepared = self.prepare(&function.return_type, location);
+                for param in &function.params.types {
+                    is_prepared &= self.prepare(param, location);
+                }
+                is_prepared
+            }
+            TypeRef::Array(ref array) => {
+                let is_prepared = self.prepare(&array.base_type, location);
+                if let Some(ArrayLength::Constant(id)) = array.length {
+                    self.evaluate_length(id);
+                }
+                is_prepared
+            }
+            TypeRef::User(ref user) => match self.typedef_names.get(&user.name) {
+                Some(&i) => self.demand(Definition::Typedef(i), &user.name, location),
+                None => true,
+            },
+            _ => true,
+        }
+    }
+
+    fn evaluate_length(&mut self, id: ConstantId) {
+        if self.table.array_length(id).is_some() {
+            return; // as `int[N] a, b;`
+        }
+        // being evaluated, which the `TypeTable` reports
+        let mut expr = match self.constants[id.0].take() {
+            Some(expr) => expr,
+            None => return,
+        };
+        let location = expr.location();
+        self.demands.push(Demand::Length(location));
+        let length = self.evaluate(&mut expr, "the length of an array");
+        self.demands.pop();
+        self.constants[id.0] = Some(expr);
+
+        // not to report the errors of the type again, the length is 0 if it
+        // has an error
+        let length = match length {
+            Some(length) if length.type_.is_signed() && length.value < 0 => {
+                let error = SemanticError::new(SemanticErrorKind::InvalidArrayLength,
+                    location, format!("the array length `{}` is negative", length.value));
+                self.semantic_error(error);
+                0
+            }
+            Some(length) => length.value as usize,
+            None => 0,
+        };
+        self.table.set_array_length(id, length);
+    }
+
+    fn resolve_type(&mut self, type_: &mut TypeNode) -> Option<TypeId> {
+        if type_.type_.is_none() && self.prepare(&type_.type_ref, type_.location) {
+            match self.table.resolve(&type_.type_ref, type_.location) {
+                Ok(id) => type_.type_ = Some(id),
+                Err(err) => self.error(err),
+            }
+        }
+        type_.type_
+    }
+
+    fn error(&mut self, err: TypeError) {
+        self.handler.emit(err.to_diagnostic());
+    }
+
+    fn semantic_error(&mut self, error: SemanticError) {
+        self.handler.emit(error.to_diagnostic());
+    }
+}
+
+impl<'a> Visitor for TypeResolver<'a> {
+    fn visit_type(&mut self, type_: &mut TypeNode) {
+        self.resolve_type(type_);
+    }
+
+    fn visit_variable(&mut self, node: &mut VariableNode) {
+        // an enumerator in a constant expression, defined in a later enum
+        let toplevel = self.entities.toplevel();
+        if self.entities.lookup(toplevel, &node.name).is_none() {
+            if let Some(&i) = self.enumerator_names.get(&node.name) {
+                self.demand(Definition::Enum(i), &node.name, node.location);
+            }
+        }
+    }
+
+    fn visit_sizeof_type(&mut self, node: &mut SizeofTypeNode) {
+        visitor::walk_sizeof_type(self, node);
+        if let Some(type_) = node.type_.type_ {
+            self.lay_out(type_);
+        }
+    }
+
+    fn visit_defun(&mut self, func: &mut DefinedFunction) {
+        let mut is_resolved = true;
+        for param in &mut func.params.params {
+            is_resolved &= self.resolve_type(&mut param.type_).is_some();
+        }
+        // not to report the errors of the parameters twice
+        if is_resolved {
+            self.resolve_type(&mut func.type_);
+        }
+        self.visit_block(&mut func.body);
+    }
+
+    fn visit_funcdecl(&mut self, func: &mut UndefinedFunction) {
+        let mut is_resolved = true;
+        for param in &mut func.params.params {
+            is_resolved &= self.resolve_type(&mut param.type_).is_some();
+        }
+        if is_resolved {
+            self.resolve_type(&mut func.type_);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::compile;
+
+    #[test]
+    fn array_length_of_the_size_of_a_struct() {
+        let compiled = compile("struct b { int x; };\n\
+                                struct a { char[sizeof(struct b)] buf; };\n\
+                                char[sizeof(struct a)] global;\n\
+                                int main(void) { return sizeof(global); }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn enumerator_of_the_size_of_a_later_struct() {
+        let compiled = compile("enum { N = sizeof(struct q) };\n\
+                                struct q { long x; char c; };\n\
+                                char[N] buf;\n\
+                                int main(void) { return N; }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn enumerator_used_before_its_enum() {
+        let compiled = compile("typedef int[SIZE] row;\n\
+                                enum { SIZE = 4 };\n\
+                                row r;\n\
+                                int main(void) { return sizeof(row); }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn size_of_the_struct_being_defined() {
+        let compiled = compile("struct s { char[sizeof(struct s)] buf; };\n");
+        assert_eq!(compiled.errors(), ["E0060"]);
+    }
+
+    #[test]
+    fn typedef_depending_on_itself() {
+        let compiled = compile("typedef char[sizeof(struct packet)] raw_packet;\n\
+                                struct packet { int length; raw_packet data; };\n");
+        assert_eq!(compiled.errors(), ["E0096"]);
+    }
+
+    #[test]
+    fn enumerator_depending_on_itself() {
+        let compiled = compile("enum { N = sizeof(struct s) };\n\
+                                struct s { char[N] buf; };\n");
+        assert_eq!(compiled.errors(), ["E0096"]);
+    }
+
+    #[test]
+    fn enumerator_values() {
+        let compiled = compile("enum { A, B = 5, C, D = -1, E };\n\
+                                char[C - B] one;\n\
+                                char[E + 1] also_one;\n\
+                                int main(void) { return sizeof(one) + sizeof(also_one) + A; }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn invalid_enumerators() {
+        assert_eq!(compile("enum { A = 1, A = 2 };\n").errors(), ["E0062"]);
+        assert_eq!(compile("int n;\nenum { A = n };\n").errors(), ["E0077"]);
+    }
+
+    #[test]
+    fn negative_array_length() {
+        assert_eq!(compile("char[1 - 2] buf;\n").errors(), ["E0079"]);
+    }
+}
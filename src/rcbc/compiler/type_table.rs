@@ -0,0 +1,1153 @@
+//! The table of all the types of a compilation unit.
+//!
+//! Every `TypeRef` written in the source is resolved into a canonical
+//! `Type` here, identified by a `TypeId`. The same type always has the same
+//! id: the derived types (pointers, arrays and functions) are made once for
+//! each combination, and a struct, union or typedef is made once by its
+//! definition.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use super::location::Location;
+use super::type_::*;
+use super::diagnostic::Diagnostic;
+use super::error_codes::ErrorCode;
+use super::target::{Target, DataLayout};
+use super::suggestion;
+
+type Result<T> = ::std::result::Result<T, TypeError>;
+
+pub struct TypeTable {
+    target: Target,
+    types: Vec<Type>,
+    derived: HashMap<DerivedKey, TypeId>,
+    integers: HashMap<IntegerTypeRef, TypeId>,
+    void: TypeId,
+    structs: HashMap<String, TypeId>,
+    unions: HashMap<String, TypeId>,
+    typedefs: HashMap<String, TypeId>,
+    enums: HashMap<String, Location>, // an enum is an `int`
+    array_lengths: HashMap<ConstantId, usize>, // by `TypeResolver`
+    laid_out: HashSet<TypeId>, // the structs and unions, even incomplete
+    incomplete: HashSet<TypeId>, // declared but not defined
+}
+
+// How a derived type is made from other types, for hash-consing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum DerivedKey {
+    Pointer(TypeId),
+    Array(TypeId, Option<usize>),
+    Function(TypeId, Vec<TypeId>, bool),
+    Const(TypeId),
+}
+
+#[derive(Debug)]
+pub struct TypeError {
+    kind: TypeErrorKind,
+    location: Location,
+    message: String,
+    secondaries: Vec<(Location, String)>, // other locations to show
+    help: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeErrorKind {
+    UndefinedStruct,
+    UndefinedUnion,
+    UndefinedType,
+    DuplicatedType,
+    RecursiveType,
+    InvalidArrayElement,
+    InvalidReturnType,
+    IncompleteMember,
+    DuplicatedMember,
+    UndefinedEnum,
+    RecursiveArrayLength,
+}
+
+impl TypeTable {
+    /// The table with the sizes of the types on the target.
+    pub fn new(target: &Target) -> TypeTable {
+        let mut table = TypeTable {
+            target: *target,
+            types: Vec::new(),
+            derived: HashMap::new(),
+            integers: HashMap::new(),
+            void: TypeId(0),
+            structs: HashMap::new(),
+            unions: HashMap::new(),
+            typedefs: HashMap::new(),
+            enums: HashMap::new(),
+            array_lengths: HashMap::new(),
+            laid_out: HashSet::new(),
+            incomplete: HashSet::new(),
+        };
+        table.void = table.add(Type::Void(VoidType {}));
+        for &integer in &[IntegerTypeRef::Char, IntegerTypeRef::Short,
+                          IntegerTypeRef::Int, IntegerTypeRef::Long,
+                          IntegerTypeRef::UnsignedChar,
+                          IntegerTypeRef::UnsignedShort,
+                          IntegerTypeRef::UnsignedInt,
+                          IntegerTypeRef::UnsignedLong] {
+            let layout = table.integer_layout(integer);
+            let type_ = Type::Integer(IntegerType {
+                size: layout.size,
+                alignment: layout.alignment,
+                is_signed: integer.is_signed(),
+                name: integer.name(),
+            });
+            let id = table.add(type_);
+            table.integers.insert(integer, id);
+        }
+        table
+    }
+
+    fn integer_layout(&self, integer: IntegerTypeRef) -> DataLayout {
+        match integer {
+            IntegerTypeRef::Char | IntegerTypeRef::UnsignedChar => self.target.char_,
+            IntegerTypeRef::Short | IntegerTypeRef::UnsignedShort => self.target.short,
+            IntegerTypeRef::Int | IntegerTypeRef::UnsignedInt => self.target.int,
+            IntegerTypeRef::Long | IntegerTypeRef::UnsignedLong => self.target.long,
+        }
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn add(&mut self, type_: Type) -> TypeId {
+        self.types.push(type_);
+        TypeId(self.types.len() - 1)
+    }
+
+    pub fn get(&self, id: TypeId) -> &Type {
+        &self.types[id.0]
+    }
+
+    pub fn void(&self) -> TypeId {
+        self.void
+    }
+
+    pub fn integer(&self, integer: IntegerTypeRef) -> TypeId {
+        self.integers[&integer]
+    }
+
+    pub fn pointer_to(&mut self, base_type: TypeId) -> TypeId {
+        let pointer = self.target.pointer;
+        self.derive(DerivedKey::Pointer(base_type), Type::Pointer(PointerType {
+            size: pointer.size,
+            alignment: pointer.alignment,
+            base_type: base_type,
+        }))
+    }
+
+    pub fn array_of(&mut self, base_type: TypeId, length: Option<usize>) -> TypeId {
+        let pointer_size = self.target.pointer.size;
+        self.derive(DerivedKey::Array(base_type, length), Type::Array(ArrayType {
+            base_type: base_type,
+            length: length,
+            pointer_size: pointer_size,
+        }))
+    }
+
+    pub fn function(&mut self, return_type: TypeId, param_types: Vec<TypeId>,
+                    is_variadic: bool) -> TypeId {
+        let key = DerivedKey::Function(return_type, param_types.clone(), is_variadic);
+        self.derive(key, Type::Function(FunctionType {
+            return_type: return_type,
+            param_types: param_types,
+            is_variadic: is_variadic,
+        }))
+    }
+
+    pub fn const_of(&mut self, base_type: TypeId) -> TypeId {
+        if let Type::Const(_) = *self.get(base_type) {
+            return base_type; // `const const int` is `const int`
+        }
+        self.derive(DerivedKey::Const(base_type), Type::Const(ConstType {
+            base_type: base_type,
+        }))
+    }
+
+    fn derive(&mut self, key: DerivedKey, type_: Type) -> TypeId {
+        if let Some(&id) = self.derived.get(&key) {
+            return id;
+        }
+        let id = self.add(type_);
+        self.derived.insert(key, id);
+        id
+    }
+
+    /// Define `struct name` without its members, which are set by
+    /// `set_members` after they are resolved, so a member can point to the
+    /// struct itself.
+    pub fn define_struct(&mut self, name: &str, location: Location) -> Result<TypeId> {
+        if let Some(&id) = self.structs.get(name) {
+            return Err(self.duplicated(id, format!("struct {}", name), location));
+        }
+        let id = self.add(Type::Struct(StructType {
+            name: name.to_string(),
+            members: Vec::new(),
+            location: location,
+            layout: None,
+        }));
+        self.structs.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    /// Define `union name`, like `define_struct`.
+    pub fn define_union(&mut self, name: &str, location: Location) -> Result<TypeId> {
+        if let Some(&id) = self.unions.get(name) {
+            return Err(self.duplicated(id, format!("union {}", name), location));
+        }
+        let id = self.add(Type::Union(UnionType {
+            name: name.to_string(),
+            members: Vec::new(),
+            location: location,
+            layout: None,
+        }));
+        self.unions.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    pub fn define_typedef(&mut self, name: &str, real_type: TypeId,
+                          location: Location) -> Result<TypeId> {
+        if let Some(&id) = self.typedefs.get(name) {
+            return Err(self.duplicated(id, format!("type {}", name), location));
+        }
+        let id = self.add(Type::User(UserType {
+            name: name.to_string(),
+            real_type: real_type,
+            location: location,
+        }));
+        self.typedefs.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    /// Define `enum name`, whose values are of `int`.
+    pub fn define_enum(&mut self, name: &str, location: Location) -> Result<()> {
+        if let Some(&first) = self.enums.get(name) {
+            return Err(TypeError::new(TypeErrorKind::DuplicatedType, location,
+                                      format!("`enum {}` is defined more than once",
+                                              name))
+                           .with_secondary(first, "first defined here"));
+        }
+        self.enums.insert(name.to_string(), location);
+        Ok(())
+    }
+
+    /// Set the value of the constant expression of an array length, which
+    /// must be set before the array type is resolved.
+    pub fn set_array_length(&mut self, id: ConstantId, length: usize) {
+        self.array_lengths.insert(id, length);
+    }
+
+    pub fn array_length(&self, id: ConstantId) -> Option<usize> {
+        self.array_lengths.get(&id).cloned()
+    }
+
+    pub fn set_members(&mut self, id: TypeId, members: Vec<Member>) {
+        match self.types[id.0] {
+            Type::Struct(ref mut struct_) => struct_.members = members,
+            Type::Union(ref mut union) => union.members = members,
+            _ => panic!("set members to a non-composite type"),
+        }
+    }
+
+    /// Leave the struct or union without members and without a layout, as
+    /// it is declared but not defined.
+    pub fn set_incomplete(&mut self, id: TypeId) {
+        self.incomplete.insert(id);
+        self.laid_out.insert(id);
+    }
+
+    fn duplicated(&self, id: TypeId, name: String, location: Location) -> TypeError {
+        TypeError::new(TypeErrorKind::DuplicatedType, location,
+                       format!("`{}` is defined more than once", name))
+            .with_secondary(self.location(id).unwrap_or_default(),
+                            "first defined here")
+    }
+
+    /// Resolve the type written at `location` into the canonical type.
+    pub fn resolve(&mut self, typeref: &TypeRef, location: Location) -> Result<TypeId> {
+        match *typeref {
+            TypeRef::Void => Ok(self.void),
+            TypeRef::Integer(integer) => Ok(self.integer(integer)),
+            TypeRef::Struct(ref struct_) => {
+                self.structs.get(&struct_.name).cloned().ok_or_else(|| {
+                    undefined(TypeErrorKind::UndefinedStruct, location,
+                              format!("undefined struct `{}`", struct_.name),
+                              &struct_.name, self.structs.keys())
+                })
+            }
+            TypeRef::Union(ref union) => {
+                self.unions.get(&union.name).cloned().ok_or_else(|| {
+                    undefined(TypeErrorKind::UndefinedUnion, location,
+                              format!("undefined union `{}`", union.name),
+                              &union.name, self.unions.keys())
+                })
+            }
+            TypeRef::Enum(ref enum_) => {
+                if !self.enums.contains_key(&enum_.name) {
+                    return Err(undefined(TypeErrorKind::UndefinedEnum, location,
+                                         format!("undefined enum `{}`", enum_.name),
+                                         &enum_.name, self.enums.keys()));
+                }
+                Ok(self.integer(IntegerTypeRef::Int))
+            }
+            TypeRef::User(ref user) => {
+                self.typedefs.get(&user.name).cloned().ok_or_else(|| {
+                    undefined(TypeErrorKind::UndefinedType, location,
+                              format!("undefined type `{}`", user.name),
+                              &user.name, self.typedefs.keys())
+                })
+            }
+            TypeRef::Pointer(ref pointer) => {
+                let base_type = self.resolve(&pointer.base_type, location)?;
+                Ok(self.pointer_to(base_type))
+            }
+            TypeRef::Array(ref array) => {
+                let base_type = self.resolve(&array.base_type, location)?;
+                match *self.get(self.real_type(base_type)) {
+                    Type::Void(_) | Type::Function(_) => {
+                        return Err(TypeError::new(
+                            TypeErrorKind::InvalidArrayElement, location,
+                            format!("array of `{}` is not allowed",
+                                    self.type_name(base_type))));
+                    }
+                    _ => {}
+                }
+                let length = match array.length {
+                    Some(ArrayLength::Fixed(length)) => Some(length),
+                    Some(ArrayLength::Constant(id)) => match self.array_length(id) {
+                        Some(length) => Some(length),
+                        // the `TypeResolver` is still evaluating it
+                        None => return Err(TypeError::new(
+                            TypeErrorKind::RecursiveArrayLength, location,
+                            "the length of the array depends on the array itself"
+                                .to_string())),
+                    },
+                    None => None,
+                };
+                Ok(self.array_of(base_type, length))
+            }
+            TypeRef::Function(ref function) => {
+                let return_type = self.resolve(&function.return_type, location)?;
+                match *self.get(self.real_type(return_type)) {
+                    Type::Array(_) | Type::Function(_) => {
+                        return Err(TypeError::new(
+                            TypeErrorKind::InvalidReturnType, location,
+                            format!("function returning `{}` is not allowed",
+                                    self.type_name(return_type))));
+                    }
+                    _ => {}
+                }
+                // an array or a function parameter is a pointer
+                let mut param_types = Vec::new();
+                for param in &function.params.types {
+                    let param_type = self.resolve(param, location)?;
+                    param_types.push(self.decay(param_type));
+                }
+                Ok(self.function(return_type, param_types,
+                                 function.params.is_variadic))
+            }
+            TypeRef::Const(ref const_) => {
+                let base_type = self.resolve(&const_.base_type, location)?;
+                Ok(self.const_of(base_type))
+            }
+        }
+    }
+
+    /// the type under the typedef names and `const`.
+    pub fn real_type(&self, id: TypeId) -> TypeId {
+        match *self.get(id) {
+            Type::User(ref user) => self.real_type(user.real_type),
+            Type::Const(ref const_) => self.real_type(const_.base_type),
+            _ => id,
+        }
+    }
+
+    /// whether the objects of the type are `const`, which cannot be assigned.
+    pub fn is_const(&self, id: TypeId) -> bool {
+        match *self.get(id) {
+            Type::User(ref user) => self.is_const(user.real_type),
+            Type::Const(_) => true,
+            _ => false,
+        }
+    }
+
+    /// the size in bytes, `None` for the types without a size, such as
+    /// `void`, functions, and arrays without a length.
+    pub fn size(&self, id: TypeId) -> Option<usize> {
+        match *self.get(id) {
+            Type::Void(_) | Type::Function(_) => None,
+            Type::Integer(ref integer) => Some(integer.size),
+            Type::Pointer(ref pointer) => Some(pointer.size),
+            Type::Array(ref array) => {
+                let length = array.length?;
+                self.size(array.base_type).map(|size| size * length)
+            }
+            Type::Struct(ref struct_) => struct_.layout.map(|l| l.size),
+            Type::Union(ref union) => union.layout.map(|l| l.size),
+            Type::User(ref user) => self.size(user.real_type),
+            Type::Const(ref const_) => self.size(const_.base_type),
+        }
+    }
+
+    pub fn alignment(&self, id: TypeId) -> Option<usize> {
+        match *self.get(id) {
+            Type::Void(_) | Type::Function(_) => None,
+            Type::Integer(ref integer) => Some(integer.alignment),
+            Type::Pointer(ref pointer) => Some(pointer.alignment),
+            Type::Array(ref array) => self.alignment(array.base_type),
+            Type::Struct(ref struct_) => struct_.layout.map(|l| l.alignment),
+            Type::Union(ref union) => union.layout.map(|l| l.alignment),
+            Type::User(ref user) => self.alignment(user.real_type),
+            Type::Const(ref const_) => self.alignment(const_.base_type),
+        }
+    }
+
+    pub fn is_signed(&self, id: TypeId) -> bool {
+        match *self.get(self.real_type(id)) {
+            Type::Integer(ref integer) => integer.is_signed,
+            _ => false,
+        }
+    }
+
+    pub fn is_void(&self, id: TypeId) -> bool {
+        match *self.get(self.real_type(id)) {
+            Type::Void(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_integer(&self, id: TypeId) -> bool {
+        match *self.get(self.real_type(id)) {
+            Type::Integer(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_pointer(&self, id: TypeId) -> bool {
+        match *self.get(self.real_type(id)) {
+            Type::Pointer(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_array(&self, id: TypeId) -> bool {
+        match *self.get(self.real_type(id)) {
+            Type::Array(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_function(&self, id: TypeId) -> bool {
+        match *self.get(self.real_type(id)) {
+            Type::Function(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_composite(&self, id: TypeId) -> bool {
+        self.members(id).is_some()
+    }
+
+    /// an integer or a pointer, which can be a condition.
+    pub fn is_scalar(&self, id: TypeId) -> bool {
+        self.is_integer(id) || self.is_pointer(id)
+    }
+
+    pub fn is_void_pointer(&self, id: TypeId) -> bool {
+        match self.base_type(id) {
+            Some(base_type) if self.is_pointer(id) => self.is_void(base_type),
+            _ => false,
+        }
+    }
+
+    /// whether the two types are the same under the typedef names. An
+    /// array without a length is the same as the arrays of any length.
+    pub fn is_compatible(&self, left: TypeId, right: TypeId) -> bool {
+        let left = self.real_type(left);
+        let right = self.real_type(right);
+        if left == right {
+            return true;
+        }
+        match (self.get(left), self.get(right)) {
+            (Type::Pointer(l), Type::Pointer(r)) =>
+                self.is_compatible(l.base_type, r.base_type),
+            (Type::Array(l), Type::Array(r)) =>
+                (l.length.is_none() || r.length.is_none() || l.length == r.length) &&
+                self.is_compatible(l.base_type, r.base_type),
+            (Type::Function(l), Type::Function(r)) =>
+                l.is_variadic == r.is_variadic &&
+                l.param_types.len() == r.param_types.len() &&
+                self.is_compatible(l.return_type, r.return_type) &&
+                l.param_types.iter().zip(&r.param_types)
+                                    .all(|(&l, &r)| self.is_compatible(l, r)),
+            _ => false,
+        }
+    }
+
+    /// whether a pointer of the type `from` assigned to a pointer of `to`
+    /// loses the `const` of what it points to, at any level, as `char*`
+    /// from `const char*`.
+    pub fn discards_const(&self, to: TypeId, from: TypeId) -> bool {
+        if !self.is_pointer(to) || !self.is_pointer(from) {
+            return false;
+        }
+        match (self.base_type(to), self.base_type(from)) {
+            (Some(to), Some(from)) =>
+                (self.is_const(from) && !self.is_const(to)) || self.discards_const(to, from),
+            _ => false,
+        }
+    }
+
+    /// the type pointed by a pointer, or the element type of an array.
+    pub fn base_type(&self, id: TypeId) -> Option<TypeId> {
+        match *self.get(self.real_type(id)) {
+            Type::Pointer(ref pointer) => Some(pointer.base_type),
+            Type::Array(ref array) => Some(array.base_type),
+            _ => None,
+        }
+    }
+
+    /// the function type of a function or a pointer to a function.
+    pub fn function_type(&self, id: TypeId) -> Option<&FunctionType> {
+        let id = match self.base_type(id) {
+            Some(base_type) if self.is_pointer(id) => base_type,
+            _ => id,
+        };
+        match *self.get(self.real_type(id)) {
+            Type::Function(ref function) => Some(function),
+            _ => None,
+        }
+    }
+
+    pub fn member(&self, id: TypeId, name: &str) -> Option<&Member> {
+        self.members(id)
+            .and_then(|members| members.iter().find(|m| m.name == name))
+    }
+
+    /// the type of an array or a function in an expression, which is a
+    /// pointer to the element or the function.
+    pub fn decay(&mut self, id: TypeId) -> TypeId {
+        match *self.get(self.real_type(id)) {
+            Type::Array(ref array) => {
+                let base_type = array.base_type;
+                self.pointer_to(base_type)
+            }
+            Type::Function(_) => self.pointer_to(id),
+            _ => id,
+        }
+    }
+
+    /// the integer promotion, which makes the integers smaller than `int`
+    /// an `int`.
+    pub fn integer_promotion(&self, id: TypeId) -> TypeId {
+        let int = self.integer(IntegerTypeRef::Int);
+        match *self.get(self.real_type(id)) {
+            Type::Integer(ref integer) if integer.size < self.target.int.size => int,
+            _ => id,
+        }
+    }
+
+    /// the usual arithmetic conversions, the common type of the integer
+    /// operands of a binary operator.
+    pub fn usual_arithmetic_conversion(&self, left: TypeId, right: TypeId) -> TypeId {
+        let left = self.real_type(self.integer_promotion(left));
+        let right = self.real_type(self.integer_promotion(right));
+        let (l, r) = match (self.integer_ref(left), self.integer_ref(right)) {
+            (Some(l), Some(r)) => (l, r),
+            _ => return left,
+        };
+        let (signed, unsigned) = if l.is_signed() == r.is_signed() {
+            return if l.rank() >= r.rank() { left } else { right };
+        } else if l.is_signed() {
+            (l, r)
+        } else {
+            (r, l)
+        };
+        if unsigned.rank() >= signed.rank() {
+            self.integer(unsigned)
+        } else if self.integer_layout(signed).size > self.integer_layout(unsigned).size {
+            self.integer(signed) // holds all the values of the unsigned
+        } else {
+            self.integer(signed.to_unsigned())
+        }
+    }
+
+    pub fn integer_ref(&self, id: TypeId) -> Option<IntegerTypeRef> {
+        self.integers.iter()
+                     .find(|&(_, &integer)| integer == id)
+                     .map(|(&integer, _)| integer)
+    }
+
+    /// where the struct, union or typedef is defined.
+    pub fn location(&self, id: TypeId) -> Option<Location> {
+        match *self.get(id) {
+            Type::Struct(ref struct_) => Some(struct_.location),
+            Type::Union(ref union) => Some(union.location),
+            Type::User(ref user) => Some(user.location),
+            _ => None,
+        }
+    }
+
+    pub fn members(&self, id: TypeId) -> Option<&[Member]> {
+        match *self.get(self.real_type(id)) {
+            Type::Struct(ref struct_) => Some(&struct_.members),
+            Type::Union(ref union) => Some(&union.members),
+            _ => None,
+        }
+    }
+
+    /// the type in C syntax, such as `int *[4]`.
+    pub fn type_name(&self, id: TypeId) -> String {
+        self.declare(id, "")
+    }
+
+    /// the C declaration of `name` with the type, such as `int (*f)(int)`.
+    pub fn declare(&self, id: TypeId, name: &str) -> String {
+        let declaration = match *self.get(id) {
+            Type::Void(_) => format!("void {}", name),
+            Type::Integer(ref integer) => format!("{} {}", integer.name, name),
+            Type::Struct(ref struct_) => format!("struct {} {}", struct_.name, name),
+            Type::Union(ref union) => format!("union {} {}", union.name, name),
+            Type::User(ref user) => format!("{} {}", user.name, name),
+            Type::Const(ref const_) => match *self.get(const_.base_type) {
+                // `int *const p`, the pointer itself is const
+                Type::Pointer(_) =>
+                    return self.declare(const_.base_type, &format!("const {}", name)),
+                _ => format!("const {}", self.declare(const_.base_type, name)),
+            },
+            Type::Pointer(ref pointer) => {
+                let declarator = match *self.get(pointer.base_type) {
+                    Type::Array(_) | Type::Function(_) => format!("(*{})", name),
+                    _ => format!("*{}", name),
+                };
+                return self.declare(pointer.base_type, &declarator);
+            }
+            Type::Array(ref array) => {
+                let declarator = match array.length {
+                    Some(length) => format!("{}[{}]", name, length),
+                    None => format!("{}[]", name),
+                };
+                return self.declare(array.base_type, &declarator);
+            }
+            Type::Function(ref function) => {
+                let mut params: Vec<String> = function.param_types
+                                                      .iter()
+                                                      .map(|&p| self.type_name(p))
+                                                      .collect();
+                if function.is_variadic {
+                    params.push("...".to_string());
+                }
+                if params.is_empty() {
+                    params.push("void".to_string());
+                }
+                let declarator = format!("{}({})", name, params.join(", "));
+                return self.declare(function.return_type, &declarator);
+            }
+        };
+        declaration.trim_end().to_string()
+    }
+
+    // the structs and unions in the order of their definitions.
+    fn composites(&self) -> Vec<TypeId> {
+        let mut composites: Vec<TypeId> = self.structs.values()
+                                              .chain(self.unions.values())
+                                              .cloned()
+                                              .collect();
+        composites.sort_by_key(|&id| self.location(id).map(|l| (l.file, l.begin)));
+        composites
+    }
+
+    /// Check the definitions of all the structs and unions, that none
+    /// contains itself by value. A cycle is reported once, at the first of
+    /// its structs and unions.
+    pub fn semantic_check(&self) -> Vec<TypeError> {
+        let mut errors = Vec::new();
+        let mut in_cycles = HashSet::new();
+        for id in self.composites() {
+            if in_cycles.contains(&id) {
+                continue;
+            }
+            let mut path = Vec::new();
+            if self.contains_itself(id, id, &mut path) {
+                in_cycles.extend(path.iter().map(|member| self.value_type(member.type_)));
+                errors.push(self.recursive_type_error(id, &path));
+            }
+        }
+        errors
+    }
+
+    // whether the struct or union `target` is in `id` by value, where `path`
+    // is the members leading to the `id`, and then to the `target`.
+    fn contains_itself<'a>(&'a self, target: TypeId, id: TypeId,
+                           path: &mut Vec<&'a Member>) -> bool {
+        if let Some(members) = self.members(id) {
+            let entered = &path[..path.len().saturating_sub(1)];
+            if entered.iter().any(|m| self.value_type(m.type_) == id) {
+                return false; // another cycle, reported by its own
+            }
+            for member in members {
+                path.push(member);
+                let member_type = self.value_type(member.type_);
+                if member_type == target ||
+                        self.contains_itself(target, member_type, path) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+
+    fn recursive_type_error(&self, id: TypeId, path: &[&Member]) -> TypeError {
+        let location = self.location(id).unwrap_or_default();
+        let mut error = TypeError::new(TypeErrorKind::RecursiveType, location,
+            format!("`{}` contains itself by value", self.type_name(id)));
+        for member in path {
+            let label = format!("contains `{}` by value",
+                                self.type_name(self.value_type(member.type_)));
+            error = error.with_secondary(member.location, &label);
+        }
+        let last = path[path.len() - 1];
+        let pointer = self.type_name(self.value_type(last.type_));
+        error.with_help(format!("refer to it by a pointer instead, \
+                                 as `{}* {};`", pointer, last.name))
+    }
+
+    /// Lay out the members of all the structs and unions, as the C compiler
+    /// does for the platform: every member is at the next offset aligned for
+    /// its type, and the size is rounded up to the largest alignment.
+    pub fn compute_layouts(&mut self) -> Vec<TypeError> {
+        let mut errors = Vec::new();
+        for id in self.composites() {
+            self.lay_out(id, &mut errors);
+        }
+        errors
+    }
+
+    /// Lay out the struct or union `id` before the others, as its size is
+    /// needed by a constant expression. The members of the ones it contains
+    /// by value must have been set.
+    pub fn compute_layout(&mut self, id: TypeId) -> Vec<TypeError> {
+        let mut errors = Vec::new();
+        self.lay_out(id, &mut errors);
+        errors
+    }
+
+    // each struct and union is laid out once, its errors reported once.
+    fn lay_out(&mut self, id: TypeId, errors: &mut Vec<TypeError>) {
+        let (members, is_union) = match *self.get(id) {
+            Type::Struct(ref struct_) => (struct_.members.clone(), false),
+            Type::Union(ref union) => (union.members.clone(), true),
+            _ => return,
+        };
+        if !self.laid_out.insert(id) {
+            return; // done, or contains itself, reported by `semantic_check`
+        }
+
+        let mut members = members;
+        let mut offset = 0;
+        let mut size = 0;
+        let mut alignment = 1;
+        let mut is_complete = true;
+        let mut names: HashMap<&str, Location> = HashMap::new();
+        for member in &mut members {
+            let value_type = self.value_type(member.type_);
+            self.lay_out(value_type, errors);
+
+            let (member_size, member_alignment) =
+                    match (self.size(member.type_), self.alignment(member.type_)) {
+                (Some(size), Some(alignment)) => (size, alignment),
+                _ => {
+                    // a broken struct or union is reported by its own
+                    if self.members(value_type).is_none()
+                            || self.incomplete.contains(&value_type) {
+                        errors.push(TypeError::new(
+                            TypeErrorKind::IncompleteMember, member.location,
+                            format!("member `{}` has incomplete type `{}`",
+                                    member.name, self.type_name(member.type_))));
+                    }
+                    is_complete = false;
+                    continue;
+                }
+            };
+            if is_union {
+                member.offset = 0;
+                size = size.max(member_size);
+            } else {
+                offset = align(offset, member_alignment);
+                member.offset = offset;
+                offset += member_size;
+                size = offset;
+            }
+            alignment = alignment.max(member_alignment);
+        }
+        for member in &members {
+            if let Some(&previous) = names.get(member.name.as_str()) {
+                errors.push(TypeError::new(
+                    TypeErrorKind::DuplicatedMember, member.location,
+                    format!("duplicated member `{}` in `{}`",
+                            member.name, self.type_name(id)))
+                    .with_secondary(previous, "first defined here"));
+            } else {
+                names.insert(&member.name, member.location);
+            }
+        }
+
+        if !is_complete {
+            return;
+        }
+        let layout = Some(Layout {
+            size: align(size, alignment),
+            alignment: alignment,
+        });
+        match self.types[id.0] {
+            Type::Struct(ref mut struct_) => {
+                struct_.members = members;
+                struct_.layout = layout;
+            }
+            Type::Union(ref mut union) => {
+                union.members = members;
+                union.layout = layout;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// The layouts of all the structs and unions, like the one of
+    /// `clang -fdump-record-layouts`, as
+    ///
+    /// ```text
+    /// *** Dumping Record Layout
+    ///          0 | struct point
+    ///          0 |   char tag
+    ///          1 |   (3 bytes of padding)
+    ///          4 |   int x
+    ///            | [sizeof=8, align=4]
+    /// ```
+    pub fn dump_record_layouts(&self) -> String {
+        let mut dump = String::new();
+        for id in self.composites() {
+            let layout = match *self.get(id) {
+                Type::Struct(ref struct_) => struct_.layout,
+                Type::Union(ref union) => union.layout,
+                _ => None,
+            };
+            if let Some(layout) = layout {
+                dump.push_str("\n*** Dumping Record Layout\n");
+                dump.push_str(&format!("{:>10} | {}\n", 0, self.type_name(id)));
+                self.dump_members(id, 0, 1, &mut dump);
+                dump.push_str(&format!("{:>10} | [sizeof={}, align={}]\n", "",
+                                       layout.size, layout.alignment));
+            }
+        }
+        dump
+    }
+
+    // the members of a struct or union at `base`, and the padding between,
+    // in which the members of a nested one are shown deeper.
+    fn dump_members(&self, id: TypeId, base: usize, depth: usize, dump: &mut String) {
+        let indent = "  ".repeat(depth);
+        let members = self.members(id).unwrap_or(&[]);
+        let mut end = 0;
+        for member in members {
+            if member.offset > end {
+                dump.push_str(&format!("{:>10} | {}({} bytes of padding)\n",
+                                       base + end, indent, member.offset - end));
+            }
+            dump.push_str(&format!("{:>10} | {}{}\n", base + member.offset, indent,
+                                   self.declare(member.type_, &member.name)));
+            if let Type::Struct(_) | Type::Union(_) = *self.get(self.real_type(member.type_)) {
+                self.dump_members(self.real_type(member.type_),
+                                  base + member.offset, depth + 1, dump);
+            }
+            end = end.max(member.offset + self.size(member.type_).unwrap_or(0));
+        }
+        let size = self.size(id).unwrap_or(0);
+        if size > end {
+            dump.push_str(&format!("{:>10} | {}({} bytes of padding)\n",
+                                   base + end, indent, size - end));
+        }
+    }
+
+    /// the struct or union which is stored in a value of the type, or the
+    /// real type of the others.
+    pub fn value_type(&self, id: TypeId) -> TypeId {
+        match *self.get(self.real_type(id)) {
+            Type::Array(ref array) => self.value_type(array.base_type),
+            _ => self.real_type(id),
+        }
+    }
+}
+
+// the error of an undefined name, suggesting the closest defined one.
+fn undefined<'a, I>(kind: TypeErrorKind, location: Location, message: String,
+                    name: &str, names: I) -> TypeError
+        where I: Iterator<Item = &'a String> {
+    let error = TypeError::new(kind, location, message);
+    match suggestion::suggest(name, names.map(|name| name.as_str())) {
+        Some(name) => error.with_help(suggestion::did_you_mean(name)),
+        None => error,
+    }
+}
+
+// round `offset` up to the multiple of `alignment`.
+fn align(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+impl TypeError {
+    pub fn new(kind: TypeErrorKind, location: Location, message: String) -> TypeError {
+        TypeError {
+            kind: kind,
+            location: location,
+            message: message,
+            secondaries: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_secondary(mut self, location: Location, label: &str) -> TypeError {
+        self.secondaries.push((location, label.to_string()));
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> TypeError {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn kind(&self) -> TypeErrorKind {
+        self.kind
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(self.message.clone())
+                                 .with_code(self.kind.code())
+                                 .with_primary(self.location, "");
+        for &(location, ref label) in &self.secondaries {
+            diagnostic = diagnostic.with_secondary(location, label.clone());
+        }
+        match self.help {
+            Some(ref help) => diagnostic.with_help(help.clone()),
+            None => diagnostic,
+        }
+    }
+}
+
+impl TypeErrorKind {
+    pub const ALL: &'static [TypeErrorKind] = &[
+        TypeErrorKind::UndefinedStruct,
+        TypeErrorKind::UndefinedUnion,
+        TypeErrorKind::UndefinedType,
+        TypeErrorKind::DuplicatedType,
+        TypeErrorKind::RecursiveType,
+        TypeErrorKind::InvalidArrayElement,
+        TypeErrorKind::InvalidReturnType,
+        TypeErrorKind::IncompleteMember,
+        TypeErrorKind::DuplicatedMember,
+        TypeErrorKind::UndefinedEnum,
+        TypeErrorKind::RecursiveArrayLength,
+    ];
+}
+
+impl ErrorCode for TypeErrorKind {
+    fn code(&self) -> &'static str {
+        match *self {
+            TypeErrorKind::UndefinedStruct => "E0050",
+            TypeErrorKind::UndefinedUnion => "E0051",
+            TypeErrorKind::UndefinedType => "E0052",
+            TypeErrorKind::DuplicatedType => "E0053",
+            TypeErrorKind::RecursiveType => "E0054",
+            TypeErrorKind::InvalidArrayElement => "E0055",
+            TypeErrorKind::InvalidReturnType => "E0056",
+            TypeErrorKind::IncompleteMember => "E0057",
+            TypeErrorKind::DuplicatedMember => "E0058",
+            TypeErrorKind::UndefinedEnum => "E0080",
+            TypeErrorKind::RecursiveArrayLength => "E0096",
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::testing::compile;
+
+    fn int() -> TypeRef {
+        TypeRef::Integer(IntegerTypeRef::Int)
+    }
+
+    // define the struct or union, after the ones defined before.
+    fn define(table: &mut TypeTable, name: &str, is_union: bool,
+              members: &[(&str, TypeId)]) -> TypeId {
+        let location = Location { begin: table.composites().len(), ..Location::default() };
+        let id = if is_union {
+            table.define_union(name, location).unwrap()
+        } else {
+            table.define_struct(name, location).unwrap()
+        };
+        let members = members.iter().map(|&(name, type_)| Member {
+            name: name.to_string(),
+            type_: type_,
+            location: location,
+            offset: 0,
+        }).collect();
+        table.set_members(id, members);
+        id
+    }
+
+    fn offsets(table: &TypeTable, id: TypeId) -> Vec<usize> {
+        table.members(id).unwrap().iter().map(|member| member.offset).collect()
+    }
+
+    #[test]
+    fn same_types_have_same_id() {
+        let mut table = TypeTable::new(&Target::i386());
+        let location = Location::default();
+        let p = table.resolve(&TypeRef::pointer(int()), location).unwrap();
+        let q = table.resolve(&TypeRef::pointer(int()), location).unwrap();
+        let a = table.resolve(&TypeRef::array(int(), Some(ArrayLength::Fixed(4))),
+                              location).unwrap();
+        assert_eq!(p, q);
+        assert_ne!(p, a);
+        assert_eq!(table.type_name(a), "int [4]");
+        assert_eq!(table.size(a), Some(16));
+    }
+
+    #[test]
+    fn unevaluated_array_length() {
+        let mut table = TypeTable::new(&Target::i386());
+        let array = TypeRef::array(int(), Some(ArrayLength::Constant(ConstantId(0))));
+        let error = table.resolve(&array, Location::default()).unwrap_err();
+        assert_eq!(error.kind(), TypeErrorKind::RecursiveArrayLength);
+        table.set_array_length(ConstantId(0), 3);
+        let id = table.resolve(&array, Location::default()).unwrap();
+        assert_eq!(table.size(id), Some(12));
+    }
+
+    #[test]
+    fn undefined_types() {
+        let compiled = compile("struct point { int x; };\n\
+                                struct pont p;\n\
+                                union u* q;\n\
+                                enum color c;\n");
+        assert_eq!(compiled.errors(), ["E0050", "E0051", "E0080"]);
+        assert!(compiled.diagnostics[0].helps[0].contains("point"));
+    }
+
+    #[test]
+    fn duplicated_types() {
+        let compiled = compile("struct s { int x; };\nstruct s { int y; };\n\
+                                typedef int t;\ntypedef long t;\n");
+        assert_eq!(compiled.errors(), ["E0053", "E0053"]);
+    }
+
+    #[test]
+    fn layouts() {
+        // the offsets, the size and the alignment of `t`
+        for &(target, t_offsets, t_size, t_alignment) in
+                &[(Target::i386(), [0, 4, 8, 20], 24, 4),
+                  (Target::x86_64(), [0, 8, 16, 32], 40, 8)] {
+            let mut table = TypeTable::new(&target);
+            let (char_, int) = (table.integer(IntegerTypeRef::Char),
+                                table.integer(IntegerTypeRef::Int));
+            let long_ = table.integer(IntegerTypeRef::Long);
+            let chars = table.resolve(&TypeRef::array(TypeRef::Integer(IntegerTypeRef::Char),
+                                                      Some(ArrayLength::Fixed(3))),
+                                      Location::default()).unwrap();
+            let s = define(&mut table, "s", false, &[("c", char_), ("i", int), ("d", char_)]);
+            let next = table.pointer_to(s);
+            let t = define(&mut table, "t", false, &[("c", char_), ("l", long_),
+                                                     ("s", s), ("next", next)]);
+            let u = define(&mut table, "u", true, &[("c", chars), ("i", int)]);
+            assert!(table.compute_layouts().is_empty());
+
+            assert_eq!(offsets(&table, s), [0, 4, 8]);
+            assert_eq!((table.size(s), table.alignment(s)), (Some(12), Some(4)));
+            assert_eq!(offsets(&table, t), t_offsets);
+            assert_eq!((table.size(t), table.alignment(t)), (Some(t_size), Some(t_alignment)));
+            assert_eq!(offsets(&table, u), [0, 0]);
+            assert_eq!((table.size(u), table.alignment(u)), (Some(4), Some(4)));
+        }
+    }
+
+    #[test]
+    fn dump_record_layouts() {
+        let mut table = TypeTable::new(&Target::i386());
+        let (char_, short, int) = (table.integer(IntegerTypeRef::Char),
+                                   table.integer(IntegerTypeRef::Short),
+                                   table.integer(IntegerTypeRef::Int));
+        let inner = define(&mut table, "inner", false, &[("a", short), ("b", char_)]);
+        define(&mut table, "outer", false, &[("tag", char_), ("in", inner), ("x", int)]);
+        define(&mut table, "value", true, &[("c", char_), ("i", int)]);
+        assert!(table.compute_layouts().is_empty());
+        assert_eq!(table.dump_record_layouts(), "
+*** Dumping Record Layout
+         0 | struct inner
+         0 |   short a
+         2 |   char b
+         3 |   (1 bytes of padding)
+           | [sizeof=4, align=2]
+
+*** Dumping Record Layout
+         0 | struct outer
+         0 |   char tag
+         1 |   (1 bytes of padding)
+         2 |   struct inner in
+         2 |     short a
+         4 |     char b
+         5 |     (1 bytes of padding)
+         6 |   (2 bytes of padding)
+         8 |   int x
+           | [sizeof=12, align=4]
+
+*** Dumping Record Layout
+         0 | union value
+         0 |   char c
+         0 |   int i
+           | [sizeof=4, align=4]
+");
+    }
+
+    #[test]
+    fn recursive_types() {
+        assert_eq!(compile("struct s { int n; struct s next; };\n").errors(), ["E0054"]);
+        // a cycle is reported once, not by each of its structs
+        let compiled = compile("struct a { struct b x; };\n\
+                                struct b { struct a[2] y; };\n\
+                                union c { struct a z; };\n");
+        assert_eq!(compiled.errors(), ["E0054"]);
+        assert!(compiled.has_message("`struct a` contains itself by value"));
+        compile("struct a { struct b* x; };\nstruct b { struct a y; };\n").assert_clean();
+    }
+
+    #[test]
+    fn duplicated_members() {
+        assert_eq!(compile("struct s { int x; char y; long x; };\n").errors(), ["E0058"]);
+        assert_eq!(compile("union u { int x; char x; };\n").errors(), ["E0058"]);
+        compile("struct s { int x; };\nunion u { int x; };\n").assert_clean();
+    }
+
+    #[test]
+    fn invalid_derived_types() {
+        assert_eq!(compile("void[4] a;\n").errors(), ["E0055"]);
+        assert_eq!(compile("typedef int[4] ints;\nints f(void) { }\n").errors(),
+                   ["E0056"]);
+    }
+}
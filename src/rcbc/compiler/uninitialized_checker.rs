@@ -0,0 +1,409 @@
+//! The pass warning the reads of the local variables which may have no
+//! value yet.
+//!
+//! The analysis follows the control flow of each function body, with the set
+//! of the local variables definitely assigned at every point. Where the
+//! control flows join, after an `if`, a loop, a `switch` or at a label, the
+//! set is the intersection of the incoming ones. An assignment never undoes
+//! another one, so the set at the head of a loop is the one before it. A
+//! `goto` can jump backwards, so a function is analyzed again until the sets
+//! at its labels do not change.
+//!
+//! Only the scalar variables without initializer are followed. Taking the
+//! address of a variable counts as assigning it, as it may be assigned
+//! through the pointer.
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use super::ast::*;
+use super::location::Location;
+use super::type_table::TypeTable;
+use super::entity::{EntityId, EntityTable};
+use super::constant_evaluator::ConstantEvaluator;
+use super::diagnostic::{Diagnostic, DiagnosticHandler};
+use super::warning::Warning;
+use super::visitor::Visitor;
+
+// the variables definitely assigned, `None` where the control can not reach.
+type State = Option<HashSet<EntityId>>;
+
+pub struct UninitializedChecker<'a> {
+    entities: &'a EntityTable,
+    table: &'a TypeTable,
+    handler: &'a mut DiagnosticHandler,
+    followed: HashSet<EntityId>, // the variables declared without a value
+    labels: HashMap<String, State>, // by the `goto`s of the last analysis
+    gotos: HashMap<String, State>, // by the `goto`s of this analysis
+    jumps: Vec<Jumps>, // of the loops and the `switch`es around
+    uses: Vec<(EntityId, Location)>, // the reads without a value
+}
+
+// the states at the jumps out of a loop or a `switch`.
+struct Jumps {
+    is_loop: bool,
+    breaks: State,
+    continues: State,
+}
+
+// the state where the control comes from either of the states.
+fn join(left: State, right: State) -> State {
+    match (left, right) {
+        (Some(left), Some(right)) => Some(left.intersection(&right).cloned().collect()),
+        (None, state) | (state, None) => state,
+    }
+}
+
+impl<'a> UninitializedChecker<'a> {
+    pub fn new(entities: &'a EntityTable, table: &'a TypeTable,
+               handler: &'a mut DiagnosticHandler) -> UninitializedChecker<'a> {
+        UninitializedChecker {
+            entities: entities,
+            table: table,
+            handler: handler,
+            followed: HashSet::new(),
+            labels: HashMap::new(),
+            gotos: HashMap::new(),
+            jumps: Vec::new(),
+            uses: Vec::new(),
+        }
+    }
+
+    /// Check all the functions, the warnings are reported to the handler.
+    pub fn check(&mut self, ast: &mut AST) {
+        self.visit_ast(ast);
+    }
+
+    fn check_function(&mut self, func: &DefinedFunction) {
+        self.labels.clear();
+        loop {
+            self.uses.clear();
+            self.block(&func.body, Some(HashSet::new()));
+            let gotos = mem::take(&mut self.gotos);
+            if gotos == self.labels {
+                break;
+            }
+            self.labels = gotos;
+        }
+        self.warn_uses();
+    }
+
+    fn warn_uses(&mut self) {
+        let mut warned = HashSet::new();
+        for (entity, location) in mem::take(&mut self.uses) {
+            if !warned.insert(entity) {
+                continue; // only the first read of each variable
+            }
+            let entity = self.entities.get(entity);
+            self.handler.warn(Warning::Uninitialized,
+                Diagnostic::warning(format!("`{}` may be used uninitialized",
+                                            entity.name))
+                    .with_primary(location, "")
+                    .with_secondary(entity.location,
+                                    format!("`{}` is declared here without a value",
+                                            entity.name)));
+        }
+    }
+
+    fn assign(&mut self, entity: Option<EntityId>, state: &mut State) {
+        if let (Some(entity), &mut Some(ref mut assigned)) = (entity, state) {
+            assigned.insert(entity);
+        }
+    }
+
+    fn read(&mut self, node: &VariableNode, state: &State) {
+        if let (Some(entity), Some(assigned)) = (node.entity, state) {
+            if self.followed.contains(&entity) && !assigned.contains(&entity) {
+                self.uses.push((entity, node.location));
+            }
+        }
+    }
+
+    // the state after the statement, from the state before it.
+    fn stmt(&mut self, node: &Node, state: State) -> State {
+        match node.node_ref() {
+            NodeRef::BlockNode(node) => self.block(node, state),
+            NodeRef::ExprStmtNode(node) => self.expr(&*node.expr, state),
+            NodeRef::IfNode(node) => {
+                let state = self.expr(&*node.condition, state);
+                let then_clause = self.stmt(&*node.then_clause, state.clone());
+                let else_clause = match node.else_clause {
+                    Some(ref else_clause) => self.stmt(&**else_clause, state),
+                    None => state,
+                };
+                join(then_clause, else_clause)
+            }
+            NodeRef::WhileNode(node) => {
+                let state = self.expr(&*node.condition, state);
+                let is_infinite = self.is_always_true(Some(&*node.condition));
+                let jumps = self.in_loop(true, |this| {
+                    this.stmt(&*node.body, state.clone());
+                });
+                join(if is_infinite { None } else { state }, jumps.breaks)
+            }
+            NodeRef::DoWhileNode(node) => {
+                let mut end = None;
+                let jumps = self.in_loop(true, |this| end = this.stmt(&*node.body, state));
+                let state = self.expr(&*node.condition, join(end, jumps.continues));
+                let is_infinite = self.is_always_true(Some(&*node.condition));
+                join(if is_infinite { None } else { state }, jumps.breaks)
+            }
+            NodeRef::ForNode(node) => {
+                let mut state = state;
+                if let Some(ref init_expr) = node.init_expr {
+                    state = self.expr(&**init_expr, state);
+                }
+                if let Some(ref cond_expr) = node.cond_expr {
+                    state = self.expr(&**cond_expr, state);
+                }
+                let mut end = None;
+                let jumps = self.in_loop(true, |this| {
+                    end = this.stmt(&*node.body, state.clone());
+                });
+                if let Some(ref step_expr) = node.step_expr {
+                    self.expr(&**step_expr, join(end, jumps.continues));
+                }
+                let is_infinite = self.is_always_true(node.cond_expr.as_deref());
+                join(if is_infinite { None } else { state }, jumps.breaks)
+            }
+            NodeRef::SwitchNode(node) => {
+                let state = self.expr(&*node.expr, state);
+                let mut end = state.clone(); // without any clause taken
+                let jumps = self.in_loop(false, |this| {
+                    if let Some(ref cases) = node.cases {
+                        end = this.cases(&**cases, state);
+                    }
+                });
+                join(end, jumps.breaks)
+            }
+            NodeRef::BreakNode(_) => {
+                if let Some(jumps) = self.jumps.last_mut() {
+                    jumps.breaks = join(jumps.breaks.take(), state);
+                }
+                None
+            }
+            NodeRef::ContinueNode(_) => {
+                if let Some(jumps) = self.jumps.iter_mut().rev().find(|j| j.is_loop) {
+                    jumps.continues = join(jumps.continues.take(), state);
+                }
+                None
+            }
+            NodeRef::GotoNode(node) => {
+                let target = self.gotos.remove(&node.label).unwrap_or(None);
+                self.gotos.insert(node.label.clone(), join(target, state));
+                None
+            }
+            NodeRef::ReturnNode(node) => {
+                if let Some(ref expr) = node.expr {
+                    self.expr(&**expr, state);
+                }
+                None
+            }
+            NodeRef::LabelNode(node) => {
+                let gotos = self.labels.get(&node.label).cloned().unwrap_or(None);
+                let state = join(state, gotos);
+                self.stmt(&*node.stmt, state)
+            }
+            _ => state,
+        }
+    }
+
+    fn block(&mut self, node: &BlockNode, state: State) -> State {
+        let mut state = state;
+        for var in &node.variables {
+            let is_followed = var.initializer.is_none() && !var.is_private &&
+                var.type_.type_.is_some_and(|type_| self.table.is_scalar(type_));
+            if let Some(ref initializer) = var.initializer {
+                state = self.expr(&**initializer, state);
+            }
+            if let Some(entity) = var.entity {
+                if is_followed {
+                    self.followed.insert(entity);
+                    // no value again, when the block is entered by a loop
+                    if let Some(ref mut assigned) = state {
+                        assigned.remove(&entity);
+                    }
+                } else {
+                    self.assign(Some(entity), &mut state);
+                }
+            }
+        }
+        for stmt in &node.stmts {
+            state = self.stmt(&**stmt, state);
+        }
+        state
+    }
+
+    // the clauses of a `switch`, where the control falls through them.
+    fn cases(&mut self, node: &Node, state: State) -> State {
+        let cases = match node.node_ref() {
+            NodeRef::CasesNode(cases) => cases,
+            _ => return state,
+        };
+        let mut end = None;
+        for clause in cases.normal_cases.iter().chain(&cases.default_case) {
+            let stmts = match clause.node_ref() {
+                NodeRef::CaseNode(case) => &case.stmt,
+                NodeRef::DefaultCaseNode(default) => &default.stmt,
+                _ => continue,
+            };
+            let mut clause_state = join(state.clone(), end);
+            for stmt in stmts {
+                clause_state = self.stmt(&**stmt, clause_state);
+            }
+            end = clause_state;
+        }
+        // without `default`, no clause may be taken
+        if cases.default_case.is_none() {
+            end = join(end, state);
+        }
+        end
+    }
+
+    fn in_loop<F: FnOnce(&mut Self)>(&mut self, is_loop: bool, f: F) -> Jumps {
+        self.jumps.push(Jumps {
+            is_loop: is_loop,
+            breaks: None,
+            continues: None,
+        });
+        f(self);
+        self.jumps.pop().unwrap()
+    }
+
+    // whether the condition is a constant other than 0, where a missing one
+    // of `for` is true.
+    fn is_always_true(&self, condition: Option<&Node>) -> bool {
+        match condition {
+            Some(condition) => ConstantEvaluator::new(self.table, self.entities)
+                                   .evaluate(condition)
+                                   .is_ok_and(|constant| constant.value != 0),
+            None => true,
+        }
+    }
+
+    // the state after the expression is evaluated.
+    fn expr(&mut self, node: &Node, state: State) -> State {
+        match node.node_ref() {
+            NodeRef::VariableNode(node) => {
+                self.read(node, &state);
+                state
+            }
+            NodeRef::AssignNode(node) => {
+                match node.lhs.node_ref() {
+                    NodeRef::VariableNode(var) => {
+                        let mut state = self.expr(&*node.rhs, state);
+                        self.assign(var.entity, &mut state);
+                        state
+                    }
+                    _ => {
+                        let state = self.expr(&*node.lhs, state);
+                        self.expr(&*node.rhs, state)
+                    }
+                }
+            }
+            NodeRef::AddressNode(node) => {
+                match node.node.node_ref() {
+                    NodeRef::VariableNode(var) => {
+                        let mut state = state;
+                        self.assign(var.entity, &mut state);
+                        state
+                    }
+                    _ => self.expr(&*node.node, state),
+                }
+            }
+            NodeRef::LogicalAndNode(node) => {
+                let state = self.expr(&*node.left, state);
+                let right = self.expr(&*node.right, state.clone());
+                join(state, right)
+            }
+            NodeRef::LogicalOrNode(node) => {
+                let state = self.expr(&*node.left, state);
+                let right = self.expr(&*node.right, state.clone());
+                join(state, right)
+            }
+            NodeRef::CondExprNode(node) => {
+                let state = self.expr(&*node.condition, state);
+                let then_clause = self.expr(&*node.then_clause, state.clone());
+                let else_clause = self.expr(&*node.else_clause, state);
+                join(then_clause, else_clause)
+            }
+            NodeRef::OpAssignNode(node) => {
+                let state = self.expr(&*node.lhs, state);
+                self.expr(&*node.rhs, state)
+            }
+            NodeRef::BinaryOpNode(node) => {
+                let state = self.expr(&*node.left, state);
+                self.expr(&*node.right, state)
+            }
+            NodeRef::FuncallNode(node) => {
+                let mut state = self.expr(&*node.expr, state);
+                for arg in &node.args {
+                    state = self.expr(&**arg, state);
+                }
+                state
+            }
+            NodeRef::ArefNode(node) => {
+                let state = self.expr(&*node.expr, state);
+                self.expr(&*node.idx, state)
+            }
+            NodeRef::UnaryOpNode(node) => self.expr(&*node.node, state),
+            NodeRef::PrefixOpNode(node) => self.expr(&*node.node, state),
+            NodeRef::SuffixOpNode(node) => self.expr(&*node.expr, state),
+            NodeRef::DereferenceNode(node) => self.expr(&*node.node, state),
+            NodeRef::CastNode(node) => self.expr(&*node.node, state),
+            NodeRef::MemberNode(node) => self.expr(&*node.expr, state),
+            NodeRef::PtrMemberNode(node) => self.expr(&*node.expr, state),
+            // the operand of `sizeof` is not evaluated
+            _ => state,
+        }
+    }
+}
+
+impl<'a> Visitor for UninitializedChecker<'a> {
+    fn visit_defun(&mut self, func: &mut DefinedFunction) {
+        self.check_function(func);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::compile;
+    use super::super::warning::Warning;
+
+    fn warned(body: &str) -> usize {
+        let compiled = compile(&format!("void set(int* p) {{ *p = 0; }}\n\
+                                         int f(int n) {{\n\
+                                             int x; int y;\n{}\n\
+                                         }}\n", body));
+        assert!(compiled.errors().is_empty(), "{:#?}", compiled.diagnostics);
+        compiled.warnings().iter().filter(|&&w| w == Warning::Uninitialized).count()
+    }
+
+    #[test]
+    fn assigned() {
+        assert_eq!(warned("x = 1; return x;"), 0);
+        assert_eq!(warned("if (n) x = 1; else x = 2; return x;"), 0);
+        assert_eq!(warned("set(&x); return x;"), 0);
+        assert_eq!(warned("switch (n) { case 0: x = 1; break; default: x = 2; } return x;"), 0);
+        assert_eq!(warned("while (1) { x = n; break; } return x;"), 0);
+        assert_eq!(warned("do { x = n; } while (n); return x;"), 0);
+        assert_eq!(warned("if (n) return 0; else x = 1; return x;"), 0);
+    }
+
+    #[test]
+    fn maybe_uninitialized() {
+        assert_eq!(warned("return x;"), 1);
+        assert_eq!(warned("if (n) x = 1; return x;"), 1);
+        assert_eq!(warned("while (n) { x = n; n--; } return x;"), 1);
+        assert_eq!(warned("switch (n) { case 0: x = 1; break; } return x;"), 1);
+        assert_eq!(warned("x = y; return x + y;"), 1); // once for `y`
+        assert_eq!(warned("goto skip; x = 1; skip: return x;"), 1);
+    }
+
+    #[test]
+    fn backward_goto() {
+        assert_eq!(warned("again: if (n) { y = x; return y; }\n\
+                           x = 1; n = 1; goto again;"), 1);
+        assert_eq!(warned("x = 1;\nagain: if (n) { y = x; return y; }\n\
+                           n = 1; goto again;"), 0);
+    }
+}
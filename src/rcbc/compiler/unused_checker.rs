@@ -0,0 +1,229 @@
+//! The pass warning the unused names and the unreachable statements.
+//!
+//! A local variable, a parameter, a `static` function or a `static` global
+//! variable is unused if no expression refers to it, where a function calling
+//! itself does not count. An import is unused if nothing of the header, or
+//! of the headers it imports, is referred to, neither a name nor a type. A
+//! statement right after `return`, `break`, `continue` or `goto` in the same
+//! block can not be reached, unless it has a label.
+
+use std::collections::HashSet;
+use super::ast::*;
+use super::location::Location;
+use super::source_map::FileId;
+use super::type_::*;
+use super::type_table::TypeTable;
+use super::entity::{EntityId, EntityKind, EntityTable};
+use super::diagnostic::{Diagnostic, DiagnosticHandler};
+use super::warning::Warning;
+use super::visitor::{self, Visitor};
+
+pub struct UnusedChecker<'a> {
+    entities: &'a EntityTable,
+    table: &'a TypeTable,
+    handler: &'a mut DiagnosticHandler,
+    function: Option<EntityId>, // the current one
+    used: HashSet<EntityId>,
+    used_files: HashSet<FileId>, // where the names and the types are defined
+}
+
+impl<'a> UnusedChecker<'a> {
+    pub fn new(entities: &'a EntityTable, table: &'a TypeTable,
+               handler: &'a mut DiagnosticHandler) -> UnusedChecker<'a> {
+        UnusedChecker {
+            entities: entities,
+            table: table,
+            handler: handler,
+            function: None,
+            used: HashSet::new(),
+            used_files: HashSet::new(),
+        }
+    }
+
+    /// Check the whole compilation unit, the warnings are reported to the
+    /// handler.
+    pub fn check(&mut self, ast: &mut AST) {
+        self.visit_ast(ast);
+        self.check_entities();
+        self.check_imports(&ast.imports);
+    }
+
+    fn check_entities(&mut self) {
+        for (id, entity) in self.entities.entities().iter().enumerate() {
+            if self.used.contains(&EntityId(id)) {
+                continue;
+            }
+            let (warning, message) = match entity.kind {
+                EntityKind::LocalVariable =>
+                    (Warning::UnusedVariable, "unused variable"),
+                EntityKind::Parameter =>
+                    (Warning::UnusedParameter, "unused parameter"),
+                EntityKind::GlobalVariable if entity.is_private =>
+                    (Warning::UnusedVariable, "unused `static` variable"),
+                EntityKind::Function if entity.is_private =>
+                    (Warning::UnusedFunction, "unused `static` function"),
+                _ => continue,
+            };
+            self.handler.warn(warning,
+                Diagnostic::warning(format!("{} `{}`", message, entity.name))
+                    .with_primary(entity.location, ""));
+        }
+    }
+
+    fn check_imports(&mut self, imports: &[ImportNode]) {
+        for import in imports {
+            // the headers which are not loaded are reported elsewhere
+            if let Some(file) = import.file {
+                // a name of a header it imports counts, as `size_t` of `stdio`
+                let is_used = self.used_files.contains(&file) ||
+                              import.headers.iter().any(|h| self.used_files.contains(h));
+                if !is_used {
+                    self.handler.warn(Warning::UnusedImport,
+                        Diagnostic::warning(format!("unused import `{}`", import.name))
+                            .with_primary(import.location, "")
+                            .with_help("remove the import"));
+                }
+            }
+        }
+    }
+
+    // record the files of the named types in the type.
+    fn use_type(&mut self, type_: TypeId) {
+        if let Some(location) = self.table.location(type_) {
+            self.used_files.insert(location.file);
+        }
+        match *self.table.get(type_) {
+            Type::Pointer(ref pointer) => self.use_type(pointer.base_type),
+            Type::Array(ref array) => self.use_type(array.base_type),
+            Type::Const(ref const_) => self.use_type(const_.base_type),
+            Type::User(ref user) => self.use_type(user.real_type),
+            Type::Function(ref function) => {
+                self.use_type(function.return_type);
+                for &param in &function.param_types {
+                    self.use_type(param);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // warn the first statement which can not be reached after a jump.
+    fn check_reachable(&mut self, stmts: &[Box<Node>]) {
+        let mut jump: Option<Location> = None;
+        for stmt in stmts {
+            if let NodeRef::LabelNode(_) = stmt.node_ref() {
+                jump = None; // by a `goto`
+            }
+            if let Some(jump) = jump {
+                self.handler.warn(Warning::UnreachableCode,
+                    Diagnostic::warning("unreachable statement")
+                        .with_primary(stmt.location(), "")
+                        .with_secondary(jump, "any code after this is unreachable"));
+                return;
+            }
+            match stmt.node_ref() {
+                NodeRef::ReturnNode(_) | NodeRef::BreakNode(_) |
+                NodeRef::ContinueNode(_) | NodeRef::GotoNode(_) => {
+                    jump = Some(stmt.location());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'a> Visitor for UnusedChecker<'a> {
+    fn visit_defun(&mut self, func: &mut DefinedFunction) {
+        self.function = func.entity;
+        visitor::walk_defun(self, func);
+        self.function = None;
+    }
+
+    // the types of a declaration are used only if the name is
+    fn visit_vardecl(&mut self, _var: &mut UndefinedVariable) {}
+    fn visit_funcdecl(&mut self, _func: &mut UndefinedFunction) {}
+
+    fn visit_type(&mut self, type_: &mut TypeNode) {
+        if let Some(type_) = type_.type_ {
+            self.use_type(type_);
+        }
+    }
+
+    fn visit_variable(&mut self, node: &mut VariableNode) {
+        if let Some(entity) = node.entity {
+            if Some(entity) != self.function {
+                self.used.insert(entity);
+                self.used_files.insert(self.entities.get(entity).location.file);
+            }
+        }
+    }
+
+    fn visit_block(&mut self, node: &mut BlockNode) {
+        self.check_reachable(&node.stmts);
+        visitor::walk_block(self, node);
+    }
+
+    fn visit_case(&mut self, node: &mut CaseNode) {
+        self.check_reachable(&node.stmt);
+        visitor::walk_case(self, node);
+    }
+
+    fn visit_default_case(&mut self, node: &mut DefaultCaseNode) {
+        self.check_reachable(&node.stmt);
+        visitor::walk_default_case(self, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{compile_warning, Compiled};
+    use super::super::warning::Warning;
+
+    fn check(src: &str) -> Compiled {
+        compile_warning(src, &["all", "unused-parameter"])
+    }
+
+    #[test]
+    fn used_names() {
+        let compiled = check("import stdio;\n\
+                              static int count;\n\
+                              static int next(int step) { count += step; return count; }\n\
+                              int main(void) { int n = next(1); printf(\"%d\\n\", n); return 0; }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn unused_names() {
+        let compiled = check("static int count;\n\
+                              static int next(int step) { return next(1); }\n\
+                              int main(void) { int n; return 0; }\n");
+        let mut warnings = compiled.warnings();
+        warnings.sort_by_key(|w| w.name());
+        assert_eq!(warnings, [Warning::UnusedFunction, Warning::UnusedParameter,
+                              Warning::UnusedVariable, Warning::UnusedVariable]);
+    }
+
+    #[test]
+    fn import_used_through_the_headers_it_imports() {
+        let compiled = check("import stdio;\n\
+                              int main(void) { size_t n = 0; return n; }\n");
+        compiled.assert_clean();
+    }
+
+    #[test]
+    fn unused_import() {
+        let compiled = check("import stdio;\n\
+                              int main(void) { return 0; }\n");
+        assert_eq!(compiled.warnings(), [Warning::UnusedImport]);
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let compiled = check("int main(void) {\n\
+                                  return 0;\n\
+                                  return 1;\n\
+                              }\n\
+                              int f(int x) { goto end; end: return x; }\n");
+        assert_eq!(compiled.warnings(), [Warning::UnreachableCode]);
+    }
+}
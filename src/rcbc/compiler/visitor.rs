@@ -0,0 +1,314 @@
+//! The traversal of the AST for the semantic passes.
+//!
+//! A pass implements `Visitor` and overrides only the methods for the nodes
+//! it cares about. The default methods call the `walk_*` functions, which
+//! visit the children of the node, so an overriding method calls the
+//! `walk_*` function itself to go on into the children.
+
+use super::ast::*;
+
+pub trait Visitor {
+    fn visit_ast(&mut self, ast: &mut AST) { walk_ast(self, ast) }
+    fn visit_defvar(&mut self, var: &mut DefinedVariable) { walk_defvar(self, var) }
+    fn visit_defun(&mut self, func: &mut DefinedFunction) { walk_defun(self, func) }
+    fn visit_vardecl(&mut self, var: &mut UndefinedVariable) { walk_vardecl(self, var) }
+    fn visit_funcdecl(&mut self, func: &mut UndefinedFunction) { walk_funcdecl(self, func) }
+    fn visit_param(&mut self, param: &mut Parameter) { walk_param(self, param) }
+    fn visit_type(&mut self, type_: &mut TypeNode) {}
+
+    /// Every expression and statement is visited by this first, so it can be
+    /// replaced by another node.
+    fn visit_node(&mut self, node: &mut Box<Node>) { walk_node(self, node) }
+
+    fn visit_block(&mut self, node: &mut BlockNode) { walk_block(self, node) }
+    fn visit_expr_stmt(&mut self, node: &mut ExprStmtNode) { walk_expr_stmt(self, node) }
+    fn visit_if(&mut self, node: &mut IfNode) { walk_if(self, node) }
+    fn visit_while(&mut self, node: &mut WhileNode) { walk_while(self, node) }
+    fn visit_dowhile(&mut self, node: &mut DoWhileNode) { walk_dowhile(self, node) }
+    fn visit_for(&mut self, node: &mut ForNode) { walk_for(self, node) }
+    fn visit_switch(&mut self, node: &mut SwitchNode) { walk_switch(self, node) }
+    fn visit_cases(&mut self, node: &mut CasesNode) { walk_cases(self, node) }
+    fn visit_case(&mut self, node: &mut CaseNode) { walk_case(self, node) }
+    fn visit_default_case(&mut self, node: &mut DefaultCaseNode) { walk_default_case(self, node) }
+    fn visit_break(&mut self, node: &mut BreakNode) {}
+    fn visit_continue(&mut self, node: &mut ContinueNode) {}
+    fn visit_goto(&mut self, node: &mut GotoNode) {}
+    fn visit_return(&mut self, node: &mut ReturnNode) { walk_return(self, node) }
+    fn visit_label(&mut self, node: &mut LabelNode) { walk_label(self, node) }
+
+    fn visit_integer_literal(&mut self, node: &mut IntegerLiteralNode) {}
+    fn visit_string_literal(&mut self, node: &mut StringLiteralNode) {}
+    fn visit_variable(&mut self, node: &mut VariableNode) {}
+    fn visit_binary_op(&mut self, node: &mut BinaryOpNode) { walk_binary_op(self, node) }
+    fn visit_logical_and(&mut self, node: &mut LogicalAndNode) { walk_logical_and(self, node) }
+    fn visit_logical_or(&mut self, node: &mut LogicalOrNode) { walk_logical_or(self, node) }
+    fn visit_unary_op(&mut self, node: &mut UnaryOpNode) { walk_unary_op(self, node) }
+    fn visit_prefix_op(&mut self, node: &mut PrefixOpNode) { walk_prefix_op(self, node) }
+    fn visit_suffix_op(&mut self, node: &mut SuffixOpNode) { walk_suffix_op(self, node) }
+    fn visit_dereference(&mut self, node: &mut DereferenceNode) { walk_dereference(self, node) }
+    fn visit_address(&mut self, node: &mut AddressNode) { walk_address(self, node) }
+    fn visit_cast(&mut self, node: &mut CastNode) { walk_cast(self, node) }
+    fn visit_sizeof_type(&mut self, node: &mut SizeofTypeNode) { walk_sizeof_type(self, node) }
+    fn visit_sizeof_expr(&mut self, node: &mut SizeofExprNode) { walk_sizeof_expr(self, node) }
+    fn visit_aref(&mut self, node: &mut ArefNode) { walk_aref(self, node) }
+    fn visit_member(&mut self, node: &mut MemberNode) { walk_member(self, node) }
+    fn visit_ptr_member(&mut self, node: &mut PtrMemberNode) { walk_ptr_member(self, node) }
+    fn visit_funcall(&mut self, node: &mut FuncallNode) { walk_funcall(self, node) }
+    fn visit_cond_expr(&mut self, node: &mut CondExprNode) { walk_cond_expr(self, node) }
+    fn visit_assign(&mut self, node: &mut AssignNode) { walk_assign(self, node) }
+    fn visit_op_assign(&mut self, node: &mut OpAssignNode) { walk_op_assign(self, node) }
+}
+
+pub fn walk_ast<V: Visitor + ?Sized>(v: &mut V, ast: &mut AST) {
+    for var in &mut ast.vardecls {
+        v.visit_vardecl(var);
+    }
+    for func in &mut ast.funcdecls {
+        v.visit_funcdecl(func);
+    }
+    for var in &mut ast.defvars {
+        v.visit_defvar(var);
+    }
+    for func in &mut ast.defuns {
+        v.visit_defun(func);
+    }
+}
+
+pub fn walk_defvar<V: Visitor + ?Sized>(v: &mut V, var: &mut DefinedVariable) {
+    v.visit_type(&mut var.type_);
+    if let Some(ref mut initializer) = var.initializer {
+        v.visit_node(initializer);
+    }
+}
+
+pub fn walk_defun<V: Visitor + ?Sized>(v: &mut V, func: &mut DefinedFunction) {
+    v.visit_type(&mut func.type_);
+    for param in &mut func.params.params {
+        v.visit_param(param);
+    }
+    v.visit_block(&mut func.body);
+}
+
+pub fn walk_vardecl<V: Visitor + ?Sized>(v: &mut V, var: &mut UndefinedVariable) {
+    v.visit_type(&mut var.type_);
+}
+
+pub fn walk_funcdecl<V: Visitor + ?Sized>(v: &mut V, func: &mut UndefinedFunction) {
+    v.visit_type(&mut func.type_);
+    for param in &mut func.params.params {
+        v.visit_param(param);
+    }
+}
+
+pub fn walk_param<V: Visitor + ?Sized>(v: &mut V, param: &mut Parameter) {
+    v.visit_type(&mut param.type_);
+}
+
+pub fn walk_node<V: Visitor + ?Sized>(v: &mut V, node: &mut Box<Node>) {
+    match node.node_mut() {
+        NodeMut::AST(node) => v.visit_ast(node),
+        NodeMut::BlockNode(node) => v.visit_block(node),
+        NodeMut::ExprStmtNode(node) => v.visit_expr_stmt(node),
+        NodeMut::IfNode(node) => v.visit_if(node),
+        NodeMut::WhileNode(node) => v.visit_while(node),
+        NodeMut::DoWhileNode(node) => v.visit_dowhile(node),
+        NodeMut::ForNode(node) => v.visit_for(node),
+        NodeMut::SwitchNode(node) => v.visit_switch(node),
+        NodeMut::CasesNode(node) => v.visit_cases(node),
+        NodeMut::CaseNode(node) => v.visit_case(node),
+        NodeMut::DefaultCaseNode(node) => v.visit_default_case(node),
+        NodeMut::BreakNode(node) => v.visit_break(node),
+        NodeMut::ContinueNode(node) => v.visit_continue(node),
+        NodeMut::GotoNode(node) => v.visit_goto(node),
+        NodeMut::ReturnNode(node) => v.visit_return(node),
+        NodeMut::LabelNode(node) => v.visit_label(node),
+        NodeMut::IntegerLiteralNode(node) => v.visit_integer_literal(node),
+        NodeMut::StringLiteralNode(node) => v.visit_string_literal(node),
+        NodeMut::VariableNode(node) => v.visit_variable(node),
+        NodeMut::BinaryOpNode(node) => v.visit_binary_op(node),
+        NodeMut::LogicalAndNode(node) => v.visit_logical_and(node),
+        NodeMut::LogicalOrNode(node) => v.visit_logical_or(node),
+        NodeMut::UnaryOpNode(node) => v.visit_unary_op(node),
+        NodeMut::PrefixOpNode(node) => v.visit_prefix_op(node),
+        NodeMut::SuffixOpNode(node) => v.visit_suffix_op(node),
+        NodeMut::DereferenceNode(node) => v.visit_dereference(node),
+        NodeMut::AddressNode(node) => v.visit_address(node),
+        NodeMut::CastNode(node) => v.visit_cast(node),
+        NodeMut::SizeofTypeNode(node) => v.visit_sizeof_type(node),
+        NodeMut::SizeofExprNode(node) => v.visit_sizeof_expr(node),
+        NodeMut::ArefNode(node) => v.visit_aref(node),
+        NodeMut::MemberNode(node) => v.visit_member(node),
+        NodeMut::PtrMemberNode(node) => v.visit_ptr_member(node),
+        NodeMut::FuncallNode(node) => v.visit_funcall(node),
+        NodeMut::CondExprNode(node) => v.visit_cond_expr(node),
+        NodeMut::AssignNode(node) => v.visit_assign(node),
+        NodeMut::OpAssignNode(node) => v.visit_op_assign(node),
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(v: &mut V, node: &mut BlockNode) {
+    for var in &mut node.variables {
+        v.visit_defvar(var);
+    }
+    for stmt in &mut node.stmts {
+        v.visit_node(stmt);
+    }
+}
+
+pub fn walk_expr_stmt<V: Visitor + ?Sized>(v: &mut V, node: &mut ExprStmtNode) {
+    v.visit_node(&mut node.expr);
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(v: &mut V, node: &mut IfNode) {
+    v.visit_node(&mut node.condition);
+    v.visit_node(&mut node.then_clause);
+    if let Some(ref mut else_clause) = node.else_clause {
+        v.visit_node(else_clause);
+    }
+}
+
+pub fn walk_while<V: Visitor + ?Sized>(v: &mut V, node: &mut WhileNode) {
+    v.visit_node(&mut node.condition);
+    v.visit_node(&mut node.body);
+}
+
+pub fn walk_dowhile<V: Visitor + ?Sized>(v: &mut V, node: &mut DoWhileNode) {
+    v.visit_node(&mut node.body);
+    v.visit_node(&mut node.condition);
+}
+
+pub fn walk_for<V: Visitor + ?Sized>(v: &mut V, node: &mut ForNode) {
+    if let Some(ref mut init_expr) = node.init_expr {
+        v.visit_node(init_expr);
+    }
+    if let Some(ref mut cond_expr) = node.cond_expr {
+        v.visit_node(cond_expr);
+    }
+    if let Some(ref mut step_expr) = node.step_expr {
+        v.visit_node(step_expr);
+    }
+    v.visit_node(&mut node.body);
+}
+
+pub fn walk_switch<V: Visitor + ?Sized>(v: &mut V, node: &mut SwitchNode) {
+    v.visit_node(&mut node.expr);
+    if let Some(ref mut cases) = node.cases {
+        v.visit_node(cases);
+    }
+}
+
+pub fn walk_cases<V: Visitor + ?Sized>(v: &mut V, node: &mut CasesNode) {
+    for case in &mut node.normal_cases {
+        v.visit_node(case);
+    }
+    if let Some(ref mut default_case) = node.default_case {
+        v.visit_node(default_case);
+    }
+}
+
+pub fn walk_case<V: Visitor + ?Sized>(v: &mut V, node: &mut CaseNode) {
+    v.visit_node(&mut node.expr);
+    for stmt in &mut node.stmt {
+        v.visit_node(stmt);
+    }
+}
+
+pub fn walk_default_case<V: Visitor + ?Sized>(v: &mut V, node: &mut DefaultCaseNode) {
+    for stmt in &mut node.stmt {
+        v.visit_node(stmt);
+    }
+}
+
+pub fn walk_return<V: Visitor + ?Sized>(v: &mut V, node: &mut ReturnNode) {
+    if let Some(ref mut expr) = node.expr {
+        v.visit_node(expr);
+    }
+}
+
+pub fn walk_label<V: Visitor + ?Sized>(v: &mut V, node: &mut LabelNode) {
+    v.visit_node(&mut node.stmt);
+}
+
+pub fn walk_binary_op<V: Visitor + ?Sized>(v: &mut V, node: &mut BinaryOpNode) {
+    v.visit_node(&mut node.left);
+    v.visit_node(&mut node.right);
+}
+
+pub fn walk_logical_and<V: Visitor + ?Sized>(v: &mut V, node: &mut LogicalAndNode) {
+    v.visit_node(&mut node.left);
+    v.visit_node(&mut node.right);
+}
+
+pub fn walk_logical_or<V: Visitor + ?Sized>(v: &mut V, node: &mut LogicalOrNode) {
+    v.visit_node(&mut node.left);
+    v.visit_node(&mut node.right);
+}
+
+pub fn walk_unary_op<V: Visitor + ?Sized>(v: &mut V, node: &mut UnaryOpNode) {
+    v.visit_node(&mut node.node);
+}
+
+pub fn walk_prefix_op<V: Visitor + ?Sized>(v: &mut V, node: &mut PrefixOpNode) {
+    v.visit_node(&mut node.node);
+}
+
+pub fn walk_suffix_op<V: Visitor + ?Sized>(v: &mut V, node: &mut SuffixOpNode) {
+    v.visit_node(&mut node.expr);
+}
+
+pub fn walk_dereference<V: Visitor + ?Sized>(v: &mut V, node: &mut DereferenceNode) {
+    v.visit_node(&mut node.node);
+}
+
+pub fn walk_address<V: Visitor + ?Sized>(v: &mut V, node: &mut AddressNode) {
+    v.visit_node(&mut node.node);
+}
+
+pub fn walk_cast<V: Visitor + ?Sized>(v: &mut V, node: &mut CastNode) {
+    v.visit_type(&mut node.type_);
+    v.visit_node(&mut node.node);
+}
+
+pub fn walk_sizeof_type<V: Visitor + ?Sized>(v: &mut V, node: &mut SizeofTypeNode) {
+    v.visit_type(&mut node.type_);
+}
+
+pub fn walk_sizeof_expr<V: Visitor + ?Sized>(v: &mut V, node: &mut SizeofExprNode) {
+    v.visit_node(&mut node.node);
+}
+
+pub fn walk_aref<V: Visitor + ?Sized>(v: &mut V, node: &mut ArefNode) {
+    v.visit_node(&mut node.expr);
+    v.visit_node(&mut node.idx);
+}
+
+pub fn walk_member<V: Visitor + ?Sized>(v: &mut V, node: &mut MemberNode) {
+    v.visit_node(&mut node.expr);
+}
+
+pub fn walk_ptr_member<V: Visitor + ?Sized>(v: &mut V, node: &mut PtrMemberNode) {
+    v.visit_node(&mut node.expr);
+}
+
+pub fn walk_funcall<V: Visitor + ?Sized>(v: &mut V, node: &mut FuncallNode) {
+    v.visit_node(&mut node.expr);
+    for arg in &mut node.args {
+        v.visit_node(arg);
+    }
+}
+
+pub fn walk_cond_expr<V: Visitor + ?Sized>(v: &mut V, node: &mut CondExprNode) {
+    v.visit_node(&mut node.condition);
+    v.visit_node(&mut node.then_clause);
+    v.visit_node(&mut node.else_clause);
+}
+
+pub fn walk_assign<V: Visitor + ?Sized>(v: &mut V, node: &mut AssignNode) {
+    v.visit_node(&mut node.lhs);
+    v.visit_node(&mut node.rhs);
+}
+
+pub fn walk_op_assign<V: Visitor + ?Sized>(v: &mut V, node: &mut OpAssignNode) {
+    v.visit_node(&mut node.lhs);
+    v.visit_node(&mut node.rhs);
+}
@@ -0,0 +1,284 @@
+//! The registry of warnings and the options controlling them.
+//!
+//! Every warning has a stable name used by the command line flags:
+//! `-W<name>` and `-Wno-<name>` turn it on and off, `-Wall` turns on the
+//! warnings of the `All` group, `-Werror` and `-Werror=<name>` make them
+//! errors, and `-w` silences all of them.
+
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Warning {
+    Comment,
+    Shadow,
+    ReturnType,
+    Uninitialized,
+    UnusedVariable,
+    UnusedParameter,
+    UnusedFunction,
+    UnusedImport,
+    UnreachableCode,
+    Format,
+    CImport,
+    DiscardedQualifiers,
+}
+
+/// When a warning is enabled without being named on the command line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WarningGroup {
+    Default, // always, unless turned off
+    All,     // by `-Wall`
+    Extra,   // only by `-W<name>`
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WarningLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// The state of every warning, built from the `-W` flags in order.
+#[derive(Debug, Copy, Clone)]
+pub struct WarningOptions {
+    enabled: u64,    // bit set by `Warning as u64`
+    disabled: u64,
+    as_errors: u64,
+    is_all: bool,
+    is_all_errors: bool,
+    is_suppressed: bool,
+}
+
+impl Warning {
+    pub const ALL: &'static [Warning] = &[
+        Warning::Comment,
+        Warning::Shadow,
+        Warning::ReturnType,
+        Warning::Uninitialized,
+        Warning::UnusedVariable,
+        Warning::UnusedParameter,
+        Warning::UnusedFunction,
+        Warning::UnusedImport,
+        Warning::UnreachableCode,
+        Warning::Format,
+        Warning::CImport,
+        Warning::DiscardedQualifiers,
+    ];
+
+    /// the stable name used in `-W<name>`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Warning::Comment => "comment",
+            Warning::Shadow => "shadow",
+            Warning::ReturnType => "return-type",
+            Warning::Uninitialized => "uninitialized",
+            Warning::UnusedVariable => "unused-variable",
+            Warning::UnusedParameter => "unused-parameter",
+            Warning::UnusedFunction => "unused-function",
+            Warning::UnusedImport => "unused-import",
+            Warning::UnreachableCode => "unreachable-code",
+            Warning::Format => "format",
+            Warning::CImport => "c-import",
+            Warning::DiscardedQualifiers => "discarded-qualifiers",
+        }
+    }
+
+    pub fn group(&self) -> WarningGroup {
+        match *self {
+            Warning::Comment => WarningGroup::All,
+            Warning::Shadow => WarningGroup::All,
+            Warning::ReturnType => WarningGroup::Default,
+            Warning::Uninitialized => WarningGroup::Default,
+            Warning::UnusedVariable => WarningGroup::All,
+            Warning::UnusedParameter => WarningGroup::Extra,
+            Warning::UnusedFunction => WarningGroup::All,
+            Warning::UnusedImport => WarningGroup::All,
+            Warning::UnreachableCode => WarningGroup::All,
+            Warning::Format => WarningGroup::Default,
+            Warning::CImport => WarningGroup::Default,
+            Warning::DiscardedQualifiers => WarningGroup::Default,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Warning::Comment =>
+                "`/*` inside a block comment, which is likely an unclosed comment",
+            Warning::Shadow =>
+                "a local variable or a parameter hiding another one of the same name",
+            Warning::ReturnType =>
+                "the end of a function returning a value is reachable without `return`",
+            Warning::Uninitialized =>
+                "a read of a local variable which may not be assigned yet",
+            Warning::UnusedVariable =>
+                "a local variable or a `static` global variable which is never used",
+            Warning::UnusedParameter =>
+                "a parameter which is never used in its function",
+            Warning::UnusedFunction =>
+                "a `static` function which is never called or referred to",
+            Warning::UnusedImport =>
+                "an import of which nothing is used",
+            Warning::UnreachableCode =>
+                "a statement after `return`, `break`, `continue` or `goto`",
+            Warning::Format =>
+                "a call of a `printf`-style function not matching its format string",
+            Warning::CImport =>
+                "a declaration or a macro of an imported C header which Cb can not represent",
+            Warning::DiscardedQualifiers =>
+                "a pointer converted to one which loses the `const` of what it points to",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Warning> {
+        Warning::ALL.iter().cloned().find(|warning| warning.name() == name)
+    }
+
+    fn bit(&self) -> u64 {
+        1 << (*self as u64)
+    }
+}
+
+impl WarningOptions {
+    pub fn new() -> WarningOptions {
+        WarningOptions {
+            enabled: 0,
+            disabled: 0,
+            as_errors: 0,
+            is_all: false,
+            is_all_errors: false,
+            is_suppressed: false,
+        }
+    }
+
+    /// apply the value of a `-W` flag, such as `all` for `-Wall` and
+    /// `no-comment` for `-Wno-comment`. Return an error for unknown names.
+    pub fn apply(&mut self, flag: &str) -> Result<(), String> {
+        let lookup = |name: &str| Warning::from_name(name).ok_or_else(
+            || format!("unknown warning option `-W{}`", flag));
+
+        if flag == "all" {
+            self.is_all = true;
+        } else if flag == "error" {
+            self.is_all_errors = true;
+        } else if let Some(name) = flag.strip_prefix("error=") {
+            let warning = lookup(name)?;
+            self.turn_on(warning);
+            self.as_errors |= warning.bit();
+        } else if let Some(name) = flag.strip_prefix("no-") {
+            let warning = lookup(name)?;
+            self.enabled &= !warning.bit();
+            self.disabled |= warning.bit();
+        } else {
+            let warning = lookup(flag)?;
+            self.turn_on(warning);
+        }
+        Ok(())
+    }
+
+    /// `-w`, no warning is shown.
+    pub fn suppress_all(&mut self) {
+        self.is_suppressed = true;
+    }
+
+    pub fn level(&self, warning: Warning) -> WarningLevel {
+        let bit = warning.bit();
+        let is_enabled = if self.enabled & bit != 0 {
+            true
+        } else if self.disabled & bit != 0 {
+            false
+        } else {
+            match warning.group() {
+                WarningGroup::Default => true,
+                WarningGroup::All => self.is_all,
+                WarningGroup::Extra => false,
+            }
+        };
+
+        if self.is_suppressed || !is_enabled {
+            WarningLevel::Allow
+        } else if self.is_all_errors || self.as_errors & bit != 0 {
+            WarningLevel::Deny
+        } else {
+            WarningLevel::Warn
+        }
+    }
+
+    fn turn_on(&mut self, warning: Warning) {
+        self.enabled |= warning.bit();
+        self.disabled &= !warning.bit();
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(flags: &[&str]) -> WarningOptions {
+        let mut options = WarningOptions::new();
+        for flag in flags {
+            options.apply(flag).unwrap();
+        }
+        options
+    }
+
+    #[test]
+    fn names_are_unique() {
+        for warning in Warning::ALL {
+            assert_eq!(Warning::from_name(warning.name()), Some(*warning));
+        }
+        assert_eq!(Warning::from_name("no-such-warning"), None);
+    }
+
+    #[test]
+    fn groups() {
+        let default = options(&[]);
+        assert_eq!(default.level(Warning::Format), WarningLevel::Warn);
+        assert_eq!(default.level(Warning::Shadow), WarningLevel::Allow);
+        assert_eq!(default.level(Warning::UnusedParameter), WarningLevel::Allow);
+
+        let all = options(&["all"]);
+        assert_eq!(all.level(Warning::Shadow), WarningLevel::Warn);
+        assert_eq!(all.level(Warning::UnusedParameter), WarningLevel::Allow);
+        assert_eq!(options(&["unused-parameter"]).level(Warning::UnusedParameter),
+                   WarningLevel::Warn);
+    }
+
+    #[test]
+    fn later_flags_win() {
+        assert_eq!(options(&["all", "no-shadow"]).level(Warning::Shadow), WarningLevel::Allow);
+        assert_eq!(options(&["no-shadow", "shadow"]).level(Warning::Shadow), WarningLevel::Warn);
+        assert_eq!(options(&["no-format"]).level(Warning::Format), WarningLevel::Allow);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(options(&["error"]).level(Warning::Format), WarningLevel::Deny);
+        assert_eq!(options(&["error"]).level(Warning::Shadow), WarningLevel::Allow);
+        let one = options(&["error=shadow"]);
+        assert_eq!(one.level(Warning::Shadow), WarningLevel::Deny);
+        assert_eq!(one.level(Warning::Format), WarningLevel::Warn);
+    }
+
+    #[test]
+    fn suppressed() {
+        let mut options = options(&["all", "error"]);
+        options.suppress_all();
+        for &warning in Warning::ALL {
+            assert_eq!(options.level(warning), WarningLevel::Allow);
+        }
+    }
+
+    #[test]
+    fn unknown_flags() {
+        let mut options = WarningOptions::new();
+        assert_eq!(options.apply("shadows"), Err("unknown warning option `-Wshadows`".to_string()));
+        assert!(options.apply("no-shadows").is_err());
+        assert!(options.apply("error=shadows").is_err());
+    }
+}
@@ -12,6 +12,9 @@ mod linker;
 
 
 const EXT_CFLAT_SOURCE:    &'static str = "cb";
+const EXT_CFLAT_HEADER:    &'static str = "hb";
+const EXT_C_HEADER:        &'static str = "h" ;
+const EXT_RUST_SOURCE:     &'static str = "rs";
 const EXT_ASSEMBLY_SOURCE: &'static str = "s" ;
 const EXT_OBJECT_FILE:     &'static str = "o" ;
 // const EXT_STATIC_LIBRARY:  &'static str = "a" ;
@@ -34,8 +37,22 @@ pub fn cli() {
     
     opts.optflag("h", "help", "Display this information");
     opts.optflag("", "version", "Display compiler version information");
+    opts.optopt("", "explain", "Explain an error code in detail", "CODE");
+    opts.optopt("", "print-header", "Show a standard header built in the compiler", "NAME");
 
     opts.optflag("", "dump-tokens", "Show the token stream by lexer.");
+    opts.optflag("", "dump-record-layouts", "Show the layouts of structs and unions.");
+    opts.optopt("", "emit", "Write the header or the bindings of each source instead of \
+                             compiling it",
+                "header|c-header|rust-bindings");
+
+    opts.optopt("", "target", "The machine to compile for, i386 by default", "i386|x86_64");
+    opts.optopt("", "error-format", "How the errors are shown", "human|json");
+    opts.optopt("", "color", "Coloring the errors shown for human", "auto|always|never");
+    opts.optmulti("W", "", "Control the warnings: -Wall, -W<name>, -Wno-<name>, \
+                            -Werror, -Werror=<name>", "WARNING");
+    opts.optflag("w", "", "Inhibit all warning messages");
+    opts.optmulti("I", "", "Add a directory to search the imported headers in", "DIR");
     
     let matches = match opts.parse(&argv[1..]) {
         Ok(val) => val,
@@ -48,6 +65,10 @@ pub fn cli() {
 
     if matches.opt_present("h") {
         print_usage(program, opts);
+    } else if let Some(code) = matches.opt_str("explain") {
+        explain(&code);
+    } else if let Some(name) = matches.opt_str("print-header") {
+        print_header(&name);
     } else if matches.free.len() > 0 {
         cli_main(matches);
     } else {
@@ -61,6 +82,24 @@ fn print_usage(program: &str, opts: Options) {
     ));
 }
 
+/// print the long explanation of an error code, such as `E0012`.
+fn explain(code: &str) {
+    match compiler::explain(&code.to_uppercase()) {
+        Some(explanation) => print!("{}", explanation),
+        None => shutdown_for(&format!("no explanation for the error code `{}`", code)),
+    }
+}
+
+/// print a standard header, such as `stdio`.
+fn print_header(name: &str) {
+    let name = name.trim_end_matches(".hb");
+    match compiler::standard_header(name) {
+        Some(header) => print!("{}", header),
+        None => shutdown_for(&format!("no standard header `{}` (one of {})", name,
+                                      compiler::standard_headers().join(", "))),
+    }
+}
+
 /// throw fatal error and shutdown the program.
 fn shutdown_for(reason: &str) -> ! {
     println!("rcbc: fatal error: {}", reason);
@@ -72,12 +111,70 @@ fn cli_main(matches: Matches) {
     let src_files: Vec<PathBuf> = matches.free.iter().map(PathBuf::from).collect();
     let exec_file = PathBuf::from(EXECUTABLE_FILE_DEFAULT);
     
-    let compiler = compiler::Compiler::new();
+    let mut compiler = compiler::Compiler::new();
     let assembler = assembler::Assembler::new();
     let linker = linker::Linker::new();
 
+    let error_format = match matches.opt_str("error-format") {
+        None => compiler::ErrorFormat::Human,
+        Some(ref format) if format == "human" => compiler::ErrorFormat::Human,
+        Some(ref format) if format == "json" => compiler::ErrorFormat::Json,
+        Some(format) => shutdown_for(&format!(
+            "unknown error format `{}` (human or json)", format)),
+    };
+    let color = match matches.opt_str("color") {
+        None => compiler::ColorConfig::Auto,
+        Some(ref color) if color == "auto" => compiler::ColorConfig::Auto,
+        Some(ref color) if color == "always" => compiler::ColorConfig::Always,
+        Some(ref color) if color == "never" => compiler::ColorConfig::Never,
+        Some(color) => shutdown_for(&format!(
+            "unknown color setting `{}` (auto, always or never)", color)),
+    };
+
+    let target = match matches.opt_str("target") {
+        None => compiler::Target::default(),
+        Some(name) => match compiler::Target::from_name(&name) {
+            Some(target) => target,
+            None => shutdown_for(&format!(
+                "unknown target `{}` (i386 or x86_64)", name)),
+        },
+    };
+
+    let emit = match matches.opt_str("emit") {
+        None => compiler::Emit::Assembly,
+        Some(ref emit) if emit == "header" => compiler::Emit::Header,
+        Some(ref emit) if emit == "c-header" => compiler::Emit::CHeader,
+        Some(ref emit) if emit == "rust-bindings" => compiler::Emit::RustBindings,
+        Some(emit) => shutdown_for(&format!(
+            "unknown output `{}` to emit (header, c-header or rust-bindings)", emit)),
+    };
+
+    let mut warnings = compiler::WarningOptions::new();
+    for flag in matches.opt_strs("W") {
+        if let Err(why) = warnings.apply(&flag) {
+            shutdown_for(&why);
+        }
+    }
+    if matches.opt_present("w") {
+        warnings.suppress_all();
+    }
+
+    let is_dump_tokens = matches.opt_present("dump-tokens");
+    let is_dump_record_layouts = matches.opt_present("dump-record-layouts");
+    // only the assembly is assembled and linked, not a header or a dump
+    let is_linking = emit == compiler::Emit::Assembly &&
+                     !is_dump_tokens && !is_dump_record_layouts;
+
     let compiler_opts = compiler::CompileOptionBuilder::new()
-        .is_dump_tokens(matches.opt_present("dump-tokens")).finalize();
+        .is_dump_tokens(is_dump_tokens)
+        .is_dump_record_layouts(is_dump_record_layouts)
+        .target(target)
+        .error_format(error_format)
+        .color(color)
+        .warnings(warnings)
+        .import_dirs(matches.opt_strs("I").into_iter().map(PathBuf::from).collect())
+        .emit(emit)
+        .finalize();
 
     for src_file in src_files.iter() {
         if !src_file.exists() {
@@ -96,12 +193,22 @@ fn cli_main(matches: Matches) {
         let src_file = src.clone();
         let asm_file = asm_file_name_of(src);
         let obj_file = obj_file_name_of(src);
+        let out_file = match emit {
+            compiler::Emit::Assembly => asm_file.clone(),
+            compiler::Emit::Header => src.with_extension(EXT_CFLAT_HEADER),
+            compiler::Emit::CHeader => src.with_extension(EXT_C_HEADER),
+            compiler::Emit::RustBindings => src.with_extension(EXT_RUST_SOURCE),
+        };
 
-        let compile_result = compiler.compile(&src_file, &asm_file, compiler_opts);
+        let compile_result = compiler.compile(&src_file, &out_file, &compiler_opts);
 
-        if let Err(err) = compile_result {
-            println!("Compiler Error: {}", err);
-            return;
+        if compile_result.is_err() {
+            // the diagnostics have been shown by the compiler.
+            report_summary(&compiler, error_format);
+            process::exit(1);
+        }
+        if !is_linking {
+            continue;
         }
 
         let assemble_result = assembler.assemble(&asm_file, &obj_file);
@@ -114,6 +221,11 @@ fn cli_main(matches: Matches) {
         obj_files.push(obj_file);
     }
 
+    report_summary(&compiler, error_format);
+    if !is_linking {
+        return;
+    }
+
     let link_result = linker.link(&obj_files, &exec_file);
 
     if let Err(err) = link_result {
@@ -122,6 +234,20 @@ fn cli_main(matches: Matches) {
     }
 }
 
+/// show how many warnings and errors are found, like
+/// `rcbc: 2 warnings and 1 error generated`.
+fn report_summary(compiler: &compiler::Compiler, error_format: compiler::ErrorFormat) {
+    if error_format != compiler::ErrorFormat::Human {
+        return;
+    }
+    if let Some(summary) = compiler.summary() {
+        eprintln!("rcbc: {}", summary);
+    }
+    if let Some(code) = compiler.first_error_code() {
+        eprintln!("For more information about this error, try `rcbc --explain {}`.", code);
+    }
+}
+
 /// judge if the file in path `file` is a valid source file or not.
 fn is_source_file(file: &Path) -> bool {
     let src_ext: &OsStr = OsStr::new(EXT_CFLAT_SOURCE);